@@ -0,0 +1,501 @@
+//! Tamper-evident audit log of what the agent accessed and sent.
+//!
+//! Every path decision made by `validate_path`, every tool execution and
+//! every API request is appended to a JSON Lines file in the app data
+//! directory. Each entry carries the hash of the entry before it and a hash
+//! of its own contents, so editing, removing or reordering entries breaks
+//! the chain and is reported by [`AuditLog::verify`].
+
+use crate::claude::{
+    constants::audit::{DEFAULT_QUERY_LIMIT, GENESIS_HASH, TAIL_CHUNK_SIZE},
+    tools::history::ExecutionRecord,
+    whitelist::FileOperation,
+};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::{BufRead, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Something the agent did that is recorded in the audit log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AuditEvent {
+    /// A path checked against the whitelist
+    PathAccess {
+        operation: FileOperation,
+        /// Path as requested
+        path: String,
+        /// Path the request resolved to, when it got that far
+        canonical_path: Option<String>,
+        allowed: bool,
+        /// Why access was denied
+        reason: Option<String>,
+    },
+    /// A finished tool call
+    ToolExecution(ExecutionRecord),
+    /// A request sent to the Claude API
+    ApiRequest {
+        model: String,
+        /// SHA-256 of the request body
+        payload_hash: String,
+        status: Option<u16>,
+        input_tokens: Option<u32>,
+        output_tokens: Option<u32>,
+        error: Option<String>,
+    },
+}
+
+impl AuditEvent {
+    /// Name of the event kind, as stored in the `kind` field
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AuditEvent::PathAccess { .. } => "path_access",
+            AuditEvent::ToolExecution(_) => "tool_execution",
+            AuditEvent::ApiRequest { .. } => "api_request",
+        }
+    }
+}
+
+/// One line of the audit log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Position in the log, from 0
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    /// Hash of the previous entry, or all zeros for the first one
+    pub prev_hash: String,
+    pub event: AuditEvent,
+    /// SHA-256 over the fields above
+    pub hash: String,
+}
+
+/// The fields of an entry that its hash covers, in a fixed order
+#[derive(Serialize)]
+struct HashedFields<'a> {
+    seq: u64,
+    timestamp: &'a DateTime<Utc>,
+    prev_hash: &'a str,
+    event: &'a AuditEvent,
+}
+
+impl AuditEntry {
+    fn new(seq: u64, prev_hash: String, event: AuditEvent) -> Result<Self> {
+        let timestamp = Utc::now();
+        let hash = Self::compute_hash(seq, &timestamp, &prev_hash, &event)?;
+        Ok(Self {
+            seq,
+            timestamp,
+            prev_hash,
+            event,
+            hash,
+        })
+    }
+
+    fn compute_hash(
+        seq: u64,
+        timestamp: &DateTime<Utc>,
+        prev_hash: &str,
+        event: &AuditEvent,
+    ) -> Result<String> {
+        let fields = serde_json::to_vec(&HashedFields {
+            seq,
+            timestamp,
+            prev_hash,
+            event,
+        })?;
+        Ok(format!("{:x}", Sha256::digest(&fields)))
+    }
+
+    /// Check that the stored hash matches the entry's contents
+    fn is_intact(&self) -> bool {
+        Self::compute_hash(self.seq, &self.timestamp, &self.prev_hash, &self.event)
+            .is_ok_and(|hash| hash == self.hash)
+    }
+}
+
+/// Criteria for reading back audit entries; unset fields match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditFilter {
+    /// `path_access`, `tool_execution` or `api_request`
+    pub kind: Option<String>,
+    /// Only path decisions that were allowed (`true`) or denied (`false`)
+    pub allowed: Option<bool>,
+    /// Substring of the requested or canonical path, or of a tool's name
+    pub contains: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// Maximum number of entries, keeping the most recent
+    pub limit: Option<usize>,
+}
+
+impl AuditFilter {
+    fn matches(&self, entry: &AuditEntry) -> bool {
+        if self
+            .kind
+            .as_deref()
+            .is_some_and(|kind| kind != entry.event.kind())
+        {
+            return false;
+        }
+        if self.since.is_some_and(|since| entry.timestamp < since)
+            || self.until.is_some_and(|until| entry.timestamp > until)
+        {
+            return false;
+        }
+        if let Some(wanted) = self.allowed {
+            match &entry.event {
+                AuditEvent::PathAccess { allowed, .. } if *allowed == wanted => {}
+                _ => return false,
+            }
+        }
+        if let Some(needle) = &self.contains {
+            let found = match &entry.event {
+                AuditEvent::PathAccess {
+                    path,
+                    canonical_path,
+                    ..
+                } => {
+                    path.contains(needle.as_str())
+                        || canonical_path
+                            .as_deref()
+                            .is_some_and(|path| path.contains(needle.as_str()))
+                }
+                AuditEvent::ToolExecution(record) => record.tool_name.contains(needle.as_str()),
+                AuditEvent::ApiRequest { model, .. } => model.contains(needle.as_str()),
+            };
+            if !found {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Result of checking the whole hash chain
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditVerification {
+    /// Entries read before the first problem, or in total when there is none
+    pub entries_checked: u64,
+    pub valid: bool,
+    /// Line (from 1) of the first entry that does not fit the chain
+    pub first_invalid_line: Option<usize>,
+    pub problem: Option<String>,
+}
+
+/// Append-only, hash-chained JSON Lines audit log. The app and MCP server
+/// mode append to the same file from separate processes, so every append
+/// takes an exclusive lock on the file and continues from the entry last
+/// written to it rather than from state kept in memory.
+#[derive(Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+    /// Serializes appends within this process
+    append_lock: Mutex<()>,
+}
+
+impl AuditLog {
+    /// Continue the log at `path`, creating it on the first entry
+    pub fn open(path: PathBuf) -> Self {
+        Self {
+            path,
+            append_lock: Mutex::new(()),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append an event. Failures are reported on stderr rather than failing
+    /// the operation being audited.
+    pub fn record(&self, event: AuditEvent) {
+        if let Err(e) = self.append(event) {
+            eprintln!("Failed to write audit log {}: {}", self.path.display(), e);
+        }
+    }
+
+    fn append(&self, event: AuditEvent) -> Result<()> {
+        let _guard = self
+            .append_lock
+            .lock()
+            .map_err(|_| anyhow!("Audit log lock poisoned"))?;
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&self.path)?;
+        // Released when the file is closed
+        file.lock()?;
+
+        let (seq, prev_hash) = match Self::read_last_entry(&mut file) {
+            Ok(Some(last)) => (last.seq + 1, last.hash),
+            Ok(None) => (0, GENESIS_HASH.to_string()),
+            Err(e) => {
+                // New entries start a fresh chain, which verify() reports as a break
+                eprintln!(
+                    "Failed to read the last audit entry in {}: {}",
+                    self.path.display(),
+                    e
+                );
+                (0, GENESIS_HASH.to_string())
+            }
+        };
+        let entry = AuditEntry::new(seq, prev_hash, event)?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+        Ok(())
+    }
+
+    /// Entries matching the filter, oldest first
+    pub fn query(&self, filter: &AuditFilter) -> Result<Vec<AuditEntry>> {
+        let mut entries: Vec<AuditEntry> = self
+            .read_entries()?
+            .into_iter()
+            .filter(|entry| filter.matches(entry))
+            .collect();
+
+        let limit = filter.limit.unwrap_or(DEFAULT_QUERY_LIMIT);
+        if entries.len() > limit {
+            entries.drain(..entries.len() - limit);
+        }
+        Ok(entries)
+    }
+
+    /// Walk the chain from the start and report the first entry that was
+    /// modified, removed or moved
+    pub fn verify(&self) -> Result<AuditVerification> {
+        let file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(AuditVerification {
+                    entries_checked: 0,
+                    valid: true,
+                    first_invalid_line: None,
+                    problem: None,
+                })
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut expected_seq = 0u64;
+        let mut expected_prev = GENESIS_HASH.to_string();
+        for (index, line) in std::io::BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            let invalid = |problem: String| AuditVerification {
+                entries_checked: expected_seq,
+                valid: false,
+                first_invalid_line: Some(index + 1),
+                problem: Some(problem),
+            };
+
+            let entry: AuditEntry = match serde_json::from_str(&line) {
+                Ok(entry) => entry,
+                Err(e) => return Ok(invalid(format!("Entry cannot be parsed: {}", e))),
+            };
+            if entry.seq != expected_seq {
+                return Ok(invalid(format!(
+                    "Expected entry {} but found entry {}",
+                    expected_seq, entry.seq
+                )));
+            }
+            if entry.prev_hash != expected_prev {
+                return Ok(invalid(format!(
+                    "Entry {} does not follow the entry before it",
+                    entry.seq
+                )));
+            }
+            if !entry.is_intact() {
+                return Ok(invalid(format!(
+                    "Entry {} was modified after it was written",
+                    entry.seq
+                )));
+            }
+
+            expected_seq += 1;
+            expected_prev = entry.hash;
+        }
+
+        Ok(AuditVerification {
+            entries_checked: expected_seq,
+            valid: true,
+            first_invalid_line: None,
+            problem: None,
+        })
+    }
+
+    fn read_entries(&self) -> Result<Vec<AuditEntry>> {
+        let file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut entries = Vec::new();
+        for line in std::io::BufReader::new(file).lines() {
+            let line = line?;
+            match serde_json::from_str(&line) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => eprintln!("Skipping unreadable audit entry: {}", e),
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Parse the last non-empty line, reading backwards from the end so the
+    /// cost of an append does not grow with the log
+    fn read_last_entry(file: &mut std::fs::File) -> Result<Option<AuditEntry>> {
+        let mut end = file.seek(SeekFrom::End(0))?;
+        let mut tail: Vec<u8> = Vec::new();
+        let line = loop {
+            let content_end = tail
+                .iter()
+                .rposition(|byte| !byte.is_ascii_whitespace())
+                .map_or(0, |pos| pos + 1);
+            let content = &tail[..content_end];
+            if let Some(newline) = content.iter().rposition(|&byte| byte == b'\n') {
+                break &content[newline + 1..];
+            }
+            if end == 0 {
+                break content;
+            }
+
+            let start = end.saturating_sub(TAIL_CHUNK_SIZE);
+            let mut chunk = vec![0; (end - start) as usize];
+            file.seek(SeekFrom::Start(start))?;
+            file.read_exact(&mut chunk)?;
+            chunk.extend_from_slice(&tail);
+            tail = chunk;
+            end = start;
+        };
+
+        if line.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_slice(line)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::claude::test_support::TempDir;
+
+    fn temp_log() -> (TempDir, AuditLog) {
+        let dir = TempDir::new();
+        let path = dir.join("audit_log.jsonl");
+        (dir, AuditLog::open(path))
+    }
+
+    fn path_event(path: &str, allowed: bool) -> AuditEvent {
+        AuditEvent::PathAccess {
+            operation: FileOperation::Read,
+            path: path.to_string(),
+            canonical_path: Some(path.to_string()),
+            allowed,
+            reason: (!allowed).then(|| "not in the whitelist".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_chain_continues_across_reopen_and_verifies() {
+        let (_dir, log) = temp_log();
+        log.record(path_event("/repo/src/main.rs", true));
+        log.record(AuditEvent::ApiRequest {
+            model: "claude-sonnet-4".to_string(),
+            payload_hash: "abc".to_string(),
+            status: Some(200),
+            input_tokens: Some(120),
+            output_tokens: Some(30),
+            error: None,
+        });
+
+        let reopened = AuditLog::open(log.path().to_path_buf());
+        reopened.record(path_event("/etc/passwd", false));
+
+        let verification = reopened.verify().unwrap();
+        assert!(verification.valid, "{:?}", verification.problem);
+        assert_eq!(verification.entries_checked, 3);
+
+        let denied = reopened
+            .query(&AuditFilter {
+                allowed: Some(false),
+                ..AuditFilter::default()
+            })
+            .unwrap();
+        assert_eq!(denied.len(), 1);
+        assert_eq!(denied[0].seq, 2);
+
+        let api = reopened
+            .query(&AuditFilter {
+                kind: Some("api_request".to_string()),
+                ..AuditFilter::default()
+            })
+            .unwrap();
+        assert_eq!(api.len(), 1);
+    }
+
+    #[test]
+    fn test_logs_sharing_a_file_keep_one_chain() {
+        // The app and MCP server mode each open their own AuditLog on the file
+        let (_dir, app_log) = temp_log();
+        let server_log = AuditLog::open(app_log.path().to_path_buf());
+        // Entries longer than a tail chunk are found by reading further back
+        let long_path = format!("/repo/{}", "x".repeat(TAIL_CHUNK_SIZE as usize * 2));
+
+        std::thread::scope(|scope| {
+            for log in [&app_log, &server_log] {
+                let long_path = &long_path;
+                scope.spawn(move || {
+                    for i in 0..25 {
+                        log.record(path_event(&format!("/repo/file{}.rs", i), true));
+                        log.record(path_event(long_path, true));
+                    }
+                });
+            }
+        });
+
+        let verification = app_log.verify().unwrap();
+        assert!(verification.valid, "{:?}", verification.problem);
+        assert_eq!(verification.entries_checked, 100);
+    }
+
+    #[test]
+    fn test_verify_detects_modified_and_removed_entries() {
+        let (_dir, log) = temp_log();
+        for i in 0..3 {
+            log.record(path_event(&format!("/repo/file{}.rs", i), true));
+        }
+        let original = std::fs::read_to_string(log.path()).unwrap();
+
+        // Rewriting a path in place keeps the line parseable but breaks its hash
+        std::fs::write(
+            log.path(),
+            original.replace("/repo/file1.rs", "/repo/other.rs"),
+        )
+        .unwrap();
+        let verification = log.verify().unwrap();
+        assert!(!verification.valid);
+        assert_eq!(verification.first_invalid_line, Some(2));
+        assert!(verification.problem.unwrap().contains("modified"));
+
+        // Dropping an entry leaves a gap in the chain
+        let without_second: Vec<&str> = original
+            .lines()
+            .enumerate()
+            .filter(|(i, _)| *i != 1)
+            .map(|(_, line)| line)
+            .collect();
+        std::fs::write(log.path(), without_second.join("\n")).unwrap();
+        let verification = log.verify().unwrap();
+        assert!(!verification.valid);
+        assert_eq!(verification.entries_checked, 1);
+        assert!(verification.problem.unwrap().contains("Expected entry 1"));
+    }
+}
@@ -1,12 +1,18 @@
 use crate::claude::{
+    audit::AuditEvent,
+    constants::file_limits::TRASH_DIR_NAME,
     error::{ClaudeError, ClaudeResult, ErrorContext, ErrorHandler},
     message_processor::MessageProcessor,
-    tools::{AgentTool, ToolExecutionContext, ToolExecutionEngine, ToolRegistry},
+    secrets::SecretReport,
+    tools::{
+        chain::ExecutionConfig, AgentTool, ToolExecutionContext, ToolExecutionEngine, ToolRegistry,
+    },
     types::*,
     whitelist::WhitelistConfig,
     ClaudeConfig, Conversation,
 };
 use reqwest::Client;
+use sha2::{Digest, Sha256};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
@@ -21,6 +27,8 @@ pub struct ClaudeClient {
     error_handler: ErrorHandler,
     last_request: Mutex<Option<Instant>>,
     whitelist: Option<Arc<RwLock<WhitelistConfig>>>,
+    /// Secrets found in content sent while handling the current message
+    secret_report: Mutex<SecretReport>,
 }
 
 impl ClaudeClient {
@@ -34,24 +42,12 @@ impl ClaudeClient {
             .build()
             .map_err(ClaudeError::HttpError)?;
 
+        // The registry describes the tools to Claude; the engine runs them
         let mut tool_registry = ToolRegistry::new();
-
-        // Register default tools
-        tool_registry.register(crate::claude::tools::ReadFileTool::new());
-        tool_registry.register(crate::claude::tools::WriteFileTool::new());
-        tool_registry.register(crate::claude::tools::ListDirectoryTool::new());
-
-        // Initialize enhanced tool execution engine
-        let mut tool_execution_engine = ToolExecutionEngine::new();
-
-        // Register tools with both registry and execution engine
-        let read_tool = Arc::new(crate::claude::tools::ReadFileTool::new());
-        let write_tool = Arc::new(crate::claude::tools::WriteFileTool::new());
-        let list_tool = Arc::new(crate::claude::tools::ListDirectoryTool::new());
-
-        tool_execution_engine.register_tool(read_tool.clone());
-        tool_execution_engine.register_tool(write_tool.clone());
-        tool_execution_engine.register_tool(list_tool.clone());
+        for tool in Self::default_tools(&config) {
+            tool_registry.register_boxed(tool);
+        }
+        let tool_execution_engine = Self::build_tool_engine(&config, None);
 
         Ok(Self {
             config,
@@ -62,18 +58,123 @@ impl ClaudeClient {
             error_handler: ErrorHandler::new(),
             last_request: Mutex::new(None),
             whitelist: None,
+            secret_report: Mutex::new(SecretReport::default()),
         })
     }
 
+    /// Tools a client offers Claude: the built-in tools, then tools imported
+    /// from MCP servers, then plugins whose names are still free
+    fn default_tools(config: &ClaudeConfig) -> Vec<Box<dyn AgentTool>> {
+        use crate::claude::tools::{
+            CargoCheckTool, CodeOutlineTool, CopyFileTool, CreateDirectoryTool, DeleteFileTool,
+            FileInfoTool, FindSymbolTool, GitBlameTool, GitCommitTool, GitDiffTool, GitLogTool,
+            GitShowTool, GitStatusTool, ListDirectoryTool, MoveFileTool, ReadFileTool, SymbolIndex,
+            WriteFileTool,
+        };
+
+        // Deleted files go to an app-managed trash when a data dir is known
+        let delete_tool = match &config.data_dir {
+            Some(data_dir) => DeleteFileTool::new().with_trash_dir(data_dir.join(TRASH_DIR_NAME)),
+            None => DeleteFileTool::new(),
+        };
+
+        let symbol_index = config
+            .symbol_index
+            .clone()
+            .unwrap_or_else(|| Arc::new(RwLock::new(SymbolIndex::new(None))));
+
+        let mut tools: Vec<Box<dyn AgentTool>> = vec![
+            Box::new(ReadFileTool::new()),
+            Box::new(WriteFileTool::new()),
+            Box::new(ListDirectoryTool::new()),
+            Box::new(MoveFileTool::new()),
+            Box::new(CopyFileTool::new()),
+            Box::new(delete_tool),
+            Box::new(CreateDirectoryTool::new()),
+            Box::new(FileInfoTool::new()),
+            Box::new(GitStatusTool::new()),
+            Box::new(GitDiffTool::new()),
+            Box::new(GitLogTool::new()),
+            Box::new(GitShowTool::new()),
+            Box::new(GitBlameTool::new()),
+        ];
+        if config.enable_git_commit {
+            tools.push(Box::new(GitCommitTool::new()));
+        }
+        tools.push(Box::new(CargoCheckTool::new()));
+        tools.push(Box::new(CodeOutlineTool::new()));
+        tools.push(Box::new(FindSymbolTool::new(symbol_index)));
+
+        // Tools imported from connected MCP servers
+        if let Some(mcp) = &config.mcp {
+            tools.extend(mcp.tools());
+        }
+        // Plugins cannot replace built-in or MCP tools of the same name
+        for plugin in &config.plugins {
+            if tools.iter().any(|tool| tool.name() == plugin.name()) {
+                eprintln!(
+                    "Skipping plugin '{}': a tool with that name already exists",
+                    plugin.name()
+                );
+            } else {
+                tools.push(Box::new(plugin.clone()));
+            }
+        }
+        tools
+    }
+
+    /// Execution engine with the tools a client offers Claude, bound to the
+    /// whitelist so every path goes through its rules. Also used to run tools
+    /// without a conversation (e.g. workflows).
+    pub fn build_tool_engine(
+        config: &ClaudeConfig,
+        whitelist: Option<&Arc<RwLock<WhitelistConfig>>>,
+    ) -> ToolExecutionEngine {
+        let mut execution_config = ExecutionConfig::default();
+        execution_config
+            .tool_timeouts
+            .extend(config.tool_timeouts.clone());
+        let mut tool_execution_engine = ToolExecutionEngine::new().with_config(execution_config);
+        if let Some(approval) = &config.approval {
+            tool_execution_engine = tool_execution_engine.with_approval(approval.clone());
+        }
+        if let Some(checkpoints) = &config.checkpoints {
+            tool_execution_engine = tool_execution_engine.with_checkpoints(checkpoints.clone());
+        }
+        if let Some(breakers) = &config.breakers {
+            tool_execution_engine = tool_execution_engine.with_breakers(breakers.clone());
+        }
+        if let Some(history) = &config.history {
+            tool_execution_engine =
+                tool_execution_engine.with_history(history.clone(), config.session_id.clone());
+        }
+        if let Some(feedback) = &config.feedback {
+            tool_execution_engine = tool_execution_engine.with_learned_feedback(feedback.clone());
+        }
+        if let Some(audit_log) = &config.audit {
+            tool_execution_engine = tool_execution_engine.with_audit_log(audit_log.clone());
+        }
+
+        for mut tool in Self::default_tools(config) {
+            if let Some(whitelist) = whitelist {
+                tool.set_whitelist(whitelist.clone());
+            }
+            tool_execution_engine.register_tool(Arc::from(tool));
+        }
+
+        tool_execution_engine
+    }
+
     #[allow(dead_code)]
     pub fn register_tool<T: AgentTool + 'static>(&mut self, tool: T) {
         self.tool_registry.register(tool);
     }
 
-    /// Set the whitelist configuration for all tools
-    #[allow(dead_code)]
+    /// Set the whitelist configuration for all tools, including the ones
+    /// the execution engine runs
     pub fn set_whitelist(&mut self, whitelist: Arc<RwLock<WhitelistConfig>>) {
         self.tool_registry.set_whitelist(whitelist.clone());
+        self.tool_execution_engine = Self::build_tool_engine(&self.config, Some(&whitelist));
         self.message_processor.set_whitelist(whitelist.clone());
         self.whitelist = Some(whitelist);
     }
@@ -82,6 +183,78 @@ impl ClaudeClient {
         &self,
         conversation: &Conversation,
         message: String,
+    ) -> ClaudeResult<String> {
+        let message = self.screen_user_message(message)?;
+        self.send_screened_message(conversation, message).await
+    }
+
+    /// Secrets found since the last call, cleared for the next message
+    pub fn take_secret_report(&self) -> SecretReport {
+        std::mem::take(&mut *self.secret_report.lock().unwrap())
+    }
+
+    /// Apply the secret scanner to a message from the user before it is sent
+    /// or kept in the conversation
+    fn screen_user_message(&self, message: String) -> ClaudeResult<String> {
+        let Some(scanner) = &self.config.secrets else {
+            return Ok(message);
+        };
+
+        let outcome = scanner.scan("user_message", &message);
+        if outcome.findings.is_empty() {
+            return Ok(message);
+        }
+        let mut report = self.secret_report.lock().unwrap();
+        report.findings.extend(outcome.findings);
+        if outcome.blocked {
+            return Err(ClaudeError::ValidationError {
+                field: "message".to_string(),
+                message: format!(
+                    "Message not sent: it appears to contain secrets ({})",
+                    report.summary()
+                ),
+                context: None,
+            });
+        }
+        Ok(outcome.content)
+    }
+
+    /// Apply the secret scanner to a tool result, returning the content to
+    /// send and whether it was withheld
+    fn screen_tool_result(&self, tool_name: &str, content: String) -> (String, bool) {
+        let Some(scanner) = &self.config.secrets else {
+            return (content, false);
+        };
+
+        let outcome = scanner.scan(&format!("tool_result:{}", tool_name), &content);
+        if outcome.findings.is_empty() {
+            return (content, false);
+        }
+        let withheld = outcome.blocked.then(|| {
+            let rules = SecretReport {
+                findings: outcome.findings.clone(),
+            }
+            .summary();
+            format!(
+                "Tool result withheld: it appears to contain secrets ({}). Ask the user to share what is needed without them.",
+                rules
+            )
+        });
+        self.secret_report
+            .lock()
+            .unwrap()
+            .findings
+            .extend(outcome.findings);
+        match withheld {
+            Some(message) => (message, true),
+            None => (outcome.content, false),
+        }
+    }
+
+    async fn send_screened_message(
+        &self,
+        conversation: &Conversation,
+        message: String,
     ) -> ClaudeResult<String> {
         // Process the user message using the message processor
         let user_message = self.message_processor.process_user_message(&message)?;
@@ -116,6 +289,40 @@ impl ClaudeClient {
     }
 
     async fn make_api_call(&self, request: ClaudeRequest) -> ClaudeResult<ClaudeResponse> {
+        let Some(audit_log) = &self.config.audit else {
+            return self.send_api_request(request).await;
+        };
+
+        let model = request.model.clone();
+        let payload_hash = serde_json::to_vec(&request)
+            .map(|payload| format!("{:x}", Sha256::digest(&payload)))
+            .unwrap_or_default();
+        let result = self.send_api_request(request).await;
+
+        let (input_tokens, output_tokens) = match &result {
+            Ok(response) => (
+                Some(response.usage.input_tokens),
+                Some(response.usage.output_tokens),
+            ),
+            Err(_) => (None, None),
+        };
+        let status = match &result {
+            Err(ClaudeError::ApiError { status, .. }) => Some(*status),
+            _ => None,
+        };
+        audit_log.record(AuditEvent::ApiRequest {
+            model,
+            payload_hash,
+            status,
+            input_tokens,
+            output_tokens,
+            error: result.as_ref().err().map(|e| e.to_string()),
+        });
+
+        result
+    }
+
+    async fn send_api_request(&self, request: ClaudeRequest) -> ClaudeResult<ClaudeResponse> {
         // Rate limiting: ensure at least 1 second between requests
         let sleep_duration = {
             let last_request = self.last_request.lock().unwrap();
@@ -206,8 +413,8 @@ impl ClaudeClient {
                     .clone()
                     .unwrap_or_else(|| Arc::new(RwLock::new(WhitelistConfig::default())));
 
+                // Attempt timeouts come from the engine's per-tool configuration
                 let context = ToolExecutionContext::new(name.clone(), input.clone(), whitelist)
-                    .with_timeout(Duration::from_secs(30))
                     .with_max_retries(3);
 
                 // Execute tool using enhanced execution engine
@@ -221,14 +428,15 @@ impl ClaudeClient {
                 {
                     Ok(execution_result) => {
                         // Create ToolResult content block with the tool execution result
-                        let tool_result_content = execution_result.into_content_block();
-
-                        tool_result_blocks.push(ContentBlock::ToolResult {
-                            tool_use_id: id.clone(),
-                            content: tool_result_content,
-                            is_error: Some(execution_result.is_error()),
-                            metadata: None,
-                        });
+                        let (tool_result_content, withheld) =
+                            self.screen_tool_result(name, execution_result.into_content_block());
+
+                        tool_result_blocks.push(ContentBlock::tool_result_with_metadata(
+                            id.clone(),
+                            tool_result_content,
+                            Some(execution_result.is_error() || withheld),
+                            execution_result.metadata,
+                        ));
                     }
                     Err(e) => {
                         // Create error tool result
@@ -334,9 +542,14 @@ impl ClaudeClient {
         conversation: &mut Conversation,
         user_message: String,
     ) -> ClaudeResult<String> {
+        // Secrets are screened out before the message is kept, since the
+        // conversation is sent again with every later message
+        let user_message = self.screen_user_message(user_message)?;
         conversation.add_user_message(user_message.clone());
 
-        let response = self.send_message(conversation, user_message).await?;
+        let response = self
+            .send_screened_message(conversation, user_message)
+            .await?;
 
         conversation.add_assistant_message(response.clone());
 
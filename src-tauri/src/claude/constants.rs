@@ -235,6 +235,394 @@ pub mod file_limits {
     /// Security limit for files accessible through the whitelist system
     /// Prevents reading of large files that could contain sensitive data
     pub const DEFAULT_WHITELIST_MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+
+    /// Directory name (inside the app data dir) where deleted files are moved
+    /// Deletions from tools are never permanent so users can recover mistakes
+    pub const TRASH_DIR_NAME: &str = "trash";
+
+    /// Metadata file written next to each trashed item recording where it came from
+    pub const TRASH_ORIGIN_FILE: &str = ".origin.json";
+
+    /// Number of leading bytes inspected when deciding whether a file is binary
+    /// Large enough to see past short headers, small enough to stay cheap
+    pub const BINARY_SNIFF_BYTES: usize = 8 * 1024;
+
+    /// Number of leading bytes shown as hex when a binary file is read
+    pub const BINARY_PREVIEW_BYTES: usize = 64;
+}
+
+/// Git tool limits
+#[allow(dead_code)]
+pub mod git_limits {
+    /// Default number of commits returned by git_log
+    pub const DEFAULT_LOG_COUNT: usize = 20;
+
+    /// Maximum number of commits returned by git_log in one call
+    /// Keeps history listings within a reasonable tool result size
+    pub const MAX_LOG_COUNT: usize = 200;
+
+    /// Maximum patch text returned by git_diff and git_show in bytes (100KB)
+    /// Larger patches are truncated; per-file stats are always complete
+    pub const MAX_PATCH_BYTES: usize = 100 * 1024;
+
+    /// Maximum length of a revision or ref argument
+    pub const MAX_REF_LENGTH: usize = 256;
+}
+
+/// Secret detection in content sent to the API
+pub mod secret_scanning {
+    /// Event that carries the secrets found while handling a message
+    pub const SECRET_REPORT_EVENT: &str = "secrets_detected";
+
+    /// Minimum entropy, in bits per character, of a value assigned to a
+    /// key-like name (`password = ...`) before it is treated as a secret
+    pub const GENERIC_SECRET_MIN_ENTROPY: f64 = 3.5;
+
+    /// Values assigned to key-like names, e.g. `password = "..."`; group 1 is the value
+    pub const GENERIC_SECRET_PATTERN: &str = r#"(?i)(?:api[_-]?key|secret|token|passw(?:or)?d|pwd|credentials?)[a-z0-9_\-]*["']?\s*[:=]\s*["']?([^\s"',;]{12,})"#;
+
+    /// Built-in rules as (name, regex); with a capture group only the group is secret
+    pub const BUILTIN_RULES: &[(&str, &str)] = &[
+        (
+            "private_key",
+            r"(?s)-----BEGIN [A-Z ]*PRIVATE KEY-----.*?-----END [A-Z ]*PRIVATE KEY-----",
+        ),
+        ("aws_access_key_id", r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b"),
+        (
+            "aws_secret_access_key",
+            r#"(?i)aws_?secret_?access_?key["']?\s*[:=]\s*["']?([A-Za-z0-9/+=]{40})"#,
+        ),
+        (
+            "github_token",
+            r"\b(?:gh[pousr]_[A-Za-z0-9]{36,}|github_pat_[A-Za-z0-9_]{22,})",
+        ),
+        ("anthropic_api_key", r"\bsk-ant-[A-Za-z0-9_\-]{20,}"),
+        ("openai_api_key", r"\bsk-(?:proj-)?[A-Za-z0-9_\-]{20,}"),
+        ("slack_token", r"\bxox[abposr]-[A-Za-z0-9-]{10,}"),
+        ("stripe_secret_key", r"\b[rs]k_live_[A-Za-z0-9]{20,}"),
+        ("google_api_key", r"\bAIza[0-9A-Za-z_\-]{35}"),
+        (
+            "jwt",
+            r"\beyJ[A-Za-z0-9_-]{8,}\.eyJ[A-Za-z0-9_-]{8,}\.[A-Za-z0-9_-]{8,}",
+        ),
+        (
+            "connection_string_password",
+            r"(?i)\b[a-z][a-z0-9+.\-]*://[^\s:/@]+:([^\s@/]+)@",
+        ),
+    ];
+}
+
+/// Ignore files that keep generated and vendored files away from tools
+pub mod ignore_files {
+    /// Ignore file read only by the agent, for files it should skip that git tracks
+    pub const AGENT_IGNORE_FILE_NAME: &str = ".agentignore";
+
+    /// Ignore files honored in whitelisted directories, lowest precedence first
+    pub const IGNORE_FILE_NAMES: [&str; 3] = [".gitignore", ".ignore", AGENT_IGNORE_FILE_NAME];
+}
+
+/// Code outline and symbol index limits
+#[allow(dead_code)]
+pub mod code_index {
+    /// File name of the persisted symbol index inside the app data dir
+    pub const INDEX_FILE_NAME: &str = "symbol_index.json";
+
+    /// Maximum source file size that is parsed, in bytes (1MB)
+    /// Larger files are usually generated or vendored and slow to parse
+    pub const MAX_SOURCE_FILE_BYTES: u64 = 1024 * 1024;
+
+    /// Maximum number of files indexed per directory scan
+    /// Bounds the time and memory spent indexing very large trees
+    pub const MAX_INDEXED_FILES: usize = 20_000;
+
+    /// Directories that are never descended into while indexing
+    pub const SKIPPED_DIRECTORIES: &[&str] = &[
+        "target",
+        "node_modules",
+        "dist",
+        "build",
+        "__pycache__",
+        "venv",
+    ];
+
+    /// Default number of results returned by find_symbol
+    pub const DEFAULT_SYMBOL_RESULTS: usize = 50;
+
+    /// Maximum number of results returned by find_symbol
+    pub const MAX_SYMBOL_RESULTS: usize = 500;
+
+    /// Maximum characters kept from a symbol's signature line
+    pub const MAX_SIGNATURE_CHARS: usize = 200;
+}
+
+/// Cargo diagnostics tool limits
+#[allow(dead_code)]
+pub mod cargo_limits {
+    /// Default number of diagnostics returned by cargo_check
+    /// Errors are listed before warnings so the most relevant ones always fit
+    pub const DEFAULT_MAX_DIAGNOSTICS: usize = 20;
+
+    /// Upper bound for the caller-provided diagnostic limit
+    pub const MAX_DIAGNOSTICS: usize = 100;
+
+    /// Maximum characters kept from a single diagnostic message
+    pub const MAX_MESSAGE_CHARS: usize = 2000;
+
+    /// Maximum bytes of failing test output returned by `cargo test` (20KB)
+    pub const MAX_TEST_OUTPUT_BYTES: usize = 20 * 1024;
+
+    /// Maximum time a cargo invocation may run in seconds (10 minutes)
+    /// Cold builds of large workspaces can be slow; hung builds are killed
+    pub const COMMAND_TIMEOUT_SECS: u64 = 600;
+}
+
+/// Human-in-the-loop approval limits
+#[allow(dead_code)]
+pub mod approval {
+    /// File the approval policy is persisted to in the app config directory
+    pub const POLICY_FILE_NAME: &str = "approval_policy.json";
+
+    /// How long a pending approval waits for the user before it is denied (5 minutes)
+    pub const APPROVAL_TIMEOUT_SECS: u64 = 300;
+
+    /// Maximum size of an existing file that is read to build a diff preview (1MB)
+    pub const MAX_DIFF_SOURCE_BYTES: u64 = 1024 * 1024;
+
+    /// Maximum bytes of unified diff included in an approval request (50KB)
+    pub const MAX_DIFF_PREVIEW_BYTES: usize = 50 * 1024;
+}
+
+/// Workspace checkpoint storage limits
+#[allow(dead_code)]
+pub mod checkpoints {
+    /// Directory in the app data dir that holds checkpoints
+    pub const CHECKPOINT_DIR_NAME: &str = "checkpoints";
+
+    /// Manifest written inside each checkpoint directory
+    pub const MANIFEST_FILE_NAME: &str = "checkpoint.json";
+
+    /// Total size of stored snapshots before the oldest checkpoints are pruned (256MB)
+    pub const MAX_STORAGE_BYTES: u64 = 256 * 1024 * 1024;
+
+    /// Maximum number of checkpoints kept regardless of size
+    pub const MAX_CHECKPOINTS: usize = 100;
+
+    /// Files larger than this are recorded but their content is not kept (10MB)
+    pub const MAX_SNAPSHOT_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+    /// Maximum files captured from a single directory operand
+    pub const MAX_FILES_PER_DIRECTORY: usize = 5000;
+
+    /// Maximum bytes of unified diff returned per file
+    pub const MAX_DIFF_BYTES: usize = 100 * 1024;
+
+    /// Maximum characters of the user message kept as a checkpoint label
+    pub const MAX_LABEL_CHARS: usize = 80;
+}
+
+/// Tool execution timeouts
+#[allow(dead_code)]
+pub mod tool_timeouts {
+    use super::Duration;
+
+    /// Time a tool attempt may run when no per-tool timeout is configured
+    pub const DEFAULT_TOOL_TIMEOUT_SECS: u64 = 30;
+
+    /// Default timeouts for tools that routinely take longer than the default
+    /// cargo_check runs its own 10 minute limit, so the engine allows slightly more
+    pub const TOOL_TIMEOUT_SECS: &[(&str, u64)] = &[
+        ("cargo_check", 660),
+        ("find_symbol", 120),
+        ("git_log", 60),
+        ("git_diff", 60),
+        ("git_show", 60),
+        ("git_blame", 60),
+        ("copy_file", 120),
+        ("move_file", 120),
+        ("delete_file", 120),
+    ];
+
+    /// Upper bound for timeouts raised by recovery (30 minutes)
+    pub const MAX_TOOL_TIMEOUT_SECS: u64 = 30 * 60;
+
+    pub const fn max_tool_timeout() -> Duration {
+        Duration::from_secs(MAX_TOOL_TIMEOUT_SECS)
+    }
+}
+
+/// Per-tool circuit breakers and concurrency limits in the execution engine
+#[allow(dead_code)]
+pub mod tool_breakers {
+    use super::Duration;
+
+    /// Consecutive failed calls after which a tool's breaker opens
+    pub const FAILURE_THRESHOLD: u32 = 3;
+
+    /// How long an open breaker refuses calls before letting a probe through
+    pub const OPEN_SECS: u64 = 60;
+
+    /// Calls to one tool that may run at the same time unless configured otherwise
+    pub const DEFAULT_MAX_CONCURRENT_CALLS: usize = 4;
+
+    /// Tools that must not run alongside themselves, or only a few at a time
+    pub const TOOL_CONCURRENCY_LIMITS: &[(&str, usize)] =
+        &[("cargo_check", 1), ("git_commit", 1), ("find_symbol", 2)];
+
+    pub const fn open_duration() -> Duration {
+        Duration::from_secs(OPEN_SECS)
+    }
+}
+
+/// On-disk history of tool executions
+#[allow(dead_code)]
+pub mod execution_history {
+    /// JSON Lines file in the app data directory that executions are appended to
+    pub const HISTORY_FILE_NAME: &str = "tool_history.jsonl";
+
+    /// Executions kept on disk; older entries are dropped when the file is compacted
+    pub const MAX_HISTORY_RECORDS: usize = 10_000;
+
+    /// String inputs longer than this are shortened before they are stored
+    pub const MAX_INPUT_VALUE_CHARS: usize = 200;
+
+    /// Input fields whose values are never stored, only their length
+    pub const REDACTED_INPUT_KEYS: &[&str] = &["content", "password", "secret", "token", "api_key"];
+}
+
+/// Learning from tool executions and feeding it back
+#[allow(dead_code)]
+pub mod feedback_learning {
+    /// File in the app data directory that learned tool patterns are saved to
+    pub const LEARNING_FILE_NAME: &str = "tool_learning.json";
+
+    /// Event that carries feedback hints to the frontend
+    pub const HINT_EVENT: &str = "tool-feedback-hint";
+
+    /// Durations kept per tool for latency percentiles
+    pub const LATENCY_SAMPLE_SIZE: usize = 200;
+
+    /// Calls needed before a tool's latency is compared against its percentiles
+    pub const MIN_LATENCY_SAMPLES: usize = 20;
+
+    /// Failures of the same kind before they are pointed out
+    pub const REPEATED_FAILURE_THRESHOLD: u32 = 3;
+
+    /// Distinct paths whose failures are tracked per tool
+    pub const MAX_TRACKED_TARGETS: usize = 100;
+}
+
+/// Model Context Protocol client limits
+#[allow(dead_code)]
+pub mod mcp {
+    use super::Duration;
+
+    /// Protocol revision sent in the initialize handshake
+    pub const PROTOCOL_VERSION: &str = "2025-03-26";
+
+    /// Earlier revisions still accepted from servers
+    pub const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &["2025-03-26", "2024-11-05"];
+
+    /// Prefix of tool names imported from MCP servers: mcp__<server>__<tool>
+    pub const TOOL_NAME_PREFIX: &str = "mcp";
+
+    /// Separator between the parts of a namespaced tool name
+    pub const TOOL_NAME_SEPARATOR: &str = "__";
+
+    /// Longest tool name the API accepts
+    pub const MAX_TOOL_NAME_CHARS: usize = 64;
+
+    /// Time a server may take to answer a request before it is abandoned
+    pub const REQUEST_TIMEOUT_SECS: u64 = 60;
+
+    /// Time a server may take to start and complete the initialize handshake
+    pub const CONNECT_TIMEOUT_SECS: u64 = 30;
+
+    /// Maximum pages followed when listing tools or resources
+    pub const MAX_LIST_PAGES: usize = 20;
+
+    /// Maximum bytes of resource or tool output returned to Claude (100KB)
+    pub const MAX_OUTPUT_BYTES: usize = 100 * 1024;
+
+    /// Command line flag that runs the app as an MCP server on stdio
+    pub const SERVER_FLAG: &str = "--mcp-server";
+
+    /// Audit log of calls made by MCP clients, in the app data directory
+    pub const AUDIT_LOG_FILE_NAME: &str = "mcp_audit.jsonl";
+
+    /// String arguments longer than this are shortened in the audit log
+    pub const MAX_AUDIT_VALUE_CHARS: usize = 200;
+
+    pub const fn request_timeout() -> Duration {
+        Duration::from_secs(REQUEST_TIMEOUT_SECS)
+    }
+
+    pub const fn connect_timeout() -> Duration {
+        Duration::from_secs(CONNECT_TIMEOUT_SECS)
+    }
+}
+
+/// WebAssembly plugin limits
+#[allow(dead_code)]
+pub mod plugins {
+    /// Directory in the app data dir that plugin modules are loaded from
+    pub const PLUGIN_DIR_NAME: &str = "plugins";
+
+    /// Fuel a plugin call may burn when its manifest sets none
+    /// Roughly one unit per WebAssembly instruction
+    pub const DEFAULT_FUEL: u64 = 1_000_000_000;
+
+    /// Upper bound for fuel granted by a manifest
+    pub const MAX_FUEL: u64 = 100_000_000_000;
+
+    /// Fuel for the `describe` call made while loading a plugin
+    pub const DESCRIBE_FUEL: u64 = 10_000_000;
+
+    /// Linear memory a plugin may use when its manifest sets none (64MB)
+    pub const DEFAULT_MAX_MEMORY_MB: u64 = 64;
+
+    /// Upper bound for memory granted by a manifest (1GB)
+    pub const MAX_MEMORY_MB: u64 = 1024;
+
+    /// Largest message exchanged with a plugin in either direction (4MB)
+    pub const MAX_MESSAGE_BYTES: usize = 4 * 1024 * 1024;
+}
+
+/// Declarative tool workflows
+#[allow(dead_code)]
+pub mod workflows {
+    /// Directory under `config/` that workflow files are loaded from
+    pub const WORKFLOW_DIR_NAME: &str = "workflows";
+
+    /// Event emitted with the chain status while a workflow runs
+    pub const PROGRESS_EVENT: &str = "workflow-progress";
+
+    /// Upper bound for the retries a workflow step may ask for
+    pub const MAX_STEP_RETRIES: u32 = 10;
+}
+
+/// Resolving file paths beneath an open handle of a whitelisted directory
+#[allow(dead_code)]
+pub mod secure_fs {
+    /// Symlinks followed while resolving one path before giving up,
+    /// matching the kernel's own limit
+    pub const MAX_SYMLINK_FOLLOWS: u32 = 40;
+}
+
+/// Audit log of file access, tool executions and API requests
+#[allow(dead_code)]
+pub mod audit {
+    /// File in the app data directory that audit entries are appended to
+    pub const AUDIT_LOG_FILE_NAME: &str = "audit_log.jsonl";
+
+    /// `prev_hash` of the first entry in the log
+    pub const GENESIS_HASH: &str =
+        "0000000000000000000000000000000000000000000000000000000000000000";
+
+    /// Entries returned by a query that sets no limit
+    pub const DEFAULT_QUERY_LIMIT: usize = 500;
+
+    /// Bytes read at a time when looking for the last entry before an append
+    pub const TAIL_CHUNK_SIZE: u64 = 8192;
 }
 
 /// API version and protocol constants
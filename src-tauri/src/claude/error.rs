@@ -441,7 +441,8 @@ pub struct CircuitBreaker {
     state: std::sync::atomic::AtomicU8, // 0 = Closed, 1 = Open, 2 = Half-Open
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum CircuitBreakerState {
     Closed = circuit_breaker::STATE_CLOSED as isize,
     Open = circuit_breaker::STATE_OPEN as isize,
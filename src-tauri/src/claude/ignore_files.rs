@@ -0,0 +1,119 @@
+//! `.gitignore`, `.ignore` and `.agentignore` rules inside whitelisted
+//! directories, so tools skip build output, dependencies and generated files.
+//!
+//! Rules are read from every directory between the whitelisted root and the
+//! path being checked. As in git, a deeper ignore file overrides a shallower
+//! one, and within one directory `.agentignore` overrides `.ignore`, which
+//! overrides `.gitignore`.
+
+use crate::claude::constants::ignore_files::IGNORE_FILE_NAMES;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::{Path, PathBuf};
+
+/// Ignore rules for the paths inside one directory
+#[derive(Debug)]
+pub struct IgnoreMatcher {
+    directory: PathBuf,
+    /// Rules of each directory from the root down to `directory`
+    levels: Vec<Gitignore>,
+}
+
+impl IgnoreMatcher {
+    /// Load the ignore files of `root` and every directory below it down to
+    /// `directory`. A `directory` outside `root` gets no rules.
+    pub fn for_directory(root: &Path, directory: &Path) -> Self {
+        let mut levels = Vec::new();
+        if let Ok(relative) = directory.strip_prefix(root) {
+            let mut current = root.to_path_buf();
+            levels.push(load_level(&current));
+            for component in relative.components() {
+                current.push(component);
+                levels.push(load_level(&current));
+            }
+        }
+
+        Self {
+            directory: directory.to_path_buf(),
+            levels,
+        }
+    }
+
+    /// Check if a path inside the matcher's directory, or below it, is ignored
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if !path.starts_with(&self.directory) || path == self.directory {
+            return false;
+        }
+
+        // The deepest directory with an opinion decides, including `!` re-includes
+        for level in self.levels.iter().rev() {
+            let matched = level.matched_path_or_any_parents(path, is_dir);
+            if matched.is_ignore() {
+                return true;
+            }
+            if matched.is_whitelist() {
+                return false;
+            }
+        }
+        false
+    }
+}
+
+/// Check if a path inside the whitelisted `root` is excluded by an ignore file
+pub fn is_ignored(root: &Path, path: &Path, is_dir: bool) -> bool {
+    if path == root {
+        return false;
+    }
+    let Some(parent) = path.parent() else {
+        return false;
+    };
+    IgnoreMatcher::for_directory(root, parent).is_ignored(path, is_dir)
+}
+
+/// Rules from the ignore files directly inside `directory`
+fn load_level(directory: &Path) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(directory);
+    for name in IGNORE_FILE_NAMES {
+        let file = directory.join(name);
+        if file.is_file() {
+            if let Some(e) = builder.add(&file) {
+                eprintln!("Failed to parse ignore file {}: {}", file.display(), e);
+            }
+        }
+    }
+    builder.build().unwrap_or_else(|e| {
+        eprintln!(
+            "Failed to load ignore files in {}: {}",
+            directory.display(),
+            e
+        );
+        Gitignore::empty()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::claude::test_support::TempDir;
+
+    #[test]
+    fn test_nested_ignore_files_take_precedence() {
+        let root = TempDir::new();
+        std::fs::create_dir_all(root.join("app/generated")).unwrap();
+        std::fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+        std::fs::write(root.join(".agentignore"), "fixtures/\n").unwrap();
+        std::fs::write(root.join("app/.gitignore"), "generated/\n!keep.log\n").unwrap();
+
+        assert!(is_ignored(&root, &root.join("target/debug/app"), false));
+        assert!(is_ignored(&root, &root.join("target"), true));
+        assert!(is_ignored(&root, &root.join("build.log"), false));
+        assert!(is_ignored(&root, &root.join("tests/fixtures"), true));
+        assert!(is_ignored(&root, &root.join("app/generated/api.rs"), false));
+        assert!(!is_ignored(&root, &root.join("app/keep.log"), false));
+        assert!(!is_ignored(&root, &root.join("app/main.rs"), false));
+        assert!(!is_ignored(&root, &root, true));
+
+        let matcher = IgnoreMatcher::for_directory(&root, &root.join("app"));
+        assert!(matcher.is_ignored(&root.join("app/generated"), true));
+        assert!(!matcher.is_ignored(&root.join("src/main.rs"), false));
+    }
+}
@@ -0,0 +1,191 @@
+//! Connection to a single MCP server: the initialize handshake followed by
+//! tool and resource requests.
+
+use super::protocol::{
+    CallToolResult, InitializeResult, ListResourcesResult, ListToolsResult, McpToolInfo,
+    ReadResourceResult, Resource,
+};
+use super::transport::{HttpTransport, McpTransport, StdioTransport};
+use crate::claude::constants::mcp::{
+    connect_timeout, MAX_LIST_PAGES, PROTOCOL_VERSION, SUPPORTED_PROTOCOL_VERSIONS,
+};
+use crate::config::McpServerConfig;
+use anyhow::{anyhow, Result};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+
+#[derive(Debug)]
+pub struct McpClient {
+    server_name: String,
+    transport: Box<dyn McpTransport>,
+    info: InitializeResult,
+}
+
+impl McpClient {
+    /// Launch or reach the configured server and complete the handshake
+    pub async fn connect(config: &McpServerConfig) -> Result<Self> {
+        let transport: Box<dyn McpTransport> = match (&config.command, &config.url) {
+            (Some(command), _) => {
+                Box::new(StdioTransport::spawn(command, &config.args, &config.env)?)
+            }
+            (None, Some(url)) => Box::new(HttpTransport::new(url)?),
+            (None, None) => {
+                return Err(anyhow!(
+                    "MCP server '{}' needs either a command or a url",
+                    config.name
+                ))
+            }
+        };
+
+        tokio::time::timeout(
+            connect_timeout(),
+            Self::initialize(config.name.clone(), transport),
+        )
+        .await
+        .map_err(|_| {
+            anyhow!(
+                "MCP server '{}' did not complete the handshake within {:?}",
+                config.name,
+                connect_timeout()
+            )
+        })?
+    }
+
+    /// Run the initialize handshake over a connected transport
+    pub async fn initialize(server_name: String, transport: Box<dyn McpTransport>) -> Result<Self> {
+        let params = json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": {},
+            "clientInfo": {
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+        });
+        let result = transport
+            .request("initialize", Some(params))
+            .await
+            .map_err(|e| anyhow!("MCP server '{}' failed to initialize: {}", server_name, e))?;
+        let info: InitializeResult = serde_json::from_value(result).map_err(|e| {
+            anyhow!(
+                "MCP server '{}' sent an invalid initialize result: {}",
+                server_name,
+                e
+            )
+        })?;
+
+        if !SUPPORTED_PROTOCOL_VERSIONS.contains(&info.protocol_version.as_str()) {
+            return Err(anyhow!(
+                "MCP server '{}' uses unsupported protocol version {}",
+                server_name,
+                info.protocol_version
+            ));
+        }
+
+        transport.notify("notifications/initialized", None).await?;
+
+        Ok(Self {
+            server_name,
+            transport,
+            info,
+        })
+    }
+
+    pub fn server_name(&self) -> &str {
+        &self.server_name
+    }
+
+    pub fn info(&self) -> &InitializeResult {
+        &self.info
+    }
+
+    pub fn supports_tools(&self) -> bool {
+        self.info.capabilities.tools.is_some()
+    }
+
+    pub fn supports_resources(&self) -> bool {
+        self.info.capabilities.resources.is_some()
+    }
+
+    /// All tools the server offers, following pagination
+    pub async fn list_tools(&self) -> Result<Vec<McpToolInfo>> {
+        if !self.supports_tools() {
+            return Ok(Vec::new());
+        }
+
+        let mut tools = Vec::new();
+        let mut cursor = None;
+        for _ in 0..MAX_LIST_PAGES {
+            let page: ListToolsResult = self.list_page("tools/list", cursor).await?;
+            tools.extend(page.tools);
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        Ok(tools)
+    }
+
+    pub async fn call_tool(&self, name: &str, arguments: Value) -> Result<CallToolResult> {
+        let result = self
+            .transport
+            .request(
+                "tools/call",
+                Some(json!({ "name": name, "arguments": arguments })),
+            )
+            .await?;
+        self.decode(result)
+    }
+
+    /// All resources the server offers, following pagination
+    pub async fn list_resources(&self) -> Result<Vec<Resource>> {
+        if !self.supports_resources() {
+            return Ok(Vec::new());
+        }
+
+        let mut resources = Vec::new();
+        let mut cursor = None;
+        for _ in 0..MAX_LIST_PAGES {
+            let page: ListResourcesResult = self.list_page("resources/list", cursor).await?;
+            resources.extend(page.resources);
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        Ok(resources)
+    }
+
+    pub async fn read_resource(&self, uri: &str) -> Result<ReadResourceResult> {
+        if !self.supports_resources() {
+            return Err(anyhow!(
+                "MCP server '{}' does not offer resources",
+                self.server_name
+            ));
+        }
+        let result = self
+            .transport
+            .request("resources/read", Some(json!({ "uri": uri })))
+            .await?;
+        self.decode(result)
+    }
+
+    async fn list_page<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        cursor: Option<String>,
+    ) -> Result<T> {
+        let params = cursor.map(|cursor| json!({ "cursor": cursor }));
+        let result = self.transport.request(method, params).await?;
+        self.decode(result)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, result: Value) -> Result<T> {
+        serde_json::from_value(result).map_err(|e| {
+            anyhow!(
+                "Invalid response from MCP server '{}': {}",
+                self.server_name,
+                e
+            )
+        })
+    }
+}
@@ -0,0 +1,493 @@
+//! Model Context Protocol (MCP) client. Servers listed in the runtime
+//! configuration are launched (stdio) or reached (local HTTP), their tools
+//! are offered to Claude under namespaced names and calls are forwarded to
+//! them. Resources the servers publish are available through the
+//! `list_mcp_resources` and `read_mcp_resource` tools.
+//!
+//! The [`server`] module does the reverse and serves the agent's own tools to
+//! other MCP clients.
+
+pub mod client;
+pub mod protocol;
+pub mod server;
+pub mod tool;
+pub mod transport;
+
+pub use client::McpClient;
+pub use server::{McpAuditLog, McpServer};
+pub use tool::{namespaced_tool_name, McpListResourcesTool, McpReadResourceTool, McpTool};
+
+use crate::claude::tools::AgentTool;
+use crate::config::McpServerConfig;
+use anyhow::Result;
+use protocol::{Implementation, Resource};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+
+/// Connection state of a configured server as shown in the UI
+#[derive(Debug, Clone, Serialize)]
+pub struct McpServerStatus {
+    pub name: String,
+    pub transport: String,
+    pub connected: bool,
+    pub server_info: Option<Implementation>,
+    pub tools: Vec<String>,
+    pub supports_resources: bool,
+    pub error: Option<String>,
+}
+
+/// A resource together with the server offering it
+#[derive(Debug, Clone, Serialize)]
+pub struct McpServerResource {
+    pub server: String,
+    #[serde(flatten)]
+    pub resource: Resource,
+}
+
+#[derive(Debug)]
+struct ConfiguredServer {
+    config: McpServerConfig,
+    client: Option<Arc<McpClient>>,
+    tools: Vec<McpTool>,
+    error: Option<String>,
+}
+
+/// Connected MCP servers and the tools imported from them. Connections are
+/// made in the background at startup; tools appear once their server is up.
+#[derive(Debug, Default)]
+pub struct McpManager {
+    servers: RwLock<Vec<ConfiguredServer>>,
+}
+
+impl McpManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Connect to every enabled server. Failures are kept for the status
+    /// report instead of stopping the other servers.
+    pub async fn connect_all(&self, configs: &[McpServerConfig]) {
+        let connections = configs
+            .iter()
+            .filter(|config| config.enabled)
+            .map(|config| async move { (config, McpClient::connect(config).await) });
+
+        for (config, connection) in futures::future::join_all(connections).await {
+            let result = match connection {
+                Ok(client) => self.add_server(config.clone(), client).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                eprintln!("MCP server '{}' unavailable: {}", config.name, e);
+                self.store(ConfiguredServer {
+                    config: config.clone(),
+                    client: None,
+                    tools: Vec::new(),
+                    error: Some(e.to_string()),
+                });
+            }
+        }
+    }
+
+    /// Import the tools of a connected server
+    pub async fn add_server(&self, config: McpServerConfig, client: McpClient) -> Result<()> {
+        let client = Arc::new(client);
+        let mut names = HashSet::new();
+        let mut tools = Vec::new();
+        for info in client.list_tools().await? {
+            let tool = McpTool::new(client.clone(), &info, config.require_approval);
+            if names.insert(tool.name().to_string()) {
+                tools.push(tool);
+            } else {
+                eprintln!(
+                    "Skipping MCP tool '{}' of server '{}': its name collides with another tool",
+                    info.name, config.name
+                );
+            }
+        }
+
+        self.store(ConfiguredServer {
+            config,
+            client: Some(client),
+            tools,
+            error: None,
+        });
+        Ok(())
+    }
+
+    fn store(&self, server: ConfiguredServer) {
+        let mut servers = self.servers.write().unwrap();
+        servers.retain(|existing| existing.config.name != server.config.name);
+        servers.push(server);
+    }
+
+    fn resource_clients(&self) -> Vec<Arc<McpClient>> {
+        self.servers
+            .read()
+            .unwrap()
+            .iter()
+            .filter_map(|server| server.client.clone())
+            .filter(|client| client.supports_resources())
+            .collect()
+    }
+
+    /// Tools to register for Claude: every imported tool, plus the resource
+    /// tools when a connected server offers resources
+    pub fn tools(&self) -> Vec<Box<dyn AgentTool>> {
+        let mut names = HashSet::new();
+        let mut tools: Vec<Box<dyn AgentTool>> = Vec::new();
+        for server in self.servers.read().unwrap().iter() {
+            for tool in &server.tools {
+                if names.insert(tool.name().to_string()) {
+                    tools.push(Box::new(tool.clone()));
+                }
+            }
+        }
+
+        let resource_clients = self.resource_clients();
+        if !resource_clients.is_empty() {
+            tools.push(Box::new(McpListResourcesTool::new(
+                resource_clients.clone(),
+            )));
+            tools.push(Box::new(McpReadResourceTool::new(resource_clients)));
+        }
+        tools
+    }
+
+    pub fn statuses(&self) -> Vec<McpServerStatus> {
+        self.servers
+            .read()
+            .unwrap()
+            .iter()
+            .map(|server| McpServerStatus {
+                name: server.config.name.clone(),
+                transport: if server.config.command.is_some() {
+                    "stdio".to_string()
+                } else {
+                    "http".to_string()
+                },
+                connected: server.client.is_some(),
+                server_info: server
+                    .client
+                    .as_ref()
+                    .map(|client| client.info().server_info.clone()),
+                tools: server
+                    .tools
+                    .iter()
+                    .map(|tool| tool.name().to_string())
+                    .collect(),
+                supports_resources: server
+                    .client
+                    .as_ref()
+                    .is_some_and(|client| client.supports_resources()),
+                error: server.error.clone(),
+            })
+            .collect()
+    }
+
+    /// Resources of every connected server; servers that fail are skipped
+    pub async fn list_resources(&self) -> Vec<McpServerResource> {
+        let mut resources = Vec::new();
+        for client in self.resource_clients() {
+            match client.list_resources().await {
+                Ok(listed) => {
+                    resources.extend(listed.into_iter().map(|resource| McpServerResource {
+                        server: client.server_name().to_string(),
+                        resource,
+                    }))
+                }
+                Err(e) => eprintln!(
+                    "Failed to list resources of MCP server '{}': {}",
+                    client.server_name(),
+                    e
+                ),
+            }
+        }
+        resources
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::protocol::{JsonRpcRequest, JsonRpcResponse};
+    use super::transport::{HttpTransport, StdioTransport};
+    use super::*;
+    use crate::claude::tools::ToolRegistry;
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+    fn stub_config(name: &str) -> McpServerConfig {
+        McpServerConfig {
+            name: name.to_string(),
+            command: Some("stub".to_string()),
+            args: Vec::new(),
+            env: HashMap::new(),
+            url: None,
+            enabled: true,
+            require_approval: false,
+        }
+    }
+
+    /// Answers requests the way a small MCP server with one tool and one
+    /// resource would
+    fn stub_result(request: &JsonRpcRequest) -> Option<Result<Value, String>> {
+        let params = request.params.clone().unwrap_or_default();
+        let result = match request.method.as_str() {
+            "initialize" => Ok(json!({
+                "protocolVersion": "2025-03-26",
+                "capabilities": {"tools": {}, "resources": {}},
+                "serverInfo": {"name": "stub", "version": "1.0"},
+            })),
+            "tools/list" => Ok(json!({"tools": [{
+                "name": "echo.text",
+                "description": "Echo text back",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {"text": {"type": "string"}},
+                    "required": ["text"],
+                },
+            }]})),
+            "tools/call" if params["arguments"]["text"] == "fail" => Ok(json!({
+                "content": [{"type": "text", "text": "echo failed"}],
+                "isError": true,
+            })),
+            "tools/call" => Ok(json!({
+                "content": [{"type": "text", "text": params["arguments"]["text"]}],
+            })),
+            "resources/list" => Ok(json!({"resources": [{
+                "uri": "stub://notes",
+                "name": "Notes",
+                "mimeType": "text/plain",
+            }]})),
+            "resources/read" => Ok(json!({"contents": [{
+                "uri": params["uri"],
+                "text": "remember the milk",
+            }]})),
+            _ if request.id.is_none() => return None,
+            method => Err(format!("unknown method {}", method)),
+        };
+        Some(result)
+    }
+
+    fn stub_response(request: &JsonRpcRequest) -> Option<JsonRpcResponse> {
+        let id = request.id.clone()?;
+        Some(match stub_result(request)? {
+            Ok(result) => JsonRpcResponse::success(id, result),
+            Err(message) => JsonRpcResponse::error(id, -32601, message),
+        })
+    }
+
+    /// Stub server speaking newline-delimited JSON over in-memory pipes.
+    /// It pings the client first to check that the client answers requests.
+    fn spawn_stdio_stub() -> StdioTransport {
+        let (client_side, server_side) = tokio::io::duplex(64 * 1024);
+        let (client_read, client_write) = tokio::io::split(client_side);
+        let (server_read, mut server_write) = tokio::io::split(server_side);
+
+        tokio::spawn(async move {
+            let ping = json!({"jsonrpc": "2.0", "id": "ping-1", "method": "ping"});
+            let _ = server_write
+                .write_all(format!("{}\n", ping).as_bytes())
+                .await;
+
+            let mut lines = BufReader::new(server_read).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let message: Value = serde_json::from_str(&line).unwrap();
+                if message.get("method").is_none() {
+                    // The client's answer to our ping
+                    assert_eq!(message["id"], "ping-1");
+                    continue;
+                }
+                let request: JsonRpcRequest = serde_json::from_value(message).unwrap();
+                if let Some(response) = stub_response(&request) {
+                    let line = format!("{}\n", serde_json::to_string(&response).unwrap());
+                    let _ = server_write.write_all(line.as_bytes()).await;
+                }
+            }
+        });
+
+        StdioTransport::from_streams(client_read, client_write, None)
+    }
+
+    /// Stub streamable HTTP server on a loopback port. Responses to
+    /// `tools/list` are sent as an event stream with a notification first.
+    async fn spawn_http_stub() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buffer = Vec::new();
+                let mut chunk = [0u8; 4096];
+                let body = loop {
+                    let read = socket.read(&mut chunk).await.unwrap();
+                    if read == 0 {
+                        break None;
+                    }
+                    buffer.extend_from_slice(&chunk[..read]);
+                    let text = String::from_utf8_lossy(&buffer).to_string();
+                    let Some(header_end) = text.find("\r\n\r\n") else {
+                        continue;
+                    };
+                    let length = text[..header_end]
+                        .lines()
+                        .find_map(|line| {
+                            let (name, value) = line.split_once(':')?;
+                            name.eq_ignore_ascii_case("content-length")
+                                .then(|| value.trim().parse::<usize>().ok())?
+                        })
+                        .unwrap_or(0);
+                    if buffer.len() >= header_end + 4 + length {
+                        break Some(buffer[header_end + 4..header_end + 4 + length].to_vec());
+                    }
+                };
+                let Some(body) = body else {
+                    continue;
+                };
+
+                let request: JsonRpcRequest = serde_json::from_slice(&body).unwrap();
+                let reply = match stub_response(&request) {
+                    None => {
+                        "HTTP/1.1 202 Accepted\r\nconnection: close\r\ncontent-length: 0\r\n\r\n"
+                            .to_string()
+                    }
+                    Some(response) if request.method == "tools/list" => {
+                        let events = format!(
+                            "event: message\r\ndata: {}\r\n\r\nevent: message\r\ndata: {}\r\n\r\n",
+                            json!({"jsonrpc": "2.0", "method": "notifications/progress"}),
+                            serde_json::to_string(&response).unwrap()
+                        );
+                        format!(
+                            "HTTP/1.1 200 OK\r\nconnection: close\r\ncontent-type: text/event-stream\r\ncontent-length: {}\r\n\r\n{}",
+                            events.len(),
+                            events
+                        )
+                    }
+                    Some(response) => {
+                        let json = serde_json::to_string(&response).unwrap();
+                        format!(
+                            "HTTP/1.1 200 OK\r\nconnection: close\r\ncontent-type: application/json\r\nmcp-session-id: stub-session\r\ncontent-length: {}\r\n\r\n{}",
+                            json.len(),
+                            json
+                        )
+                    }
+                };
+                let _ = socket.write_all(reply.as_bytes()).await;
+            }
+        });
+
+        format!("http://{}/mcp", address)
+    }
+
+    #[test]
+    fn test_namespaced_tool_names() {
+        assert_eq!(namespaced_tool_name("docs", "search"), "mcp__docs__search");
+        assert_eq!(
+            namespaced_tool_name("docs", "search.pages/v2"),
+            "mcp__docs__search_pages_v2"
+        );
+        assert_eq!(namespaced_tool_name("docs", &"x".repeat(100)).len(), 64);
+    }
+
+    #[tokio::test]
+    async fn test_stdio_server_tools_are_imported_and_forwarded() {
+        let client = McpClient::initialize("stub".to_string(), Box::new(spawn_stdio_stub()))
+            .await
+            .unwrap();
+        assert_eq!(client.info().server_info.name, "stub");
+
+        let manager = McpManager::new();
+        manager
+            .add_server(stub_config("stub"), client)
+            .await
+            .unwrap();
+
+        let mut registry = ToolRegistry::new();
+        for tool in manager.tools() {
+            registry.register_boxed(tool);
+        }
+        let names: Vec<String> = registry
+            .get_all_tools()
+            .into_iter()
+            .map(|tool| tool.name)
+            .collect();
+        assert!(names.contains(&"mcp__stub__echo_text".to_string()));
+        assert!(names.contains(&"list_mcp_resources".to_string()));
+
+        let output = registry
+            .execute_tool("mcp__stub__echo_text", json!({"text": "hello"}))
+            .await
+            .unwrap();
+        assert_eq!(output, "hello");
+
+        // The server's schema is enforced before the call is forwarded
+        assert!(registry
+            .execute_tool("mcp__stub__echo_text", json!({}))
+            .await
+            .is_err());
+
+        let error = registry
+            .execute_tool("mcp__stub__echo_text", json!({"text": "fail"}))
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "echo failed");
+
+        let listing = registry
+            .execute_tool("list_mcp_resources", json!({}))
+            .await
+            .unwrap();
+        assert!(listing.contains("stub: stub://notes (Notes) [text/plain]"));
+        let contents = registry
+            .execute_tool(
+                "read_mcp_resource",
+                json!({"server": "stub", "uri": "stub://notes"}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(contents, "remember the milk");
+
+        let status = &manager.statuses()[0];
+        assert!(status.connected);
+        assert_eq!(status.tools, vec!["mcp__stub__echo_text".to_string()]);
+        assert_eq!(manager.list_resources().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_http_server_with_event_stream_responses() {
+        let url = spawn_http_stub().await;
+        let transport = HttpTransport::new(&url).unwrap();
+        let client = McpClient::initialize("web".to_string(), Box::new(transport))
+            .await
+            .unwrap();
+
+        let tools = client.list_tools().await.unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name, "echo.text");
+
+        let result = client
+            .call_tool("echo.text", json!({"text": "over http"}))
+            .await
+            .unwrap();
+        assert_eq!(result.content[0].render(), "over http");
+    }
+
+    #[tokio::test]
+    async fn test_failed_servers_are_reported() {
+        let mut config = stub_config("missing");
+        config.command = Some("/nonexistent/mcp-server".to_string());
+
+        let manager = McpManager::new();
+        manager.connect_all(&[config]).await;
+
+        let status = &manager.statuses()[0];
+        assert!(!status.connected);
+        assert!(status
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("Failed to launch MCP server"));
+        assert!(manager.tools().is_empty());
+    }
+}
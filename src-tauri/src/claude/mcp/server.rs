@@ -0,0 +1,475 @@
+//! MCP server mode: serves a [`ToolRegistry`] to other MCP clients (editors,
+//! other agents) over stdio. Calls run through the [`ToolExecutionEngine`]
+//! with the tools bound to the same [`WhitelistConfig`] the app uses, and
+//! every call is written to an audit log. Executed calls can also be recorded
+//! in the app's hash-chained [`AuditLog`].
+
+use super::protocol::{
+    error_codes, CallToolResult, Implementation, IncomingMessage, JsonRpcRequest, JsonRpcResponse,
+    McpToolInfo, ToolContent,
+};
+use crate::claude::audit::AuditLog;
+use crate::claude::constants::mcp::{
+    MAX_AUDIT_VALUE_CHARS, PROTOCOL_VERSION, SUPPORTED_PROTOCOL_VERSIONS,
+};
+use crate::claude::tools::execution::ToolExecutionStatus;
+use crate::claude::tools::{
+    AgentTool, ListDirectoryTool, ReadFileTool, ToolExecutionContext, ToolExecutionEngine,
+    ToolExecutionResult, ToolRegistry, ToolRequest, WriteFileTool,
+};
+use crate::claude::whitelist::WhitelistConfig;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::RwLock;
+
+/// One served tool call as recorded in the audit log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpAuditEntry {
+    pub timestamp: DateTime<Utc>,
+    pub client: String,
+    pub tool: String,
+    /// Call arguments with long string values shortened
+    pub arguments: Value,
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+/// Append-only JSON Lines log of tool calls made by MCP clients. Each entry
+/// is also summarized on stderr, which is free in stdio mode.
+#[derive(Debug, Clone, Default)]
+pub struct McpAuditLog {
+    path: Option<PathBuf>,
+}
+
+impl McpAuditLog {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path: Some(path) }
+    }
+
+    pub fn record(&self, entry: &McpAuditEntry) {
+        eprintln!(
+            "[mcp audit] {} {} -> {}{}",
+            entry.client,
+            entry.tool,
+            entry.status,
+            entry
+                .error
+                .as_deref()
+                .map(|error| format!(": {}", error))
+                .unwrap_or_default()
+        );
+
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Err(e) = Self::append(path, entry) {
+            eprintln!("Failed to write MCP audit log {}: {}", path.display(), e);
+        }
+    }
+
+    fn append(path: &PathBuf, entry: &McpAuditEntry) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        Ok(())
+    }
+}
+
+/// Shorten long strings (e.g. file contents) so the log records what was
+/// called without copying everything that was written
+fn summarize_arguments(value: &Value) -> Value {
+    match value {
+        Value::String(text) if text.chars().count() > MAX_AUDIT_VALUE_CHARS => {
+            let prefix: String = text.chars().take(MAX_AUDIT_VALUE_CHARS).collect();
+            Value::String(format!("{}… ({} chars)", prefix, text.chars().count()))
+        }
+        Value::Array(items) => Value::Array(items.iter().map(summarize_arguments).collect()),
+        Value::Object(object) => Value::Object(
+            object
+                .iter()
+                .map(|(key, value)| (key.clone(), summarize_arguments(value)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+fn status_label(result: &ToolExecutionResult) -> &'static str {
+    match result.status {
+        ToolExecutionStatus::Success => "success",
+        ToolExecutionStatus::PartialSuccess { .. } => "partial_success",
+        ToolExecutionStatus::Failed { .. } => "failed",
+        ToolExecutionStatus::Timeout => "timeout",
+        ToolExecutionStatus::Cancelled => "cancelled",
+        ToolExecutionStatus::Skipped { .. } => "skipped",
+    }
+}
+
+/// MCP content for an execution result; failures are tool errors the client
+/// shows to its model rather than protocol errors
+pub fn call_result_for(result: &ToolExecutionResult) -> CallToolResult {
+    CallToolResult {
+        content: vec![ToolContent::Text {
+            text: result.into_content_block(),
+        }],
+        structured_content: None,
+        is_error: result.is_error(),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CallToolParams {
+    name: String,
+    #[serde(default)]
+    arguments: Option<Value>,
+}
+
+#[derive(Debug)]
+pub struct McpServer {
+    tool_registry: ToolRegistry,
+    tool_execution_engine: ToolExecutionEngine,
+    whitelist: Arc<RwLock<WhitelistConfig>>,
+    audit_log: McpAuditLog,
+    client: RwLock<Option<Implementation>>,
+}
+
+impl McpServer {
+    /// Serve the tools of `tool_registry`, executing calls with the matching
+    /// tools of `tool_execution_engine`. Both must already be bound to `whitelist`.
+    pub fn new(
+        tool_registry: ToolRegistry,
+        tool_execution_engine: ToolExecutionEngine,
+        whitelist: Arc<RwLock<WhitelistConfig>>,
+    ) -> Self {
+        Self {
+            tool_registry,
+            tool_execution_engine,
+            whitelist,
+            audit_log: McpAuditLog::default(),
+            client: RwLock::new(None),
+        }
+    }
+
+    /// Serve the whitelisted read_file, write_file and list_directory tools
+    pub fn for_file_tools(whitelist: Arc<RwLock<WhitelistConfig>>) -> Self {
+        let mut tool_registry = ToolRegistry::new();
+        tool_registry.register(ReadFileTool::new());
+        tool_registry.register(WriteFileTool::new());
+        tool_registry.register(ListDirectoryTool::new());
+        tool_registry.set_whitelist(whitelist.clone());
+
+        let mut tool_execution_engine = ToolExecutionEngine::new();
+        let tools: Vec<Box<dyn AgentTool>> = vec![
+            Box::new(ReadFileTool::new()),
+            Box::new(WriteFileTool::new()),
+            Box::new(ListDirectoryTool::new()),
+        ];
+        for mut tool in tools {
+            tool.set_whitelist(whitelist.clone());
+            tool_execution_engine.register_tool(Arc::from(tool));
+        }
+
+        Self::new(tool_registry, tool_execution_engine, whitelist)
+    }
+
+    pub fn with_audit_log(mut self, audit_log: McpAuditLog) -> Self {
+        self.audit_log = audit_log;
+        self
+    }
+
+    /// Also record executed calls in the app's tamper-evident audit log
+    pub fn with_tool_audit_log(mut self, audit_log: Arc<AuditLog>) -> Self {
+        self.tool_execution_engine =
+            std::mem::take(&mut self.tool_execution_engine).with_audit_log(audit_log);
+        self
+    }
+
+    /// Answer newline-delimited JSON-RPC requests until the input ends
+    pub async fn serve<R, W>(&self, reader: R, mut writer: W) -> Result<()>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut lines = BufReader::new(reader).lines();
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let response = match serde_json::from_str(&line)
+                .map_err(anyhow::Error::from)
+                .and_then(IncomingMessage::parse)
+            {
+                Ok(IncomingMessage::Request(request)) => self.handle(request).await,
+                // The server sends no requests, so there are no responses to route
+                Ok(IncomingMessage::Response(_)) => None,
+                Err(e) => Some(JsonRpcResponse::error(
+                    Value::Null,
+                    error_codes::PARSE_ERROR,
+                    format!("Invalid message: {}", e),
+                )),
+            };
+
+            if let Some(response) = response {
+                let mut line = serde_json::to_string(&response)?;
+                line.push('\n');
+                writer.write_all(line.as_bytes()).await?;
+                writer.flush().await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle one request; notifications get no response
+    pub async fn handle(&self, request: JsonRpcRequest) -> Option<JsonRpcResponse> {
+        let id = request.id.clone()?;
+        let result = match request.method.as_str() {
+            "initialize" => self.initialize(request.params).await,
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(self.list_tools()),
+            "tools/call" => self.call_tool(request.params).await,
+            method => {
+                return Some(JsonRpcResponse::error(
+                    id,
+                    error_codes::METHOD_NOT_FOUND,
+                    format!("Method not found: {}", method),
+                ))
+            }
+        };
+
+        Some(match result {
+            Ok(result) => JsonRpcResponse::success(id, result),
+            Err(e) => JsonRpcResponse::error(id, error_codes::INVALID_PARAMS, e.to_string()),
+        })
+    }
+
+    async fn initialize(&self, params: Option<Value>) -> Result<Value> {
+        let params = params.unwrap_or_default();
+        if let Some(client) = params
+            .get("clientInfo")
+            .and_then(|info| serde_json::from_value::<Implementation>(info.clone()).ok())
+        {
+            *self.client.write().await = Some(client);
+        }
+
+        // Answer in the client's revision when we speak it, otherwise in ours
+        let protocol_version = params
+            .get("protocolVersion")
+            .and_then(Value::as_str)
+            .filter(|version| SUPPORTED_PROTOCOL_VERSIONS.contains(version))
+            .unwrap_or(PROTOCOL_VERSION);
+
+        Ok(json!({
+            "protocolVersion": protocol_version,
+            "capabilities": {"tools": {"listChanged": false}},
+            "serverInfo": {
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+            "instructions": "File tools restricted to the directories whitelisted in the agent.",
+        }))
+    }
+
+    /// Definitions of the served tools. Tools that need the user's approval
+    /// are left out since there is no one to ask in server mode.
+    fn list_tools(&self) -> Value {
+        let mut tools: Vec<McpToolInfo> = self
+            .tool_registry
+            .get_all_tools()
+            .into_iter()
+            .filter(|tool| {
+                self.tool_registry
+                    .get_tool(&tool.name)
+                    .is_some_and(|tool| !tool.requires_approval())
+            })
+            .map(|tool| McpToolInfo {
+                name: tool.name,
+                description: Some(tool.description),
+                input_schema: serde_json::to_value(tool.input_schema).unwrap_or_default(),
+            })
+            .collect();
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
+
+        json!({ "tools": tools })
+    }
+
+    async fn call_tool(&self, params: Option<Value>) -> Result<Value> {
+        let params: CallToolParams = serde_json::from_value(params.unwrap_or_default())
+            .map_err(|e| anyhow!("Invalid tools/call parameters: {}", e))?;
+        let arguments = params.arguments.unwrap_or_else(|| json!({}));
+        let client = self
+            .client
+            .read()
+            .await
+            .as_ref()
+            .map(|client| client.name.clone())
+            .unwrap_or_else(|| "unknown".to_string());
+
+        let start = Instant::now();
+        let result = match self.tool_registry.get_tool(&params.name) {
+            Some(tool) if !tool.requires_approval() => {
+                let context = ToolExecutionContext::new(
+                    params.name.clone(),
+                    arguments.clone(),
+                    self.whitelist.clone(),
+                );
+                self.tool_execution_engine
+                    .execute_single_tool(
+                        ToolRequest::new(params.name.clone(), arguments.clone()),
+                        context,
+                    )
+                    .await
+                    .map_err(|e| anyhow!("{}", e))
+            }
+            _ => Err(anyhow!("Unknown tool: {}", params.name)),
+        };
+
+        let (status, error) = match &result {
+            Ok(result) if result.is_error() => (status_label(result), Some(result.result.render())),
+            Ok(result) => (status_label(result), None),
+            Err(e) => ("rejected", Some(e.to_string())),
+        };
+        self.audit_log.record(&McpAuditEntry {
+            timestamp: Utc::now(),
+            client,
+            tool: params.name,
+            arguments: summarize_arguments(&arguments),
+            status: status.to_string(),
+            error,
+            duration_ms: start.elapsed().as_millis() as u64,
+        });
+
+        Ok(serde_json::to_value(call_result_for(&result?))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::claude::audit::AuditFilter;
+    use crate::claude::mcp::transport::StdioTransport;
+    use crate::claude::mcp::McpClient;
+    use crate::claude::test_support::whitelisted_temp_dir;
+
+    /// Connect the MCP client to a server running on in-memory pipes
+    async fn connect(server: McpServer) -> McpClient {
+        let (client_side, server_side) = tokio::io::duplex(64 * 1024);
+        let (client_read, client_write) = tokio::io::split(client_side);
+        let (server_read, server_write) = tokio::io::split(server_side);
+        tokio::spawn(async move { server.serve(server_read, server_write).await });
+
+        McpClient::initialize(
+            "agent".to_string(),
+            Box::new(StdioTransport::from_streams(
+                client_read,
+                client_write,
+                None,
+            )),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_serves_file_tools_within_whitelist() {
+        let (dir, whitelist) = whitelisted_temp_dir();
+        let audit_path = dir.join("audit").join("mcp_audit.jsonl");
+        let tool_audit = Arc::new(AuditLog::open(dir.join("audit").join("audit_log.jsonl")));
+        let server = McpServer::for_file_tools(whitelist)
+            .with_audit_log(McpAuditLog::new(audit_path.clone()))
+            .with_tool_audit_log(tool_audit.clone());
+        let client = connect(server).await;
+
+        let mut names: Vec<String> = client
+            .list_tools()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|tool| {
+                assert_eq!(tool.input_schema["type"], "object");
+                tool.name
+            })
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["list_directory", "read_file", "write_file"]);
+
+        let file = dir.join("notes.txt");
+        let written = client
+            .call_tool(
+                "write_file",
+                json!({"path": file.to_string_lossy(), "content": "hello from mcp"}),
+            )
+            .await
+            .unwrap();
+        assert!(!written.is_error);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "hello from mcp");
+
+        let read = client
+            .call_tool("read_file", json!({"path": file.to_string_lossy()}))
+            .await
+            .unwrap();
+        assert!(!read.is_error);
+        assert!(read.content[0].render().contains("hello from mcp"));
+
+        // Paths outside the whitelist are refused as tool errors
+        let outside = std::env::temp_dir().join("rustic-agent-outside.txt");
+        let denied = client
+            .call_tool(
+                "write_file",
+                json!({"path": outside.to_string_lossy(), "content": "nope"}),
+            )
+            .await
+            .unwrap();
+        assert!(denied.is_error);
+        assert!(!outside.exists());
+
+        // Unknown tools are protocol errors
+        assert!(client.call_tool("delete_file", json!({})).await.is_err());
+
+        let entries: Vec<McpAuditEntry> = std::fs::read_to_string(&audit_path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let statuses: Vec<&str> = entries.iter().map(|entry| entry.status.as_str()).collect();
+        assert_eq!(statuses, vec!["success", "success", "failed", "rejected"]);
+        assert_eq!(entries[0].tool, "write_file");
+        assert_eq!(entries[0].client, env!("CARGO_PKG_NAME"));
+        assert!(entries[2].error.is_some());
+
+        // Executed calls also reach the app's audit log; the unknown tool never ran
+        let executions = tool_audit
+            .query(&AuditFilter {
+                kind: Some("tool_execution".to_string()),
+                ..AuditFilter::default()
+            })
+            .unwrap();
+        assert_eq!(executions.len(), 3);
+        assert!(tool_audit.verify().unwrap().valid);
+    }
+
+    #[test]
+    fn test_audit_arguments_are_shortened() {
+        let long = "x".repeat(MAX_AUDIT_VALUE_CHARS + 10);
+        let summary = summarize_arguments(&json!({"path": "a.txt", "content": long}));
+        assert_eq!(summary["path"], "a.txt");
+        assert!(summary["content"]
+            .as_str()
+            .unwrap()
+            .ends_with(&format!("({} chars)", MAX_AUDIT_VALUE_CHARS + 10)));
+    }
+}
@@ -0,0 +1,296 @@
+//! Adapters that expose MCP server tools and resources as [`AgentTool`]s.
+
+use super::client::McpClient;
+use super::protocol::{CallToolResult, McpToolInfo};
+use crate::claude::constants::mcp::{
+    MAX_OUTPUT_BYTES, MAX_TOOL_NAME_CHARS, TOOL_NAME_PREFIX, TOOL_NAME_SEPARATOR,
+};
+use crate::claude::tools::{input_schema_for, parse_input, AgentTool};
+use crate::claude::types::ToolInputSchema;
+use crate::claude::whitelist::WhitelistConfig;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Name under which a server's tool is offered to Claude: mcp__<server>__<tool>,
+/// with characters the API does not accept replaced and the length capped
+pub fn namespaced_tool_name(server: &str, tool: &str) -> String {
+    let name = [TOOL_NAME_PREFIX, server, tool].join(TOOL_NAME_SEPARATOR);
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(MAX_TOOL_NAME_CHARS)
+        .collect()
+}
+
+/// A tool of an MCP server. Calls are forwarded to the server unchanged.
+#[derive(Debug, Clone)]
+pub struct McpTool {
+    client: Arc<McpClient>,
+    name: String,
+    remote_name: String,
+    description: String,
+    input_schema: ToolInputSchema,
+    require_approval: bool,
+}
+
+impl McpTool {
+    pub fn new(client: Arc<McpClient>, info: &McpToolInfo, require_approval: bool) -> Self {
+        let description = format!(
+            "[MCP server '{}'] {}",
+            client.server_name(),
+            info.description.as_deref().unwrap_or(&info.name)
+        );
+
+        Self {
+            name: namespaced_tool_name(client.server_name(), &info.name),
+            remote_name: info.name.clone(),
+            description,
+            input_schema: convert_input_schema(&info.input_schema),
+            require_approval,
+            client,
+        }
+    }
+}
+
+/// Convert a server's JSON Schema into a tool schema. Servers that leave
+/// `additionalProperties` out allow extra properties, and schemas using
+/// constructs the tool schema cannot express are passed on as a bare object
+/// schema so the server does the validation.
+fn convert_input_schema(schema: &Value) -> ToolInputSchema {
+    let mut schema = schema.clone();
+    if let Some(object) = schema.as_object_mut() {
+        object
+            .entry("additionalProperties")
+            .or_insert(Value::Bool(true));
+    }
+
+    serde_json::from_value(schema).unwrap_or_else(|_| ToolInputSchema {
+        r#type: "object".to_string(),
+        properties: Default::default(),
+        required: Vec::new(),
+        additional_properties: true,
+    })
+}
+
+/// Text shown to Claude for a tool call result
+fn render_call_result(result: &CallToolResult) -> String {
+    if result.content.is_empty() {
+        return match &result.structured_content {
+            Some(structured) => serde_json::to_string_pretty(structured).unwrap_or_default(),
+            None => "Tool returned no content".to_string(),
+        };
+    }
+
+    let parts: Vec<String> = result.content.iter().map(|item| item.render()).collect();
+    parts.join("\n")
+}
+
+/// Cap server output so one call cannot flood the conversation
+fn truncate_output(mut text: String) -> String {
+    if text.len() <= MAX_OUTPUT_BYTES {
+        return text;
+    }
+    let mut end = MAX_OUTPUT_BYTES;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    let total = text.len();
+    text.truncate(end);
+    text.push_str(&format!(
+        "\n[Output truncated: showing {} of {} bytes]",
+        end, total
+    ));
+    text
+}
+
+#[async_trait]
+impl AgentTool for McpTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn input_schema(&self) -> ToolInputSchema {
+        self.input_schema.clone()
+    }
+
+    async fn execute(&self, input: Value) -> Result<String> {
+        let result = self.client.call_tool(&self.remote_name, input).await?;
+        let text = truncate_output(render_call_result(&result));
+        if result.is_error {
+            Err(anyhow!("{}", text))
+        } else {
+            Ok(text)
+        }
+    }
+
+    // Server tools run outside the whitelist; the server enforces its own access
+    fn set_whitelist(&mut self, _whitelist: Arc<RwLock<WhitelistConfig>>) {}
+
+    fn requires_approval(&self) -> bool {
+        self.require_approval
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct ListResourcesInput {
+    /// Only list resources of this MCP server
+    server: Option<String>,
+}
+
+/// Lists the resources offered by connected MCP servers
+#[derive(Debug, Clone)]
+pub struct McpListResourcesTool {
+    clients: Vec<Arc<McpClient>>,
+}
+
+impl McpListResourcesTool {
+    pub fn new(clients: Vec<Arc<McpClient>>) -> Self {
+        Self { clients }
+    }
+}
+
+#[async_trait]
+impl AgentTool for McpListResourcesTool {
+    fn name(&self) -> &str {
+        "list_mcp_resources"
+    }
+
+    fn description(&self) -> &str {
+        "List resources (documents, data, files) offered by connected MCP servers. Read one with read_mcp_resource."
+    }
+
+    fn input_schema(&self) -> ToolInputSchema {
+        input_schema_for::<ListResourcesInput>()
+    }
+
+    async fn execute(&self, input: Value) -> Result<String> {
+        let input: ListResourcesInput = parse_input(input)?;
+        let clients: Vec<&Arc<McpClient>> = self
+            .clients
+            .iter()
+            .filter(|client| {
+                input
+                    .server
+                    .as_deref()
+                    .is_none_or(|server| client.server_name() == server)
+            })
+            .collect();
+        if clients.is_empty() {
+            return Err(anyhow!(
+                "No connected MCP server named '{}' offers resources",
+                input.server.unwrap_or_default()
+            ));
+        }
+
+        let mut lines = Vec::new();
+        for client in clients {
+            match client.list_resources().await {
+                Ok(resources) => {
+                    for resource in resources {
+                        let mut line = format!(
+                            "{}: {} ({})",
+                            client.server_name(),
+                            resource.uri,
+                            resource.name
+                        );
+                        if let Some(mime_type) = &resource.mime_type {
+                            line.push_str(&format!(" [{}]", mime_type));
+                        }
+                        if let Some(description) = &resource.description {
+                            line.push_str(&format!(" - {}", description));
+                        }
+                        lines.push(line);
+                    }
+                }
+                Err(e) => lines.push(format!(
+                    "{}: failed to list resources: {}",
+                    client.server_name(),
+                    e
+                )),
+            }
+        }
+
+        if lines.is_empty() {
+            Ok("No MCP resources available".to_string())
+        } else {
+            Ok(truncate_output(lines.join("\n")))
+        }
+    }
+
+    fn set_whitelist(&mut self, _whitelist: Arc<RwLock<WhitelistConfig>>) {}
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct ReadResourceInput {
+    /// MCP server offering the resource
+    server: String,
+    /// URI of the resource, as listed by list_mcp_resources
+    uri: String,
+}
+
+/// Reads a resource from a connected MCP server
+#[derive(Debug, Clone)]
+pub struct McpReadResourceTool {
+    clients: Vec<Arc<McpClient>>,
+}
+
+impl McpReadResourceTool {
+    pub fn new(clients: Vec<Arc<McpClient>>) -> Self {
+        Self { clients }
+    }
+}
+
+#[async_trait]
+impl AgentTool for McpReadResourceTool {
+    fn name(&self) -> &str {
+        "read_mcp_resource"
+    }
+
+    fn description(&self) -> &str {
+        "Read a resource from a connected MCP server by its URI."
+    }
+
+    fn input_schema(&self) -> ToolInputSchema {
+        input_schema_for::<ReadResourceInput>()
+    }
+
+    async fn execute(&self, input: Value) -> Result<String> {
+        let input: ReadResourceInput = parse_input(input)?;
+        let client = self
+            .clients
+            .iter()
+            .find(|client| client.server_name() == input.server)
+            .ok_or_else(|| {
+                anyhow!(
+                    "No connected MCP server named '{}' offers resources",
+                    input.server
+                )
+            })?;
+
+        let result = client.read_resource(&input.uri).await?;
+        let parts: Vec<String> = result
+            .contents
+            .iter()
+            .map(|contents| contents.render())
+            .collect();
+        Ok(truncate_output(parts.join("\n")))
+    }
+
+    fn set_whitelist(&mut self, _whitelist: Arc<RwLock<WhitelistConfig>>) {}
+}
@@ -0,0 +1,345 @@
+//! Transports carrying JSON-RPC messages to an MCP server: newline-delimited
+//! JSON over a child process's stdio, or HTTP POST to a local endpoint that
+//! answers with JSON or a server-sent event stream.
+
+use super::protocol::{error_codes, IncomingMessage, JsonRpcRequest, JsonRpcResponse};
+use crate::claude::constants::mcp::request_timeout;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::{oneshot, Mutex};
+use tokio::task::JoinHandle;
+
+/// Header carrying the session assigned by a streamable HTTP server
+const SESSION_HEADER: &str = "Mcp-Session-Id";
+
+#[async_trait]
+pub trait McpTransport: Send + Sync + std::fmt::Debug {
+    /// Send a request and wait for its result
+    async fn request(&self, method: &str, params: Option<Value>) -> Result<Value>;
+
+    /// Send a notification, which has no response
+    async fn notify(&self, method: &str, params: Option<Value>) -> Result<()>;
+}
+
+type PendingRequests = Arc<StdMutex<HashMap<u64, oneshot::Sender<JsonRpcResponse>>>>;
+type SharedWriter = Arc<Mutex<Box<dyn AsyncWrite + Send + Unpin>>>;
+
+/// Newline-delimited JSON-RPC over a pair of byte streams, normally the
+/// stdin/stdout of a launched server process
+pub struct StdioTransport {
+    writer: SharedWriter,
+    pending: PendingRequests,
+    next_id: AtomicU64,
+    reader: JoinHandle<()>,
+    // Held so the server process is killed when the transport is dropped
+    _child: Option<Child>,
+}
+
+impl std::fmt::Debug for StdioTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StdioTransport")
+            .field("next_id", &self.next_id)
+            .field("process_id", &self._child.as_ref().and_then(Child::id))
+            .finish_non_exhaustive()
+    }
+}
+
+impl StdioTransport {
+    /// Launch a server process and talk to it over its stdin and stdout.
+    /// Its stderr is passed through to ours so server logs stay visible.
+    pub fn spawn(command: &str, args: &[String], env: &HashMap<String, String>) -> Result<Self> {
+        let mut child = Command::new(command)
+            .args(args)
+            .envs(env)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| anyhow!("Failed to launch MCP server '{}': {}", command, e))?;
+
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow!("MCP server '{}' has no stdin", command))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow!("MCP server '{}' has no stdout", command))?;
+
+        Ok(Self::from_streams(stdout, stdin, Some(child)))
+    }
+
+    /// Talk to a server over already connected streams
+    pub fn from_streams<R, W>(reader: R, writer: W, child: Option<Child>) -> Self
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + Unpin + 'static,
+    {
+        let writer: SharedWriter = Arc::new(Mutex::new(Box::new(writer)));
+        let pending: PendingRequests = Arc::new(StdMutex::new(HashMap::new()));
+        let reader = tokio::spawn(read_messages(reader, writer.clone(), pending.clone()));
+
+        Self {
+            writer,
+            pending,
+            next_id: AtomicU64::new(1),
+            reader,
+            _child: child,
+        }
+    }
+}
+
+impl Drop for StdioTransport {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+#[async_trait]
+impl McpTransport for StdioTransport {
+    async fn request(&self, method: &str, params: Option<Value>) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id, sender);
+
+        let request = JsonRpcRequest::new(id, method, params);
+        if let Err(e) = write_message(&self.writer, &request).await {
+            self.pending.lock().unwrap().remove(&id);
+            return Err(e);
+        }
+
+        match tokio::time::timeout(request_timeout(), receiver).await {
+            Ok(Ok(response)) => response.into_result(),
+            Ok(Err(_)) => Err(anyhow!(
+                "MCP server closed the connection before answering '{}'",
+                method
+            )),
+            Err(_) => {
+                self.pending.lock().unwrap().remove(&id);
+                Err(anyhow!(
+                    "MCP request '{}' timed out after {:?}",
+                    method,
+                    request_timeout()
+                ))
+            }
+        }
+    }
+
+    async fn notify(&self, method: &str, params: Option<Value>) -> Result<()> {
+        write_message(&self.writer, &JsonRpcRequest::notification(method, params)).await
+    }
+}
+
+async fn write_message<T: serde::Serialize>(writer: &SharedWriter, message: &T) -> Result<()> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+
+    let mut writer = writer.lock().await;
+    writer
+        .write_all(line.as_bytes())
+        .await
+        .map_err(|e| anyhow!("Failed to write to MCP server: {}", e))?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Route responses to their waiting requests and answer the server's own
+/// requests until the stream ends. Requests still pending at the end fail.
+async fn read_messages<R: AsyncRead + Unpin>(
+    reader: R,
+    writer: SharedWriter,
+    pending: PendingRequests,
+) {
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let message = serde_json::from_str(&line)
+            .map_err(anyhow::Error::from)
+            .and_then(IncomingMessage::parse);
+
+        match message {
+            Ok(IncomingMessage::Response(response)) => {
+                let sender = response
+                    .id
+                    .as_u64()
+                    .and_then(|id| pending.lock().unwrap().remove(&id));
+                if let Some(sender) = sender {
+                    let _ = sender.send(response);
+                }
+            }
+            Ok(IncomingMessage::Request(request)) => {
+                // Notifications need no answer; requests get a ping reply or
+                // a method-not-found error since the client offers no features
+                let Some(id) = request.id else {
+                    continue;
+                };
+                let response = match request.method.as_str() {
+                    "ping" => JsonRpcResponse::success(id, json!({})),
+                    method => JsonRpcResponse::error(
+                        id,
+                        error_codes::METHOD_NOT_FOUND,
+                        format!("Method not supported by client: {}", method),
+                    ),
+                };
+                if let Err(e) = write_message(&writer, &response).await {
+                    eprintln!("Failed to answer MCP server request: {}", e);
+                }
+            }
+            Err(e) => eprintln!("Ignoring malformed MCP message: {}", e),
+        }
+    }
+
+    // Dropping the senders wakes every waiting request with an error
+    pending.lock().unwrap().clear();
+}
+
+/// JSON-RPC over HTTP POST to a local streamable HTTP server
+#[derive(Debug)]
+pub struct HttpTransport {
+    client: reqwest::Client,
+    url: reqwest::Url,
+    session_id: StdMutex<Option<String>>,
+    next_id: AtomicU64,
+}
+
+impl HttpTransport {
+    pub fn new(url: &str) -> Result<Self> {
+        let url = reqwest::Url::parse(url)
+            .map_err(|e| anyhow!("Invalid MCP server URL '{}': {}", url, e))?;
+        let client = reqwest::Client::builder()
+            .timeout(request_timeout())
+            .build()?;
+
+        Ok(Self {
+            client,
+            url,
+            session_id: StdMutex::new(None),
+            next_id: AtomicU64::new(1),
+        })
+    }
+
+    async fn post(&self, message: &JsonRpcRequest) -> Result<reqwest::Response> {
+        let mut request = self
+            .client
+            .post(self.url.clone())
+            .header(
+                reqwest::header::ACCEPT,
+                "application/json, text/event-stream",
+            )
+            .json(message);
+        let session_id = self.session_id.lock().unwrap().clone();
+        if let Some(session_id) = session_id {
+            request = request.header(SESSION_HEADER, session_id);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| anyhow!("Failed to reach MCP server at {}: {}", self.url, e))?;
+
+        if let Some(session_id) = response
+            .headers()
+            .get(SESSION_HEADER)
+            .and_then(|value| value.to_str().ok())
+        {
+            *self.session_id.lock().unwrap() = Some(session_id.to_string());
+        }
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!(
+                "MCP server returned {} for '{}': {}",
+                status,
+                message.method,
+                body.trim()
+            ));
+        }
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl McpTransport for HttpTransport {
+    async fn request(&self, method: &str, params: Option<Value>) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let response = self.post(&JsonRpcRequest::new(id, method, params)).await?;
+
+        let is_event_stream = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("text/event-stream"));
+
+        let response = if is_event_stream {
+            read_event_stream(response, id).await?
+        } else {
+            response
+                .json::<JsonRpcResponse>()
+                .await
+                .map_err(|e| anyhow!("Invalid response from MCP server: {}", e))?
+        };
+        response.into_result()
+    }
+
+    async fn notify(&self, method: &str, params: Option<Value>) -> Result<()> {
+        self.post(&JsonRpcRequest::notification(method, params))
+            .await
+            .map(|_| ())
+    }
+}
+
+/// Read server-sent events until the response to request `id` arrives.
+/// Other messages on the stream (notifications, server requests) are skipped.
+async fn read_event_stream(mut response: reqwest::Response, id: u64) -> Result<JsonRpcResponse> {
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut finished = false;
+
+    loop {
+        while let Some(end) = buffer.windows(2).position(|window| window == b"\n\n") {
+            let event: Vec<u8> = buffer.drain(..end + 2).collect();
+            if let Some(response) = parse_event(&event, id) {
+                return Ok(response);
+            }
+        }
+
+        if finished {
+            return parse_event(&buffer, id).ok_or_else(|| {
+                anyhow!("MCP server ended the event stream without answering the request")
+            });
+        }
+
+        match response.chunk().await? {
+            // Carriage returns only appear in line endings; JSON escapes its own
+            Some(chunk) => buffer.extend(chunk.iter().filter(|byte| **byte != b'\r')),
+            None => finished = true,
+        }
+    }
+}
+
+fn parse_event(event: &[u8], id: u64) -> Option<JsonRpcResponse> {
+    let event = String::from_utf8_lossy(event);
+    let data: Vec<&str> = event
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(|data| data.strip_prefix(' ').unwrap_or(data))
+        .collect();
+    if data.is_empty() {
+        return None;
+    }
+
+    let value: Value = serde_json::from_str(&data.join("\n")).ok()?;
+    match IncomingMessage::parse(value).ok()? {
+        IncomingMessage::Response(response) if response.id.as_u64() == Some(id) => Some(response),
+        _ => None,
+    }
+}
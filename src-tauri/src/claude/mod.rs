@@ -3,12 +3,19 @@ use crate::claude::error::{ClaudeError, ClaudeResult};
 use crate::claude::types::{ContentBlock, MessageRole};
 use serde::{Deserialize, Serialize};
 
+pub mod audit;
 pub mod client;
 pub mod constants;
 pub mod error;
+pub mod ignore_files;
+pub mod mcp;
 pub mod message;
 pub mod message_processor;
 pub mod model_registry;
+pub mod secrets;
+pub mod secure_fs;
+#[cfg(test)]
+pub mod test_support;
 pub mod tools;
 pub mod types;
 pub mod whitelist;
@@ -24,6 +31,48 @@ pub struct ClaudeConfig {
     pub temperature: f32,
     #[serde(skip)]
     pub model_registry: ModelRegistry,
+    /// App-managed data directory (trash, caches); `None` outside of Tauri
+    #[serde(skip)]
+    pub data_dir: Option<std::path::PathBuf>,
+    /// Register the approval-gated git_commit tool
+    #[serde(skip)]
+    pub enable_git_commit: bool,
+    /// Symbol index shared with the file watcher; `None` uses a private in-memory index
+    #[serde(skip)]
+    pub symbol_index: Option<tools::SharedSymbolIndex>,
+    /// Approval policy and pending-request channel; `None` runs tools without asking
+    #[serde(skip)]
+    pub approval: Option<tools::ApprovalGate>,
+    /// Checkpoint store for snapshots taken before file edits; `None` disables them
+    #[serde(skip)]
+    pub checkpoints: Option<std::sync::Arc<tools::CheckpointStore>>,
+    /// Per-tool attempt timeouts overriding the engine defaults
+    #[serde(skip)]
+    pub tool_timeouts: std::collections::HashMap<String, std::time::Duration>,
+    /// Connected MCP servers whose tools are registered alongside the built-in ones
+    #[serde(skip)]
+    pub mcp: Option<std::sync::Arc<mcp::McpManager>>,
+    /// WebAssembly plugin tools loaded from the plugins directory
+    #[serde(skip)]
+    pub plugins: Vec<tools::WasmPluginTool>,
+    /// Per-tool circuit breakers shared across clients; `None` gives each engine its own
+    #[serde(skip)]
+    pub breakers: Option<std::sync::Arc<tools::ToolBreakers>>,
+    /// On-disk history that finished tool calls are written to; `None` keeps no history
+    #[serde(skip)]
+    pub history: Option<std::sync::Arc<tools::ExecutionHistoryStore>>,
+    /// Conversation or workflow that tool calls are recorded under
+    #[serde(skip)]
+    pub session_id: Option<String>,
+    /// Learned tool feedback and where it is delivered; `None` disables learning
+    #[serde(skip)]
+    pub feedback: Option<tools::LearnedFeedback>,
+    /// Scanner for secrets in tool results and outgoing messages; `None` sends content as is
+    #[serde(skip)]
+    pub secrets: Option<std::sync::Arc<secrets::SecretScanner>>,
+    /// Audit log for tool executions and API requests; `None` records nothing
+    #[serde(skip)]
+    pub audit: Option<std::sync::Arc<audit::AuditLog>>,
 }
 
 impl Default for ClaudeConfig {
@@ -34,6 +83,20 @@ impl Default for ClaudeConfig {
             max_tokens: model_config::DEFAULT_MAX_TOKENS,
             temperature: model_config::DEFAULT_TEMPERATURE,
             model_registry: ModelRegistry::new(),
+            data_dir: None,
+            enable_git_commit: false,
+            symbol_index: None,
+            approval: None,
+            checkpoints: None,
+            tool_timeouts: std::collections::HashMap::new(),
+            mcp: None,
+            plugins: Vec::new(),
+            breakers: None,
+            history: None,
+            session_id: None,
+            feedback: None,
+            secrets: None,
+            audit: None,
         }
     }
 }
@@ -424,6 +487,20 @@ mod tests {
             max_tokens: test_data::TEST_MAX_TOKENS,
             temperature: model_config::DEFAULT_TEMPERATURE,
             model_registry: ModelRegistry::new(),
+            data_dir: None,
+            enable_git_commit: false,
+            symbol_index: None,
+            approval: None,
+            checkpoints: None,
+            tool_timeouts: std::collections::HashMap::new(),
+            mcp: None,
+            plugins: Vec::new(),
+            breakers: None,
+            history: None,
+            session_id: None,
+            feedback: None,
+            secrets: None,
+            audit: None,
         };
         let model_info = config.get_model_info().unwrap();
 
@@ -0,0 +1,1164 @@
+//! Opening files beneath a whitelisted directory without a check-then-use race.
+//!
+//! `validate_path` decides whether a path may be used, but a tool that then
+//! opens the path by name can be redirected by a symlink swapped in between
+//! the check and the open. On Linux, paths are instead resolved relative to an
+//! open handle of the whitelisted root: with `openat2(RESOLVE_BENEATH)` where
+//! the kernel supports it, and otherwise with one `openat(O_NOFOLLOW)` per
+//! component, reading symlinks by hand. Either way resolution never leaves the
+//! root, and when symlinks are not followed none is crossed. Entries are
+//! stat'ed, listed, moved and removed with the matching `*at` calls relative
+//! to their parent directory's handle, never following a final symlink.
+//! Other platforms use paths by name.
+
+use anyhow::{anyhow, Result};
+use std::ffi::OsString;
+use std::fs::{File, Metadata};
+use std::path::{Path, PathBuf};
+
+/// How a file is opened
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileAccess {
+    /// Read an existing file
+    Read,
+    /// Write a file, creating or truncating it
+    Write,
+    /// Write a file that must not exist yet
+    CreateNew,
+}
+
+/// An entry of a directory read with [`SecureRoot::read_dir`]
+#[derive(Debug)]
+pub struct DirEntry {
+    pub name: OsString,
+    /// Metadata of the entry itself (not a symlink's target); `None` when
+    /// the entry disappeared while the directory was being read
+    pub metadata: Option<Metadata>,
+}
+
+/// An open handle to a whitelisted directory that files are resolved against
+#[derive(Debug)]
+pub struct SecureRoot {
+    root: PathBuf,
+    follow_symlinks: bool,
+    #[cfg(target_os = "linux")]
+    fd: std::os::fd::OwnedFd,
+}
+
+impl SecureRoot {
+    /// Open the (canonical) whitelisted directory `root`
+    pub fn open(root: &Path, follow_symlinks: bool) -> Result<Self> {
+        #[cfg(target_os = "linux")]
+        let fd = {
+            let path = sys::c_path(root.as_os_str())?;
+            sys::open(&path, libc::O_PATH | libc::O_DIRECTORY)
+                .map_err(|e| anyhow!("Failed to open '{}': {}", root.display(), e))?
+        };
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            follow_symlinks,
+            #[cfg(target_os = "linux")]
+            fd,
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Open a regular file given by an absolute path inside the root, or a
+    /// path relative to it. FIFOs, devices and sockets are refused; on Linux
+    /// they are opened with `O_NONBLOCK` first, so the open itself cannot
+    /// block a thread waiting for a writer that never comes.
+    pub fn open_file(&self, path: &Path, access: FileAccess) -> Result<File> {
+        let relative = self.relative(path)?;
+        if relative.as_os_str().is_empty() {
+            return Err(anyhow!("'{}' is a directory", path.display()));
+        }
+        let file = self.open_relative(path, relative, access)?;
+        let metadata = file
+            .metadata()
+            .map_err(|e| anyhow!("Failed to stat '{}': {}", path.display(), e))?;
+        if !metadata.is_file() {
+            return Err(anyhow!("'{}' is not a regular file", path.display()));
+        }
+        Ok(file)
+    }
+
+    /// Create a directory and any missing parents inside the root
+    pub fn create_dir_all(&self, path: &Path) -> Result<()> {
+        let relative = self.relative(path)?;
+        self.create_relative(path, relative)
+    }
+
+    /// Metadata of the entry at `path`, describing a symlink rather than its target
+    pub fn symlink_metadata(&self, path: &Path) -> Result<Metadata> {
+        let relative = self.relative(path)?;
+        self.stat_relative(path, relative)
+    }
+
+    /// Entries of the directory at `path`, in no particular order
+    pub fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>> {
+        let relative = self.relative(path)?;
+        self.read_dir_relative(path, relative)
+    }
+
+    /// Move the entry at `from` to `to` beneath `destination`, which may be
+    /// this root. Without `replace` an existing destination is never
+    /// overwritten. Across filesystems the entry is copied, recreating
+    /// symlinks rather than following them, and the original removed.
+    pub fn move_to(
+        &self,
+        from: &Path,
+        destination: &SecureRoot,
+        to: &Path,
+        replace: bool,
+    ) -> Result<()> {
+        self.move_relative(from, destination, to, replace)
+            .map_err(|e| {
+                anyhow!(
+                    "Failed to move '{}' to '{}': {}",
+                    from.display(),
+                    to.display(),
+                    e
+                )
+            })
+    }
+
+    /// Remove the entry at `path`, emptying a directory first without
+    /// following symlinks out of it
+    pub fn remove(&self, path: &Path) -> Result<()> {
+        self.remove_relative(path)
+            .map_err(|e| anyhow!("Failed to remove '{}': {}", path.display(), e))
+    }
+
+    fn relative<'a>(&self, path: &'a Path) -> Result<&'a Path> {
+        if path.is_relative() {
+            return Ok(path);
+        }
+        path.strip_prefix(&self.root).map_err(|_| {
+            anyhow!(
+                "Access denied: '{}' is outside '{}'",
+                path.display(),
+                self.root.display()
+            )
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl SecureRoot {
+    fn escape_error(&self, path: &Path) -> anyhow::Error {
+        anyhow!(
+            "Access denied: '{}' resolves outside '{}'",
+            path.display(),
+            self.root.display()
+        )
+    }
+
+    fn open_relative(&self, path: &Path, relative: &Path, access: FileAccess) -> Result<File> {
+        self.resolve(path, relative, access.flags()).map(File::from)
+    }
+
+    /// Open `relative` with `flags`, never leaving the root
+    fn resolve(
+        &self,
+        path: &Path,
+        relative: &Path,
+        flags: libc::c_int,
+    ) -> Result<std::os::fd::OwnedFd> {
+        let resolve = if self.follow_symlinks {
+            libc::RESOLVE_BENEATH | libc::RESOLVE_NO_MAGICLINKS
+        } else {
+            libc::RESOLVE_BENEATH | libc::RESOLVE_NO_SYMLINKS
+        };
+        let nofollow = if self.follow_symlinks {
+            0
+        } else {
+            libc::O_NOFOLLOW
+        };
+
+        let c_relative = sys::c_path(relative.as_os_str())?;
+        match sys::openat2(&self.fd, &c_relative, flags | nofollow, resolve) {
+            Ok(fd) => Ok(fd),
+            // Without openat2 (or when it refuses an absolute symlink that
+            // stays inside the root) walk the path one component at a time
+            Err(e)
+                if matches!(
+                    e.raw_os_error(),
+                    Some(libc::ENOSYS | libc::EPERM | libc::EXDEV | libc::EAGAIN)
+                ) =>
+            {
+                self.walk(path, relative, Walk::File(flags))
+            }
+            Err(e) if e.raw_os_error() == Some(libc::ELOOP) && !self.follow_symlinks => {
+                Err(anyhow!(
+                    "Access denied: Path '{}' contains a symlink",
+                    path.display()
+                ))
+            }
+            Err(e) => Err(anyhow!("Failed to open '{}': {}", path.display(), e)),
+        }
+    }
+
+    fn create_relative(&self, path: &Path, relative: &Path) -> Result<()> {
+        self.walk(path, relative, Walk::CreateDirectories)
+            .map(|_| ())
+    }
+
+    /// Open the directory at `relative` (the root itself when empty)
+    fn open_dir(
+        &self,
+        path: &Path,
+        relative: &Path,
+        flags: libc::c_int,
+    ) -> Result<std::os::fd::OwnedFd> {
+        if relative.as_os_str().is_empty() {
+            return sys::openat(&self.fd, c".", flags)
+                .map_err(|e| anyhow!("Failed to open '{}': {}", path.display(), e));
+        }
+        self.resolve(path, relative, flags | libc::O_DIRECTORY)
+    }
+
+    /// The directory holding the entry at `path`, and the entry's name in it
+    fn open_parent(&self, path: &Path) -> Result<(std::os::fd::OwnedFd, std::ffi::CString)> {
+        use std::path::Component;
+
+        let relative = self.relative(path)?;
+        let Some(Component::Normal(name)) = relative.components().next_back() else {
+            return Err(anyhow!(
+                "'{}' is not an entry inside '{}'",
+                path.display(),
+                self.root.display()
+            ));
+        };
+        let parent = relative.parent().unwrap_or(Path::new(""));
+        let parent = self.open_dir(path, parent, libc::O_PATH | libc::O_DIRECTORY)?;
+        Ok((parent, sys::c_path(name)?))
+    }
+
+    fn stat_relative(&self, path: &Path, relative: &Path) -> Result<Metadata> {
+        let fd = if relative.as_os_str().is_empty() {
+            self.fd.try_clone()?
+        } else {
+            let (parent, name) = self.open_parent(path)?;
+            sys::openat(&parent, &name, libc::O_PATH | libc::O_NOFOLLOW)
+                .map_err(|e| anyhow!("Failed to stat '{}': {}", path.display(), e))?
+        };
+        File::from(fd)
+            .metadata()
+            .map_err(|e| anyhow!("Failed to stat '{}': {}", path.display(), e))
+    }
+
+    fn read_dir_relative(&self, path: &Path, relative: &Path) -> Result<Vec<DirEntry>> {
+        let read_error =
+            |e: std::io::Error| anyhow!("Failed to read directory '{}': {}", path.display(), e);
+        let dir = self.open_dir(path, relative, libc::O_RDONLY)?;
+        let names =
+            sys::read_dir_names(dir.try_clone().map_err(read_error)?).map_err(read_error)?;
+
+        Ok(names
+            .into_iter()
+            .map(|name| {
+                use std::os::unix::ffi::OsStringExt;
+
+                let metadata = sys::openat(&dir, &name, libc::O_PATH | libc::O_NOFOLLOW)
+                    .and_then(|fd| File::from(fd).metadata())
+                    .ok();
+                DirEntry {
+                    name: OsString::from_vec(name.into_bytes()),
+                    metadata,
+                }
+            })
+            .collect())
+    }
+
+    fn move_relative(
+        &self,
+        from: &Path,
+        destination: &SecureRoot,
+        to: &Path,
+        replace: bool,
+    ) -> Result<()> {
+        let (from_parent, from_name) = self.open_parent(from)?;
+        let (to_parent, to_name) = destination.open_parent(to)?;
+        match rename_entry(&from_parent, &from_name, &to_parent, &to_name, replace) {
+            Err(e) if e.raw_os_error() == Some(libc::EXDEV) => {}
+            result => return Ok(result?),
+        }
+
+        // Across filesystems: copy next to the destination, swap the copy
+        // into place, then remove the original
+        let temp_name = sys::c_path(std::ffi::OsStr::new(&format!(
+            ".{}.moving",
+            uuid::Uuid::new_v4().simple()
+        )))?;
+        let copied = copy_entry(&from_parent, &from_name, &to_parent, &temp_name)
+            .and_then(|()| rename_entry(&to_parent, &temp_name, &to_parent, &to_name, replace));
+        if let Err(e) = copied {
+            let _ = remove_entry(&to_parent, &temp_name);
+            return Err(e.into());
+        }
+        Ok(remove_entry(&from_parent, &from_name)?)
+    }
+
+    fn remove_relative(&self, path: &Path) -> Result<()> {
+        let (parent, name) = self.open_parent(path)?;
+        Ok(remove_entry(&parent, &name)?)
+    }
+
+    /// Resolve `relative` one component at a time, each opened with
+    /// `O_NOFOLLOW` relative to the directory opened before it
+    fn walk(&self, path: &Path, relative: &Path, walk: Walk) -> Result<std::os::fd::OwnedFd> {
+        use crate::claude::constants::secure_fs::MAX_SYMLINK_FOLLOWS;
+        use std::collections::VecDeque;
+
+        let mut pending: VecDeque<std::ffi::OsString> = components(relative)
+            .ok_or_else(|| self.escape_error(path))?
+            .collect();
+        // Directories opened so far; the root is never popped
+        let mut directories = vec![self.fd.try_clone()?];
+        let mut follows = 0;
+
+        while let Some(name) = pending.pop_front() {
+            if name == ".." {
+                if directories.len() == 1 {
+                    return Err(self.escape_error(path));
+                }
+                directories.pop();
+                continue;
+            }
+
+            let is_last = pending.is_empty();
+            let parent = directories.last().expect("root is always open");
+            let c_name = sys::c_path(&name)?;
+            let opened = match walk {
+                Walk::File(flags) if is_last => {
+                    sys::openat(parent, &c_name, flags | libc::O_NOFOLLOW)
+                }
+                Walk::File(_) => open_directory(parent, &c_name, false),
+                Walk::CreateDirectories => open_directory(parent, &c_name, true),
+            };
+
+            match opened {
+                Ok(fd) if is_last => return Ok(fd),
+                Ok(fd) => directories.push(fd),
+                Err(e) if matches!(e.raw_os_error(), Some(libc::ELOOP | libc::ENOTDIR)) => {
+                    // A symlink refuses O_NOFOLLOW; anything else is a real error
+                    let Ok(target) = sys::readlinkat(parent, &c_name) else {
+                        return Err(anyhow!("Failed to open '{}': {}", path.display(), e));
+                    };
+                    if !self.follow_symlinks {
+                        return Err(anyhow!(
+                            "Access denied: Path '{}' contains a symlink",
+                            path.display()
+                        ));
+                    }
+                    follows += 1;
+                    if follows > MAX_SYMLINK_FOLLOWS {
+                        return Err(anyhow!(
+                            "Too many levels of symbolic links in '{}'",
+                            path.display()
+                        ));
+                    }
+
+                    // Absolute targets restart at the root and must stay inside it
+                    let target = if target.is_absolute() {
+                        directories.truncate(1);
+                        target
+                            .strip_prefix(&self.root)
+                            .map_err(|_| self.escape_error(path))?
+                            .to_path_buf()
+                    } else {
+                        target
+                    };
+                    let spliced: Vec<_> = components(&target)
+                        .ok_or_else(|| self.escape_error(path))?
+                        .collect();
+                    for name in spliced.into_iter().rev() {
+                        pending.push_front(name);
+                    }
+                }
+                Err(e) => return Err(anyhow!("Failed to open '{}': {}", path.display(), e)),
+            }
+        }
+
+        // The path ended at a directory, e.g. the root itself or `dir/..`
+        match walk {
+            Walk::File(_) => Err(anyhow!("'{}' is a directory", path.display())),
+            Walk::CreateDirectories => Ok(directories.pop().expect("root is always open")),
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl SecureRoot {
+    fn open_relative(&self, path: &Path, relative: &Path, access: FileAccess) -> Result<File> {
+        let mut options = std::fs::OpenOptions::new();
+        match access {
+            FileAccess::Read => options.read(true),
+            FileAccess::Write => options.write(true).create(true).truncate(true),
+            FileAccess::CreateNew => options.write(true).create_new(true),
+        };
+        options
+            .open(self.root.join(relative))
+            .map_err(|e| anyhow!("Failed to open '{}': {}", path.display(), e))
+    }
+
+    fn create_relative(&self, path: &Path, relative: &Path) -> Result<()> {
+        std::fs::create_dir_all(self.root.join(relative))
+            .map_err(|e| anyhow!("Failed to create directory '{}': {}", path.display(), e))
+    }
+
+    fn stat_relative(&self, path: &Path, relative: &Path) -> Result<Metadata> {
+        std::fs::symlink_metadata(self.root.join(relative))
+            .map_err(|e| anyhow!("Failed to stat '{}': {}", path.display(), e))
+    }
+
+    fn read_dir_relative(&self, path: &Path, relative: &Path) -> Result<Vec<DirEntry>> {
+        let entries = std::fs::read_dir(self.root.join(relative))
+            .map_err(|e| anyhow!("Failed to read directory '{}': {}", path.display(), e))?;
+        Ok(entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| DirEntry {
+                name: entry.file_name(),
+                metadata: entry.metadata().ok(),
+            })
+            .collect())
+    }
+
+    fn move_relative(
+        &self,
+        from: &Path,
+        destination: &SecureRoot,
+        to: &Path,
+        replace: bool,
+    ) -> Result<()> {
+        let source = self.root.join(self.relative(from)?);
+        let target = destination.root.join(destination.relative(to)?);
+        if !replace && std::fs::symlink_metadata(&target).is_ok() {
+            return Err(anyhow!("'{}' already exists", to.display()));
+        }
+        match std::fs::rename(&source, &target) {
+            Err(e) if e.kind() == std::io::ErrorKind::CrossesDevices => {}
+            result => return Ok(result?),
+        }
+
+        copy_recursive(&source, &target)?;
+        self.remove_relative(from)
+    }
+
+    fn remove_relative(&self, path: &Path) -> Result<()> {
+        let relative = self.relative(path)?;
+        if relative.as_os_str().is_empty() {
+            return Err(anyhow!("Refusing to remove '{}'", path.display()));
+        }
+        let target = self.root.join(relative);
+        if std::fs::symlink_metadata(&target)?.is_dir() {
+            std::fs::remove_dir_all(&target)?;
+        } else {
+            std::fs::remove_file(&target)?;
+        }
+        Ok(())
+    }
+}
+
+/// Recursively copy a file or directory. Symlinks are recreated rather than
+/// followed, so a link inside a moved tree never pulls in the file it points at.
+#[cfg(not(target_os = "linux"))]
+fn copy_recursive(source: &Path, destination: &Path) -> std::io::Result<()> {
+    let metadata = std::fs::symlink_metadata(source)?;
+    if metadata.file_type().is_symlink() {
+        copy_symlink(source, destination)
+    } else if metadata.is_dir() {
+        std::fs::create_dir(destination)?;
+        for entry in std::fs::read_dir(source)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &destination.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else if metadata.is_file() {
+        std::fs::copy(source, destination).map(|_| ())
+    } else {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("'{}' is not a regular file or directory", source.display()),
+        ))
+    }
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn copy_symlink(source: &Path, destination: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(std::fs::read_link(source)?, destination)
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(source: &Path, _destination: &Path) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        format!("Refusing to copy symlink '{}'", source.display()),
+    ))
+}
+
+#[cfg(target_os = "linux")]
+impl FileAccess {
+    /// Open flags; `O_NONBLOCK` only matters for the special files that
+    /// `open_file` refuses, and has no effect on regular files
+    fn flags(self) -> libc::c_int {
+        let flags = match self {
+            FileAccess::Read => libc::O_RDONLY,
+            FileAccess::Write => libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC,
+            FileAccess::CreateNew => libc::O_WRONLY | libc::O_CREAT | libc::O_EXCL,
+        };
+        flags | libc::O_NONBLOCK
+    }
+}
+
+/// What the last component of a walked path is opened as
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy)]
+enum Walk {
+    File(libc::c_int),
+    CreateDirectories,
+}
+
+/// Components of a relative path as names and `..`; `None` for absolute paths
+#[cfg(target_os = "linux")]
+fn components(path: &Path) -> Option<impl Iterator<Item = std::ffi::OsString> + '_> {
+    use std::path::Component;
+
+    if path
+        .components()
+        .any(|c| matches!(c, Component::RootDir | Component::Prefix(_)))
+    {
+        return None;
+    }
+    Some(path.components().filter_map(|c| match c {
+        Component::Normal(name) => Some(name.to_os_string()),
+        Component::ParentDir => Some("..".into()),
+        _ => None,
+    }))
+}
+
+/// Open a directory inside `parent` without following a symlink, creating it
+/// first when asked to
+#[cfg(target_os = "linux")]
+fn open_directory(
+    parent: &std::os::fd::OwnedFd,
+    name: &std::ffi::CStr,
+    create: bool,
+) -> std::io::Result<std::os::fd::OwnedFd> {
+    let flags = libc::O_PATH | libc::O_DIRECTORY | libc::O_NOFOLLOW;
+    match sys::openat(parent, name, flags) {
+        Err(e) if create && e.raw_os_error() == Some(libc::ENOENT) => {
+            match sys::mkdirat(parent, name) {
+                Err(e) if e.raw_os_error() != Some(libc::EEXIST) => return Err(e),
+                _ => {}
+            }
+            sys::openat(parent, name, flags)
+        }
+        result => result,
+    }
+}
+
+/// Rename an entry between two directories. Without `replace` an existing
+/// destination is an error, checked atomically where the filesystem supports it.
+#[cfg(target_os = "linux")]
+fn rename_entry(
+    from_parent: &std::os::fd::OwnedFd,
+    from_name: &std::ffi::CStr,
+    to_parent: &std::os::fd::OwnedFd,
+    to_name: &std::ffi::CStr,
+    replace: bool,
+) -> std::io::Result<()> {
+    if replace {
+        return sys::renameat2(from_parent, from_name, to_parent, to_name, 0);
+    }
+    match sys::renameat2(
+        from_parent,
+        from_name,
+        to_parent,
+        to_name,
+        libc::RENAME_NOREPLACE,
+    ) {
+        // Filesystems without RENAME_NOREPLACE get a check before the rename
+        Err(e) if e.raw_os_error() == Some(libc::EINVAL) => {
+            if sys::openat(to_parent, to_name, libc::O_PATH | libc::O_NOFOLLOW).is_ok() {
+                return Err(std::io::Error::from_raw_os_error(libc::EEXIST));
+            }
+            sys::renameat2(from_parent, from_name, to_parent, to_name, 0)
+        }
+        result => result,
+    }
+}
+
+/// Copy an entry between two directories without following symlinks: links
+/// are recreated, directories copied entry by entry, and anything other than
+/// a regular file, directory or symlink refused
+#[cfg(target_os = "linux")]
+fn copy_entry(
+    from_parent: &std::os::fd::OwnedFd,
+    from_name: &std::ffi::CStr,
+    to_parent: &std::os::fd::OwnedFd,
+    to_name: &std::ffi::CStr,
+) -> std::io::Result<()> {
+    let file_type = File::from(sys::openat(
+        from_parent,
+        from_name,
+        libc::O_PATH | libc::O_NOFOLLOW,
+    )?)
+    .metadata()?
+    .file_type();
+
+    if file_type.is_symlink() {
+        use std::os::unix::ffi::OsStringExt;
+
+        // Link targets read from the kernel never contain a NUL byte
+        let target = sys::readlinkat(from_parent, from_name)?.into_os_string();
+        let target = std::ffi::CString::new(target.into_vec())
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        sys::symlinkat(&target, to_parent, to_name)
+    } else if file_type.is_dir() {
+        let source = sys::openat(
+            from_parent,
+            from_name,
+            libc::O_RDONLY | libc::O_DIRECTORY | libc::O_NOFOLLOW,
+        )?;
+        sys::mkdirat(to_parent, to_name)?;
+        let target = sys::openat(
+            to_parent,
+            to_name,
+            libc::O_PATH | libc::O_DIRECTORY | libc::O_NOFOLLOW,
+        )?;
+        for name in sys::read_dir_names(source.try_clone()?)? {
+            copy_entry(&source, &name, &target, &name)?;
+        }
+        Ok(())
+    } else if file_type.is_file() {
+        // O_NONBLOCK keeps a FIFO swapped in after the check from blocking the
+        // open; the handle is checked again before anything is read
+        let mut source = File::from(sys::openat(
+            from_parent,
+            from_name,
+            libc::O_RDONLY | libc::O_NOFOLLOW | libc::O_NONBLOCK,
+        )?);
+        if !source.metadata()?.is_file() {
+            return Err(std::io::Error::from_raw_os_error(libc::EINVAL));
+        }
+        let mut target = File::from(sys::openat(
+            to_parent,
+            to_name,
+            libc::O_WRONLY | libc::O_CREAT | libc::O_EXCL | libc::O_NOFOLLOW,
+        )?);
+        std::io::copy(&mut source, &mut target).map(|_| ())
+    } else {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "not a regular file, directory or symlink",
+        ))
+    }
+}
+
+/// Remove an entry, emptying directories first without following symlinks
+/// out of them
+#[cfg(target_os = "linux")]
+fn remove_entry(parent: &std::os::fd::OwnedFd, name: &std::ffi::CStr) -> std::io::Result<()> {
+    match sys::unlinkat(parent, name, 0) {
+        Err(e) if e.raw_os_error() == Some(libc::EISDIR) => {
+            let dir = sys::openat(
+                parent,
+                name,
+                libc::O_RDONLY | libc::O_DIRECTORY | libc::O_NOFOLLOW,
+            )?;
+            for child in sys::read_dir_names(dir.try_clone()?)? {
+                remove_entry(&dir, &child)?;
+            }
+            sys::unlinkat(parent, name, libc::AT_REMOVEDIR)
+        }
+        result => result,
+    }
+}
+
+/// Thin wrappers around the `*at` system calls
+#[cfg(target_os = "linux")]
+mod sys {
+    use std::ffi::{CStr, CString, OsStr, OsString};
+    use std::io;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::os::unix::ffi::{OsStrExt, OsStringExt};
+    use std::path::PathBuf;
+
+    pub fn c_path(path: &OsStr) -> anyhow::Result<CString> {
+        CString::new(path.as_bytes())
+            .map_err(|_| anyhow::anyhow!("Invalid path: contains null bytes"))
+    }
+
+    fn owned(fd: libc::c_long) -> io::Result<OwnedFd> {
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the kernel just returned this descriptor and nothing else owns it
+        Ok(unsafe { OwnedFd::from_raw_fd(fd as libc::c_int) })
+    }
+
+    pub fn open(path: &CStr, flags: libc::c_int) -> io::Result<OwnedFd> {
+        // SAFETY: `path` is a valid NUL-terminated string
+        owned(unsafe { libc::open(path.as_ptr(), flags | libc::O_CLOEXEC) }.into())
+    }
+
+    pub fn openat(dir: &OwnedFd, name: &CStr, flags: libc::c_int) -> io::Result<OwnedFd> {
+        // SAFETY: `dir` is an open descriptor and `name` a valid NUL-terminated string
+        owned(
+            unsafe {
+                libc::openat(
+                    dir.as_raw_fd(),
+                    name.as_ptr(),
+                    flags | libc::O_CLOEXEC,
+                    0o666 as libc::c_uint,
+                )
+            }
+            .into(),
+        )
+    }
+
+    pub fn openat2(
+        dir: &OwnedFd,
+        path: &CStr,
+        flags: libc::c_int,
+        resolve: u64,
+    ) -> io::Result<OwnedFd> {
+        // SAFETY: `open_how` is plain data, for which all zeroes is valid
+        let mut how: libc::open_how = unsafe { std::mem::zeroed() };
+        how.flags = (flags | libc::O_CLOEXEC) as u64;
+        how.mode = if flags & libc::O_CREAT != 0 { 0o666 } else { 0 };
+        how.resolve = resolve;
+        // SAFETY: every pointer is valid for the duration of the call and the
+        // size passed is the size of `how`
+        owned(unsafe {
+            libc::syscall(
+                libc::SYS_openat2,
+                dir.as_raw_fd(),
+                path.as_ptr(),
+                &how as *const libc::open_how,
+                std::mem::size_of::<libc::open_how>(),
+            )
+        })
+    }
+
+    pub fn readlinkat(dir: &OwnedFd, name: &CStr) -> io::Result<PathBuf> {
+        let mut buffer = vec![0u8; libc::PATH_MAX as usize];
+        // SAFETY: the buffer is writable for the length passed
+        let len = unsafe {
+            libc::readlinkat(
+                dir.as_raw_fd(),
+                name.as_ptr(),
+                buffer.as_mut_ptr().cast(),
+                buffer.len(),
+            )
+        };
+        if len < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        buffer.truncate(len as usize);
+        Ok(PathBuf::from(OsString::from_vec(buffer)))
+    }
+
+    pub fn mkdirat(dir: &OwnedFd, name: &CStr) -> io::Result<()> {
+        // SAFETY: `dir` is an open descriptor and `name` a valid NUL-terminated string
+        if unsafe { libc::mkdirat(dir.as_raw_fd(), name.as_ptr(), 0o777) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    pub fn symlinkat(target: &CStr, dir: &OwnedFd, name: &CStr) -> io::Result<()> {
+        // SAFETY: both strings are valid and NUL-terminated, `dir` is open
+        if unsafe { libc::symlinkat(target.as_ptr(), dir.as_raw_fd(), name.as_ptr()) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    pub fn unlinkat(dir: &OwnedFd, name: &CStr, flags: libc::c_int) -> io::Result<()> {
+        // SAFETY: `dir` is an open descriptor and `name` a valid NUL-terminated string
+        if unsafe { libc::unlinkat(dir.as_raw_fd(), name.as_ptr(), flags) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    pub fn renameat2(
+        from_dir: &OwnedFd,
+        from_name: &CStr,
+        to_dir: &OwnedFd,
+        to_name: &CStr,
+        flags: libc::c_uint,
+    ) -> io::Result<()> {
+        // SAFETY: both descriptors are open and both names valid NUL-terminated strings
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_renameat2,
+                from_dir.as_raw_fd(),
+                from_name.as_ptr(),
+                to_dir.as_raw_fd(),
+                to_name.as_ptr(),
+                flags,
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Names in a directory opened for reading, without `.` and `..`
+    pub fn read_dir_names(dir: OwnedFd) -> io::Result<Vec<CString>> {
+        // SAFETY: `dir` is an open directory descriptor; on success the stream
+        // owns it and closes it in closedir
+        let stream = unsafe { libc::fdopendir(dir.as_raw_fd()) };
+        if stream.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        std::mem::forget(dir);
+
+        let mut names = Vec::new();
+        let result = loop {
+            // SAFETY: errno is thread-local; clearing it tells the end of the
+            // stream apart from an error
+            unsafe { *libc::__errno_location() = 0 };
+            // SAFETY: `stream` is open until closedir below
+            let entry = unsafe { libc::readdir(stream) };
+            if entry.is_null() {
+                let error = io::Error::last_os_error();
+                break match error.raw_os_error() {
+                    Some(0) | None => Ok(names),
+                    _ => Err(error),
+                };
+            }
+            // SAFETY: readdir returned a valid entry with a NUL-terminated name
+            let name = unsafe { CStr::from_ptr((*entry).d_name.as_ptr()) };
+            if name != c"." && name != c".." {
+                names.push(name.to_owned());
+            }
+        };
+        // SAFETY: `stream` came from fdopendir and is closed once
+        unsafe { libc::closedir(stream) };
+        result
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::claude::test_support::TempDir;
+    use std::io::{Read, Write};
+    use std::os::unix::fs::symlink;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    /// A whitelisted root next to a directory outside it holding a secret
+    fn sandbox() -> (TempDir, PathBuf, PathBuf) {
+        let base = TempDir::new();
+        let root = base.join("root");
+        let outside = base.join("outside");
+        std::fs::create_dir_all(root.join("real")).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(root.join("real/file"), "inside").unwrap();
+        std::fs::write(outside.join("file"), "secret").unwrap();
+        (base, root, outside)
+    }
+
+    fn read(root: &SecureRoot, path: &Path) -> Result<String> {
+        let mut content = String::new();
+        root.open_file(path, FileAccess::Read)?
+            .read_to_string(&mut content)?;
+        Ok(content)
+    }
+
+    fn walk_read(root: &SecureRoot, path: &Path) -> Result<String> {
+        let relative = root.relative(path)?;
+        let mut content = String::new();
+        File::from(root.walk(path, relative, Walk::File(libc::O_RDONLY))?)
+            .read_to_string(&mut content)?;
+        Ok(content)
+    }
+
+    #[test]
+    fn test_symlinks_cannot_leave_the_root() {
+        let (_base, root_dir, outside) = sandbox();
+        symlink(&outside, root_dir.join("escape")).unwrap();
+        symlink("../outside/file", root_dir.join("relative_escape")).unwrap();
+        symlink("real", root_dir.join("inner")).unwrap();
+        symlink(root_dir.join("real/file"), root_dir.join("absolute_inner")).unwrap();
+
+        for follow_symlinks in [false, true] {
+            let root = SecureRoot::open(&root_dir, follow_symlinks).unwrap();
+            for open in [read, walk_read] {
+                assert_eq!(open(&root, &root_dir.join("real/file")).unwrap(), "inside");
+                assert!(open(&root, &root_dir.join("escape/file")).is_err());
+                assert!(open(&root, &root_dir.join("relative_escape")).is_err());
+                assert!(open(&root, Path::new("real/../../outside/file")).is_err());
+                assert!(open(&root, &outside.join("file")).is_err());
+
+                let inner = open(&root, &root_dir.join("inner/file"));
+                let absolute_inner = open(&root, &root_dir.join("absolute_inner"));
+                if follow_symlinks {
+                    assert_eq!(inner.unwrap(), "inside");
+                    assert_eq!(absolute_inner.unwrap(), "inside");
+                } else {
+                    assert!(inner.unwrap_err().to_string().contains("symlink"));
+                    assert!(absolute_inner.is_err());
+                }
+            }
+
+            assert!(root
+                .open_file(&root_dir.join("escape/planted"), FileAccess::Write)
+                .is_err());
+            assert!(root
+                .create_dir_all(&root_dir.join("escape/nested"))
+                .is_err());
+        }
+        assert!(!outside.join("planted").exists());
+        assert!(!outside.join("nested").exists());
+
+        let root = SecureRoot::open(&root_dir, false).unwrap();
+        root.create_dir_all(&root_dir.join("a/b/c")).unwrap();
+        root.open_file(&root_dir.join("a/b/c/new.txt"), FileAccess::Write)
+            .unwrap()
+            .write_all(b"written")
+            .unwrap();
+        assert_eq!(
+            std::fs::read_to_string(root_dir.join("a/b/c/new.txt")).unwrap(),
+            "written"
+        );
+    }
+
+    /// Keep swapping `root/dir` between the real directory and a symlink out of
+    /// the root until the returned flag is set
+    fn start_swapper(
+        root_dir: &Path,
+        outside: &Path,
+    ) -> (Arc<AtomicBool>, std::thread::JoinHandle<()>) {
+        symlink(outside, root_dir.join("evil")).unwrap();
+        let stop = Arc::new(AtomicBool::new(false));
+        let swapper = {
+            let stop = stop.clone();
+            let root_dir = root_dir.to_path_buf();
+            std::thread::spawn(move || {
+                let (dir, real, evil) = (
+                    root_dir.join("dir"),
+                    root_dir.join("real"),
+                    root_dir.join("evil"),
+                );
+                while !stop.load(Ordering::Relaxed) {
+                    let _ = std::fs::rename(&real, &dir);
+                    let _ = std::fs::rename(&dir, &real);
+                    let _ = std::fs::rename(&evil, &dir);
+                    let _ = std::fs::rename(&dir, &evil);
+                }
+            })
+        };
+        (stop, swapper)
+    }
+
+    /// Move an entry back once the swapper has put `real` back in place
+    fn move_back(root: &SecureRoot, from: &Path, to: &Path) {
+        while root.move_to(from, root, to, false).is_err() {
+            std::thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn test_racing_symlink_swaps_never_escape() {
+        let (_base, root_dir, outside) = sandbox();
+        let (stop, swapper) = start_swapper(&root_dir, &outside);
+
+        let target = root_dir.join("dir/file");
+        let planted = root_dir.join("dir/planted");
+        let mut inside_reads = 0;
+        for i in 0..4000 {
+            let root = SecureRoot::open(&root_dir, i % 2 == 0).unwrap();
+            for open in [read, walk_read] {
+                if let Ok(content) = open(&root, &target) {
+                    assert_eq!(content, "inside");
+                    inside_reads += 1;
+                }
+            }
+            let _ = root.open_file(&planted, FileAccess::Write);
+            assert!(!outside.join("planted").exists());
+        }
+
+        stop.store(true, Ordering::Relaxed);
+        swapper.join().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(outside.join("file")).unwrap(),
+            "secret"
+        );
+        assert!(inside_reads > 0, "the race never let a read through");
+    }
+
+    #[test]
+    fn test_entries_are_stat_listed_and_moved_beneath_the_root() {
+        let (_base, root_dir, outside) = sandbox();
+        symlink(&outside, root_dir.join("escape")).unwrap();
+        std::fs::create_dir_all(root_dir.join("full")).unwrap();
+        std::fs::write(root_dir.join("full/kept"), "kept").unwrap();
+
+        for follow_symlinks in [false, true] {
+            let root = SecureRoot::open(&root_dir, follow_symlinks).unwrap();
+
+            // The link itself is described, and nothing behind it is reachable
+            assert!(root
+                .symlink_metadata(&root_dir.join("escape"))
+                .unwrap()
+                .is_symlink());
+            assert!(root
+                .symlink_metadata(&root_dir.join("escape/file"))
+                .is_err());
+            assert!(root.read_dir(&root_dir.join("escape")).is_err());
+
+            let entries = root.read_dir(&root_dir).unwrap();
+            let escape = entries.iter().find(|e| e.name == "escape").unwrap();
+            assert!(escape.metadata.as_ref().unwrap().is_symlink());
+            assert!(entries.iter().any(|e| e.name == "real"));
+
+            // Moves through the link are refused in either direction
+            assert!(root
+                .move_to(
+                    &root_dir.join("escape/file"),
+                    &root,
+                    &root_dir.join("stolen"),
+                    false
+                )
+                .is_err());
+            assert!(root
+                .move_to(
+                    &root_dir.join("real/file"),
+                    &root,
+                    &root_dir.join("escape/planted"),
+                    false
+                )
+                .is_err());
+
+            let (file, moved) = (root_dir.join("real/file"), root_dir.join("moved"));
+            root.move_to(&file, &root, &moved, false).unwrap();
+            assert_eq!(read(&root, &moved).unwrap(), "inside");
+            root.move_to(&moved, &root, &file, false).unwrap();
+
+            // Without `replace` an existing destination is kept, and a
+            // directory never replaces a non-empty one
+            std::fs::write(root_dir.join("other"), "other").unwrap();
+            assert!(root
+                .move_to(&root_dir.join("other"), &root, &file, false)
+                .is_err());
+            assert!(root
+                .move_to(&root_dir.join("real"), &root, &root_dir.join("full"), true)
+                .is_err());
+            assert_eq!(read(&root, &file).unwrap(), "inside");
+            assert_eq!(read(&root, &root_dir.join("full/kept")).unwrap(), "kept");
+        }
+        assert!(outside.join("file").exists());
+        assert!(!outside.join("planted").exists());
+    }
+
+    #[test]
+    fn test_copied_trees_keep_symlinks_and_removal_stays_inside() {
+        let (_base, root_dir, outside) = sandbox();
+        symlink(&outside, root_dir.join("real/escape")).unwrap();
+        let root = SecureRoot::open(&root_dir, false).unwrap();
+        let (real, copy) = (
+            sys::c_path("real".as_ref()).unwrap(),
+            sys::c_path("copy".as_ref()).unwrap(),
+        );
+
+        // The fallback for moves across filesystems
+        copy_entry(&root.fd, &real, &root.fd, &copy).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(root_dir.join("copy/file")).unwrap(),
+            "inside"
+        );
+        let link = root_dir.join("copy/escape");
+        assert!(std::fs::symlink_metadata(&link).unwrap().is_symlink());
+        assert_eq!(std::fs::read_link(&link).unwrap(), outside);
+
+        remove_entry(&root.fd, &copy).unwrap();
+        remove_entry(&root.fd, &real).unwrap();
+        assert!(!root_dir.join("copy").exists());
+        assert!(!root_dir.join("real").exists());
+        assert_eq!(
+            std::fs::read_to_string(outside.join("file")).unwrap(),
+            "secret"
+        );
+    }
+
+    #[test]
+    fn test_special_files_are_refused_without_blocking() {
+        let (_base, root_dir, _outside) = sandbox();
+        let fifo = root_dir.join("real/fifo");
+        let c_fifo = sys::c_path(fifo.as_os_str()).unwrap();
+        // SAFETY: `c_fifo` is a valid NUL-terminated path
+        assert_eq!(unsafe { libc::mkfifo(c_fifo.as_ptr(), 0o600) }, 0);
+        let root = SecureRoot::open(&root_dir, false).unwrap();
+
+        // Without a writer, a blocking open of the FIFO would never return
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let results = [FileAccess::Read, FileAccess::Write]
+                .map(|access| root.open_file(Path::new("real/fifo"), access).is_err());
+            let _ = sender.send(results);
+        });
+        let refused = receiver
+            .recv_timeout(std::time::Duration::from_secs(5))
+            .expect("opening a FIFO should not block");
+        assert_eq!(refused, [true, true]);
+    }
+
+    #[test]
+    fn test_racing_symlink_swaps_never_move_or_trash_outside_files() {
+        let (base, root_dir, outside) = sandbox();
+        std::fs::create_dir_all(base.join("trash")).unwrap();
+        std::fs::write(root_dir.join("seed"), "seed").unwrap();
+        let (stop, swapper) = start_swapper(&root_dir, &outside);
+
+        let (target, planted) = (root_dir.join("dir/file"), root_dir.join("dir/planted"));
+        let (file, seed) = (root_dir.join("real/file"), root_dir.join("seed"));
+        let trashed = base.join("trash/file");
+        let mut moves = 0;
+        for i in 0..2000 {
+            let root = SecureRoot::open(&root_dir, i % 2 == 0).unwrap();
+            let trash = SecureRoot::open(&base.join("trash"), false).unwrap();
+
+            // move_file out of the swapped directory
+            let moved = root_dir.join("moved");
+            if root.move_to(&target, &root, &moved, false).is_ok() {
+                assert_eq!(read(&root, &moved).unwrap(), "inside");
+                move_back(&root, &moved, &file);
+                moves += 1;
+            }
+            // delete_file, which moves into the trash
+            if root.move_to(&target, &trash, &trashed, false).is_ok() {
+                assert_eq!(std::fs::read_to_string(&trashed).unwrap(), "inside");
+                while trash.move_to(&trashed, &root, &file, false).is_err() {
+                    std::thread::yield_now();
+                }
+                moves += 1;
+            }
+            // move_file into the swapped directory
+            if root.move_to(&seed, &root, &planted, false).is_ok() {
+                move_back(&root, &root_dir.join("real/planted"), &seed);
+            }
+
+            assert!(outside.join("file").exists());
+            assert!(!outside.join("planted").exists());
+        }
+
+        stop.store(true, Ordering::Relaxed);
+        swapper.join().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(outside.join("file")).unwrap(),
+            "secret"
+        );
+        assert!(moves > 0, "the race never let a move through");
+    }
+}
@@ -0,0 +1,66 @@
+//! Fixtures shared by the unit tests
+
+use crate::claude::whitelist::WhitelistConfig;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// A fresh, canonical directory under the system temp directory. It is
+/// removed with everything in it when dropped, so a failing test cleans up too.
+#[derive(Debug)]
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new() -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "rustic-agent-test-{}",
+            uuid::Uuid::new_v4().simple()
+        ));
+        std::fs::create_dir_all(&path).unwrap();
+        Self {
+            path: path.canonicalize().unwrap(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Default for TempDir {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+/// A temp directory and an enabled whitelist holding just that directory
+pub fn whitelisted_temp_dir() -> (TempDir, Arc<RwLock<WhitelistConfig>>) {
+    let dir = TempDir::new();
+    let mut whitelist = WhitelistConfig::default();
+    whitelist.add_directory(dir.path()).unwrap();
+    whitelist.set_enabled(true);
+    (dir, Arc::new(RwLock::new(whitelist)))
+}
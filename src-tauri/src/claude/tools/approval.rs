@@ -0,0 +1,698 @@
+//! Human-in-the-loop approval for tool calls. An [`ApprovalPolicy`] decides
+//! per tool and path whether a call is allowed, denied or needs the user's
+//! consent; the [`ApprovalManager`] parks calls that need consent until the
+//! frontend answers them.
+
+use crate::claude::constants::approval::{
+    APPROVAL_TIMEOUT_SECS, MAX_DIFF_PREVIEW_BYTES, MAX_DIFF_SOURCE_BYTES,
+};
+use crate::claude::secure_fs::FileAccess;
+use crate::claude::tools::{resolve_tool_path, with_tool_root};
+use crate::claude::whitelist::{resolve_path, FileOperation, WhitelistConfig};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{oneshot, Mutex, RwLock};
+
+/// What happens when a tool is called. Ordered from least to most restrictive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolPermission {
+    Allow,
+    Ask,
+    Deny,
+}
+
+/// Permission override for calls of a tool that touch matching paths
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermissionRule {
+    /// Tool name, or "*" for every tool
+    pub tool: String,
+    /// Glob matched against the absolute target path, or against the file
+    /// name when the pattern has no separator. `None` matches every call.
+    #[serde(default)]
+    pub path_pattern: Option<String>,
+    pub permission: ToolPermission,
+}
+
+impl PermissionRule {
+    fn matches_tool(&self, tool_name: &str) -> bool {
+        self.tool == "*" || self.tool == tool_name
+    }
+
+    fn matches_path(&self, path: Option<&Path>) -> bool {
+        let Some(pattern) = &self.path_pattern else {
+            return true;
+        };
+        let Some(path) = path else {
+            return false;
+        };
+        let Ok(pattern) = glob::Pattern::new(pattern) else {
+            return false;
+        };
+        if pattern.matches_path(path) {
+            return true;
+        }
+        !pattern.as_str().contains('/')
+            && path
+                .file_name()
+                .is_some_and(|name| pattern.matches(&name.to_string_lossy()))
+    }
+}
+
+/// Per-tool approval policy with path-specific rules
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalPolicy {
+    /// Permission for tools not listed in `tools`
+    pub default_permission: ToolPermission,
+    /// Per-tool permission used when no rule matches
+    #[serde(default)]
+    pub tools: HashMap<String, ToolPermission>,
+    /// Path rules; for each target path the first matching rule wins
+    #[serde(default)]
+    pub rules: Vec<PermissionRule>,
+}
+
+impl Default for ApprovalPolicy {
+    fn default() -> Self {
+        // Built-in tools that only read run freely; everything else, including
+        // plugins and MCP tools this policy knows nothing about, asks first.
+        // cargo_check is not among them: build scripts, proc macros and tests
+        // run repository code.
+        let read_only = [
+            "read_file",
+            "list_directory",
+            "file_info",
+            "git_status",
+            "git_diff",
+            "git_log",
+            "git_show",
+            "git_blame",
+            "code_outline",
+            "find_symbol",
+        ];
+        let tools = read_only
+            .into_iter()
+            .map(|name| (name.to_string(), ToolPermission::Allow))
+            .collect();
+
+        Self {
+            default_permission: ToolPermission::Ask,
+            tools,
+            rules: Vec::new(),
+        }
+    }
+}
+
+impl ApprovalPolicy {
+    /// Decide the permission for a call. Target paths are resolved the way the
+    /// tool will resolve them, so `..` or symlinked spellings cannot slip past a
+    /// rule; a path that cannot be resolved needs at least `Ask`. When the call
+    /// targets several paths the most restrictive decision applies.
+    pub fn permission_for(
+        &self,
+        tool_name: &str,
+        input: &Value,
+        whitelist: &WhitelistConfig,
+    ) -> ToolPermission {
+        let tool_default = self
+            .tools
+            .get(tool_name)
+            .copied()
+            .unwrap_or(self.default_permission);
+
+        let decide = |path: Option<&Path>| {
+            self.rules
+                .iter()
+                .find(|rule| rule.matches_tool(tool_name) && rule.matches_path(path))
+                .map(|rule| rule.permission)
+                .unwrap_or(tool_default)
+        };
+
+        let paths = raw_target_paths(input);
+        if paths.is_empty() {
+            return decide(None);
+        }
+        paths
+            .into_iter()
+            .map(|path| match resolve_path(Path::new(path), whitelist) {
+                Ok(resolved) => decide(Some(&resolved)),
+                Err(_) => decide(None).max(ToolPermission::Ask),
+            })
+            .max()
+            .unwrap_or(tool_default)
+    }
+
+    /// Load the policy from disk, falling back to the default when none is saved
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let json = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    /// Save the policy atomically
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let temp_path = path.with_extension("tmp");
+        std::fs::write(&temp_path, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(temp_path, path)?;
+        Ok(())
+    }
+}
+
+/// Paths a tool call operates on as given, taken from the conventional input keys
+fn raw_target_paths(input: &Value) -> Vec<&str> {
+    let mut raw: Vec<&str> = ["path", "source", "destination", "repository"]
+        .iter()
+        .filter_map(|key| input.get(*key).and_then(Value::as_str))
+        .collect();
+    if let Some(paths) = input.get("paths").and_then(Value::as_array) {
+        raw.extend(paths.iter().filter_map(Value::as_str));
+    }
+    raw.retain(|path| !path.is_empty());
+    raw
+}
+
+/// Target paths made absolute for display in an approval request
+fn target_paths(input: &Value) -> Vec<PathBuf> {
+    let cwd = std::env::current_dir().unwrap_or_default();
+    raw_target_paths(input)
+        .into_iter()
+        .map(|path| cwd.join(path))
+        .collect()
+}
+
+/// Why the user is being asked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalKind {
+    /// A tool call the policy marks as `ask`
+    ToolCall,
+    /// A follow-up action asking the user for input after a tool ran
+    UserInput,
+    /// Error recovery that needs the user to step in before retrying
+    UserIntervention,
+}
+
+/// A pending question for the user, emitted to the frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalRequest {
+    pub id: String,
+    pub kind: ApprovalKind,
+    pub tool_name: String,
+    pub input: Value,
+    pub summary: String,
+    pub paths: Vec<String>,
+    /// Unified diff of the proposed change for file writes
+    pub diff_preview: Option<String>,
+    pub suggested_actions: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ApprovalRequest {
+    fn new(kind: ApprovalKind, tool_name: &str, input: Value, summary: String) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind,
+            tool_name: tool_name.to_string(),
+            paths: target_paths(&input)
+                .iter()
+                .map(|path| path.to_string_lossy().to_string())
+                .collect(),
+            input,
+            summary,
+            diff_preview: None,
+            suggested_actions: Vec::new(),
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Describe a proposed tool call, with a diff preview for file writes
+    pub async fn for_tool_call(
+        tool_name: &str,
+        input: &Value,
+        whitelist: &Arc<RwLock<WhitelistConfig>>,
+    ) -> Self {
+        let summary = match input.get("path").and_then(Value::as_str) {
+            Some(path) => format!("Run '{}' on {}", tool_name, path),
+            None => format!("Run '{}'", tool_name),
+        };
+        let mut request = Self::new(ApprovalKind::ToolCall, tool_name, input.clone(), summary);
+        if tool_name == "write_file" {
+            request.diff_preview = write_diff_preview(input, whitelist).await;
+        }
+        request
+    }
+
+    /// Ask the user for input requested by a follow-up action
+    pub fn for_user_input(tool_name: &str, prompt: &str, suggested_actions: Vec<String>) -> Self {
+        let mut request = Self::new(
+            ApprovalKind::UserInput,
+            tool_name,
+            Value::Null,
+            prompt.to_string(),
+        );
+        request.suggested_actions = suggested_actions;
+        request
+    }
+
+    /// Ask the user to resolve a failure before the call is retried
+    pub fn for_intervention(
+        tool_name: &str,
+        input: &Value,
+        message: &str,
+        suggested_actions: Vec<String>,
+    ) -> Self {
+        let mut request = Self::new(
+            ApprovalKind::UserIntervention,
+            tool_name,
+            input.clone(),
+            message.to_string(),
+        );
+        request.suggested_actions = suggested_actions;
+        request
+    }
+}
+
+/// Unified diff between the current file (empty if missing) and the content
+/// about to be written. Files outside the whitelist are not read, and the file
+/// is opened beneath its whitelisted directory like `read_file` opens it.
+async fn write_diff_preview(
+    input: &Value,
+    whitelist: &Arc<RwLock<WhitelistConfig>>,
+) -> Option<String> {
+    use std::io::Read;
+
+    let path_str = input.get("path").and_then(Value::as_str)?;
+    let new_content = input.get("content").and_then(Value::as_str)?;
+
+    let path = resolve_tool_path(Some(whitelist), path_str, FileOperation::Write)
+        .await
+        .ok()?;
+    let old_content = with_tool_root(Some(whitelist), &path, |root, path| {
+        let mut file = match root.open_file(path, FileAccess::Read) {
+            Ok(file) => file,
+            // Nothing at the path: the write creates the file
+            Err(_) if root.symlink_metadata(path).is_err() => return Ok(Some(Vec::new())),
+            Err(e) => return Err(e),
+        };
+        if file.metadata()?.len() > MAX_DIFF_SOURCE_BYTES {
+            return Ok(None);
+        }
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        Ok(Some(bytes))
+    })
+    .await
+    .ok()??;
+    let old_content = String::from_utf8_lossy(&old_content);
+
+    let diff = similar::TextDiff::from_lines(old_content.as_ref(), new_content)
+        .unified_diff()
+        .context_radius(3)
+        .header(&format!("a/{}", path_str), &format!("b/{}", path_str))
+        .to_string();
+
+    if diff.len() <= MAX_DIFF_PREVIEW_BYTES {
+        return Some(diff);
+    }
+    let mut end = MAX_DIFF_PREVIEW_BYTES;
+    while !diff.is_char_boundary(end) {
+        end -= 1;
+    }
+    Some(format!("{}\n[diff truncated]", &diff[..end]))
+}
+
+/// The user's answer to an approval request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalDecision {
+    pub approved: bool,
+    /// Optional note from the user, passed back to the model
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+impl ApprovalDecision {
+    pub fn approved() -> Self {
+        Self {
+            approved: true,
+            message: None,
+        }
+    }
+
+    pub fn denied(message: impl Into<String>) -> Self {
+        Self {
+            approved: false,
+            message: Some(message.into()),
+        }
+    }
+}
+
+/// Delivers approval requests to whoever can answer them (the frontend)
+pub trait ApprovalNotifier: Send + Sync {
+    fn notify(&self, request: &ApprovalRequest);
+}
+
+/// Tracks pending approval requests and the tasks waiting on them
+pub struct ApprovalManager {
+    notifier: Arc<dyn ApprovalNotifier>,
+    pending: Mutex<HashMap<String, (ApprovalRequest, oneshot::Sender<ApprovalDecision>)>>,
+    timeout: Duration,
+}
+
+impl std::fmt::Debug for ApprovalManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApprovalManager")
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ApprovalManager {
+    pub fn new(notifier: Arc<dyn ApprovalNotifier>) -> Self {
+        Self {
+            notifier,
+            pending: Mutex::new(HashMap::new()),
+            timeout: Duration::from_secs(APPROVAL_TIMEOUT_SECS),
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Publish a request and wait for the user's decision. Requests that are
+    /// not answered in time are treated as denied.
+    pub async fn request(&self, request: ApprovalRequest) -> ApprovalDecision {
+        let id = request.id.clone();
+        let (sender, receiver) = oneshot::channel();
+        self.pending
+            .lock()
+            .await
+            .insert(id.clone(), (request.clone(), sender));
+        self.notifier.notify(&request);
+
+        match tokio::time::timeout(self.timeout, receiver).await {
+            Ok(Ok(decision)) => decision,
+            _ => {
+                self.pending.lock().await.remove(&id);
+                ApprovalDecision::denied(format!(
+                    "No response from the user within {} seconds",
+                    self.timeout.as_secs()
+                ))
+            }
+        }
+    }
+
+    /// Answer a pending request
+    pub async fn respond(&self, request_id: &str, decision: ApprovalDecision) -> Result<()> {
+        let (_, sender) = self
+            .pending
+            .lock()
+            .await
+            .remove(request_id)
+            .ok_or_else(|| anyhow!("No pending approval request with id '{}'", request_id))?;
+        sender
+            .send(decision)
+            .map_err(|_| anyhow!("Approval request '{}' is no longer waiting", request_id))
+    }
+
+    /// Requests still waiting for an answer, oldest first
+    pub async fn pending_requests(&self) -> Vec<ApprovalRequest> {
+        let mut requests: Vec<ApprovalRequest> = self
+            .pending
+            .lock()
+            .await
+            .values()
+            .map(|(request, _)| request.clone())
+            .collect();
+        requests.sort_by_key(|request| request.created_at);
+        requests
+    }
+}
+
+/// Policy and manager shared between the app state and every execution engine
+#[derive(Debug, Clone)]
+pub struct ApprovalGate {
+    pub policy: Arc<RwLock<ApprovalPolicy>>,
+    pub manager: Arc<ApprovalManager>,
+}
+
+impl ApprovalGate {
+    pub fn new(policy: Arc<RwLock<ApprovalPolicy>>, manager: Arc<ApprovalManager>) -> Self {
+        Self { policy, manager }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::claude::test_support::whitelisted_temp_dir;
+    use serde_json::json;
+    use std::sync::Mutex as StdMutex;
+
+    /// Records notified requests so tests can answer them
+    #[derive(Default)]
+    struct RecordingNotifier {
+        requests: StdMutex<Vec<ApprovalRequest>>,
+    }
+
+    impl ApprovalNotifier for RecordingNotifier {
+        fn notify(&self, request: &ApprovalRequest) {
+            self.requests.lock().unwrap().push(request.clone());
+        }
+    }
+
+    #[test]
+    fn test_policy_rules_and_defaults() {
+        let mut policy = ApprovalPolicy::default();
+        policy.rules.push(PermissionRule {
+            tool: "*".to_string(),
+            path_pattern: Some("*.lock".to_string()),
+            permission: ToolPermission::Deny,
+        });
+        policy.rules.push(PermissionRule {
+            tool: "write_file".to_string(),
+            path_pattern: Some("/tmp/scratch/**".to_string()),
+            permission: ToolPermission::Allow,
+        });
+        let whitelist = WhitelistConfig::default();
+
+        assert_eq!(
+            policy.permission_for("read_file", &json!({ "path": "src/main.rs" }), &whitelist),
+            ToolPermission::Allow
+        );
+        assert_eq!(
+            policy.permission_for("write_file", &json!({ "path": "src/main.rs" }), &whitelist),
+            ToolPermission::Ask
+        );
+        assert_eq!(
+            policy.permission_for(
+                "write_file",
+                &json!({ "path": "/tmp/scratch/a/b.txt" }),
+                &whitelist
+            ),
+            ToolPermission::Allow
+        );
+        assert_eq!(
+            policy.permission_for("read_file", &json!({ "path": "Cargo.lock" }), &whitelist),
+            ToolPermission::Deny
+        );
+        // The most restrictive path decides for multi-path calls
+        assert_eq!(
+            policy.permission_for(
+                "copy_file",
+                &json!({ "source": "/tmp/scratch/x", "destination": "Cargo.lock" }),
+                &whitelist
+            ),
+            ToolPermission::Deny
+        );
+        // Rules match the resolved path, not the spelling in the input
+        assert_eq!(
+            policy.permission_for(
+                "write_file",
+                &json!({ "path": "/tmp/scratch/../../etc/passwd" }),
+                &whitelist
+            ),
+            ToolPermission::Ask
+        );
+        assert_eq!(
+            policy.permission_for(
+                "read_file",
+                &json!({ "path": "/tmp/scratch/../x/Cargo.lock" }),
+                &whitelist
+            ),
+            ToolPermission::Deny
+        );
+        // Tools the policy does not know, such as MCP tools, ask first
+        assert_eq!(
+            policy.permission_for("mcp__server__run", &json!({}), &whitelist),
+            ToolPermission::Ask
+        );
+        assert_eq!(
+            policy.permission_for("create_directory", &json!({ "path": "src" }), &whitelist),
+            ToolPermission::Ask
+        );
+        assert_eq!(
+            policy.permission_for("cargo_check", &json!({ "mode": "check" }), &whitelist),
+            ToolPermission::Ask
+        );
+    }
+
+    #[tokio::test]
+    async fn test_manager_round_trip_and_timeout() {
+        let notifier = Arc::new(RecordingNotifier::default());
+        let manager = Arc::new(ApprovalManager::new(notifier.clone()));
+
+        let waiting = {
+            let manager = manager.clone();
+            tokio::spawn(async move {
+                manager
+                    .request(ApprovalRequest::for_user_input(
+                        "read_file",
+                        "Continue?",
+                        vec![],
+                    ))
+                    .await
+            })
+        };
+        while notifier.requests.lock().unwrap().is_empty() {
+            tokio::task::yield_now().await;
+        }
+        let id = notifier.requests.lock().unwrap()[0].id.clone();
+        assert_eq!(manager.pending_requests().await.len(), 1);
+        manager
+            .respond(&id, ApprovalDecision::approved())
+            .await
+            .unwrap();
+        assert!(waiting.await.unwrap().approved);
+        assert!(manager
+            .respond(&id, ApprovalDecision::approved())
+            .await
+            .is_err());
+
+        let manager = ApprovalManager::new(notifier).with_timeout(Duration::from_millis(10));
+        let decision = manager
+            .request(ApprovalRequest::for_user_input(
+                "read_file",
+                "Continue?",
+                vec![],
+            ))
+            .await;
+        assert!(!decision.approved);
+        assert!(manager.pending_requests().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_engine_waits_for_approval() {
+        use crate::claude::tools::{
+            AgentTool, ToolExecutionContext, ToolExecutionEngine, ToolRequest, WriteFileTool,
+        };
+
+        let (dir, whitelist) = whitelisted_temp_dir();
+
+        let notifier = Arc::new(RecordingNotifier::default());
+        let manager = Arc::new(ApprovalManager::new(notifier.clone()));
+        let gate = ApprovalGate::new(
+            Arc::new(RwLock::new(ApprovalPolicy::default())),
+            manager.clone(),
+        );
+        let mut write_tool = WriteFileTool::new();
+        write_tool.set_whitelist(whitelist.clone());
+        let mut engine = ToolExecutionEngine::new().with_approval(gate);
+        engine.register_tool(Arc::new(write_tool));
+        let engine = Arc::new(engine);
+
+        let run = |name: &str| {
+            let engine = engine.clone();
+            let input = json!({ "path": dir.join(name).to_string_lossy(), "content": "hi" });
+            let context = ToolExecutionContext::new(
+                "write_file".to_string(),
+                input.clone(),
+                whitelist.clone(),
+            );
+            tokio::spawn(async move {
+                engine
+                    .execute_single_tool(
+                        ToolRequest::new("write_file".to_string(), input).with_max_retries(0),
+                        context,
+                    )
+                    .await
+                    .unwrap()
+            })
+        };
+
+        for (name, approved) in [("approved.txt", true), ("denied.txt", false)] {
+            let task = run(name);
+            let request = loop {
+                if let Some(request) = notifier.requests.lock().unwrap().pop() {
+                    break request;
+                }
+                tokio::task::yield_now().await;
+            };
+            assert!(request.diff_preview.unwrap().contains("+hi"));
+            let decision = if approved {
+                ApprovalDecision::approved()
+            } else {
+                ApprovalDecision::denied("not now")
+            };
+            manager.respond(&request.id, decision).await.unwrap();
+
+            let result = task.await.unwrap();
+            assert_eq!(result.is_success(), approved);
+            assert_eq!(dir.join(name).exists(), approved);
+            if !approved {
+                assert!(result.into_content_block().contains("not now"));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_request_includes_diff_preview() {
+        let (dir, whitelist) = whitelisted_temp_dir();
+        let file = dir.join("notes.txt");
+        std::fs::write(&file, "one\ntwo\n").unwrap();
+
+        let input = json!({ "path": file.to_string_lossy(), "content": "one\nthree\n" });
+        let request = ApprovalRequest::for_tool_call("write_file", &input, &whitelist).await;
+        let diff = request.diff_preview.unwrap();
+        assert!(diff.contains("-two"));
+        assert!(diff.contains("+three"));
+        assert_eq!(request.kind, ApprovalKind::ToolCall);
+
+        // A new file is previewed as created from nothing
+        let input = json!({ "path": dir.join("new.txt").to_string_lossy(), "content": "hi\n" });
+        let request = ApprovalRequest::for_tool_call("write_file", &input, &whitelist).await;
+        assert!(request.diff_preview.unwrap().contains("+hi"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_diff_preview_skips_special_files() {
+        let (dir, whitelist) = whitelisted_temp_dir();
+        let fifo = dir.join("fifo");
+        let c_fifo = std::ffi::CString::new(fifo.to_string_lossy().as_bytes()).unwrap();
+        // SAFETY: `c_fifo` is a valid NUL-terminated path
+        assert_eq!(unsafe { libc::mkfifo(c_fifo.as_ptr(), 0o600) }, 0);
+
+        // Reading the FIFO by name would wait for a writer forever
+        let input = json!({ "path": fifo.to_string_lossy(), "content": "hi" });
+        let request = tokio::time::timeout(
+            Duration::from_secs(5),
+            ApprovalRequest::for_tool_call("write_file", &input, &whitelist),
+        )
+        .await
+        .expect("the preview should not block");
+        assert!(request.diff_preview.is_none());
+    }
+}
@@ -0,0 +1,387 @@
+//! Per-tool circuit breakers and bulkheads for the execution engine.
+//!
+//! A tool that keeps failing gets its breaker opened, so further calls are
+//! answered straight away with an explanation instead of running the broken
+//! tool again. After a cool-down a single probe call is let through; its
+//! outcome closes or reopens the breaker. Bulkheads cap how many calls to one
+//! tool may run at the same time.
+
+use crate::claude::{constants::tool_breakers, error::CircuitBreakerState};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Failure bookkeeping for one tool
+#[derive(Debug)]
+struct Breaker {
+    state: CircuitBreakerState,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    last_error: Option<String>,
+    /// A half-open breaker admits one probe call at a time
+    probe_in_flight: bool,
+    rejected_calls: u64,
+}
+
+impl Breaker {
+    fn new() -> Self {
+        Self {
+            state: CircuitBreakerState::Closed,
+            consecutive_failures: 0,
+            opened_at: None,
+            last_error: None,
+            probe_in_flight: false,
+            rejected_calls: 0,
+        }
+    }
+
+    fn open(&mut self) {
+        self.state = CircuitBreakerState::Open;
+        self.opened_at = Some(Instant::now());
+        self.probe_in_flight = false;
+    }
+
+    fn retry_after(&self, open_duration: Duration) -> Option<Duration> {
+        match (self.state, self.opened_at) {
+            (CircuitBreakerState::Open, Some(opened_at)) => {
+                Some(open_duration.saturating_sub(opened_at.elapsed()))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Why a call was refused without running the tool
+#[derive(Debug, Clone)]
+pub struct BreakerRejection {
+    pub tool_name: String,
+    pub consecutive_failures: u32,
+    /// Time until the breaker lets a probe call through
+    pub retry_after: Duration,
+    pub last_error: Option<String>,
+}
+
+impl BreakerRejection {
+    /// Explanation for the model, so it stops calling the tool for now
+    pub fn message(&self) -> String {
+        let mut message = format!(
+            "Tool '{}' is temporarily disabled after {} consecutive failures",
+            self.tool_name, self.consecutive_failures
+        );
+        if let Some(last_error) = &self.last_error {
+            message.push_str(&format!(" (last error: {})", last_error));
+        }
+        message.push_str(&format!(
+            ". It will accept calls again in about {} seconds; do not retry it before then.",
+            self.retry_after.as_secs().max(1)
+        ));
+        message
+    }
+}
+
+/// Breaker and bulkhead state of one tool, as shown to the user
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolBreakerStatus {
+    pub tool_name: String,
+    pub state: CircuitBreakerState,
+    pub consecutive_failures: u32,
+    /// Seconds until an open breaker admits a probe call
+    pub retry_after_secs: Option<u64>,
+    pub last_error: Option<String>,
+    /// Calls refused since the breaker was last reset
+    pub rejected_calls: u64,
+    pub active_calls: usize,
+    pub max_concurrent_calls: usize,
+}
+
+/// Circuit breakers and concurrency limits keyed by tool name. Shared by all
+/// engines built from the same config, so state survives across messages.
+#[derive(Debug)]
+pub struct ToolBreakers {
+    failure_threshold: u32,
+    open_duration: Duration,
+    max_concurrent_calls: usize,
+    concurrency_limits: HashMap<String, usize>,
+    breakers: Mutex<HashMap<String, Breaker>>,
+    bulkheads: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl ToolBreakers {
+    pub fn new(
+        failure_threshold: u32,
+        open_duration: Duration,
+        max_concurrent_calls: usize,
+    ) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            open_duration,
+            max_concurrent_calls: max_concurrent_calls.max(1),
+            concurrency_limits: HashMap::new(),
+            breakers: Mutex::new(HashMap::new()),
+            bulkheads: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Limit how many calls to one tool may run at the same time
+    pub fn with_concurrency_limit(mut self, tool_name: impl Into<String>, limit: usize) -> Self {
+        self.concurrency_limits
+            .insert(tool_name.into(), limit.max(1));
+        self
+    }
+
+    /// Admit a call, or explain why the tool is not being called right now.
+    /// An open breaker whose cool-down has passed turns half-open and admits
+    /// this call as its probe.
+    pub fn check(&self, tool_name: &str) -> Result<(), BreakerRejection> {
+        let mut breakers = self.breakers.lock().unwrap();
+        let Some(breaker) = breakers.get_mut(tool_name) else {
+            return Ok(());
+        };
+
+        match breaker.state {
+            CircuitBreakerState::Closed => return Ok(()),
+            CircuitBreakerState::Open => {
+                if breaker.retry_after(self.open_duration) == Some(Duration::ZERO) {
+                    breaker.state = CircuitBreakerState::HalfOpen;
+                    breaker.probe_in_flight = true;
+                    return Ok(());
+                }
+            }
+            CircuitBreakerState::HalfOpen => {
+                if !breaker.probe_in_flight {
+                    breaker.probe_in_flight = true;
+                    return Ok(());
+                }
+            }
+        }
+
+        breaker.rejected_calls += 1;
+        Err(BreakerRejection {
+            tool_name: tool_name.to_string(),
+            consecutive_failures: breaker.consecutive_failures,
+            // A probe is already running; its result decides when calls resume
+            retry_after: breaker
+                .retry_after(self.open_duration)
+                .unwrap_or(self.open_duration),
+            last_error: breaker.last_error.clone(),
+        })
+    }
+
+    /// The tool worked; close its breaker
+    pub fn record_success(&self, tool_name: &str) {
+        if let Some(breaker) = self.breakers.lock().unwrap().get_mut(tool_name) {
+            *breaker = Breaker {
+                rejected_calls: breaker.rejected_calls,
+                ..Breaker::new()
+            };
+        }
+    }
+
+    /// The tool failed; open its breaker once failures reach the threshold,
+    /// or straight away when the half-open probe failed
+    pub fn record_failure(&self, tool_name: &str, error: &str) {
+        let mut breakers = self.breakers.lock().unwrap();
+        let breaker = breakers
+            .entry(tool_name.to_string())
+            .or_insert_with(Breaker::new);
+        breaker.consecutive_failures += 1;
+        breaker.last_error = Some(error.to_string());
+
+        if breaker.state == CircuitBreakerState::HalfOpen
+            || breaker.consecutive_failures >= self.failure_threshold
+        {
+            breaker.open();
+        }
+    }
+
+    /// An admitted call ended without telling anything about the tool's
+    /// health (e.g. the user declined it); free the half-open probe slot
+    pub fn release(&self, tool_name: &str) {
+        if let Some(breaker) = self.breakers.lock().unwrap().get_mut(tool_name) {
+            breaker.probe_in_flight = false;
+        }
+    }
+
+    /// Close a tool's breaker by hand. Returns false if the tool never failed.
+    pub fn reset(&self, tool_name: &str) -> bool {
+        self.breakers.lock().unwrap().remove(tool_name).is_some()
+    }
+
+    /// Wait for a free slot in the tool's bulkhead; the slot is held until
+    /// the permit is dropped
+    pub async fn acquire(&self, tool_name: &str) -> OwnedSemaphorePermit {
+        let semaphore = self.bulkhead(tool_name);
+        semaphore
+            .acquire_owned()
+            .await
+            .expect("bulkhead semaphores are never closed")
+    }
+
+    fn concurrency_limit(&self, tool_name: &str) -> usize {
+        self.concurrency_limits
+            .get(tool_name)
+            .copied()
+            .unwrap_or(self.max_concurrent_calls)
+    }
+
+    fn bulkhead(&self, tool_name: &str) -> Arc<Semaphore> {
+        self.bulkheads
+            .lock()
+            .unwrap()
+            .entry(tool_name.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(self.concurrency_limit(tool_name))))
+            .clone()
+    }
+
+    /// State of every tool that has failed or been called, sorted by name
+    pub fn statuses(&self) -> Vec<ToolBreakerStatus> {
+        let breakers = self.breakers.lock().unwrap();
+        let bulkheads = self.bulkheads.lock().unwrap();
+
+        let mut names: Vec<&String> = breakers.keys().chain(bulkheads.keys()).collect();
+        names.sort();
+        names.dedup();
+
+        names
+            .into_iter()
+            .map(|name| {
+                let limit = self.concurrency_limit(name);
+                let active_calls = bulkheads
+                    .get(name)
+                    .map(|semaphore| limit.saturating_sub(semaphore.available_permits()))
+                    .unwrap_or(0);
+                let breaker = breakers.get(name);
+                ToolBreakerStatus {
+                    tool_name: name.clone(),
+                    state: breaker.map_or(CircuitBreakerState::Closed, |b| b.state),
+                    consecutive_failures: breaker.map_or(0, |b| b.consecutive_failures),
+                    retry_after_secs: breaker
+                        .and_then(|b| b.retry_after(self.open_duration))
+                        .map(|remaining| remaining.as_secs()),
+                    last_error: breaker.and_then(|b| b.last_error.clone()),
+                    rejected_calls: breaker.map_or(0, |b| b.rejected_calls),
+                    active_calls,
+                    max_concurrent_calls: limit,
+                }
+            })
+            .collect()
+    }
+}
+
+impl Default for ToolBreakers {
+    fn default() -> Self {
+        tool_breakers::TOOL_CONCURRENCY_LIMITS.iter().fold(
+            Self::new(
+                tool_breakers::FAILURE_THRESHOLD,
+                tool_breakers::open_duration(),
+                tool_breakers::DEFAULT_MAX_CONCURRENT_CALLS,
+            ),
+            |breakers, (tool, limit)| breakers.with_concurrency_limit(*tool, *limit),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_of(breakers: &ToolBreakers, tool_name: &str) -> CircuitBreakerState {
+        breakers
+            .statuses()
+            .into_iter()
+            .find(|status| status.tool_name == tool_name)
+            .map(|status| status.state)
+            .unwrap_or(CircuitBreakerState::Closed)
+    }
+
+    #[test]
+    fn test_breaker_opens_at_threshold_and_explains_rejection() {
+        let breakers = ToolBreakers::new(2, Duration::from_secs(60), 4);
+
+        breakers.record_failure("git_log", "not a git repository");
+        assert!(breakers.check("git_log").is_ok());
+        breakers.record_failure("git_log", "not a git repository");
+        assert_eq!(state_of(&breakers, "git_log"), CircuitBreakerState::Open);
+
+        let rejection = breakers.check("git_log").unwrap_err();
+        assert_eq!(rejection.consecutive_failures, 2);
+        let message = rejection.message();
+        assert!(message.contains("'git_log' is temporarily disabled"));
+        assert!(message.contains("not a git repository"));
+
+        // Other tools are unaffected
+        assert!(breakers.check("read_file").is_ok());
+        assert_eq!(breakers.statuses()[0].rejected_calls, 1);
+    }
+
+    #[test]
+    fn test_half_open_probe_closes_or_reopens_breaker() {
+        let breakers = ToolBreakers::new(1, Duration::ZERO, 4);
+
+        breakers.record_failure("cargo_check", "linker not found");
+        // The cool-down has passed, so exactly one probe gets through
+        assert!(breakers.check("cargo_check").is_ok());
+        assert_eq!(
+            state_of(&breakers, "cargo_check"),
+            CircuitBreakerState::HalfOpen
+        );
+        assert!(breakers.check("cargo_check").is_err());
+
+        breakers.record_failure("cargo_check", "linker not found");
+        assert_eq!(
+            state_of(&breakers, "cargo_check"),
+            CircuitBreakerState::Open
+        );
+
+        assert!(breakers.check("cargo_check").is_ok());
+        breakers.record_success("cargo_check");
+        assert_eq!(
+            state_of(&breakers, "cargo_check"),
+            CircuitBreakerState::Closed
+        );
+        assert!(breakers.check("cargo_check").is_ok());
+        assert!(breakers.check("cargo_check").is_ok());
+    }
+
+    #[test]
+    fn test_released_probe_lets_next_call_through() {
+        let breakers = ToolBreakers::new(1, Duration::ZERO, 4);
+        breakers.record_failure("write_file", "disk full");
+
+        assert!(breakers.check("write_file").is_ok());
+        breakers.release("write_file");
+        assert!(breakers.check("write_file").is_ok());
+
+        assert!(breakers.reset("write_file"));
+        assert!(!breakers.reset("write_file"));
+    }
+
+    #[tokio::test]
+    async fn test_bulkhead_limits_concurrent_calls() {
+        let breakers = ToolBreakers::new(3, Duration::from_secs(60), 4)
+            .with_concurrency_limit("cargo_check", 1);
+
+        let permit = breakers.acquire("cargo_check").await;
+        let blocked =
+            tokio::time::timeout(Duration::from_millis(50), breakers.acquire("cargo_check")).await;
+        assert!(blocked.is_err());
+
+        let status = &breakers.statuses()[0];
+        assert_eq!(status.active_calls, 1);
+        assert_eq!(status.max_concurrent_calls, 1);
+
+        drop(permit);
+        let next =
+            tokio::time::timeout(Duration::from_millis(50), breakers.acquire("cargo_check")).await;
+        assert!(next.is_ok());
+
+        // Tools without a limit of their own use the default
+        let _permits = (
+            breakers.acquire("read_file").await,
+            breakers.acquire("read_file").await,
+        );
+        assert_eq!(breakers.statuses()[1].active_calls, 2);
+    }
+}
@@ -0,0 +1,502 @@
+//! Cargo diagnostics tool.
+//!
+//! Runs `cargo check`, `cargo clippy` or `cargo test` in a whitelisted crate
+//! with `--message-format=json` and turns the compiler messages into
+//! structured diagnostics. Errors are ordered before warnings and the list is
+//! capped so the most relevant problems always fit in the tool result.
+
+use crate::claude::constants::cargo_limits::{
+    COMMAND_TIMEOUT_SECS, DEFAULT_MAX_DIAGNOSTICS, MAX_DIAGNOSTICS, MAX_MESSAGE_CHARS,
+    MAX_TEST_OUTPUT_BYTES,
+};
+use crate::claude::tools::{
+    input_schema_for, parse_input, resolve_tool_path, AgentTool, ToolResultData,
+};
+use crate::claude::types::ToolInputSchema;
+use crate::claude::whitelist::{FileOperation, WhitelistConfig};
+use anyhow::Result;
+use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::sync::RwLock;
+
+/// Cargo subcommand to run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+enum CargoCommand {
+    #[default]
+    Check,
+    Clippy,
+    Test,
+}
+
+impl CargoCommand {
+    fn as_str(self) -> &'static str {
+        match self {
+            CargoCommand::Check => "check",
+            CargoCommand::Clippy => "clippy",
+            CargoCommand::Test => "test",
+        }
+    }
+}
+
+/// Input for `cargo_check`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct CargoCheckInput {
+    /// The crate or workspace directory containing Cargo.toml
+    path: String,
+    /// Which cargo command to run
+    #[serde(default)]
+    command: CargoCommand,
+    /// Only build this workspace package
+    package: Option<String>,
+    /// Include tests, examples and benches
+    #[serde(default)]
+    all_targets: bool,
+    /// Maximum diagnostics to return
+    #[serde(default = "default_max_diagnostics")]
+    #[schemars(range(min = 1, max = "MAX_DIAGNOSTICS"))]
+    max_diagnostics: usize,
+}
+
+fn default_max_diagnostics() -> usize {
+    DEFAULT_MAX_DIAGNOSTICS
+}
+
+/// Sort key so errors come first, then warnings, then everything else
+fn level_rank(level: &str) -> u8 {
+    match level {
+        "error" | "error: internal compiler error" => 0,
+        "warning" => 1,
+        _ => 2,
+    }
+}
+
+fn truncate_chars(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// Collect suggested replacements from a diagnostic and its children
+fn collect_suggestions(message: &Value, suggestions: &mut Vec<Value>) {
+    for span in message["spans"].as_array().into_iter().flatten() {
+        if let Some(replacement) = span["suggested_replacement"].as_str() {
+            suggestions.push(json!({
+                "file": span["file_name"],
+                "line_start": span["line_start"],
+                "column_start": span["column_start"],
+                "line_end": span["line_end"],
+                "column_end": span["column_end"],
+                "replacement": replacement,
+                "applicability": span["suggestion_applicability"],
+                "message": message["message"],
+            }));
+        }
+    }
+    for child in message["children"].as_array().into_iter().flatten() {
+        collect_suggestions(child, suggestions);
+    }
+}
+
+/// Convert a rustc diagnostic into the compact shape returned to Claude.
+/// Returns `None` for summary messages without a location ("aborting due to…").
+fn convert_diagnostic(message: &Value) -> Option<Value> {
+    let spans = message["spans"].as_array()?;
+    let primary = spans
+        .iter()
+        .find(|s| s["is_primary"].as_bool().unwrap_or(false))
+        .or_else(|| spans.first())?;
+
+    let mut suggestions = Vec::new();
+    collect_suggestions(message, &mut suggestions);
+
+    let notes: Vec<Value> = message["children"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|c| c["spans"].as_array().is_none_or(|s| s.is_empty()))
+        .map(|c| {
+            json!(format!(
+                "{}: {}",
+                c["level"].as_str().unwrap_or("note"),
+                c["message"].as_str().unwrap_or("")
+            ))
+        })
+        .collect();
+
+    Some(json!({
+        "level": message["level"],
+        "code": message["code"]["code"],
+        "message": truncate_chars(message["message"].as_str().unwrap_or(""), MAX_MESSAGE_CHARS),
+        "file": primary["file_name"],
+        "span": {
+            "line_start": primary["line_start"],
+            "column_start": primary["column_start"],
+            "line_end": primary["line_end"],
+            "column_end": primary["column_end"],
+            "label": primary["label"],
+        },
+        "notes": notes,
+        "suggestions": suggestions,
+    }))
+}
+
+/// Parsed output of a cargo invocation
+#[derive(Debug, Default)]
+struct CargoOutput {
+    diagnostics: Vec<Value>,
+    build_success: Option<bool>,
+    failed_tests: Vec<String>,
+    test_summaries: Vec<String>,
+    test_failure_output: String,
+}
+
+/// Parse cargo's JSON message stream. Lines that are not JSON come from the
+/// test harness and are scanned for failures and summaries.
+fn parse_cargo_output(stdout: &str) -> CargoOutput {
+    let mut output = CargoOutput::default();
+    let mut seen = HashSet::new();
+    let mut in_failure_section = false;
+
+    for line in stdout.lines() {
+        if line.starts_with('{') {
+            if let Ok(message) = serde_json::from_str::<Value>(line) {
+                match message["reason"].as_str() {
+                    Some("compiler-message") => {
+                        if let Some(diagnostic) = convert_diagnostic(&message["message"]) {
+                            // The same diagnostic is reported once per target (lib, test, ...)
+                            let key = format!(
+                                "{}|{}|{}|{}",
+                                diagnostic["level"],
+                                diagnostic["message"],
+                                diagnostic["file"],
+                                diagnostic["span"]["line_start"]
+                            );
+                            if seen.insert(key) {
+                                output.diagnostics.push(diagnostic);
+                            }
+                        }
+                    }
+                    Some("build-finished") => {
+                        output.build_success = message["success"].as_bool();
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+        }
+
+        if let Some(name) = line
+            .strip_prefix("test ")
+            .and_then(|rest| rest.strip_suffix(" ... FAILED"))
+        {
+            output.failed_tests.push(name.to_string());
+        } else if line.starts_with("test result:") {
+            output.test_summaries.push(line.to_string());
+        }
+
+        // libtest prints captured output of failing tests between "failures:" headers
+        if line == "failures:" {
+            in_failure_section = !in_failure_section;
+            continue;
+        }
+        if in_failure_section && output.test_failure_output.len() < MAX_TEST_OUTPUT_BYTES {
+            output.test_failure_output.push_str(line);
+            output.test_failure_output.push('\n');
+        }
+    }
+
+    output
+}
+
+/// Order diagnostics by relevance and cap them, returning the kept list and
+/// the number omitted
+fn select_diagnostics(mut diagnostics: Vec<Value>, max: usize) -> (Vec<Value>, usize) {
+    // Stable sort keeps compiler order within each level
+    diagnostics.sort_by_key(|d| level_rank(d["level"].as_str().unwrap_or("")));
+    let omitted = diagnostics.len().saturating_sub(max);
+    diagnostics.truncate(max);
+    (diagnostics, omitted)
+}
+
+#[derive(Debug)]
+pub struct CargoCheckTool {
+    whitelist: Option<Arc<RwLock<WhitelistConfig>>>,
+}
+
+impl CargoCheckTool {
+    pub fn new() -> Self {
+        Self { whitelist: None }
+    }
+}
+
+#[async_trait]
+impl AgentTool for CargoCheckTool {
+    fn set_whitelist(&mut self, whitelist: Arc<RwLock<WhitelistConfig>>) {
+        self.whitelist = Some(whitelist);
+    }
+
+    fn name(&self) -> &str {
+        "cargo_check"
+    }
+
+    fn description(&self) -> &str {
+        "Run cargo check, clippy or test in a Rust crate and return structured compiler diagnostics (errors first) with suggested fixes"
+    }
+
+    fn input_schema(&self) -> ToolInputSchema {
+        input_schema_for::<CargoCheckInput>()
+    }
+
+    async fn execute(&self, input: Value) -> Result<String> {
+        let data = self.execute_data(input).await?;
+        match data {
+            ToolResultData::Json(value) => Ok(serde_json::to_string_pretty(&value)?),
+            _ => Err(anyhow::anyhow!("Unexpected cargo_check result")),
+        }
+    }
+
+    async fn execute_data(&self, input: Value) -> Result<ToolResultData> {
+        let input: CargoCheckInput = parse_input(input)?;
+        let path_str = input.path.as_str();
+        let command = input.command.as_str();
+        let package = input.package.as_deref();
+        if let Some(package) = package {
+            if package.is_empty()
+                || package.starts_with('-')
+                || !package
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '@' | '.' | ':'))
+            {
+                return Err(anyhow::anyhow!("Invalid package name '{}'", package));
+            }
+        }
+        let all_targets = input.all_targets;
+        let max_diagnostics = input.max_diagnostics.clamp(1, MAX_DIAGNOSTICS);
+
+        // Cargo writes target/ and Cargo.lock, so the crate needs write access
+        let crate_dir: PathBuf =
+            resolve_tool_path(self.whitelist.as_ref(), path_str, FileOperation::Write).await?;
+        let manifest = crate_dir.join("Cargo.toml");
+        if !manifest.is_file() {
+            return Err(anyhow::anyhow!(
+                "No Cargo.toml found in '{}'",
+                crate_dir.display()
+            ));
+        }
+
+        let mut cmd = Command::new("cargo");
+        cmd.arg(command)
+            .arg("--message-format=json")
+            .arg("--manifest-path")
+            .arg(&manifest);
+        if all_targets {
+            cmd.arg("--all-targets");
+        }
+        if let Some(package) = package {
+            cmd.args(["--package", package]);
+        }
+        if command == "test" {
+            cmd.arg("--no-fail-fast");
+        }
+        cmd.current_dir(&crate_dir)
+            .env("CARGO_TERM_COLOR", "never")
+            .kill_on_drop(true);
+
+        let output = tokio::time::timeout(Duration::from_secs(COMMAND_TIMEOUT_SECS), cmd.output())
+            .await
+            .map_err(|_| {
+                anyhow::anyhow!(
+                    "cargo {} timed out after {}s",
+                    command,
+                    COMMAND_TIMEOUT_SECS
+                )
+            })?
+            .map_err(|e| anyhow::anyhow!("Failed to run cargo: {}", e))?;
+
+        let parsed = parse_cargo_output(&String::from_utf8_lossy(&output.stdout));
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for diagnostic in &parsed.diagnostics {
+            let level = diagnostic["level"]
+                .as_str()
+                .unwrap_or("unknown")
+                .to_string();
+            *counts.entry(level).or_default() += 1;
+        }
+        let (diagnostics, omitted) = select_diagnostics(parsed.diagnostics, max_diagnostics);
+
+        let mut result = json!({
+            "command": format!("cargo {}", command),
+            "path": crate_dir.to_string_lossy(),
+            "success": output.status.success(),
+            "build_success": parsed.build_success,
+            "counts": counts,
+            "diagnostics": diagnostics,
+            "omitted_diagnostics": omitted,
+        });
+
+        if command == "test" {
+            result["tests"] = json!({
+                "failed": parsed.failed_tests,
+                "summaries": parsed.test_summaries,
+                "failure_output": parsed.test_failure_output,
+            });
+        }
+
+        // Cargo-level failures (bad manifest, missing clippy) have no JSON diagnostics
+        if !output.status.success()
+            && diagnostics_empty(&result)
+            && parsed.test_summaries.is_empty()
+        {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let tail: Vec<&str> = stderr.lines().rev().take(20).collect();
+            result["stderr"] = json!(tail.into_iter().rev().collect::<Vec<_>>().join("\n"));
+        }
+
+        Ok(ToolResultData::json(result))
+    }
+}
+
+fn diagnostics_empty(result: &Value) -> bool {
+    result["diagnostics"]
+        .as_array()
+        .is_none_or(|d| d.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::claude::test_support::whitelisted_temp_dir;
+
+    fn compiler_message(level: &str, message: &str, line: u64) -> String {
+        json!({
+            "reason": "compiler-message",
+            "message": {
+                "level": level,
+                "message": message,
+                "code": { "code": "E0308" },
+                "spans": [{
+                    "file_name": "src/lib.rs",
+                    "line_start": line,
+                    "line_end": line,
+                    "column_start": 5,
+                    "column_end": 9,
+                    "is_primary": true,
+                    "label": "expected `u32`",
+                    "suggested_replacement": null,
+                }],
+                "children": [{
+                    "level": "help",
+                    "message": "try converting",
+                    "spans": [{
+                        "file_name": "src/lib.rs",
+                        "line_start": line,
+                        "line_end": line,
+                        "column_start": 5,
+                        "column_end": 9,
+                        "is_primary": true,
+                        "suggested_replacement": "x as u32",
+                        "suggestion_applicability": "MachineApplicable",
+                    }],
+                    "children": [],
+                }],
+            }
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_parse_orders_dedups_and_truncates() {
+        let stdout = [
+            compiler_message("warning", "unused variable", 1),
+            compiler_message("error", "mismatched types", 3),
+            // Duplicate from a second target
+            compiler_message("error", "mismatched types", 3),
+            json!({
+                "reason": "compiler-message",
+                "message": { "level": "error", "message": "aborting due to 1 previous error", "spans": [], "children": [] }
+            })
+            .to_string(),
+            json!({ "reason": "build-finished", "success": false }).to_string(),
+        ]
+        .join("\n");
+
+        let parsed = parse_cargo_output(&stdout);
+        assert_eq!(parsed.build_success, Some(false));
+        assert_eq!(parsed.diagnostics.len(), 2);
+
+        let (selected, omitted) = select_diagnostics(parsed.diagnostics, 1);
+        assert_eq!(omitted, 1);
+        assert_eq!(selected[0]["level"], "error");
+        assert_eq!(selected[0]["code"], "E0308");
+        assert_eq!(selected[0]["span"]["line_start"], 3);
+        assert_eq!(selected[0]["suggestions"][0]["replacement"], "x as u32");
+    }
+
+    #[test]
+    fn test_parse_test_output() {
+        let stdout = "running 2 tests\n\
+            test tests::ok ... ok\n\
+            test tests::broken ... FAILED\n\
+            \n\
+            failures:\n\
+            \n\
+            ---- tests::broken stdout ----\n\
+            assertion failed\n\
+            \n\
+            failures:\n\
+            \x20   tests::broken\n\
+            \n\
+            test result: FAILED. 1 passed; 1 failed; 0 ignored\n";
+
+        let parsed = parse_cargo_output(stdout);
+        assert_eq!(parsed.failed_tests, vec!["tests::broken"]);
+        assert_eq!(parsed.test_summaries.len(), 1);
+        assert!(parsed.test_failure_output.contains("assertion failed"));
+        assert!(!parsed.test_failure_output.contains("tests::ok"));
+    }
+
+    #[tokio::test]
+    async fn test_cargo_check_reports_errors() {
+        let (dir, whitelist) = whitelisted_temp_dir();
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(
+            dir.join("Cargo.toml"),
+            "[package]\nname = \"broken\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("src/lib.rs"),
+            "pub fn f() -> u32 {\n    \"nope\"\n}\n",
+        )
+        .unwrap();
+
+        let mut tool = CargoCheckTool::new();
+        tool.set_whitelist(whitelist);
+
+        let result = tool
+            .execute_data(json!({ "path": dir.to_string_lossy() }))
+            .await
+            .unwrap();
+        let ToolResultData::Json(value) = result else {
+            panic!("expected JSON result");
+        };
+
+        assert_eq!(value["success"], false);
+        let diagnostic = &value["diagnostics"][0];
+        assert_eq!(diagnostic["level"], "error");
+        assert_eq!(diagnostic["code"], "E0308");
+        assert_eq!(diagnostic["file"], "src/lib.rs");
+        assert_eq!(diagnostic["span"]["line_start"], 2);
+    }
+}
@@ -1,16 +1,24 @@
 use crate::claude::{
+    audit::{AuditEvent, AuditLog},
+    constants::tool_timeouts::{max_tool_timeout, DEFAULT_TOOL_TIMEOUT_SECS, TOOL_TIMEOUT_SECS},
     error::{ClaudeError, ClaudeResult, ErrorContext},
     tools::{
+        approval::{ApprovalGate, ApprovalRequest, ToolPermission},
+        breaker::ToolBreakers,
+        checkpoint::CheckpointStore,
         execution::{
-            ToolError, ToolErrorType, ToolExecutionContext, ToolExecutionResult, ToolResultData,
+            AppliedRecovery, FollowUpAction, RecoveryKind, StatusLevel, ToolError, ToolErrorType,
+            ToolExecutionContext, ToolExecutionResult, ToolExecutionStatus, ToolResultData,
         },
-        feedback::FeedbackManager,
-        recovery::{RecoveryResult, ToolRecoveryManager},
-        AgentTool,
+        feedback::{FeedbackDelivery, FeedbackManager, LearnedFeedback, ToolFeedbackHandler},
+        history::{ExecutionHistoryStore, ExecutionRecord},
+        recovery::{RecoveryAction, RecoveryResult, ToolRecoveryManager},
+        resolve_tool_path, validate_input, AgentTool,
     },
 };
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
@@ -25,6 +33,13 @@ pub struct ToolRequest {
     pub timeout: Option<Duration>,
     pub max_retries: Option<u32>,
     pub metadata: HashMap<String, String>,
+    /// What happens to the rest of the chain if this step fails; the chain's
+    /// policy applies when unset
+    #[serde(default)]
+    pub on_failure: Option<FailurePolicy>,
+    /// Step run when this one fails, e.g. to restore a backup
+    #[serde(default)]
+    pub compensation: Option<Box<ToolRequest>>,
 }
 
 impl ToolRequest {
@@ -37,6 +52,8 @@ impl ToolRequest {
             timeout: None,
             max_retries: None,
             metadata: HashMap::new(),
+            on_failure: None,
+            compensation: None,
         }
     }
 
@@ -60,12 +77,53 @@ impl ToolRequest {
         self
     }
 
+    pub fn with_failure_policy(mut self, policy: FailurePolicy) -> Self {
+        self.on_failure = Some(policy);
+        self
+    }
+
+    pub fn with_compensation(mut self, compensation: ToolRequest) -> Self {
+        self.compensation = Some(Box::new(compensation));
+        self
+    }
+
     fn generate_id() -> String {
         use uuid::Uuid;
         format!("req_{}", Uuid::new_v4().simple())
     }
 }
 
+/// What happens to the rest of a chain when a step fails
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailurePolicy {
+    /// Stop the chain; steps that have not run are skipped
+    #[default]
+    FailFast,
+    /// Keep running every other step, including the failed step's dependents
+    Continue,
+    /// Skip the steps that depend on the failed one and run the rest
+    SkipDependents,
+}
+
+/// How a step of a chain ended
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum StepOutcome {
+    Succeeded,
+    Failed {
+        error: String,
+    },
+    Skipped {
+        reason: String,
+    },
+    /// The step failed and its compensation step ran successfully
+    Compensated {
+        error: String,
+        compensation: String,
+    },
+}
+
 /// Status of tool chain execution
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ChainExecutionStatus {
@@ -94,6 +152,10 @@ pub struct ChainExecutionResult {
     pub status: ChainExecutionStatus,
     pub results: HashMap<String, ToolExecutionResult>,
     pub execution_order: Vec<String>,
+    /// Input each step ran with, after its templates were resolved
+    pub resolved_inputs: HashMap<String, serde_json::Value>,
+    /// How each step ended, in planned order
+    pub step_outcomes: Vec<(String, StepOutcome)>,
     pub total_time: Duration,
     pub metadata: ChainMetadata,
 }
@@ -116,6 +178,31 @@ impl ChainExecutionResult {
             .collect()
     }
 
+    /// Ids of the steps whose outcome matches `predicate`, in planned order
+    pub fn steps_where(&self, predicate: impl Fn(&StepOutcome) -> bool) -> Vec<&str> {
+        self.step_outcomes
+            .iter()
+            .filter(|(_, outcome)| predicate(outcome))
+            .map(|(id, _)| id.as_str())
+            .collect()
+    }
+
+    pub fn succeeded_steps(&self) -> Vec<&str> {
+        self.steps_where(|outcome| matches!(outcome, StepOutcome::Succeeded))
+    }
+
+    pub fn failed_steps(&self) -> Vec<&str> {
+        self.steps_where(|outcome| matches!(outcome, StepOutcome::Failed { .. }))
+    }
+
+    pub fn skipped_steps(&self) -> Vec<&str> {
+        self.steps_where(|outcome| matches!(outcome, StepOutcome::Skipped { .. }))
+    }
+
+    pub fn compensated_steps(&self) -> Vec<&str> {
+        self.steps_where(|outcome| matches!(outcome, StepOutcome::Compensated { .. }))
+    }
+
     pub fn get_errors(&self) -> Vec<&ToolExecutionResult> {
         self.results
             .values()
@@ -166,6 +253,13 @@ pub struct ToolExecutionEngine {
     recovery_manager: ToolRecoveryManager,
     execution_history: Arc<RwLock<Vec<ChainExecutionResult>>>,
     config: ExecutionConfig,
+    approval: Option<ApprovalGate>,
+    checkpoints: Option<Arc<CheckpointStore>>,
+    breakers: Arc<ToolBreakers>,
+    history: Option<Arc<ExecutionHistoryStore>>,
+    session_id: Option<String>,
+    feedback: Option<LearnedFeedback>,
+    audit_log: Option<Arc<AuditLog>>,
 }
 
 impl ToolExecutionEngine {
@@ -183,6 +277,13 @@ impl ToolExecutionEngine {
             recovery_manager: ToolRecoveryManager::default(),
             execution_history: Arc::new(RwLock::new(Vec::new())),
             config: ExecutionConfig::default(),
+            approval: None,
+            checkpoints: None,
+            breakers: Arc::new(ToolBreakers::default()),
+            history: None,
+            session_id: None,
+            feedback: None,
+            audit_log: None,
         }
     }
 
@@ -191,12 +292,65 @@ impl ToolExecutionEngine {
         self
     }
 
+    /// Replace the recovery manager, e.g. to register tool-specific strategies
+    pub fn with_recovery_manager(mut self, recovery_manager: ToolRecoveryManager) -> Self {
+        self.recovery_manager = recovery_manager;
+        self
+    }
+
+    /// Route tool calls through an approval policy and ask the user when it says so
+    pub fn with_approval(mut self, approval: ApprovalGate) -> Self {
+        self.approval = Some(approval);
+        self
+    }
+
+    /// Snapshot files into checkpoints before tools modify them
+    pub fn with_checkpoints(mut self, checkpoints: Arc<CheckpointStore>) -> Self {
+        self.checkpoints = Some(checkpoints);
+        self
+    }
+
+    /// Share circuit breakers and concurrency limits with other engines
+    pub fn with_breakers(mut self, breakers: Arc<ToolBreakers>) -> Self {
+        self.breakers = breakers;
+        self
+    }
+
+    /// Persist every finished call, tagged with the conversation or workflow
+    /// it was made for
+    pub fn with_history(
+        mut self,
+        history: Arc<ExecutionHistoryStore>,
+        session_id: Option<String>,
+    ) -> Self {
+        self.history = Some(history);
+        self.session_id = session_id;
+        self
+    }
+
+    /// Learn from every call and pass what was learned on as configured
+    pub fn with_learned_feedback(mut self, feedback: LearnedFeedback) -> Self {
+        self.feedback = Some(feedback);
+        self
+    }
+
+    /// Append every finished call to the tamper-evident audit log
+    pub fn with_audit_log(mut self, audit_log: Arc<AuditLog>) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
     /// Register a tool with the execution engine
     pub fn register_tool(&mut self, tool: Arc<dyn AgentTool>) {
         let name = tool.name().to_string();
         self.tools.insert(name, tool);
     }
 
+    /// Whether a tool with this name is registered
+    pub fn has_tool(&self, name: &str) -> bool {
+        self.tools.contains_key(name)
+    }
+
     /// Execute a single tool
     pub async fn execute_single_tool(
         &self,
@@ -204,6 +358,44 @@ impl ToolExecutionEngine {
         context: ToolExecutionContext,
     ) -> ClaudeResult<ToolExecutionResult> {
         let start_time = Instant::now();
+        let result = self.execute_guarded(request, &context).await;
+        match &result {
+            Ok(result) => {
+                if self.history.is_some() || self.audit_log.is_some() {
+                    let record = ExecutionRecord::new(&context, result, self.session_id.clone());
+                    if let Some(history) = &self.history {
+                        if let Err(e) = history.record(&record).await {
+                            eprintln!("Warning: Failed to record tool execution: {}", e);
+                        }
+                    }
+                    if let Some(audit_log) = &self.audit_log {
+                        audit_log.record(AuditEvent::ToolExecution(record));
+                    }
+                }
+            }
+            // Refused calls never ran, so they stay out of the history (and
+            // its success rates) but are still audited
+            Err(e) => {
+                if let Some(audit_log) = &self.audit_log {
+                    audit_log.record(AuditEvent::ToolExecution(ExecutionRecord::rejected(
+                        &context,
+                        e.to_string(),
+                        start_time.elapsed(),
+                        self.session_id.clone(),
+                    )));
+                }
+            }
+        }
+        result
+    }
+
+    /// Validate the input and consult the tool's breaker before running it
+    async fn execute_guarded(
+        &self,
+        request: ToolRequest,
+        context: &ToolExecutionContext,
+    ) -> ClaudeResult<ToolExecutionResult> {
+        let start_time = Instant::now();
 
         let tool = self
             .tools
@@ -217,43 +409,227 @@ impl ToolExecutionEngine {
                 ),
             })?;
 
-        let mut attempt_count = 0;
-        let max_retries = request
-            .max_retries
-            .unwrap_or(self.config.default_max_retries);
+        // Malformed input is reported back without running or retrying the tool
+        if let Err(e) = validate_input(&tool.input_schema(), &context.input) {
+            return Ok(ToolExecutionResult::failure(
+                context.execution_id.clone(),
+                request.tool_name.clone(),
+                ToolError::validation_error(e.to_string()),
+                false,
+            ));
+        }
 
-        loop {
-            // Check for timeout
-            if context.is_timeout() {
-                return Ok(ToolExecutionResult::timeout(
+        // A tool whose breaker is open is not run; the model is told why instead
+        let guarded = self.config.circuit_breaker_enabled;
+        if guarded {
+            if let Err(rejection) = self.breakers.check(&request.tool_name) {
+                let error = ToolError {
+                    error_type: ToolErrorType::ResourceError,
+                    message: rejection.message(),
+                    details: None,
+                    recovery_suggestions: vec![
+                        "Use a different tool or approach for now".to_string(),
+                        "Ask the user to check the tool's setup".to_string(),
+                    ],
+                };
+                return Ok(ToolExecutionResult::failure(
                     context.execution_id.clone(),
                     request.tool_name.clone(),
-                    context.elapsed(),
+                    error,
+                    false,
                 ));
             }
+        }
 
-            match tool.execute(context.input.clone()).await {
-                Ok(result_string) => {
-                    let _execution_time = start_time.elapsed();
-                    let result = ToolExecutionResult::success(
-                        context.execution_id.clone(),
-                        request.tool_name.clone(),
-                        ToolResultData::text(result_string),
-                    );
+        let result = self
+            .execute_admitted(tool.as_ref(), &request, context, start_time)
+            .await;
+        if guarded {
+            self.record_health(&request.tool_name, &result);
+        }
+        result
+    }
+
+    /// Run a call the breaker admitted: approval, a bulkhead slot, checkpoints
+    /// and the tool itself
+    async fn execute_admitted(
+        &self,
+        tool: &dyn AgentTool,
+        request: &ToolRequest,
+        context: &ToolExecutionContext,
+        start_time: Instant,
+    ) -> ClaudeResult<ToolExecutionResult> {
+        if let Some(denied) = self.check_approval(tool, context).await {
+            return Ok(denied);
+        }
+
+        let _permit = self.breakers.acquire(&request.tool_name).await;
+        let checkpointed = match self.checkpoint_before(tool, context).await {
+            Ok(checkpointed) => checkpointed,
+            Err(failed) => return Ok(failed),
+        };
+        let result = self
+            .run_with_recovery(tool, request, context, start_time)
+            .await;
+        if let (Some(checkpoints), false) = (&self.checkpoints, checkpointed.is_empty()) {
+            if let Err(e) = checkpoints.record_after(&checkpointed).await {
+                eprintln!("Warning: Failed to update checkpoint: {}", e);
+            }
+        }
+        result
+    }
+
+    /// Feed the outcome of an admitted call into the tool's breaker. Bad input
+    /// and refused permissions say nothing about whether the tool works.
+    fn record_health(&self, tool_name: &str, result: &ClaudeResult<ToolExecutionResult>) {
+        let result = match result {
+            Ok(result) => result,
+            Err(e) => return self.breakers.record_failure(tool_name, &e.to_string()),
+        };
+
+        // A fallback tool answered; this tool's own failure was recorded when
+        // it was replaced, and the fallback's outcome went to its own breaker
+        if result.tool_name != tool_name {
+            return;
+        }
+
+        match &result.status {
+            ToolExecutionStatus::Success | ToolExecutionStatus::PartialSuccess { .. } => {
+                self.breakers.record_success(tool_name)
+            }
+            ToolExecutionStatus::Timeout => self.breakers.record_failure(
+                tool_name,
+                result.error_context.as_deref().unwrap_or("timed out"),
+            ),
+            ToolExecutionStatus::Failed { error, .. }
+                if !matches!(
+                    error.error_type,
+                    ToolErrorType::ValidationError | ToolErrorType::PermissionError
+                ) =>
+            {
+                self.breakers.record_failure(tool_name, &error.message)
+            }
+            _ => self.breakers.release(tool_name),
+        }
+    }
+
+    /// Run a tool, retrying and recovering from failures as configured.
+    /// Every recovery action carried out is recorded in the result metadata.
+    async fn run_with_recovery(
+        &self,
+        tool: &dyn AgentTool,
+        request: &ToolRequest,
+        context: &ToolExecutionContext,
+        start_time: Instant,
+    ) -> ClaudeResult<ToolExecutionResult> {
+        let mut recoveries = Vec::new();
+        let mut result = self
+            .attempt_with_recovery(tool, request, context, &mut recoveries)
+            .await?;
+        // A fallback tool's own recoveries follow the ones that led to it
+        recoveries.append(&mut result.metadata.recoveries);
+        result.metadata.recoveries = recoveries;
+        result.metadata.execution_time = start_time.elapsed();
+        // A fallback tool's result was already seen by its own run
+        if result.tool_name == request.tool_name {
+            self.apply_learned_feedback(&mut result, &context.input)
+                .await;
+        }
+        Ok(result)
+    }
+
+    /// Let the learning handler see a finished call, and deliver the
+    /// warnings it raises from earlier calls to Claude or the user
+    async fn apply_learned_feedback(
+        &self,
+        result: &mut ToolExecutionResult,
+        input: &serde_json::Value,
+    ) {
+        let Some(feedback) = &self.feedback else {
+            return;
+        };
+
+        let actions = match feedback.handler.handle_call(result, input).await {
+            Ok(actions) => actions,
+            Err(e) => {
+                eprintln!("Warning: Learned feedback failed: {}", e);
+                return;
+            }
+        };
+
+        for action in &actions {
+            let FollowUpAction::ReportStatus {
+                message,
+                level: StatusLevel::Warning | StatusLevel::Error,
+            } = action
+            else {
+                continue;
+            };
+            match feedback.delivery {
+                FeedbackDelivery::Notes => result.metadata.notes.push(message.clone()),
+                FeedbackDelivery::Hints => {
+                    result.metadata.performance_hints.push(message.clone());
+                    if let Some(notifier) = &feedback.notifier {
+                        notifier.notify(&result.tool_name, message);
+                    }
+                }
+                FeedbackDelivery::Off => {}
+            }
+        }
+        result.follow_up_actions.extend(actions);
+    }
+
+    async fn attempt_with_recovery(
+        &self,
+        tool: &dyn AgentTool,
+        request: &ToolRequest,
+        context: &ToolExecutionContext,
+        recoveries: &mut Vec<AppliedRecovery>,
+    ) -> ClaudeResult<ToolExecutionResult> {
+        let mut attempt_count = 0;
+        let max_retries = request
+            .max_retries
+            .unwrap_or(self.config.default_max_retries);
+        let mut context = context.clone();
+        context.metadata.timeout = self.attempt_timeout(request);
+
+        loop {
+            let timeout = context.metadata.timeout;
+            let execution =
+                tokio::time::timeout(timeout, tool.execute_output(context.input.clone())).await;
+
+            let (tool_error, timed_out) = match execution {
+                Ok(Ok(output)) => {
+                    let mut result = if output.warnings.is_empty() {
+                        ToolExecutionResult::success(
+                            context.execution_id.clone(),
+                            request.tool_name.clone(),
+                            output.data,
+                        )
+                    } else {
+                        ToolExecutionResult::partial_success(
+                            context.execution_id.clone(),
+                            request.tool_name.clone(),
+                            output.data,
+                            output.warnings.clone(),
+                        )
+                    };
+                    result.metadata.warnings = output.warnings;
+                    result.metadata.bytes_processed = output.bytes_processed;
 
                     // Process result through feedback manager
                     if let Ok(follow_up_actions) =
                         self.feedback_manager.process_result(&result).await
                     {
                         let result_with_actions = result.with_follow_up_actions(follow_up_actions);
-                        return Ok(result_with_actions);
+                        return Ok(self.collect_user_input(result_with_actions).await);
                     }
 
                     return Ok(result);
                 }
-                Err(e) => {
-                    let tool_error = ToolError {
-                        error_type: ToolErrorType::ExecutionError,
+                Ok(Err(e)) => (
+                    ToolError {
+                        error_type: classify_tool_error(&e.to_string()),
                         message: e.to_string(),
                         details: Some(format!(
                             "Tool: {}, Attempt: {}",
@@ -264,63 +640,317 @@ impl ToolExecutionEngine {
                             "Check input parameters".to_string(),
                             "Verify tool configuration".to_string(),
                         ],
-                    };
+                    },
+                    false,
+                ),
+                // The tool future is dropped here. That cancels async work, but
+                // a blocking task it spawned (file tools run their I/O in
+                // spawn_blocking) keeps its thread until the call returns; file
+                // tools open with O_NONBLOCK and refuse FIFOs and devices so such
+                // a call cannot wait forever on one
+                Err(_) => (
+                    ToolError {
+                        error_type: ToolErrorType::TimeoutError,
+                        message: format!(
+                            "Tool '{}' did not finish within {:?}",
+                            request.tool_name, timeout
+                        ),
+                        details: Some(format!(
+                            "Tool: {}, Attempt: {}",
+                            request.tool_name,
+                            attempt_count + 1
+                        )),
+                        recovery_suggestions: vec![
+                            "Retry with a longer timeout".to_string(),
+                            "Narrow the operation to less data".to_string(),
+                        ],
+                    },
+                    true,
+                ),
+            };
+
+            let failed = |tool_error: ToolError| {
+                if timed_out {
+                    ToolExecutionResult::timeout(
+                        context.execution_id.clone(),
+                        request.tool_name.clone(),
+                        timeout,
+                    )
+                } else {
+                    ToolExecutionResult::failure(
+                        context.execution_id.clone(),
+                        request.tool_name.clone(),
+                        tool_error,
+                        false,
+                    )
+                }
+            };
 
-                    // Try recovery if retries are available
-                    if attempt_count < max_retries {
-                        if let Ok(recovery_actions) = self
-                            .recovery_manager
-                            .suggest_recovery(&tool_error, &context)
-                            .await
-                        {
-                            for action in recovery_actions {
-                                match self
-                                    .recovery_manager
-                                    .execute_recovery_action(&action, &context)
-                                    .await
+            // Try recovery if retries are available
+            if attempt_count >= max_retries {
+                // No more retries available
+                return Ok(failed(tool_error));
+            }
+
+            let Ok(recovery_actions) = self
+                .recovery_manager
+                .suggest_recovery(&tool_error, &context)
+                .await
+            else {
+                attempt_count += 1;
+                continue;
+            };
+
+            let mut retry = false;
+            for action in recovery_actions {
+                let applied = describe_recovery(&action, attempt_count + 1, &tool_error);
+                match self
+                    .recovery_manager
+                    .execute_recovery_action(&action, &context)
+                    .await
+                {
+                    Ok(RecoveryResult::Retry { modified_context }) => {
+                        if let Some(new_context) = modified_context {
+                            if new_context.input != context.input {
+                                // Checkpoints were taken for the original input, so a
+                                // tool that changes files is not rerun with another one
+                                if !tool.modified_paths(&context.input).is_empty()
+                                    || !tool.modified_paths(&new_context.input).is_empty()
                                 {
-                                    Ok(RecoveryResult::Retry { modified_context }) => {
-                                        attempt_count += 1;
-                                        if let Some(_new_context) = modified_context {
-                                            // Use modified context for retry
-                                            // In a full implementation, we'd update the context here
-                                        }
-                                        break; // Retry the execution
-                                    }
-                                    Ok(RecoveryResult::Abort { reason: _ }) => {
-                                        return Ok(ToolExecutionResult::failure(
-                                            context.execution_id.clone(),
-                                            request.tool_name.clone(),
-                                            tool_error,
-                                            false,
-                                        ));
-                                    }
-                                    Ok(RecoveryResult::Skip { reason: _ }) => {
-                                        return Ok(ToolExecutionResult::failure(
-                                            context.execution_id.clone(),
-                                            request.tool_name.clone(),
-                                            tool_error,
-                                            false,
-                                        ));
-                                    }
-                                    _ => continue, // Try next recovery action
+                                    continue;
+                                }
+                                let mut retried = context.clone();
+                                retried.input = new_context.input.clone();
+                                if let Some(denied) = self.check_approval(tool, &retried).await {
+                                    return Ok(denied);
                                 }
                             }
-                        } else {
-                            attempt_count += 1;
+                            // Recovery may fix the input or grant the next attempt more time
+                            context.input = new_context.input;
+                            context.metadata.timeout =
+                                new_context.metadata.timeout.min(max_tool_timeout());
                         }
-                    } else {
-                        // No more retries available
-                        return Ok(ToolExecutionResult::failure(
+                        recoveries.extend(applied);
+                        retry = true;
+                        break;
+                    }
+                    Ok(RecoveryResult::FallbackTool {
+                        tool_name, input, ..
+                    }) => {
+                        if tool_name == request.tool_name || !self.tools.contains_key(&tool_name) {
+                            continue;
+                        }
+                        recoveries.extend(applied);
+                        if self.config.circuit_breaker_enabled {
+                            self.record_health(&request.tool_name, &Ok(failed(tool_error.clone())));
+                        }
+                        // The fallback goes through its breaker, approval and checkpoints
+                        // like any call, but is recorded once, as the outcome of this one
+                        let fallback_request =
+                            ToolRequest::new(tool_name.clone(), input.clone()).with_max_retries(0);
+                        let mut fallback_context =
+                            ToolExecutionContext::new(tool_name, input, context.whitelist.clone());
+                        fallback_context.parent_execution = Some(context.execution_id.clone());
+                        fallback_context.chain_id = context.chain_id.clone();
+                        return Box::pin(self.execute_guarded(fallback_request, &fallback_context))
+                            .await;
+                    }
+                    Ok(RecoveryResult::Abort { reason: _ }) => {
+                        recoveries.extend(applied);
+                        return Ok(failed(tool_error));
+                    }
+                    Ok(RecoveryResult::Skip { reason }) => {
+                        recoveries.extend(applied);
+                        return Ok(ToolExecutionResult::skipped(
                             context.execution_id.clone(),
                             request.tool_name.clone(),
-                            tool_error,
-                            false,
+                            &reason,
                         ));
                     }
+                    Ok(RecoveryResult::UserIntervention {
+                        message,
+                        suggested_actions,
+                    }) => {
+                        let Some(approval) = &self.approval else {
+                            continue;
+                        };
+                        let decision = approval
+                            .manager
+                            .request(ApprovalRequest::for_intervention(
+                                &request.tool_name,
+                                &context.input,
+                                &message,
+                                suggested_actions,
+                            ))
+                            .await;
+                        recoveries.extend(applied);
+                        if decision.approved {
+                            retry = true;
+                            break; // The user fixed the problem, retry
+                        }
+                        let mut result = failed(tool_error);
+                        result.metadata.user_responses.extend(decision.message);
+                        return Ok(result);
+                    }
+                    Err(e) => {
+                        eprintln!("Warning: Recovery action failed: {}", e);
+                        continue; // Try next recovery action
+                    }
+                }
+            }
+
+            if !retry {
+                // Nothing could be done about the failure
+                return Ok(failed(tool_error));
+            }
+            attempt_count += 1;
+        }
+    }
+
+    /// Time a single attempt may take: the request's own timeout, else the
+    /// configured timeout for the tool, else the engine default
+    fn attempt_timeout(&self, request: &ToolRequest) -> Duration {
+        request
+            .timeout
+            .or_else(|| self.config.tool_timeouts.get(&request.tool_name).copied())
+            .unwrap_or(self.config.default_timeout)
+    }
+
+    /// Snapshot the files a call is about to modify into the current checkpoint.
+    /// Returns the resolved paths so their new state can be recorded afterwards,
+    /// or the failure to report when the snapshot could not be taken: a change
+    /// that could not be rolled back is not made.
+    async fn checkpoint_before(
+        &self,
+        tool: &dyn AgentTool,
+        context: &ToolExecutionContext,
+    ) -> Result<Vec<PathBuf>, ToolExecutionResult> {
+        let Some(checkpoints) = &self.checkpoints else {
+            return Ok(Vec::new());
+        };
+
+        let mut paths = Vec::new();
+        for (path, operation) in tool.modified_paths(&context.input) {
+            // Paths the tool may not touch are left to fail in the tool itself
+            if let Ok(path) = resolve_tool_path(Some(&context.whitelist), &path, operation).await {
+                paths.push(path);
+            }
+        }
+        if let Err(e) = checkpoints.snapshot(&paths).await {
+            let error = ToolError {
+                error_type: ToolErrorType::ResourceError,
+                message: format!("Failed to checkpoint files before the change: {}", e),
+                details: None,
+                recovery_suggestions: vec![
+                    "Ask the user to check the checkpoint storage".to_string()
+                ],
+            };
+            return Err(ToolExecutionResult::failure(
+                context.execution_id.clone(),
+                context.tool_name.clone(),
+                error,
+                false,
+            ));
+        }
+        Ok(paths)
+    }
+
+    /// Apply the approval policy to a call, asking the user when required.
+    /// Returns the failure result to report when the call may not run.
+    async fn check_approval(
+        &self,
+        tool: &dyn AgentTool,
+        context: &ToolExecutionContext,
+    ) -> Option<ToolExecutionResult> {
+        let denied = |message: String| {
+            Some(ToolExecutionResult::failure(
+                context.execution_id.clone(),
+                context.tool_name.clone(),
+                ToolError::permission_error(message),
+                false,
+            ))
+        };
+
+        let Some(approval) = &self.approval else {
+            // Without an approval channel, tools that need one are refused
+            if tool.requires_approval() {
+                return denied(format!(
+                    "Tool '{}' requires user approval, which is not available for this request",
+                    context.tool_name
+                ));
+            }
+            return None;
+        };
+
+        let mut permission = approval.policy.read().await.permission_for(
+            &context.tool_name,
+            &context.input,
+            &*context.whitelist.read().await,
+        );
+        if tool.requires_approval() {
+            permission = permission.max(ToolPermission::Ask);
+        }
+
+        match permission {
+            ToolPermission::Allow => None,
+            ToolPermission::Deny => denied(format!(
+                "Tool '{}' is denied by the approval policy",
+                context.tool_name
+            )),
+            ToolPermission::Ask => {
+                let request = ApprovalRequest::for_tool_call(
+                    &context.tool_name,
+                    &context.input,
+                    &context.whitelist,
+                )
+                .await;
+                let decision = approval.manager.request(request).await;
+                if decision.approved {
+                    return None;
                 }
+                denied(match decision.message {
+                    Some(message) => {
+                        format!("The user declined '{}': {}", context.tool_name, message)
+                    }
+                    None => format!("The user declined '{}'", context.tool_name),
+                })
+            }
+        }
+    }
+
+    /// Put `RequestUserInput` follow-up actions to the user and attach the
+    /// answers to the result so they reach the model
+    async fn collect_user_input(&self, mut result: ToolExecutionResult) -> ToolExecutionResult {
+        let Some(approval) = &self.approval else {
+            return result;
+        };
+
+        for action in &result.follow_up_actions {
+            if let FollowUpAction::RequestUserInput {
+                prompt,
+                suggested_actions,
+            } = action
+            {
+                let decision = approval
+                    .manager
+                    .request(ApprovalRequest::for_user_input(
+                        &result.tool_name,
+                        prompt,
+                        suggested_actions.clone(),
+                    ))
+                    .await;
+                let answer = match (decision.approved, decision.message) {
+                    (_, Some(message)) => message,
+                    (true, None) => "acknowledged".to_string(),
+                    (false, None) => "declined".to_string(),
+                };
+                result
+                    .metadata
+                    .user_responses
+                    .push(format!("{} -> {}", prompt, answer));
             }
         }
+        result
     }
 
     /// Execute a chain of tools with dependency management
@@ -330,141 +960,289 @@ impl ToolExecutionEngine {
         requests: Vec<ToolRequest>,
         whitelist: Arc<RwLock<crate::claude::whitelist::WhitelistConfig>>,
     ) -> ClaudeResult<ChainExecutionResult> {
+        self.execute_tool_chain_with_progress(requests, whitelist, FailurePolicy::FailFast, |_| {})
+            .await
+    }
+
+    /// Execute a chain, reporting its status as each phase starts and when it
+    /// ends. Step inputs may use the output of earlier steps through
+    /// `{{steps.<id>.output}}` templates, optionally followed by a path such
+    /// as `.items[0].name` into the output's JSON data. Templates are resolved
+    /// just before the step runs and make the step depend on the one they name.
+    ///
+    /// When a step fails its compensation step runs, if it has one, and then
+    /// its failure policy (or `policy` for steps without one) decides whether
+    /// the rest of the chain stops, carries on, or skips the step's dependents.
+    pub async fn execute_tool_chain_with_progress<F>(
+        &self,
+        requests: Vec<ToolRequest>,
+        whitelist: Arc<RwLock<crate::claude::whitelist::WhitelistConfig>>,
+        policy: FailurePolicy,
+        on_status: F,
+    ) -> ClaudeResult<ChainExecutionResult>
+    where
+        F: Fn(&ChainExecutionStatus) + Send + Sync,
+    {
         let chain_id = self.generate_chain_id();
         let start_time = Instant::now();
         let mut metadata = ChainMetadata::default();
 
+        // Steps wait for the steps whose output they use
+        let requests: Vec<ToolRequest> = requests
+            .into_iter()
+            .map(|mut request| {
+                for reference in step_references(&request.input) {
+                    if !request.depends_on.contains(&reference) {
+                        request.depends_on.push(reference);
+                    }
+                }
+                request
+            })
+            .collect();
+
         // Validate and sort requests by dependencies
         let execution_plan = self.create_execution_plan(&requests)?;
+        let policies: HashMap<&str, FailurePolicy> = requests
+            .iter()
+            .map(|request| (request.id.as_str(), request.on_failure.unwrap_or(policy)))
+            .collect();
 
         let mut results: HashMap<String, ToolExecutionResult> = HashMap::new();
         let mut execution_order = Vec::new();
-        let mut _current_status = ChainExecutionStatus::Running {
-            current_step: "Starting".to_string(),
-            progress: 0.0,
-        };
-
-        for (phase_index, phase) in execution_plan.phases.iter().enumerate() {
-            let progress = (phase_index as f32) / (execution_plan.phases.len() as f32);
-            _current_status = ChainExecutionStatus::Running {
-                current_step: format!(
-                    "Phase {} of {}",
-                    phase_index + 1,
-                    execution_plan.phases.len()
-                ),
-                progress,
-            };
+        let mut resolved_inputs = HashMap::new();
+        let mut outcomes: HashMap<String, StepOutcome> = HashMap::new();
+        // Set once a fail-fast step fails or a recovery aborts the chain
+        let mut stopped: Option<String> = None;
+
+        for phase in &execution_plan.phases {
+            if let Some(reason) = &stopped {
+                for request in phase {
+                    outcomes.insert(
+                        request.id.clone(),
+                        StepOutcome::Skipped {
+                            reason: format!("Chain stopped: {}", reason),
+                        },
+                    );
+                }
+                continue;
+            }
 
-            // Execute phase (potentially in parallel)
-            if phase.len() == 1 {
-                // Single tool execution
-                let request = &phase[0];
-                let context = self.create_execution_context(request, whitelist.clone());
-
-                match self.execute_single_tool(request.clone(), context).await {
-                    Ok(result) => {
-                        execution_order.push(request.id.clone());
-                        results.insert(request.id.clone(), result);
-                    }
-                    Err(e) => {
-                        // Handle chain failure
-                        let total_time = start_time.elapsed();
-                        metadata.completed_at = Some(chrono::Utc::now());
-
-                        return Ok(ChainExecutionResult {
-                            chain_id,
-                            status: ChainExecutionStatus::Failed {
-                                error: e.to_string(),
-                                partial_results: results.len(),
-                            },
-                            results,
-                            execution_order,
-                            total_time,
-                            metadata,
-                        });
+            on_status(&ChainExecutionStatus::Running {
+                current_step: phase
+                    .iter()
+                    .map(|request| request.id.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                progress: outcomes.len() as f32 / requests.len() as f32,
+            });
+
+            // Skip steps cut off by earlier failures and fill in references to
+            // the outputs of earlier steps; a reference that cannot be filled
+            // in fails its step
+            let mut ready = Vec::new();
+            let mut failures = Vec::new();
+            for request in phase {
+                if let Some(reason) = skip_reason(request, &outcomes, &policies) {
+                    outcomes.insert(request.id.clone(), StepOutcome::Skipped { reason });
+                    continue;
+                }
+                match resolve_step_templates(request, &results) {
+                    Ok(request) => {
+                        resolved_inputs.insert(request.id.clone(), request.input.clone());
+                        ready.push(request);
                     }
+                    Err(e) => failures.push((request, e.to_string())),
                 }
-            } else {
-                // Parallel execution
-                metadata.parallel_executions += 1;
-                let parallel_results = self.execute_parallel_phase(phase, whitelist.clone()).await;
+            }
 
-                for (request_id, result) in parallel_results {
-                    match result {
-                        Ok(tool_result) => {
-                            execution_order.push(request_id.clone());
-                            results.insert(request_id, tool_result);
-                        }
-                        Err(e) => {
-                            // Handle parallel execution failure
-                            let total_time = start_time.elapsed();
-                            metadata.completed_at = Some(chrono::Utc::now());
-
-                            return Ok(ChainExecutionResult {
-                                chain_id,
-                                status: ChainExecutionStatus::Failed {
-                                    error: format!("Parallel execution failed: {}", e),
-                                    partial_results: results.len(),
+            // Execute phase (potentially in parallel)
+            if ready.len() > 1 {
+                metadata.parallel_executions += 1;
+            }
+            let phase_results = self
+                .execute_parallel_phase(&ready, &chain_id, whitelist.clone())
+                .await;
+
+            for (request, (request_id, result)) in ready.iter().zip(phase_results) {
+                match result {
+                    Ok(tool_result) => {
+                        let abort = abort_reason(&tool_result);
+                        let error = tool_result
+                            .is_error()
+                            .then(|| tool_result.into_content_block());
+                        let skipped = match &tool_result.status {
+                            ToolExecutionStatus::Skipped { reason } => Some(reason.clone()),
+                            _ => None,
+                        };
+                        execution_order.push(request_id.clone());
+                        results.insert(request_id.clone(), tool_result);
+
+                        // A step whose recovery aborted stops the rest of the chain
+                        if let Some(reason) = abort {
+                            outcomes.insert(
+                                request_id,
+                                StepOutcome::Failed {
+                                    error: reason.clone(),
                                 },
-                                results,
-                                execution_order,
-                                total_time,
-                                metadata,
-                            });
+                            );
+                            stopped.get_or_insert(reason);
+                        } else if let Some(reason) = skipped {
+                            // Recovery skipped the step: nothing to compensate
+                            // and no reason to stop the chain
+                            outcomes.insert(request_id, StepOutcome::Skipped { reason });
+                        } else if let Some(error) = error {
+                            failures.push((request, error));
+                        } else {
+                            outcomes.insert(request_id, StepOutcome::Succeeded);
                         }
                     }
+                    Err(e) => failures.push((request, e.to_string())),
+                }
+            }
+
+            for (request, error) in failures {
+                let outcome = self
+                    .compensate(request, error, &results, &chain_id, whitelist.clone())
+                    .await;
+                if policies[request.id.as_str()] == FailurePolicy::FailFast {
+                    stopped.get_or_insert_with(|| format!("step '{}' failed", request.id));
                 }
+                outcomes.insert(request.id.clone(), outcome);
             }
         }
 
-        // Chain completed successfully
         let total_time = start_time.elapsed();
         metadata.completed_at = Some(chrono::Utc::now());
         metadata.performance_metrics = self.calculate_performance_metrics(&results, total_time);
 
-        let final_result = ChainExecutionResult {
-            chain_id: chain_id.clone(),
-            status: ChainExecutionStatus::Completed {
+        // Outcomes in the order the steps were planned
+        let step_outcomes: Vec<(String, StepOutcome)> = execution_plan
+            .phases
+            .iter()
+            .flatten()
+            .filter_map(|request| {
+                outcomes
+                    .remove(&request.id)
+                    .map(|outcome| (request.id.clone(), outcome))
+            })
+            .collect();
+
+        let succeeded = step_outcomes
+            .iter()
+            .filter(|(_, outcome)| *outcome == StepOutcome::Succeeded)
+            .count();
+        let status = match step_outcomes
+            .iter()
+            .find_map(|(id, outcome)| match outcome {
+                StepOutcome::Failed { error } | StepOutcome::Compensated { error, .. } => {
+                    Some((id, error))
+                }
+                _ => None,
+            }) {
+            None if succeeded == step_outcomes.len() => ChainExecutionStatus::Completed {
                 total_time,
                 results_count: results.len(),
             },
+            first_failure => ChainExecutionStatus::Failed {
+                error: match first_failure {
+                    Some((id, error)) => format!(
+                        "Step '{}' failed: {} ({} of {} steps succeeded)",
+                        id,
+                        error,
+                        succeeded,
+                        step_outcomes.len()
+                    ),
+                    None => stopped.unwrap_or_else(|| "Chain did not complete".to_string()),
+                },
+                partial_results: succeeded,
+            },
+        };
+
+        let final_result = ChainExecutionResult {
+            chain_id,
+            status,
             results,
             execution_order,
+            resolved_inputs,
+            step_outcomes,
             total_time,
             metadata,
         };
+        on_status(&final_result.status);
 
-        // Store in execution history
+        // Store in execution history; failed chains count towards the stats too
         self.add_to_history(final_result.clone()).await;
 
         Ok(final_result)
     }
 
-    async fn execute_parallel_phase(
+    /// Run the compensation step of a failed step, if it has one
+    async fn compensate(
         &self,
-        phase: &[ToolRequest],
+        request: &ToolRequest,
+        error: String,
+        results: &HashMap<String, ToolExecutionResult>,
+        chain_id: &str,
         whitelist: Arc<RwLock<crate::claude::whitelist::WhitelistConfig>>,
-    ) -> Vec<(String, ClaudeResult<ToolExecutionResult>)> {
-        // For now, execute sequentially to avoid lifetime issues
-        // In a full implementation, we'd need to restructure to support true parallelism
-        let mut results = Vec::new();
+    ) -> StepOutcome {
+        let Some(compensation) = &request.compensation else {
+            return StepOutcome::Failed { error };
+        };
+        let compensation = match resolve_step_templates(compensation, results) {
+            Ok(compensation) => compensation,
+            Err(e) => {
+                return StepOutcome::Failed {
+                    error: format!("{}; compensation could not start: {}", error, e),
+                }
+            }
+        };
 
-        for request in phase {
-            let context = self.create_execution_context(request, whitelist.clone());
-            let result = self.execute_single_tool(request.clone(), context).await;
-            results.push((request.id.clone(), result));
+        let context = self.create_execution_context(&compensation, chain_id, whitelist);
+        match self.execute_single_tool(compensation, context).await {
+            Ok(result) if !result.is_error() => StepOutcome::Compensated {
+                error,
+                compensation: result.into_content_block(),
+            },
+            Ok(result) => StepOutcome::Failed {
+                error: format!(
+                    "{}; compensation failed: {}",
+                    error,
+                    result.into_content_block()
+                ),
+            },
+            Err(e) => StepOutcome::Failed {
+                error: format!("{}; compensation failed: {}", error, e),
+            },
         }
+    }
 
-        results
+    async fn execute_parallel_phase(
+        &self,
+        phase: &[ToolRequest],
+        chain_id: &str,
+        whitelist: Arc<RwLock<crate::claude::whitelist::WhitelistConfig>>,
+    ) -> Vec<(String, ClaudeResult<ToolExecutionResult>)> {
+        // The steps of a phase run at the same time; each tool's bulkhead
+        // bounds how many calls to it are in flight
+        let calls = phase.iter().map(|request| {
+            let context = self.create_execution_context(request, chain_id, whitelist.clone());
+            async move {
+                let result = self.execute_single_tool(request.clone(), context).await;
+                (request.id.clone(), result)
+            }
+        });
+        futures::future::join_all(calls).await
     }
 
     fn create_execution_context(
         &self,
         request: &ToolRequest,
+        chain_id: &str,
         whitelist: Arc<RwLock<crate::claude::whitelist::WhitelistConfig>>,
     ) -> ToolExecutionContext {
         let mut context =
-            ToolExecutionContext::new(request.tool_name.clone(), request.input.clone(), whitelist);
+            ToolExecutionContext::new(request.tool_name.clone(), request.input.clone(), whitelist)
+                .with_chain(chain_id.to_string());
 
         if let Some(timeout) = request.timeout {
             context = context.with_timeout(timeout);
@@ -490,17 +1268,18 @@ impl ToolExecutionEngine {
         while !remaining.is_empty() {
             let mut current_phase = Vec::new();
 
-            // Find all requests that have no pending dependencies
-            let ready_requests: Vec<_> = remaining
+            // Find all requests that have no pending dependencies, in the
+            // order they were given
+            let ready_requests: Vec<_> = requests
                 .iter()
-                .filter(|&id| {
-                    let request = request_map[id];
-                    request
-                        .depends_on
-                        .iter()
-                        .all(|dep| !remaining.contains(dep))
+                .filter(|request| {
+                    remaining.contains(&request.id)
+                        && request
+                            .depends_on
+                            .iter()
+                            .all(|dep| !remaining.contains(dep))
                 })
-                .cloned()
+                .map(|request| request.id.clone())
                 .collect();
 
             if ready_requests.is_empty() {
@@ -649,6 +1428,8 @@ struct ExecutionPlan {
 #[derive(Debug, Clone)]
 pub struct ExecutionConfig {
     pub default_timeout: Duration,
+    /// Per-tool attempt timeouts that override `default_timeout`
+    pub tool_timeouts: HashMap<String, Duration>,
     pub default_max_retries: u32,
     pub max_parallel_executions: usize,
     pub max_history_size: usize,
@@ -659,7 +1440,11 @@ pub struct ExecutionConfig {
 impl Default for ExecutionConfig {
     fn default() -> Self {
         Self {
-            default_timeout: Duration::from_secs(30),
+            default_timeout: Duration::from_secs(DEFAULT_TOOL_TIMEOUT_SECS),
+            tool_timeouts: TOOL_TIMEOUT_SECS
+                .iter()
+                .map(|(tool, secs)| (tool.to_string(), Duration::from_secs(*secs)))
+                .collect(),
             default_max_retries: 3,
             max_parallel_executions: 4,
             max_history_size: 100,
@@ -670,7 +1455,7 @@ impl Default for ExecutionConfig {
 }
 
 /// Execution statistics for monitoring
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct ExecutionStats {
     pub total_chains: usize,
     pub successful_chains: usize,
@@ -679,10 +1464,378 @@ pub struct ExecutionStats {
     pub success_rate: f32,
 }
 
+/// Classify a tool's error message so recovery strategies can target it
+fn classify_tool_error(message: &str) -> ToolErrorType {
+    let lower = message.to_lowercase();
+    if lower.contains("access denied") || lower.contains("permission denied") {
+        ToolErrorType::PermissionError
+    } else if lower.contains("no such file or directory")
+        || lower.contains("not found")
+        || lower.contains("does not exist")
+    {
+        ToolErrorType::ValidationError
+    } else {
+        ToolErrorType::ExecutionError
+    }
+}
+
+/// Metadata records for a recovery action applied after a failed attempt
+fn describe_recovery(
+    action: &RecoveryAction,
+    attempt: u32,
+    error: &ToolError,
+) -> Vec<AppliedRecovery> {
+    action
+        .describe()
+        .into_iter()
+        .map(|(kind, description)| AppliedRecovery {
+            attempt,
+            kind,
+            description,
+            error: error.message.clone(),
+        })
+        .collect()
+}
+
+/// Why a step must be skipped because of how its dependencies ended, if it must
+fn skip_reason(
+    request: &ToolRequest,
+    outcomes: &HashMap<String, StepOutcome>,
+    policies: &HashMap<&str, FailurePolicy>,
+) -> Option<String> {
+    request
+        .depends_on
+        .iter()
+        .find_map(|dependency| match outcomes.get(dependency)? {
+            StepOutcome::Skipped { .. } => Some(format!(
+                "Depends on step '{}', which was skipped",
+                dependency
+            )),
+            StepOutcome::Failed { .. } | StepOutcome::Compensated { .. }
+                if policies.get(dependency.as_str()) == Some(&FailurePolicy::SkipDependents) =>
+            {
+                Some(format!("Depends on step '{}', which failed", dependency))
+            }
+            _ => None,
+        })
+}
+
+/// Reason a step's recovery gave up on the whole chain, if it did
+fn abort_reason(result: &ToolExecutionResult) -> Option<String> {
+    result
+        .metadata
+        .recoveries
+        .last()
+        .filter(|recovery| recovery.kind == RecoveryKind::Abort)
+        .map(|recovery| recovery.description.clone())
+}
+
+/// One step of a path into a step's output
+#[derive(Debug, Clone, PartialEq)]
+enum PathSegment {
+    Field(String),
+    Index(usize),
+}
+
+/// Reference to an earlier step's output: `steps.<id>.output`, optionally
+/// followed by `.field` and `[index]` segments into its JSON data
+#[derive(Debug, Clone, PartialEq)]
+struct StepReference {
+    expression: String,
+    step_id: String,
+    path: Vec<PathSegment>,
+}
+
+/// Literal text and step references of a templated string, in order
+#[derive(Debug, PartialEq)]
+enum TemplatePart<'a> {
+    Literal(&'a str),
+    Reference(StepReference),
+}
+
+fn template_error(message: String) -> ClaudeError {
+    ClaudeError::ValidationError {
+        field: "input".to_string(),
+        message,
+        context: Some(ErrorContext::new("chain_templates")),
+    }
+}
+
+/// Parse the expression inside `{{ }}`. Expressions not starting with
+/// `steps.` are not references and give `None`.
+fn parse_reference(expression: &str) -> ClaudeResult<Option<StepReference>> {
+    let Some(reference) = expression.strip_prefix("steps.") else {
+        return Ok(None);
+    };
+    let invalid = || {
+        template_error(format!(
+            "Invalid reference '{{{{{}}}}}', expected {{{{steps.<id>.output}}}} optionally followed by .field or [index]",
+            expression
+        ))
+    };
+
+    // The step id runs up to the `.output` that ends it or starts the path
+    let (step_id, mut rest) = reference
+        .match_indices(".output")
+        .map(|(i, _)| (&reference[..i], &reference[i + ".output".len()..]))
+        .find(|(_, rest)| rest.is_empty() || rest.starts_with(['.', '[']))
+        .ok_or_else(invalid)?;
+    if step_id.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut path = Vec::new();
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            if end == 0 {
+                return Err(invalid());
+            }
+            path.push(PathSegment::Field(after[..end].to_string()));
+            rest = &after[end..];
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let end = after.find(']').ok_or_else(invalid)?;
+            let index = after[..end].trim().parse().map_err(|_| invalid())?;
+            path.push(PathSegment::Index(index));
+            rest = &after[end + 1..];
+        } else {
+            return Err(invalid());
+        }
+    }
+
+    Ok(Some(StepReference {
+        expression: expression.to_string(),
+        step_id: step_id.to_string(),
+        path,
+    }))
+}
+
+/// Split `text` into literals and step references. Other `{{...}}` text stays
+/// literal so inputs can contain template syntax of their own.
+fn parse_template(text: &str) -> ClaudeResult<Vec<TemplatePart<'_>>> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let end = start + len + 4;
+        match parse_reference(rest[start + 2..start + 2 + len].trim())? {
+            Some(reference) => {
+                if start > 0 {
+                    parts.push(TemplatePart::Literal(&rest[..start]));
+                }
+                parts.push(TemplatePart::Reference(reference));
+            }
+            None => parts.push(TemplatePart::Literal(&rest[..end])),
+        }
+        rest = &rest[end..];
+    }
+
+    if !rest.is_empty() {
+        parts.push(TemplatePart::Literal(rest));
+    }
+    Ok(parts)
+}
+
+/// Step ids referenced by `{{steps.<id>.output...}}` templates anywhere in a
+/// step's input. Malformed templates are reported when the step runs.
+pub fn step_references(input: &serde_json::Value) -> Vec<String> {
+    let mut references: Vec<String> = Vec::new();
+    let _ = map_strings(input, &mut |text| {
+        for part in parse_template(text)? {
+            if let TemplatePart::Reference(reference) = part {
+                if !references.contains(&reference.step_id) {
+                    references.push(reference.step_id);
+                }
+            }
+        }
+        Ok(serde_json::Value::Null)
+    });
+    references
+}
+
+/// A step's output as JSON data for path lookups. Text that holds a JSON
+/// object or array is parsed so tools returning JSON as text can be used.
+fn output_data(data: &ToolResultData) -> serde_json::Value {
+    use serde_json::{json, Value};
+
+    match data {
+        ToolResultData::Text(text) => match serde_json::from_str::<Value>(text) {
+            Ok(value) if value.is_object() || value.is_array() => value,
+            _ => Value::String(text.clone()),
+        },
+        ToolResultData::Json(value) => value.clone(),
+        ToolResultData::FileReference { path, size, hash } => {
+            json!({ "path": path, "size": size, "hash": hash })
+        }
+        ToolResultData::DirectoryListing(items) => {
+            serde_json::to_value(items).unwrap_or(Value::Null)
+        }
+        ToolResultData::Binary {
+            content_type,
+            size,
+            preview,
+        } => json!({ "content_type": content_type, "size": size, "preview": preview }),
+    }
+}
+
+/// Value a reference points to. A bare `output` is the step's text output;
+/// a path selects from its JSON data.
+fn resolve_reference(
+    request_id: &str,
+    reference: &StepReference,
+    results: &HashMap<String, ToolExecutionResult>,
+) -> ClaudeResult<serde_json::Value> {
+    use serde_json::Value;
+
+    let describe = |problem: String| {
+        template_error(format!(
+            "Step '{}' references {{{{{}}}}}, but {}",
+            request_id, reference.expression, problem
+        ))
+    };
+
+    let result = match results.get(&reference.step_id) {
+        Some(result) if result.is_error() => {
+            return Err(describe(format!("step '{}' failed", reference.step_id)))
+        }
+        Some(result) => result,
+        None => {
+            return Err(describe(format!(
+                "step '{}' has not run",
+                reference.step_id
+            )))
+        }
+    };
+    if reference.path.is_empty() {
+        return Ok(Value::String(result.result.render()));
+    }
+
+    let mut value = output_data(&result.result);
+    let mut location = "output".to_string();
+    for segment in &reference.path {
+        value = match (segment, value) {
+            (PathSegment::Field(field), Value::Object(mut fields)) => {
+                let available: Vec<String> = fields.keys().cloned().collect();
+                fields.remove(field).ok_or_else(|| {
+                    describe(format!(
+                        "{} has no field '{}' (available: {})",
+                        location,
+                        field,
+                        available.join(", ")
+                    ))
+                })?
+            }
+            (PathSegment::Index(index), Value::Array(mut items)) => {
+                if *index >= items.len() {
+                    return Err(describe(format!(
+                        "{} has {} items, so index {} is out of range",
+                        location,
+                        items.len(),
+                        index
+                    )));
+                }
+                items.swap_remove(*index)
+            }
+            (segment, other) => {
+                let kind = match other {
+                    Value::Null => "null",
+                    Value::Bool(_) => "a boolean",
+                    Value::Number(_) => "a number",
+                    Value::String(_) => "text",
+                    Value::Array(_) => "an array",
+                    Value::Object(_) => "an object",
+                };
+                let wanted = match segment {
+                    PathSegment::Field(field) => format!("field '{}'", field),
+                    PathSegment::Index(index) => format!("index {}", index),
+                };
+                return Err(describe(format!(
+                    "{} is {}, so {} cannot be read",
+                    location, kind, wanted
+                )));
+            }
+        };
+        match segment {
+            PathSegment::Field(field) => location.push_str(&format!(".{}", field)),
+            PathSegment::Index(index) => location.push_str(&format!("[{}]", index)),
+        }
+    }
+    Ok(value)
+}
+
+/// Copy of `request` with its templates replaced by the data they reference.
+/// A string that is a single reference takes the referenced value as is, so
+/// numbers, arrays and objects keep their type; references inside longer text
+/// are inserted as text.
+fn resolve_step_templates(
+    request: &ToolRequest,
+    results: &HashMap<String, ToolExecutionResult>,
+) -> ClaudeResult<ToolRequest> {
+    use serde_json::Value;
+
+    let input = map_strings(&request.input, &mut |text| {
+        let parts = parse_template(text)?;
+        if let [TemplatePart::Reference(reference)] = parts.as_slice() {
+            return resolve_reference(&request.id, reference, results);
+        }
+
+        let mut rendered = String::with_capacity(text.len());
+        for part in parts {
+            match part {
+                TemplatePart::Literal(literal) => rendered.push_str(literal),
+                TemplatePart::Reference(reference) => {
+                    match resolve_reference(&request.id, &reference, results)? {
+                        Value::String(value) => rendered.push_str(&value),
+                        value => rendered.push_str(&value.to_string()),
+                    }
+                }
+            }
+        }
+        Ok(Value::String(rendered))
+    })?;
+
+    Ok(ToolRequest {
+        input,
+        ..request.clone()
+    })
+}
+
+/// Replace every string in a JSON value with the value `f` makes of it
+fn map_strings(
+    value: &serde_json::Value,
+    f: &mut impl FnMut(&str) -> ClaudeResult<serde_json::Value>,
+) -> ClaudeResult<serde_json::Value> {
+    use serde_json::Value;
+
+    Ok(match value {
+        Value::String(text) => f(text)?,
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| map_strings(item, f))
+                .collect::<ClaudeResult<_>>()?,
+        ),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, item)| Ok((key.clone(), map_strings(item, f)?)))
+                .collect::<ClaudeResult<_>>()?,
+        ),
+        other => other.clone(),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::claude::whitelist::WhitelistConfig;
+    use crate::claude::test_support::{whitelisted_temp_dir, TempDir};
+    use crate::claude::{
+        error::CircuitBreakerState,
+        whitelist::{FileOperation, WhitelistConfig},
+    };
 
     fn create_test_requests() -> Vec<ToolRequest> {
         vec![
@@ -733,6 +1886,8 @@ mod tests {
                 timeout: None,
                 max_retries: None,
                 metadata: HashMap::new(),
+                on_failure: None,
+                compensation: None,
             },
             ToolRequest {
                 id: "2".to_string(),
@@ -742,6 +1897,8 @@ mod tests {
                 timeout: None,
                 max_retries: None,
                 metadata: HashMap::new(),
+                on_failure: None,
+                compensation: None,
             },
         ];
         assert!(engine.validate_dependencies(&valid_requests).is_ok());
@@ -755,6 +1912,8 @@ mod tests {
             timeout: None,
             max_retries: None,
             metadata: HashMap::new(),
+            on_failure: None,
+            compensation: None,
         }];
         assert!(engine.validate_dependencies(&invalid_requests).is_err());
     }
@@ -771,6 +1930,8 @@ mod tests {
                 timeout: None,
                 max_retries: None,
                 metadata: HashMap::new(),
+                on_failure: None,
+                compensation: None,
             },
             ToolRequest {
                 id: "2".to_string(),
@@ -780,6 +1941,8 @@ mod tests {
                 timeout: None,
                 max_retries: None,
                 metadata: HashMap::new(),
+                on_failure: None,
+                compensation: None,
             },
             ToolRequest {
                 id: "3".to_string(),
@@ -789,6 +1952,8 @@ mod tests {
                 timeout: None,
                 max_retries: None,
                 metadata: HashMap::new(),
+                on_failure: None,
+                compensation: None,
             },
         ];
 
@@ -812,6 +1977,8 @@ mod tests {
                 timeout: None,
                 max_retries: None,
                 metadata: HashMap::new(),
+                on_failure: None,
+                compensation: None,
             },
             ToolRequest {
                 id: "2".to_string(),
@@ -821,6 +1988,8 @@ mod tests {
                 timeout: None,
                 max_retries: None,
                 metadata: HashMap::new(),
+                on_failure: None,
+                compensation: None,
             },
         ];
 
@@ -842,6 +2011,8 @@ mod tests {
             },
             results: HashMap::new(),
             execution_order: vec!["1".to_string(), "2".to_string(), "3".to_string()],
+            resolved_inputs: HashMap::new(),
+            step_outcomes: Vec::new(),
             total_time: Duration::from_secs(5),
             metadata: ChainMetadata::default(),
         };
@@ -850,6 +2021,582 @@ mod tests {
         assert_eq!(result.execution_order.len(), 3);
     }
 
+    #[test]
+    fn test_parse_step_reference_paths() {
+        let reference = parse_reference("steps.list.output.files[2].name")
+            .unwrap()
+            .unwrap();
+        assert_eq!(reference.step_id, "list");
+        assert_eq!(
+            reference.path,
+            vec![
+                PathSegment::Field("files".to_string()),
+                PathSegment::Index(2),
+                PathSegment::Field("name".to_string()),
+            ]
+        );
+
+        // Step ids may contain dots; other expressions are not references
+        let reference = parse_reference("steps.read.v2.output").unwrap().unwrap();
+        assert_eq!(reference.step_id, "read.v2");
+        assert!(parse_reference("user.name").unwrap().is_none());
+        assert!(parse_reference("steps.read.result").is_err());
+        assert!(parse_reference("steps.read.output[x]").is_err());
+    }
+
+    fn completed(step_id: &str, output: &str) -> (String, ToolExecutionResult) {
+        (
+            step_id.to_string(),
+            ToolExecutionResult::success(
+                step_id.to_string(),
+                "echo".to_string(),
+                ToolResultData::text(output),
+            ),
+        )
+    }
+
+    #[test]
+    fn test_templates_resolve_typed_values_and_explain_missing_fields() {
+        let results: HashMap<_, _> = [
+            completed("read", "plain text"),
+            completed("stats", r#"{"files": [{"name": "a.rs", "lines": 12}]}"#),
+        ]
+        .into_iter()
+        .collect();
+
+        let request = ToolRequest::new(
+            "write_file".to_string(),
+            serde_json::json!({
+                "content": "{{steps.read.output}}",
+                "lines": "{{steps.stats.output.files[0].lines}}",
+                "summary": "{{ steps.stats.output.files[0].name }} has {{steps.stats.output.files[0].lines}} lines",
+                "literal": "{{ other }}",
+            }),
+        );
+        let resolved = resolve_step_templates(&request, &results).unwrap();
+        assert_eq!(
+            resolved.input,
+            serde_json::json!({
+                "content": "plain text",
+                "lines": 12,
+                "summary": "a.rs has 12 lines",
+                "literal": "{{ other }}",
+            })
+        );
+
+        let missing = |input: serde_json::Value| {
+            let request = ToolRequest::new("write_file".to_string(), input);
+            resolve_step_templates(&request, &results)
+                .unwrap_err()
+                .to_string()
+        };
+        let error = missing(serde_json::json!({"x": "{{steps.stats.output.files[0].size}}"}));
+        assert!(
+            error.contains("output.files[0] has no field 'size' (available: lines, name)"),
+            "{}",
+            error
+        );
+        let error = missing(serde_json::json!({"x": "{{steps.stats.output.files[3]}}"}));
+        assert!(
+            error.contains("has 1 items, so index 3 is out of range"),
+            "{}",
+            error
+        );
+        let error = missing(serde_json::json!({"x": "{{steps.read.output.name}}"}));
+        assert!(
+            error.contains("output is text, so field 'name'"),
+            "{}",
+            error
+        );
+        let error = missing(serde_json::json!({"x": "{{steps.later.output}}"}));
+        assert!(error.contains("step 'later' has not run"), "{}", error);
+    }
+
+    /// Returns its `text` input
+    #[derive(Debug)]
+    struct EchoTool;
+
+    #[derive(serde::Deserialize, schemars::JsonSchema)]
+    struct EchoInput {
+        text: String,
+    }
+
+    #[async_trait::async_trait]
+    impl AgentTool for EchoTool {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn description(&self) -> &str {
+            "Returns its input text"
+        }
+
+        fn input_schema(&self) -> crate::claude::types::ToolInputSchema {
+            crate::claude::tools::input_schema_for::<EchoInput>()
+        }
+
+        async fn execute(&self, input: serde_json::Value) -> anyhow::Result<String> {
+            let input: EchoInput = crate::claude::tools::parse_input(input)?;
+            Ok(input.text)
+        }
+
+        fn set_whitelist(&mut self, _whitelist: Arc<RwLock<WhitelistConfig>>) {}
+    }
+
+    #[tokio::test]
+    async fn test_chain_passes_data_between_steps() {
+        let mut engine = ToolExecutionEngine::new();
+        engine.register_tool(Arc::new(EchoTool));
+
+        let mut list = ToolRequest::new(
+            "echo".to_string(),
+            serde_json::json!({"text": r#"{"files": ["a.rs", "b.rs"]}"#}),
+        );
+        list.id = "list".to_string();
+        // No explicit dependency: the template orders the steps
+        let mut pick = ToolRequest::new(
+            "echo".to_string(),
+            serde_json::json!({"text": "second is {{steps.list.output.files[1]}}"}),
+        );
+        pick.id = "pick".to_string();
+
+        let whitelist = Arc::new(RwLock::new(WhitelistConfig::default()));
+        let result = engine
+            .execute_tool_chain(vec![pick, list], whitelist)
+            .await
+            .unwrap();
+
+        assert!(result.is_success());
+        assert_eq!(result.execution_order, vec!["list", "pick"]);
+        assert_eq!(
+            result.resolved_inputs["pick"],
+            serde_json::json!({"text": "second is b.rs"})
+        );
+        assert_eq!(
+            result.get_final_result().unwrap().result.render(),
+            "second is b.rs"
+        );
+    }
+
+    /// Always fails
+    #[derive(Debug)]
+    struct BrokenTool;
+
+    #[async_trait::async_trait]
+    impl AgentTool for BrokenTool {
+        fn name(&self) -> &str {
+            "broken"
+        }
+
+        fn description(&self) -> &str {
+            "Always fails"
+        }
+
+        fn input_schema(&self) -> crate::claude::types::ToolInputSchema {
+            crate::claude::tools::input_schema_for::<EchoInput>()
+        }
+
+        async fn execute(&self, _input: serde_json::Value) -> anyhow::Result<String> {
+            Err(anyhow::anyhow!("disk on fire"))
+        }
+
+        fn set_whitelist(&mut self, _whitelist: Arc<RwLock<WhitelistConfig>>) {}
+    }
+
+    fn step(id: &str, tool: &str) -> ToolRequest {
+        let mut request =
+            ToolRequest::new(tool.to_string(), serde_json::json!({"text": id})).with_max_retries(0);
+        request.id = id.to_string();
+        request
+    }
+
+    async fn run_with_policy(
+        requests: Vec<ToolRequest>,
+        policy: FailurePolicy,
+    ) -> ChainExecutionResult {
+        let mut engine = ToolExecutionEngine::new();
+        engine.register_tool(Arc::new(EchoTool));
+        engine.register_tool(Arc::new(BrokenTool));
+        let whitelist = Arc::new(RwLock::new(WhitelistConfig::default()));
+        engine
+            .execute_tool_chain_with_progress(requests, whitelist, policy, |_| {})
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_fail_fast_skips_remaining_steps() {
+        let result = run_with_policy(
+            vec![
+                step("write", "broken"),
+                step("read", "echo"),
+                step("report", "echo").with_dependency("read".to_string()),
+            ],
+            FailurePolicy::FailFast,
+        )
+        .await;
+
+        assert!(!result.is_success());
+        assert_eq!(result.failed_steps(), vec!["write"]);
+        // Steps in the failing step's phase still ran; later phases did not
+        assert_eq!(result.succeeded_steps(), vec!["read"]);
+        assert_eq!(result.skipped_steps(), vec!["report"]);
+        match &result.status {
+            ChainExecutionStatus::Failed {
+                error,
+                partial_results,
+            } => {
+                assert!(error.contains("Step 'write' failed"), "{}", error);
+                assert_eq!(*partial_results, 1);
+            }
+            status => panic!("unexpected status {:?}", status),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_continue_and_skip_dependents_policies() {
+        let requests = || {
+            vec![
+                step("write", "broken"),
+                step("verify", "echo").with_dependency("write".to_string()),
+                step("log", "echo"),
+            ]
+        };
+
+        let result = run_with_policy(requests(), FailurePolicy::SkipDependents).await;
+        assert_eq!(result.failed_steps(), vec!["write"]);
+        assert_eq!(result.skipped_steps(), vec!["verify"]);
+        assert_eq!(result.succeeded_steps(), vec!["log"]);
+
+        let result = run_with_policy(requests(), FailurePolicy::Continue).await;
+        assert_eq!(result.failed_steps(), vec!["write"]);
+        assert!(result.skipped_steps().is_empty());
+        assert_eq!(result.succeeded_steps(), vec!["log", "verify"]);
+
+        // A step's own policy overrides the chain's
+        let mut requests = requests();
+        requests[0] = step("write", "broken").with_failure_policy(FailurePolicy::FailFast);
+        let result = run_with_policy(requests, FailurePolicy::Continue).await;
+        assert_eq!(result.skipped_steps(), vec!["verify"]);
+    }
+
+    #[tokio::test]
+    async fn test_failed_step_runs_its_compensation() {
+        let restore = ToolRequest::new(
+            "echo".to_string(),
+            serde_json::json!({"text": "restored {{steps.backup.output}}"}),
+        );
+        let result = run_with_policy(
+            vec![
+                step("backup", "echo"),
+                step("write", "broken")
+                    .with_dependency("backup".to_string())
+                    .with_compensation(restore),
+            ],
+            FailurePolicy::FailFast,
+        )
+        .await;
+
+        assert!(!result.is_success());
+        assert_eq!(result.compensated_steps(), vec!["write"]);
+        match &result.step_outcomes[1].1 {
+            StepOutcome::Compensated {
+                error,
+                compensation,
+            } => {
+                assert!(error.contains("disk on fire"), "{}", error);
+                assert_eq!(compensation, "restored backup");
+            }
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        }
+    }
+
+    #[derive(Debug)]
+    struct SkipStrategy;
+
+    #[async_trait::async_trait]
+    impl crate::claude::tools::recovery::RecoveryStrategy for SkipStrategy {
+        async fn suggest_recovery(
+            &self,
+            _error: &ToolError,
+            _context: &ToolExecutionContext,
+            _attempt_count: u32,
+        ) -> ClaudeResult<Vec<RecoveryAction>> {
+            Ok(vec![RecoveryAction::SkipAndContinue {
+                reason: "optional step".to_string(),
+                impact_assessment: "none".to_string(),
+            }])
+        }
+
+        fn can_handle(&self, _error: &ToolError) -> bool {
+            true
+        }
+
+        fn name(&self) -> &str {
+            "skip"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_skipped_step_neither_fails_nor_stops_the_chain() {
+        let mut recovery_manager = ToolRecoveryManager::new();
+        recovery_manager.register_strategy(Arc::new(SkipStrategy));
+        let mut engine = ToolExecutionEngine::new().with_recovery_manager(recovery_manager);
+        engine.register_tool(Arc::new(EchoTool));
+        engine.register_tool(Arc::new(BrokenTool));
+
+        let result = engine
+            .execute_tool_chain_with_progress(
+                vec![
+                    step("first", "echo"),
+                    step("write", "broken")
+                        .with_max_retries(1)
+                        .with_dependency("first".to_string())
+                        .with_compensation(step("undo", "echo")),
+                    step("last", "echo").with_dependency("first".to_string()),
+                    step("after", "echo").with_dependency("last".to_string()),
+                ],
+                Arc::new(RwLock::new(WhitelistConfig::default())),
+                FailurePolicy::FailFast,
+                |_| {},
+            )
+            .await
+            .unwrap();
+
+        assert!(result.failed_steps().is_empty());
+        assert!(result.compensated_steps().is_empty());
+        assert_eq!(result.skipped_steps(), vec!["write"]);
+        assert_eq!(result.succeeded_steps(), vec!["first", "last", "after"]);
+        assert_eq!(
+            result.step_outcomes[1].1,
+            StepOutcome::Skipped {
+                reason: "optional step".to_string()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_open_breaker_fails_fast_with_explanation() {
+        let breakers = Arc::new(ToolBreakers::new(2, Duration::from_secs(60), 4));
+        let mut engine = ToolExecutionEngine::new().with_breakers(breakers.clone());
+        engine.register_tool(Arc::new(BrokenTool));
+        engine.register_tool(Arc::new(EchoTool));
+        let whitelist = Arc::new(RwLock::new(WhitelistConfig::default()));
+
+        let call = |tool: &str| {
+            let request = step("call", tool);
+            let context = ToolExecutionContext::new(
+                tool.to_string(),
+                request.input.clone(),
+                whitelist.clone(),
+            );
+            engine.execute_single_tool(request, context)
+        };
+
+        for _ in 0..2 {
+            let result = call("broken").await.unwrap();
+            assert!(result.result.render().contains("disk on fire"));
+        }
+
+        let rejected = call("broken").await.unwrap();
+        match &rejected.status {
+            ToolExecutionStatus::Failed { error, .. } => {
+                assert!(matches!(error.error_type, ToolErrorType::ResourceError));
+                assert!(error
+                    .message
+                    .contains("temporarily disabled after 2 consecutive failures"));
+                assert!(error.message.contains("disk on fire"));
+            }
+            status => panic!("unexpected status {:?}", status),
+        }
+
+        // Bad input is the caller's mistake and does not count against a tool
+        let invalid =
+            ToolRequest::new("echo".to_string(), serde_json::json!({})).with_max_retries(0);
+        for _ in 0..3 {
+            let context = ToolExecutionContext::new(
+                "echo".to_string(),
+                serde_json::json!({}),
+                whitelist.clone(),
+            );
+            engine
+                .execute_single_tool(invalid.clone(), context)
+                .await
+                .unwrap();
+        }
+        assert!(call("echo").await.unwrap().is_success());
+
+        let statuses = breakers.statuses();
+        let broken = statuses.iter().find(|s| s.tool_name == "broken").unwrap();
+        assert_eq!(broken.state, CircuitBreakerState::Open);
+        assert_eq!(broken.rejected_calls, 1);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_breakers_keep_running_failing_tools() {
+        let mut engine = ToolExecutionEngine::new().with_config(ExecutionConfig {
+            circuit_breaker_enabled: false,
+            ..ExecutionConfig::default()
+        });
+        engine.register_tool(Arc::new(BrokenTool));
+        let whitelist = Arc::new(RwLock::new(WhitelistConfig::default()));
+
+        for _ in 0..5 {
+            let request = step("call", "broken");
+            let context = ToolExecutionContext::new(
+                "broken".to_string(),
+                request.input.clone(),
+                whitelist.clone(),
+            );
+            let result = engine.execute_single_tool(request, context).await.unwrap();
+            assert!(result.result.render().contains("disk on fire"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_repeated_failures_add_learned_notes() {
+        let feedback = crate::claude::tools::LearnedFeedback::default();
+        let mut engine = ToolExecutionEngine::new()
+            .with_config(ExecutionConfig {
+                circuit_breaker_enabled: false,
+                ..ExecutionConfig::default()
+            })
+            .with_learned_feedback(feedback.clone());
+        engine.register_tool(Arc::new(BrokenTool));
+        let whitelist = Arc::new(RwLock::new(WhitelistConfig::default()));
+
+        let mut results = Vec::new();
+        for _ in 0..3 {
+            let request = step("call", "broken");
+            let context = ToolExecutionContext::new(
+                "broken".to_string(),
+                request.input.clone(),
+                whitelist.clone(),
+            );
+            results.push(engine.execute_single_tool(request, context).await.unwrap());
+        }
+
+        assert!(results[0].metadata.notes.is_empty());
+        let content = results[2].into_content_block();
+        assert!(
+            content.contains("[Note: broken calls with input {text: string} have failed 3 times")
+        );
+
+        let stats = feedback.handler.tool_stats().await;
+        assert_eq!(stats[0].execution_count, 3);
+    }
+
+    #[tokio::test]
+    async fn test_chain_steps_are_recorded_in_history() {
+        let dir = TempDir::new();
+        let history = Arc::new(ExecutionHistoryStore::new(dir.join("tool_history.jsonl")));
+        let mut engine =
+            ToolExecutionEngine::new().with_history(history.clone(), Some("session-1".to_string()));
+        engine.register_tool(Arc::new(EchoTool));
+        engine.register_tool(Arc::new(BrokenTool));
+        let whitelist = Arc::new(RwLock::new(WhitelistConfig::default()));
+
+        let result = engine
+            .execute_tool_chain_with_progress(
+                vec![step("first", "echo"), step("second", "broken")],
+                whitelist,
+                FailurePolicy::Continue,
+                |_| {},
+            )
+            .await
+            .unwrap();
+
+        let records = history
+            .query(&crate::claude::tools::history::HistoryFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert!(records.iter().all(|record| record.chain_id.as_deref()
+            == Some(result.chain_id.as_str())
+            && record.session_id.as_deref() == Some("session-1")));
+        assert_eq!(records[1].error.as_deref(), Some("disk on fire"));
+
+        // Failed chains are kept in the in-memory history as well
+        let stats = engine.get_execution_stats().await;
+        assert_eq!(stats.total_chains, 1);
+        assert_eq!(stats.failed_chains, 1);
+    }
+
+    #[tokio::test]
+    async fn test_refused_calls_are_audited_but_not_kept_in_history() {
+        let dir = TempDir::new();
+        let history = Arc::new(ExecutionHistoryStore::new(dir.join("tool_history.jsonl")));
+        let audit_log = Arc::new(AuditLog::open(dir.join("audit_log.jsonl")));
+        let mut engine = ToolExecutionEngine::new()
+            .with_history(history.clone(), None)
+            .with_audit_log(audit_log.clone());
+        engine.register_tool(Arc::new(EchoTool));
+        let whitelist = Arc::new(RwLock::new(WhitelistConfig::default()));
+
+        for tool in ["echo", "missing"] {
+            let request = step("call", tool);
+            let context = ToolExecutionContext::new(
+                tool.to_string(),
+                request.input.clone(),
+                whitelist.clone(),
+            );
+            let _ = engine.execute_single_tool(request, context).await;
+        }
+
+        let entries = audit_log
+            .query(&crate::claude::audit::AuditFilter::default())
+            .unwrap();
+        let records: Vec<&ExecutionRecord> = entries
+            .iter()
+            .filter_map(|entry| match &entry.event {
+                AuditEvent::ToolExecution(record) => Some(record),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].tool_name, "missing");
+        assert_eq!(
+            records[1].outcome,
+            crate::claude::tools::history::ExecutionOutcome::Failed
+        );
+        assert!(records[1].error.as_deref().unwrap().contains("not found"));
+
+        let history_records = history
+            .query(&crate::claude::tools::history::HistoryFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(history_records.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_calls_fail_when_their_files_cannot_be_checkpointed() {
+        let (dir, whitelist) = whitelisted_temp_dir();
+        let file = dir.join("notes.txt");
+        std::fs::write(&file, "original").unwrap();
+        // A file where the store expects a directory makes every snapshot fail
+        let store = dir.join("checkpoints");
+        std::fs::write(&store, "").unwrap();
+
+        let mut tool = crate::claude::tools::WriteFileTool::new();
+        tool.set_whitelist(whitelist.clone());
+        let mut engine =
+            ToolExecutionEngine::new().with_checkpoints(Arc::new(CheckpointStore::new(store)));
+        engine.register_tool(Arc::new(tool));
+
+        let input = serde_json::json!({"path": file.to_string_lossy(), "content": "changed"});
+        let request = ToolRequest::new("write_file".to_string(), input.clone());
+        let context = ToolExecutionContext::new("write_file".to_string(), input, whitelist);
+        let result = engine.execute_single_tool(request, context).await.unwrap();
+
+        match result.status {
+            ToolExecutionStatus::Failed { error, .. } => {
+                assert!(error.message.contains("checkpoint"));
+            }
+            status => panic!("expected the call to fail, got {:?}", status),
+        }
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "original");
+    }
+
     #[test]
     fn test_performance_metrics_calculation() {
         let engine = ToolExecutionEngine::new();
@@ -874,10 +2621,327 @@ mod tests {
         assert!(metrics.parallel_efficiency <= 1.0);
     }
 
+    /// Tool that never finishes, standing in for a read on a FIFO or dead mount
+    #[derive(Debug)]
+    struct HangingTool;
+
+    #[async_trait::async_trait]
+    impl AgentTool for HangingTool {
+        fn name(&self) -> &str {
+            "hang"
+        }
+
+        fn description(&self) -> &str {
+            "Never returns"
+        }
+
+        fn input_schema(&self) -> crate::claude::types::ToolInputSchema {
+            crate::claude::types::ToolInputSchema {
+                r#type: "object".to_string(),
+                properties: HashMap::new(),
+                required: Vec::new(),
+                additional_properties: false,
+            }
+        }
+
+        async fn execute(&self, _input: serde_json::Value) -> anyhow::Result<String> {
+            std::future::pending().await
+        }
+
+        fn set_whitelist(&mut self, _whitelist: Arc<RwLock<WhitelistConfig>>) {}
+    }
+
+    /// Returns once as many calls as the barrier holds are waiting on it
+    #[derive(Debug)]
+    struct RendezvousTool(Arc<tokio::sync::Barrier>);
+
+    #[async_trait::async_trait]
+    impl AgentTool for RendezvousTool {
+        fn name(&self) -> &str {
+            "rendezvous"
+        }
+
+        fn description(&self) -> &str {
+            "Waits for the other calls"
+        }
+
+        fn input_schema(&self) -> crate::claude::types::ToolInputSchema {
+            crate::claude::tools::input_schema_for::<EchoInput>()
+        }
+
+        async fn execute(&self, input: serde_json::Value) -> anyhow::Result<String> {
+            self.0.wait().await;
+            Ok(input["text"].as_str().unwrap_or_default().to_string())
+        }
+
+        fn set_whitelist(&mut self, _whitelist: Arc<RwLock<WhitelistConfig>>) {}
+    }
+
+    #[tokio::test]
+    async fn test_phase_steps_run_concurrently() {
+        let mut engine = ToolExecutionEngine::new();
+        engine.register_tool(Arc::new(RendezvousTool(Arc::new(
+            tokio::sync::Barrier::new(2),
+        ))));
+
+        // Run one after the other, neither step would ever get past the barrier
+        let result = engine
+            .execute_tool_chain_with_progress(
+                vec![
+                    step("left", "rendezvous").with_timeout(Duration::from_secs(5)),
+                    step("right", "rendezvous").with_timeout(Duration::from_secs(5)),
+                ],
+                Arc::new(RwLock::new(WhitelistConfig::default())),
+                FailurePolicy::FailFast,
+                |_| {},
+            )
+            .await
+            .unwrap();
+
+        assert!(result.is_success());
+        assert_eq!(result.succeeded_steps(), vec!["left", "right"]);
+        assert_eq!(result.metadata.parallel_executions, 1);
+    }
+
+    #[tokio::test]
+    async fn test_hung_tool_times_out() {
+        let mut engine = ToolExecutionEngine::new();
+        engine.register_tool(Arc::new(HangingTool));
+
+        let request = ToolRequest::new("hang".to_string(), serde_json::json!({}))
+            .with_timeout(Duration::from_millis(50))
+            .with_max_retries(0);
+        let context = ToolExecutionContext::new(
+            "hang".to_string(),
+            serde_json::json!({}),
+            Arc::new(RwLock::new(WhitelistConfig::default())),
+        );
+
+        let result = tokio::time::timeout(
+            Duration::from_secs(5),
+            engine.execute_single_tool(request, context),
+        )
+        .await
+        .expect("engine should not hang")
+        .unwrap();
+        assert!(matches!(
+            result.status,
+            crate::claude::tools::execution::ToolExecutionStatus::Timeout
+        ));
+    }
+
+    #[test]
+    fn test_attempt_timeout_uses_tool_defaults() {
+        let engine = ToolExecutionEngine::new();
+        let cargo = ToolRequest::new("cargo_check".to_string(), serde_json::json!({}));
+        let read = ToolRequest::new("read_file".to_string(), serde_json::json!({}));
+        assert!(engine.attempt_timeout(&cargo) > engine.attempt_timeout(&read));
+        assert_eq!(
+            engine.attempt_timeout(&read.with_timeout(Duration::from_secs(5))),
+            Duration::from_secs(5)
+        );
+    }
+
+    /// Writes `content` to `path` without creating parent directories
+    #[derive(Debug)]
+    struct RawWriteTool {
+        name: &'static str,
+    }
+
+    #[derive(serde::Deserialize, schemars::JsonSchema)]
+    struct RawWriteInput {
+        path: String,
+        content: String,
+        /// Where the fallback strategy writes instead
+        #[allow(dead_code)]
+        fallback_path: Option<String>,
+    }
+
+    #[async_trait::async_trait]
+    impl AgentTool for RawWriteTool {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn description(&self) -> &str {
+            "Writes a file without creating its parent directory"
+        }
+
+        fn input_schema(&self) -> crate::claude::types::ToolInputSchema {
+            crate::claude::tools::input_schema_for::<RawWriteInput>()
+        }
+
+        async fn execute(&self, input: serde_json::Value) -> anyhow::Result<String> {
+            let input: RawWriteInput = crate::claude::tools::parse_input(input)?;
+            tokio::fs::write(&input.path, input.content).await?;
+            Ok(format!("Wrote {}", input.path))
+        }
+
+        fn set_whitelist(&mut self, _whitelist: Arc<RwLock<WhitelistConfig>>) {}
+
+        fn modified_paths(&self, input: &serde_json::Value) -> Vec<(String, FileOperation)> {
+            crate::claude::tools::path_inputs(input, &["path"], FileOperation::Write)
+        }
+    }
+
+    #[derive(Debug)]
+    struct FallbackStrategy;
+
+    #[async_trait::async_trait]
+    impl crate::claude::tools::recovery::RecoveryStrategy for FallbackStrategy {
+        async fn suggest_recovery(
+            &self,
+            _error: &ToolError,
+            context: &ToolExecutionContext,
+            _attempt_count: u32,
+        ) -> ClaudeResult<Vec<RecoveryAction>> {
+            Ok(vec![RecoveryAction::FallbackTool {
+                tool_name: "backup_write".to_string(),
+                input: serde_json::json!({
+                    "path": context.input["fallback_path"],
+                    "content": context.input["content"],
+                }),
+                reason: "primary target unavailable".to_string(),
+            }])
+        }
+
+        fn can_handle(&self, _error: &ToolError) -> bool {
+            true
+        }
+
+        fn name(&self) -> &str {
+            "fallback"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recovery_creates_missing_directory() {
+        let (dir, whitelist) = whitelisted_temp_dir();
+        let mut engine = ToolExecutionEngine::new();
+        // Recovery only creates directories for the write tools, by name
+        engine.register_tool(Arc::new(RawWriteTool { name: "write_file" }));
+
+        let target = dir.join("nested").join("out.txt");
+        let input = serde_json::json!({"path": target.to_string_lossy(), "content": "hi"});
+        let request = ToolRequest::new("write_file".to_string(), input.clone()).with_max_retries(1);
+        let context = ToolExecutionContext::new("write_file".to_string(), input, whitelist);
+
+        let result = engine.execute_single_tool(request, context).await.unwrap();
+        assert!(result.is_success());
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "hi");
+        assert_eq!(result.metadata.recoveries.len(), 1);
+        assert_eq!(
+            result.metadata.recoveries[0].kind,
+            RecoveryKind::CreateDirectory
+        );
+        assert_eq!(result.metadata.recoveries[0].attempt, 1);
+        assert!(result
+            .into_content_block()
+            .contains("[Recovery: Created missing directory"));
+    }
+
+    /// Retries a write at the fallback path instead
+    #[derive(Debug)]
+    struct RedirectStrategy;
+
+    #[async_trait::async_trait]
+    impl crate::claude::tools::recovery::RecoveryStrategy for RedirectStrategy {
+        async fn suggest_recovery(
+            &self,
+            _error: &ToolError,
+            context: &ToolExecutionContext,
+            _attempt_count: u32,
+        ) -> ClaudeResult<Vec<RecoveryAction>> {
+            Ok(vec![RecoveryAction::Retry {
+                delay: Duration::from_millis(0),
+                max_attempts: 1,
+                modified_input: Some(serde_json::json!({
+                    "path": context.input["fallback_path"],
+                    "content": context.input["content"],
+                })),
+            }])
+        }
+
+        fn can_handle(&self, _error: &ToolError) -> bool {
+            true
+        }
+
+        fn name(&self) -> &str {
+            "redirect"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_file_changing_tools_are_not_retried_with_new_input() {
+        let (dir, whitelist) = whitelisted_temp_dir();
+        let mut recovery_manager = ToolRecoveryManager::new();
+        recovery_manager.register_strategy(Arc::new(RedirectStrategy));
+        let mut engine = ToolExecutionEngine::new().with_recovery_manager(recovery_manager);
+        engine.register_tool(Arc::new(RawWriteTool { name: "raw_write" }));
+
+        let redirected = dir.join("redirected.txt");
+        let input = serde_json::json!({
+            "path": dir.join("missing").join("out.txt").to_string_lossy(),
+            "content": "hi",
+            "fallback_path": redirected.to_string_lossy(),
+        });
+        let request = ToolRequest::new("raw_write".to_string(), input.clone()).with_max_retries(1);
+        let context = ToolExecutionContext::new("raw_write".to_string(), input, whitelist);
+
+        let result = engine.execute_single_tool(request, context).await.unwrap();
+        assert!(result.is_error());
+        assert!(result.metadata.recoveries.is_empty());
+        assert!(!redirected.exists());
+    }
+
+    #[tokio::test]
+    async fn test_recovery_switches_to_fallback_tool() {
+        let (dir, whitelist) = whitelisted_temp_dir();
+        let mut recovery_manager = ToolRecoveryManager::new();
+        recovery_manager.register_strategy(Arc::new(FallbackStrategy));
+        let history = Arc::new(ExecutionHistoryStore::new(dir.join("tool_history.jsonl")));
+        let mut engine = ToolExecutionEngine::new()
+            .with_recovery_manager(recovery_manager)
+            .with_history(history.clone(), None);
+        engine.register_tool(Arc::new(RawWriteTool { name: "raw_write" }));
+        engine.register_tool(Arc::new(RawWriteTool {
+            name: "backup_write",
+        }));
+
+        let fallback = dir.join("fallback.txt");
+        let input = serde_json::json!({
+            "path": dir.join("missing").join("out.txt").to_string_lossy(),
+            "fallback_path": fallback.to_string_lossy(),
+            "content": "hi",
+        });
+        let request = ToolRequest::new("raw_write".to_string(), input.clone());
+        let context = ToolExecutionContext::new("raw_write".to_string(), input, whitelist);
+
+        let result = engine.execute_single_tool(request, context).await.unwrap();
+        assert!(result.is_success());
+        assert_eq!(result.tool_name, "backup_write");
+        assert_eq!(std::fs::read_to_string(&fallback).unwrap(), "hi");
+        assert_eq!(result.metadata.recoveries.len(), 1);
+        assert_eq!(
+            result.metadata.recoveries[0].kind,
+            RecoveryKind::FallbackTool
+        );
+
+        // One call, recorded under the tool that was asked for
+        let records = history
+            .query(&crate::claude::tools::history::HistoryFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].tool_name, "raw_write");
+        assert!(records[0].outcome.is_success());
+    }
+
     #[test]
     fn test_execution_config() {
         let config = ExecutionConfig {
             default_timeout: Duration::from_secs(60),
+            tool_timeouts: HashMap::new(),
             default_max_retries: 5,
             max_parallel_executions: 8,
             max_history_size: 200,
@@ -0,0 +1,701 @@
+//! Workspace checkpoints. Before a tool changes files the engine snapshots
+//! them into a checkpoint for the current user turn, so the turn's edits can
+//! be reviewed as diffs and rolled back later.
+
+use crate::claude::constants::checkpoints::{
+    MANIFEST_FILE_NAME, MAX_CHECKPOINTS, MAX_DIFF_BYTES, MAX_FILES_PER_DIRECTORY, MAX_LABEL_CHARS,
+    MAX_SNAPSHOT_FILE_BYTES, MAX_STORAGE_BYTES,
+};
+use crate::claude::secure_fs::{FileAccess, SecureRoot};
+use crate::claude::whitelist::{validate_path, FileOperation, WhitelistConfig};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tokio::sync::Mutex;
+
+/// What was on disk at a path
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FileState {
+    Missing,
+    File {
+        hash: String,
+        size: u64,
+    },
+    Directory,
+    /// A symlink, FIFO or other special entry. Its content is never read or
+    /// restored.
+    Other,
+}
+
+impl FileState {
+    fn read(path: &Path) -> Result<Self> {
+        let metadata = match std::fs::symlink_metadata(path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(FileState::Missing),
+            Err(e) => return Err(e.into()),
+        };
+        if metadata.is_dir() {
+            return Ok(FileState::Directory);
+        }
+        if !metadata.is_file() {
+            return Ok(FileState::Other);
+        }
+        let bytes = std::fs::read(path)?;
+        Ok(FileState::File {
+            hash: format!("{:x}", Sha256::digest(&bytes)),
+            size: bytes.len() as u64,
+        })
+    }
+}
+
+/// A path captured in a checkpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileSnapshot {
+    pub path: PathBuf,
+    /// State before the first tool in the turn touched the path
+    pub before: FileState,
+    /// State the agent left the path in; anything else means it was edited since
+    pub after: FileState,
+    /// Blob holding the original content, absent for files over the size limit
+    #[serde(default)]
+    blob: Option<String>,
+}
+
+/// Snapshots taken during one user turn
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub id: String,
+    pub label: String,
+    pub created_at: DateTime<Utc>,
+    pub files: Vec<FileSnapshot>,
+    pub size_bytes: u64,
+}
+
+impl Checkpoint {
+    fn new(label: &str) -> Self {
+        Self {
+            id: format!(
+                "{}-{}",
+                Utc::now().format("%Y%m%d%H%M%S"),
+                &uuid::Uuid::new_v4().simple().to_string()[..8]
+            ),
+            label: label.chars().take(MAX_LABEL_CHARS).collect(),
+            created_at: Utc::now(),
+            files: Vec::new(),
+            size_bytes: 0,
+        }
+    }
+
+    fn summary(&self) -> CheckpointSummary {
+        CheckpointSummary {
+            id: self.id.clone(),
+            label: self.label.clone(),
+            created_at: self.created_at,
+            file_count: self.files.len(),
+            size_bytes: self.size_bytes,
+        }
+    }
+}
+
+/// Checkpoint overview for listings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckpointSummary {
+    pub id: String,
+    pub label: String,
+    pub created_at: DateTime<Utc>,
+    pub file_count: usize,
+    pub size_bytes: u64,
+}
+
+/// How a path on disk differs from its checkpointed content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffStatus {
+    Unchanged,
+    Modified,
+    Created,
+    Deleted,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileDiff {
+    pub path: PathBuf,
+    pub status: DiffStatus,
+    /// Unified diff from the checkpoint to the current file, when both are text
+    pub diff: Option<String>,
+}
+
+/// A path that could not be restored safely
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreConflict {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+/// Outcome of restoring a checkpoint. Nothing is written when conflicts are
+/// found unless the restore was forced.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoreReport {
+    pub applied: bool,
+    pub restored: Vec<PathBuf>,
+    pub conflicts: Vec<RestoreConflict>,
+}
+
+/// Checkpoints persisted under a directory, one subdirectory per checkpoint
+#[derive(Debug)]
+pub struct CheckpointStore {
+    root: PathBuf,
+    max_bytes: u64,
+    current: Mutex<Option<Checkpoint>>,
+}
+
+impl CheckpointStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            max_bytes: MAX_STORAGE_BYTES,
+            current: Mutex::new(None),
+        }
+    }
+
+    #[allow(dead_code)] // Used by tests to exercise retention
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Start grouping snapshots under a new checkpoint. Nothing is written
+    /// until a tool actually touches a file.
+    pub async fn begin_turn(&self, label: &str) {
+        *self.current.lock().await = Some(Checkpoint::new(label));
+    }
+
+    /// Capture paths that are about to be modified. Directories are captured
+    /// file by file; paths already captured this turn keep their first snapshot.
+    pub async fn snapshot(&self, paths: &[PathBuf]) -> Result<()> {
+        let mut current = self.current.lock().await;
+        let checkpoint = current.get_or_insert_with(|| Checkpoint::new("Agent edits"));
+        let checkpoint_dir = self.root.join(&checkpoint.id);
+        let mut changed = false;
+
+        for path in paths {
+            for file in collect_files(path)? {
+                if checkpoint
+                    .files
+                    .iter()
+                    .any(|snapshot| snapshot.path == file)
+                {
+                    continue;
+                }
+                let state = FileState::read(&file)?;
+                let blob = match &state {
+                    FileState::File { size, .. } if *size <= MAX_SNAPSHOT_FILE_BYTES => {
+                        let name = format!("{}.blob", checkpoint.files.len());
+                        let blob_path = checkpoint_dir.join("blobs").join(&name);
+                        std::fs::create_dir_all(checkpoint_dir.join("blobs"))?;
+                        std::fs::copy(&file, &blob_path)?;
+                        checkpoint.size_bytes += size;
+                        Some(name)
+                    }
+                    _ => None,
+                };
+                checkpoint.files.push(FileSnapshot {
+                    path: file,
+                    before: state.clone(),
+                    after: state,
+                    blob,
+                });
+                changed = true;
+            }
+        }
+
+        if changed {
+            self.save_manifest(checkpoint)?;
+            let keep = checkpoint.id.clone();
+            drop(current);
+            self.prune(&keep)?;
+        }
+        Ok(())
+    }
+
+    /// Record what a tool left on disk so later edits can be told apart
+    pub async fn record_after(&self, paths: &[PathBuf]) -> Result<()> {
+        let mut current = self.current.lock().await;
+        let Some(checkpoint) = current.as_mut() else {
+            return Ok(());
+        };
+
+        let mut changed = false;
+        for snapshot in checkpoint.files.iter_mut() {
+            if paths.iter().any(|path| snapshot.path.starts_with(path)) {
+                snapshot.after = FileState::read(&snapshot.path)?;
+                changed = true;
+            }
+        }
+        if changed {
+            self.save_manifest(checkpoint)?;
+        }
+        Ok(())
+    }
+
+    /// All stored checkpoints, newest first
+    pub async fn list(&self) -> Result<Vec<CheckpointSummary>> {
+        let mut summaries: Vec<CheckpointSummary> =
+            self.load_all()?.iter().map(Checkpoint::summary).collect();
+        summaries.sort_by_key(|summary| std::cmp::Reverse(summary.created_at));
+        Ok(summaries)
+    }
+
+    /// Compare each path in a checkpoint with what is on disk now
+    pub async fn diff(&self, id: &str) -> Result<Vec<FileDiff>> {
+        let checkpoint = self.load(id)?;
+        let checkpoint_dir = self.root.join(id);
+
+        checkpoint
+            .files
+            .iter()
+            .map(|snapshot| {
+                let current = FileState::read(&snapshot.path)?;
+                let status = match (&snapshot.before, &current) {
+                    (before, current) if before == current => DiffStatus::Unchanged,
+                    (FileState::Missing, _) => DiffStatus::Created,
+                    (_, FileState::Missing) => DiffStatus::Deleted,
+                    _ => DiffStatus::Modified,
+                };
+
+                let diff = if status == DiffStatus::Unchanged {
+                    None
+                } else {
+                    let original = match &snapshot.blob {
+                        Some(blob) => Some(std::fs::read(checkpoint_dir.join("blobs").join(blob))?),
+                        None if snapshot.before == FileState::Missing => Some(Vec::new()),
+                        None => None,
+                    };
+                    let latest = match current {
+                        FileState::File { .. } => Some(std::fs::read(&snapshot.path)?),
+                        FileState::Missing => Some(Vec::new()),
+                        FileState::Directory | FileState::Other => None,
+                    };
+                    match (original, latest) {
+                        (Some(original), Some(latest)) => {
+                            unified_diff(&snapshot.path, &original, &latest)
+                        }
+                        _ => None,
+                    }
+                };
+
+                Ok(FileDiff {
+                    path: snapshot.path.clone(),
+                    status,
+                    diff,
+                })
+            })
+            .collect()
+    }
+
+    /// Put every path in a checkpoint back to its captured state. Paths that
+    /// changed since the agent touched them are conflicts; with `force` they
+    /// are overwritten anyway. Each path is checked against the whitelist
+    /// again and written without following symlinks; paths that fail the check
+    /// are conflicts that are never written, even when forced.
+    pub async fn restore(
+        &self,
+        id: &str,
+        force: bool,
+        whitelist: &WhitelistConfig,
+    ) -> Result<RestoreReport> {
+        let mut checkpoint = self.load(id)?;
+        let blobs = self.root.join(id).join("blobs");
+
+        let mut conflicts = Vec::new();
+        let mut roots = HashMap::new();
+        for snapshot in &checkpoint.files {
+            let current = FileState::read(&snapshot.path)?;
+            if current == snapshot.before {
+                continue;
+            }
+            let reason = if current == FileState::Other {
+                "Path is now a symlink or special file".to_string()
+            } else if snapshot.before == FileState::Other {
+                "Path was a symlink or special file, which is not kept".to_string()
+            } else if matches!(snapshot.before, FileState::File { .. }) && snapshot.blob.is_none() {
+                "File was too large to keep in the checkpoint".to_string()
+            } else {
+                match restore_root(&snapshot.path, &snapshot.before, whitelist) {
+                    Ok(root) => {
+                        roots.insert(snapshot.path.clone(), root);
+                        if current == snapshot.after {
+                            continue;
+                        }
+                        "File changed after the agent edited it".to_string()
+                    }
+                    Err(e) => e.to_string(),
+                }
+            };
+            conflicts.push(RestoreConflict {
+                path: snapshot.path.clone(),
+                reason,
+            });
+        }
+
+        if !conflicts.is_empty() && !force {
+            return Ok(RestoreReport {
+                applied: false,
+                restored: Vec::new(),
+                conflicts,
+            });
+        }
+
+        let mut restored = Vec::new();
+        for snapshot in checkpoint.files.iter_mut() {
+            let Some(root) = roots.get(&snapshot.path) else {
+                continue;
+            };
+            let current = FileState::read(&snapshot.path)?;
+            if current == snapshot.before {
+                continue;
+            }
+            if let Err(e) = restore_snapshot(root, snapshot, &current, &blobs) {
+                conflicts.push(RestoreConflict {
+                    path: snapshot.path.clone(),
+                    reason: e.to_string(),
+                });
+                continue;
+            }
+            snapshot.after = snapshot.before.clone();
+            restored.push(snapshot.path.clone());
+        }
+
+        self.save_manifest(&checkpoint)?;
+        let mut current = self.current.lock().await;
+        if current
+            .as_ref()
+            .is_some_and(|open| open.id == checkpoint.id)
+        {
+            *current = Some(checkpoint);
+        }
+
+        Ok(RestoreReport {
+            applied: true,
+            restored,
+            conflicts,
+        })
+    }
+
+    fn save_manifest(&self, checkpoint: &Checkpoint) -> Result<()> {
+        let dir = self.root.join(&checkpoint.id);
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(MANIFEST_FILE_NAME);
+        let temp_path = path.with_extension("tmp");
+        std::fs::write(&temp_path, serde_json::to_string_pretty(checkpoint)?)?;
+        std::fs::rename(temp_path, path)?;
+        Ok(())
+    }
+
+    fn load(&self, id: &str) -> Result<Checkpoint> {
+        // Ids are generated by us; anything else could escape the store directory
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(anyhow!("Invalid checkpoint id '{}'", id));
+        }
+        let path = self.root.join(id).join(MANIFEST_FILE_NAME);
+        let json = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow!("Checkpoint '{}' not found: {}", id, e))?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    fn load_all(&self) -> Result<Vec<Checkpoint>> {
+        let entries = match std::fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut checkpoints = Vec::new();
+        for entry in entries.flatten() {
+            let manifest = entry.path().join(MANIFEST_FILE_NAME);
+            match std::fs::read_to_string(&manifest)
+                .map_err(anyhow::Error::from)
+                .and_then(|json| Ok(serde_json::from_str::<Checkpoint>(&json)?))
+            {
+                Ok(checkpoint) => checkpoints.push(checkpoint),
+                Err(e) => eprintln!(
+                    "Warning: Skipping unreadable checkpoint '{}': {}",
+                    entry.path().display(),
+                    e
+                ),
+            }
+        }
+        Ok(checkpoints)
+    }
+
+    /// Drop the oldest checkpoints until the store fits its size and count limits
+    fn prune(&self, keep: &str) -> Result<()> {
+        let mut checkpoints = self.load_all()?;
+        checkpoints.sort_by_key(|checkpoint| checkpoint.created_at);
+
+        let mut total: u64 = checkpoints.iter().map(|c| c.size_bytes).sum();
+        let mut count = checkpoints.len();
+        for checkpoint in checkpoints {
+            if total <= self.max_bytes && count <= MAX_CHECKPOINTS {
+                break;
+            }
+            if checkpoint.id == keep {
+                continue;
+            }
+            std::fs::remove_dir_all(self.root.join(&checkpoint.id))?;
+            total -= checkpoint.size_bytes;
+            count -= 1;
+        }
+        Ok(())
+    }
+}
+
+/// Check a captured path against the whitelist again, as it may have changed
+/// since the snapshot, and open the whitelisted directory holding it. The
+/// directory is opened without following symlinks, so a link swapped in
+/// anywhere along the path fails the write instead of redirecting it.
+fn restore_root(
+    path: &Path,
+    before: &FileState,
+    whitelist: &WhitelistConfig,
+) -> Result<SecureRoot> {
+    let operation = match before {
+        FileState::Missing => FileOperation::Delete,
+        FileState::Directory => FileOperation::CreateDirectory,
+        FileState::File { .. } | FileState::Other => FileOperation::Write,
+    };
+    let resolved = validate_path(&path.to_string_lossy(), whitelist, operation)?;
+    if resolved != path {
+        return Err(anyhow!("Path now resolves to '{}'", resolved.display()));
+    }
+    let root = whitelist.secure_root(path)?;
+    SecureRoot::open(root.root(), false)
+}
+
+/// Put one path back to its captured state, through its whitelisted directory
+fn restore_snapshot(
+    root: &SecureRoot,
+    snapshot: &FileSnapshot,
+    current: &FileState,
+    blobs: &Path,
+) -> Result<()> {
+    match (&snapshot.before, &snapshot.blob) {
+        (FileState::File { .. }, Some(blob)) => {
+            if *current == FileState::Directory {
+                root.remove(&snapshot.path)?;
+            }
+            if let Some(parent) = snapshot.path.parent() {
+                root.create_dir_all(parent)?;
+            }
+            let mut original = std::fs::File::open(blobs.join(blob))?;
+            let mut file = root.open_file(&snapshot.path, FileAccess::Write)?;
+            std::io::copy(&mut original, &mut file)?;
+        }
+        (FileState::Missing, _) => root.remove(&snapshot.path)?,
+        (FileState::Directory, _) => root.create_dir_all(&snapshot.path)?,
+        (FileState::File { .. }, None) | (FileState::Other, _) => {
+            return Err(anyhow!("Content was not kept in the checkpoint"));
+        }
+    }
+    Ok(())
+}
+
+/// Files under a path (or the path itself when it is not a directory)
+fn collect_files(path: &Path) -> Result<Vec<PathBuf>> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => {}
+        _ => return Ok(vec![path.to_path_buf()]),
+    }
+
+    let mut files = Vec::new();
+    let mut pending = vec![path.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push(entry.path());
+            } else if file_type.is_file() {
+                if files.len() >= MAX_FILES_PER_DIRECTORY {
+                    return Err(anyhow!(
+                        "'{}' holds more than {} files, too many to checkpoint",
+                        path.display(),
+                        MAX_FILES_PER_DIRECTORY
+                    ));
+                }
+                files.push(entry.path());
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Unified diff between two versions of a file; `None` for binary content
+fn unified_diff(path: &Path, original: &[u8], latest: &[u8]) -> Option<String> {
+    let original = std::str::from_utf8(original).ok()?;
+    let latest = std::str::from_utf8(latest).ok()?;
+    let name = path.to_string_lossy();
+    let diff = similar::TextDiff::from_lines(original, latest)
+        .unified_diff()
+        .context_radius(3)
+        .header(&format!("a/{}", name), &format!("b/{}", name))
+        .to_string();
+
+    if diff.len() <= MAX_DIFF_BYTES {
+        return Some(diff);
+    }
+    let mut end = MAX_DIFF_BYTES;
+    while !diff.is_char_boundary(end) {
+        end -= 1;
+    }
+    Some(format!("{}\n[diff truncated]", &diff[..end]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::claude::test_support::TempDir;
+
+    fn whitelist_for(dir: &Path) -> WhitelistConfig {
+        let mut whitelist = WhitelistConfig::default();
+        whitelist.add_directory(dir).unwrap();
+        whitelist.set_enabled(true);
+        whitelist
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_diff_and_restore() {
+        let dir = TempDir::new();
+        let store = CheckpointStore::new(dir.join("store"));
+        let edited = dir.join("edited.txt");
+        let created = dir.join("created.txt");
+        std::fs::write(&edited, "one\ntwo\n").unwrap();
+
+        store.begin_turn("Tidy up the notes").await;
+        store
+            .snapshot(&[edited.clone(), created.clone()])
+            .await
+            .unwrap();
+        std::fs::write(&edited, "one\nthree\n").unwrap();
+        std::fs::write(&created, "new\n").unwrap();
+        store
+            .record_after(&[edited.clone(), created.clone()])
+            .await
+            .unwrap();
+
+        let checkpoints = store.list().await.unwrap();
+        assert_eq!(checkpoints.len(), 1);
+        assert_eq!(checkpoints[0].label, "Tidy up the notes");
+        assert_eq!(checkpoints[0].file_count, 2);
+
+        let diffs = store.diff(&checkpoints[0].id).await.unwrap();
+        let edited_diff = diffs.iter().find(|d| d.path == edited).unwrap();
+        assert_eq!(edited_diff.status, DiffStatus::Modified);
+        assert!(edited_diff.diff.as_ref().unwrap().contains("+three"));
+        let created_diff = diffs.iter().find(|d| d.path == created).unwrap();
+        assert_eq!(created_diff.status, DiffStatus::Created);
+
+        let report = store
+            .restore(&checkpoints[0].id, false, &whitelist_for(&dir))
+            .await
+            .unwrap();
+        assert!(report.applied);
+        assert_eq!(report.restored.len(), 2);
+        assert_eq!(std::fs::read_to_string(&edited).unwrap(), "one\ntwo\n");
+        assert!(!created.exists());
+    }
+
+    #[tokio::test]
+    async fn test_restore_detects_conflicts() {
+        let dir = TempDir::new();
+        let store = CheckpointStore::new(dir.join("store"));
+        let file = dir.join("notes.txt");
+        std::fs::write(&file, "original").unwrap();
+
+        store.begin_turn("Edit notes").await;
+        store.snapshot(std::slice::from_ref(&file)).await.unwrap();
+        std::fs::write(&file, "agent edit").unwrap();
+        store
+            .record_after(std::slice::from_ref(&file))
+            .await
+            .unwrap();
+        // The user edits the file after the agent
+        std::fs::write(&file, "user edit").unwrap();
+
+        let id = store.list().await.unwrap()[0].id.clone();
+        let whitelist = whitelist_for(&dir);
+        let report = store.restore(&id, false, &whitelist).await.unwrap();
+        assert!(!report.applied);
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "user edit");
+
+        let report = store.restore(&id, true, &whitelist).await.unwrap();
+        assert!(report.applied);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "original");
+        assert!(store.restore("../escape", false, &whitelist).await.is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_restore_rechecks_paths_and_refuses_symlinks() {
+        let dir = TempDir::new();
+        let workspace = dir.join("workspace");
+        std::fs::create_dir(&workspace).unwrap();
+        let store = CheckpointStore::new(dir.join("store"));
+        let linked = workspace.join("linked.txt");
+        let elsewhere = dir.join("elsewhere.txt");
+        let secret = dir.join("secret.txt");
+        std::fs::write(&secret, "secret").unwrap();
+
+        store.begin_turn("Edit files").await;
+        let files = [linked.clone(), elsewhere.clone()];
+        for file in &files {
+            std::fs::write(file, "original").unwrap();
+        }
+        store.snapshot(&files).await.unwrap();
+        for file in &files {
+            std::fs::write(file, "agent edit").unwrap();
+        }
+        store.record_after(&files).await.unwrap();
+        // A symlink to a file outside the workspace replaces a captured file
+        std::fs::remove_file(&linked).unwrap();
+        std::os::unix::fs::symlink(&secret, &linked).unwrap();
+
+        // Only the workspace is whitelisted now, and forcing changes nothing
+        let id = store.list().await.unwrap()[0].id.clone();
+        let report = store
+            .restore(&id, true, &whitelist_for(&workspace))
+            .await
+            .unwrap();
+        assert!(report.restored.is_empty());
+        assert_eq!(report.conflicts.len(), 2);
+        assert!(std::fs::symlink_metadata(&linked)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(std::fs::read_to_string(&secret).unwrap(), "secret");
+        assert_eq!(std::fs::read_to_string(&elsewhere).unwrap(), "agent edit");
+    }
+
+    #[tokio::test]
+    async fn test_retention_prunes_oldest() {
+        let dir = TempDir::new();
+        let store = CheckpointStore::new(dir.join("store")).with_max_bytes(10);
+        let file = dir.join("data.txt");
+
+        for turn in 0..3 {
+            std::fs::write(&file, "eight by").unwrap();
+            store.begin_turn(&format!("turn {}", turn)).await;
+            store.snapshot(std::slice::from_ref(&file)).await.unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+
+        let checkpoints = store.list().await.unwrap();
+        assert_eq!(checkpoints.len(), 1);
+        assert_eq!(checkpoints[0].label, "turn 2");
+    }
+}
@@ -17,6 +17,8 @@ pub struct ToolExecutionContext {
     pub whitelist: Arc<RwLock<WhitelistConfig>>,
     pub metadata: ToolExecutionMetadata,
     pub parent_execution: Option<String>,
+    /// Chain this call is a step of
+    pub chain_id: Option<String>,
 }
 
 impl ToolExecutionContext {
@@ -32,6 +34,7 @@ impl ToolExecutionContext {
             whitelist,
             metadata: ToolExecutionMetadata::default(),
             parent_execution: None,
+            chain_id: None,
         }
     }
 
@@ -41,6 +44,11 @@ impl ToolExecutionContext {
         self
     }
 
+    pub fn with_chain(mut self, chain_id: String) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
     pub fn with_timeout(mut self, timeout: Duration) -> Self {
         self.metadata.timeout = timeout;
         self
@@ -142,7 +150,6 @@ impl ToolExecutionResult {
         }
     }
 
-    #[allow(dead_code)] // Reserved for advanced error handling
     pub fn partial_success(
         execution_id: String,
         tool_name: String,
@@ -172,6 +179,21 @@ impl ToolExecutionResult {
         }
     }
 
+    /// Result for a call that recovery decided to skip rather than fail
+    pub fn skipped(execution_id: String, tool_name: String, reason: &str) -> Self {
+        Self {
+            execution_id,
+            tool_name,
+            status: ToolExecutionStatus::Skipped {
+                reason: reason.to_string(),
+            },
+            result: ToolResultData::Text(format!("Tool execution skipped: {}", reason)),
+            metadata: ToolResultMetadata::default(),
+            follow_up_actions: Vec::new(),
+            error_context: Some(reason.to_string()),
+        }
+    }
+
     pub fn with_follow_up_actions(mut self, actions: Vec<FollowUpAction>) -> Self {
         self.follow_up_actions = actions;
         self
@@ -203,42 +225,20 @@ impl ToolExecutionResult {
 
     /// Convert to a content block for Claude API integration
     pub fn into_content_block(&self) -> String {
-        match &self.result {
-            ToolResultData::Text(text) => text.clone(),
-            ToolResultData::Json(value) => serde_json::to_string_pretty(value).unwrap_or_default(),
-            ToolResultData::FileReference { path, size, hash } => {
-                let hash_info = hash
-                    .as_ref()
-                    .map(|h| format!(" (hash: {})", h))
-                    .unwrap_or_default();
-                format!("File reference: {} ({} bytes){}", path, size, hash_info)
-            }
-            ToolResultData::DirectoryListing(items) => {
-                format!(
-                    "Directory listing ({} items):\n{}",
-                    items.len(),
-                    items
-                        .iter()
-                        .map(|item| format!("  {}", item.display()))
-                        .collect::<Vec<_>>()
-                        .join("\n")
-                )
-            }
-            ToolResultData::Binary {
-                content_type,
-                size,
-                preview,
-            } => {
-                let preview_info = preview
-                    .as_ref()
-                    .map(|p| format!(": {}", p))
-                    .unwrap_or_default();
-                format!(
-                    "Binary data: {} ({} bytes){}",
-                    content_type, size, preview_info
-                )
-            }
+        let mut content = self.result.render();
+        for warning in &self.metadata.warnings {
+            content.push_str(&format!("\n\n[Warning: {}]", warning));
+        }
+        for response in &self.metadata.user_responses {
+            content.push_str(&format!("\n\n[User response: {}]", response));
+        }
+        for recovery in &self.metadata.recoveries {
+            content.push_str(&format!("\n\n[Recovery: {}]", recovery.description));
+        }
+        for note in &self.metadata.notes {
+            content.push_str(&format!("\n\n[Note: {}]", note));
         }
+        content
     }
 }
 
@@ -250,6 +250,7 @@ pub enum ToolExecutionStatus {
     Failed { error: ToolError, recoverable: bool },
     Timeout,
     Cancelled,
+    Skipped { reason: String },
 }
 
 impl ToolExecutionStatus {
@@ -324,6 +325,92 @@ impl ToolResultData {
             preview: Some(preview),
         }
     }
+
+    /// Render the data as text for the model
+    pub fn render(&self) -> String {
+        match self {
+            ToolResultData::Text(text) => text.clone(),
+            ToolResultData::Json(value) => format!(
+                "```json\n{}\n```",
+                serde_json::to_string_pretty(value).unwrap_or_default()
+            ),
+            ToolResultData::FileReference { path, size, hash } => {
+                let hash_info = hash
+                    .as_ref()
+                    .map(|h| format!(" (hash: {})", h))
+                    .unwrap_or_default();
+                format!("File reference: {} ({} bytes){}", path, size, hash_info)
+            }
+            ToolResultData::DirectoryListing(items) if items.is_empty() => {
+                "Directory is empty".to_string()
+            }
+            ToolResultData::DirectoryListing(items) => {
+                let directories = items
+                    .iter()
+                    .filter(|item| matches!(item.item_type, FileItemType::Directory))
+                    .count();
+                format!(
+                    "{} entries ({} directories, {} files):\n{}",
+                    items.len(),
+                    directories,
+                    items.len() - directories,
+                    items
+                        .iter()
+                        .map(|item| format!("  {}", item.display()))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                )
+            }
+            ToolResultData::Binary {
+                content_type,
+                size,
+                preview,
+            } => {
+                let preview_info = preview
+                    .as_ref()
+                    .map(|p| format!(". First bytes: {}", p))
+                    .unwrap_or_default();
+                format!(
+                    "Binary data ({}, {} bytes) cannot be shown as text{}",
+                    content_type, size, preview_info
+                )
+            }
+        }
+    }
+}
+
+/// Output of a tool call: the result data plus what the engine records about it
+#[derive(Debug, Clone)]
+pub struct ToolOutput {
+    pub data: ToolResultData,
+    pub warnings: Vec<String>,
+    pub bytes_processed: Option<u64>,
+}
+
+impl ToolOutput {
+    pub fn new(data: ToolResultData) -> Self {
+        Self {
+            data,
+            warnings: Vec::new(),
+            bytes_processed: None,
+        }
+    }
+
+    pub fn with_warning(mut self, warning: impl Into<String>) -> Self {
+        self.warnings.push(warning.into());
+        self
+    }
+
+    pub fn with_bytes_processed(mut self, bytes: u64) -> Self {
+        self.bytes_processed = Some(bytes);
+        self
+    }
+}
+
+impl From<ToolResultData> for ToolOutput {
+    fn from(data: ToolResultData) -> Self {
+        Self::new(data)
+    }
 }
 
 /// File item for directory listings
@@ -374,8 +461,15 @@ impl FileItem {
             .size
             .map(|s| format!(" ({} bytes)", s))
             .unwrap_or_default();
+        let modified_info = self
+            .modified
+            .map(|m| format!(", modified {}", m.format("%Y-%m-%d %H:%M")))
+            .unwrap_or_default();
 
-        format!("{}{}{}", self.name, type_indicator, size_info)
+        format!(
+            "{}{}{}{}",
+            self.name, type_indicator, size_info, modified_info
+        )
     }
 }
 
@@ -392,8 +486,20 @@ pub enum FileItemType {
 pub struct ToolResultMetadata {
     pub execution_time: Duration,
     pub memory_used: Option<u64>,
+    /// Bytes the tool read or wrote, when it reports them
+    #[serde(default)]
+    pub bytes_processed: Option<u64>,
     pub warnings: Vec<String>,
     pub performance_hints: Vec<String>,
+    /// Answers the user gave while this call was paused for input
+    #[serde(default)]
+    pub user_responses: Vec<String>,
+    /// Recovery actions the engine applied before this result was produced
+    #[serde(default)]
+    pub recoveries: Vec<AppliedRecovery>,
+    /// Lessons from earlier calls to this tool, passed on to Claude
+    #[serde(default)]
+    pub notes: Vec<String>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -402,13 +508,43 @@ impl Default for ToolResultMetadata {
         Self {
             execution_time: Duration::from_millis(0),
             memory_used: None,
+            bytes_processed: None,
             warnings: Vec::new(),
             performance_hints: Vec::new(),
+            user_responses: Vec::new(),
+            recoveries: Vec::new(),
+            notes: Vec::new(),
             created_at: Utc::now(),
         }
     }
 }
 
+/// Kind of recovery the engine performed after a failed attempt
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryKind {
+    Retry,
+    ModifyInput,
+    IncreaseTimeout,
+    CreateDirectory,
+    FallbackTool,
+    UserIntervention,
+    Skip,
+    Abort,
+    OtherFix,
+}
+
+/// A recovery action that was actually carried out, kept so the UI can show it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AppliedRecovery {
+    /// Attempt (1-based) whose failure triggered the recovery
+    pub attempt: u32,
+    pub kind: RecoveryKind,
+    pub description: String,
+    /// Error message of the failed attempt
+    pub error: String,
+}
+
 impl ToolResultMetadata {
     pub fn with_execution_time(mut self, duration: Duration) -> Self {
         self.execution_time = duration;
@@ -711,4 +847,46 @@ mod tests {
         assert!(content.contains("2048 bytes"));
         assert!(content.contains("hash: abc123"));
     }
+
+    #[test]
+    fn test_structured_data_rendering() {
+        let listing = ToolResultData::directory_listing(vec![
+            FileItem::new("src".to_string(), FileItemType::Directory),
+            FileItem::new("Cargo.toml".to_string(), FileItemType::File).with_size(512),
+        ]);
+        let content = listing.render();
+        assert!(content.starts_with("2 entries (1 directories, 1 files):"));
+        assert!(content.contains("  src/"));
+        assert!(content.contains("  Cargo.toml (512 bytes)"));
+        assert_eq!(
+            ToolResultData::directory_listing(Vec::new()).render(),
+            "Directory is empty"
+        );
+
+        let json = ToolResultData::json(serde_json::json!({"ok": true})).render();
+        assert!(json.starts_with("```json\n") && json.ends_with("\n```"));
+
+        let binary = ToolResultData::binary_with_preview("image/png", 64, "89 50 4e 47".into());
+        assert_eq!(
+            binary.render(),
+            "Binary data (image/png, 64 bytes) cannot be shown as text. First bytes: 89 50 4e 47"
+        );
+    }
+
+    #[test]
+    fn test_content_block_includes_warnings() {
+        let mut result = ToolExecutionResult::success(
+            "exec_1".to_string(),
+            "list_directory".to_string(),
+            ToolResultData::text("a.txt"),
+        );
+        result
+            .metadata
+            .warnings
+            .push("Listing truncated".to_string());
+        assert_eq!(
+            result.into_content_block(),
+            "a.txt\n\n[Warning: Listing truncated]"
+        );
+    }
 }
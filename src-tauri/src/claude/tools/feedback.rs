@@ -1,12 +1,20 @@
 use crate::claude::{
+    constants::feedback_learning::{
+        LATENCY_SAMPLE_SIZE, MAX_TRACKED_TARGETS, MIN_LATENCY_SAMPLES, REPEATED_FAILURE_THRESHOLD,
+    },
     error::ClaudeResult,
     tools::execution::{
-        FollowUpAction, StatusLevel, ToolError, ToolErrorType, ToolExecutionResult, ToolResultData,
+        FollowUpAction, StatusLevel, ToolError, ToolErrorType, ToolExecutionResult,
+        ToolExecutionStatus, ToolResultData,
     },
 };
 use async_trait::async_trait;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 
 /// Trait for handling tool execution results and generating follow-up actions
@@ -18,6 +26,16 @@ pub trait ToolFeedbackHandler: Send + Sync + std::fmt::Debug {
         result: &ToolExecutionResult,
     ) -> ClaudeResult<Vec<FollowUpAction>>;
 
+    /// Process a result together with the input the tool was called with.
+    /// Handlers that learn from inputs override this; the default ignores it.
+    async fn handle_call(
+        &self,
+        result: &ToolExecutionResult,
+        _input: &Value,
+    ) -> ClaudeResult<Vec<FollowUpAction>> {
+        self.handle_result(result).await
+    }
+
     /// Handle tool execution errors and suggest recovery actions
     async fn handle_error(
         &self,
@@ -68,6 +86,16 @@ impl FeedbackManager {
     pub async fn process_result(
         &self,
         result: &ToolExecutionResult,
+    ) -> ClaudeResult<Vec<FollowUpAction>> {
+        self.process_call(result, &Value::Null).await
+    }
+
+    /// Process a tool result and the input that produced it through all
+    /// applicable handlers
+    pub async fn process_call(
+        &self,
+        result: &ToolExecutionResult,
+        input: &Value,
     ) -> ClaudeResult<Vec<FollowUpAction>> {
         // Store result in history
         {
@@ -87,7 +115,7 @@ impl FeedbackManager {
         // Process through all handlers that can handle this result
         for handler in &self.handlers {
             if handler.can_handle(result) {
-                match handler.handle_result(result).await {
+                match handler.handle_call(result, input).await {
                     Ok(mut actions) => {
                         all_actions.append(&mut actions);
                     }
@@ -494,11 +522,80 @@ impl PatternCondition {
     }
 }
 
+/// Where feedback learned from earlier calls goes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeedbackDelivery {
+    /// Appended to the tool_result as notes for Claude
+    #[default]
+    Notes,
+    /// Shown to the user only
+    Hints,
+    /// Kept in the result's follow-up actions and nowhere else
+    Off,
+}
+
+impl std::str::FromStr for FeedbackDelivery {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "notes" => Ok(Self::Notes),
+            "hints" => Ok(Self::Hints),
+            "off" => Ok(Self::Off),
+            other => Err(format!(
+                "Unknown feedback delivery '{}' (use notes, hints or off)",
+                other
+            )),
+        }
+    }
+}
+
+/// Shows feedback hints to the user (the frontend)
+pub trait HintNotifier: Send + Sync + std::fmt::Debug {
+    fn notify(&self, tool_name: &str, hint: &str);
+}
+
+/// Learning handler and where its feedback is delivered, shared between the
+/// app state and every execution engine
+#[derive(Debug, Clone)]
+pub struct LearnedFeedback {
+    pub handler: Arc<SmartFeedbackHandler>,
+    pub delivery: FeedbackDelivery,
+    pub notifier: Option<Arc<dyn HintNotifier>>,
+}
+
+impl LearnedFeedback {
+    pub fn new(handler: Arc<SmartFeedbackHandler>, delivery: FeedbackDelivery) -> Self {
+        Self {
+            handler,
+            delivery,
+            notifier: None,
+        }
+    }
+
+    pub fn with_notifier(mut self, notifier: Arc<dyn HintNotifier>) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+}
+
+impl Default for LearnedFeedback {
+    fn default() -> Self {
+        Self::new(
+            Arc::new(SmartFeedbackHandler::new()),
+            FeedbackDelivery::default(),
+        )
+    }
+}
+
 /// Smart feedback handler that learns from execution patterns
 #[derive(Debug)]
 pub struct SmartFeedbackHandler {
     execution_patterns: Arc<RwLock<HashMap<String, ExecutionPattern>>>,
     learning_enabled: bool,
+    /// File the learned patterns are saved to after every call
+    store_path: Option<PathBuf>,
 }
 
 impl SmartFeedbackHandler {
@@ -506,6 +603,29 @@ impl SmartFeedbackHandler {
         Self {
             execution_patterns: Arc::new(RwLock::new(HashMap::new())),
             learning_enabled: true,
+            store_path: None,
+        }
+    }
+
+    /// Continue from the patterns saved at `path`, and keep saving there.
+    /// A missing or unreadable file starts from scratch.
+    pub fn load(path: PathBuf) -> Self {
+        let patterns = match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                eprintln!(
+                    "Failed to parse learned tool patterns {}: {}",
+                    path.display(),
+                    e
+                );
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+
+        Self {
+            execution_patterns: Arc::new(RwLock::new(patterns)),
+            learning_enabled: true,
+            store_path: Some(path),
         }
     }
 
@@ -514,7 +634,7 @@ impl SmartFeedbackHandler {
         self
     }
 
-    async fn learn_from_result(&self, result: &ToolExecutionResult) {
+    async fn learn_from_result(&self, result: &ToolExecutionResult, input: &Value) {
         if !self.learning_enabled {
             return;
         }
@@ -525,16 +645,51 @@ impl SmartFeedbackHandler {
             .or_insert_with(|| ExecutionPattern::new(result.tool_name.clone()));
 
         pattern.add_execution(result);
+        pattern.add_input(result, input);
+
+        if let Some(path) = &self.store_path {
+            if let Err(e) = Self::save(path, &patterns) {
+                eprintln!(
+                    "Failed to save learned tool patterns {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+    }
+
+    fn save(path: &Path, patterns: &HashMap<String, ExecutionPattern>) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, serde_json::to_string(patterns)?)?;
+        std::fs::rename(&temp_path, path)?;
+        Ok(())
     }
 
-    async fn suggest_optimizations(&self, result: &ToolExecutionResult) -> Vec<FollowUpAction> {
+    async fn suggest_optimizations(
+        &self,
+        result: &ToolExecutionResult,
+        input: &Value,
+    ) -> Vec<FollowUpAction> {
         let patterns = self.execution_patterns.read().await;
         if let Some(pattern) = patterns.get(&result.tool_name) {
-            pattern.suggest_optimizations(result)
+            pattern.suggest_optimizations(result, input)
         } else {
             Vec::new()
         }
     }
+
+    /// What has been learned about each tool, sorted by name
+    #[allow(dead_code)]
+    pub async fn tool_stats(&self) -> Vec<LearnedToolStats> {
+        let patterns = self.execution_patterns.read().await;
+        let mut stats: Vec<LearnedToolStats> =
+            patterns.values().map(LearnedToolStats::from).collect();
+        stats.sort_by(|a, b| a.tool_name.cmp(&b.tool_name));
+        stats
+    }
 }
 
 #[async_trait]
@@ -542,12 +697,20 @@ impl ToolFeedbackHandler for SmartFeedbackHandler {
     async fn handle_result(
         &self,
         result: &ToolExecutionResult,
+    ) -> ClaudeResult<Vec<FollowUpAction>> {
+        self.handle_call(result, &Value::Null).await
+    }
+
+    async fn handle_call(
+        &self,
+        result: &ToolExecutionResult,
+        input: &Value,
     ) -> ClaudeResult<Vec<FollowUpAction>> {
         // Learn from this execution
-        self.learn_from_result(result).await;
+        self.learn_from_result(result, input).await;
 
         // Generate optimization suggestions
-        let optimizations = self.suggest_optimizations(result).await;
+        let optimizations = self.suggest_optimizations(result, input).await;
 
         Ok(optimizations)
     }
@@ -579,14 +742,48 @@ impl Default for SmartFeedbackHandler {
     }
 }
 
+/// Summary of what was learned about one tool
+#[derive(Debug, Clone, Serialize)]
+pub struct LearnedToolStats {
+    pub tool_name: String,
+    pub execution_count: u32,
+    pub success_rate: f64,
+    pub p50_ms: Option<u64>,
+    pub p95_ms: Option<u64>,
+    /// Failure counts by input shape, then by kind of failure
+    pub failures_by_shape: HashMap<String, HashMap<String, u32>>,
+}
+
+impl From<&ExecutionPattern> for LearnedToolStats {
+    fn from(pattern: &ExecutionPattern) -> Self {
+        Self {
+            tool_name: pattern.tool_name.clone(),
+            execution_count: pattern.execution_count,
+            success_rate: pattern.success_rate,
+            p50_ms: pattern.percentile(50).map(|d| d.as_millis() as u64),
+            p95_ms: pattern.percentile(95).map(|d| d.as_millis() as u64),
+            failures_by_shape: pattern.failures_by_shape.clone(),
+        }
+    }
+}
+
 /// Execution pattern for learning and optimization
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct ExecutionPattern {
     tool_name: String,
     execution_count: u32,
     average_execution_time: std::time::Duration,
     common_errors: HashMap<String, u32>,
     success_rate: f64,
+    /// Durations of the most recent calls in milliseconds, for percentiles
+    #[serde(default)]
+    recent_durations_ms: VecDeque<u64>,
+    /// Failure counts by input shape (field names and value types), then by kind
+    #[serde(default)]
+    failures_by_shape: HashMap<String, HashMap<String, u32>>,
+    /// Failure counts by the path a call targeted, then by kind
+    #[serde(default)]
+    failures_by_target: HashMap<String, HashMap<String, u32>>,
 }
 
 impl ExecutionPattern {
@@ -597,6 +794,9 @@ impl ExecutionPattern {
             average_execution_time: std::time::Duration::from_millis(0),
             common_errors: HashMap::new(),
             success_rate: 0.0,
+            recent_durations_ms: VecDeque::new(),
+            failures_by_shape: HashMap::new(),
+            failures_by_target: HashMap::new(),
         }
     }
 
@@ -608,6 +808,12 @@ impl ExecutionPattern {
         let total_time = self.average_execution_time * (self.execution_count - 1) + new_time;
         self.average_execution_time = total_time / self.execution_count;
 
+        self.recent_durations_ms
+            .push_back(new_time.as_millis() as u64);
+        if self.recent_durations_ms.len() > LATENCY_SAMPLE_SIZE {
+            self.recent_durations_ms.pop_front();
+        }
+
         // Track errors
         if result.is_error() {
             if let Some(error_context) = &result.error_context {
@@ -621,35 +827,166 @@ impl ExecutionPattern {
             / self.execution_count as f64;
     }
 
-    fn suggest_optimizations(&self, result: &ToolExecutionResult) -> Vec<FollowUpAction> {
+    /// Remember which inputs failed, and how
+    fn add_input(&mut self, result: &ToolExecutionResult, input: &Value) {
+        let Some(kind) = failure_kind(result) else {
+            return;
+        };
+
+        *self
+            .failures_by_shape
+            .entry(input_shape(input))
+            .or_default()
+            .entry(kind.to_string())
+            .or_insert(0) += 1;
+
+        if let Some(target) = input_target(input) {
+            // Stop tracking new paths once enough are known; known ones keep counting
+            if self.failures_by_target.len() < MAX_TRACKED_TARGETS
+                || self.failures_by_target.contains_key(target)
+            {
+                *self
+                    .failures_by_target
+                    .entry(target.to_string())
+                    .or_default()
+                    .entry(kind.to_string())
+                    .or_insert(0) += 1;
+            }
+        }
+    }
+
+    /// Duration below which `percent` of the recent calls finished
+    fn percentile(&self, percent: usize) -> Option<Duration> {
+        if self.recent_durations_ms.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<u64> = self.recent_durations_ms.iter().copied().collect();
+        sorted.sort_unstable();
+        let index = (sorted.len() * percent).div_ceil(100).saturating_sub(1);
+        Some(Duration::from_millis(sorted[index.min(sorted.len() - 1)]))
+    }
+
+    fn suggest_optimizations(
+        &self,
+        result: &ToolExecutionResult,
+        input: &Value,
+    ) -> Vec<FollowUpAction> {
         let mut suggestions = Vec::new();
 
-        // Suggest timeout adjustments if execution time is consistently high
-        if result.metadata.execution_time > self.average_execution_time * 2 {
-            suggestions.push(FollowUpAction::ReportStatus {
-                message: format!(
-                    "Tool '{}' took longer than average. Consider optimizing.",
-                    self.tool_name
-                ),
-                level: StatusLevel::Info,
+        if let Some(kind) = failure_kind(result) {
+            // The same path failing again is the most specific thing to point out
+            let target = input_target(input).and_then(|target| {
+                let count = *self.failures_by_target.get(target)?.get(kind)?;
+                Some((target, count))
             });
+            let shape = input_shape(input);
+            let shape_count = self
+                .failures_by_shape
+                .get(&shape)
+                .and_then(|kinds| kinds.get(kind))
+                .copied()
+                .unwrap_or(0);
+
+            match target {
+                Some((target, count)) if count >= REPEATED_FAILURE_THRESHOLD => {
+                    suggestions.push(FollowUpAction::ReportStatus {
+                        message: format!(
+                            "'{}' has failed {} times with {} errors in {}; try a different path or ask the user instead of retrying",
+                            target, count, kind, self.tool_name
+                        ),
+                        level: StatusLevel::Warning,
+                    });
+                }
+                _ if shape_count >= REPEATED_FAILURE_THRESHOLD => {
+                    suggestions.push(FollowUpAction::ReportStatus {
+                        message: format!(
+                            "{} calls with input {} have failed {} times with {} errors; change the input rather than retrying it",
+                            self.tool_name, shape, shape_count, kind
+                        ),
+                        level: StatusLevel::Warning,
+                    });
+                }
+                _ => {}
+            }
+            return suggestions;
         }
 
-        // Suggest retry if success rate is low
-        if self.success_rate < 0.8 && result.is_error() {
-            suggestions.push(FollowUpAction::RetryWithModifiedInput {
-                modified_input: serde_json::json!({"retry_reason": "low_success_rate"}),
-                delay: Some(std::time::Duration::from_secs(2)),
-            });
+        // Point out calls far slower than usual once there is enough data
+        if self.recent_durations_ms.len() >= MIN_LATENCY_SAMPLES {
+            if let (Some(p50), Some(p95)) = (self.percentile(50), self.percentile(95)) {
+                let elapsed = result.metadata.execution_time;
+                if elapsed > p95 && elapsed > p50 * 2 {
+                    suggestions.push(FollowUpAction::ReportStatus {
+                        message: format!(
+                            "{} took {} ms, slower than 95% of recent calls (median {} ms, p95 {} ms); narrow the request if this repeats",
+                            self.tool_name,
+                            elapsed.as_millis(),
+                            p50.as_millis(),
+                            p95.as_millis()
+                        ),
+                        level: StatusLevel::Warning,
+                    });
+                }
+            }
         }
 
         suggestions
     }
 }
 
+/// Short name for how a call failed, or `None` if it did not
+fn failure_kind(result: &ToolExecutionResult) -> Option<&'static str> {
+    match &result.status {
+        ToolExecutionStatus::Failed { error, .. } => Some(match error.error_type {
+            ToolErrorType::ValidationError => "validation",
+            ToolErrorType::ExecutionError => "execution",
+            ToolErrorType::PermissionError => "permission",
+            ToolErrorType::TimeoutError => "timeout",
+            ToolErrorType::NetworkError => "network",
+            ToolErrorType::ResourceError => "resource",
+        }),
+        ToolExecutionStatus::Timeout => Some("timeout"),
+        _ => None,
+    }
+}
+
+/// Field names and value types of an input, e.g. `{content: string, path: string}`
+fn input_shape(input: &Value) -> String {
+    fn kind(value: &Value) -> &'static str {
+        match value {
+            Value::Null => "null",
+            Value::Bool(_) => "bool",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        }
+    }
+
+    match input {
+        Value::Object(object) => {
+            let mut fields: Vec<String> = object
+                .iter()
+                .map(|(key, value)| format!("{}: {}", key, kind(value)))
+                .collect();
+            fields.sort();
+            format!("{{{}}}", fields.join(", "))
+        }
+        other => kind(other).to_string(),
+    }
+}
+
+/// The path a call works on, if its input names one
+fn input_target(input: &Value) -> Option<&str> {
+    ["path", "source", "file_path"]
+        .iter()
+        .find_map(|key| input.get(key).and_then(Value::as_str))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::claude::test_support::TempDir;
     use crate::claude::tools::execution::{ToolExecutionResult, ToolResultData};
 
     #[tokio::test]
@@ -774,4 +1111,83 @@ mod tests {
         assert_eq!(pattern.execution_count, 2);
         assert!(pattern.success_rate < 0.9); // Should decrease due to failure
     }
+
+    fn failed_read(execution_id: &str) -> ToolExecutionResult {
+        ToolExecutionResult::failure(
+            execution_id.to_string(),
+            "read_file".to_string(),
+            ToolError::execution_error("No such file or directory"),
+            true,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_repeated_path_failure_is_pointed_out() {
+        let handler = SmartFeedbackHandler::new();
+        let input = serde_json::json!({ "path": "src/missing.rs" });
+
+        for i in 1..REPEATED_FAILURE_THRESHOLD {
+            let actions = handler
+                .handle_call(&failed_read(&format!("exec_{}", i)), &input)
+                .await
+                .unwrap();
+            assert!(actions.is_empty());
+        }
+
+        let actions = handler
+            .handle_call(&failed_read("exec_last"), &input)
+            .await
+            .unwrap();
+        assert!(actions.iter().any(|action| matches!(
+            action,
+            FollowUpAction::ReportStatus { message, level: StatusLevel::Warning }
+                if message.contains("src/missing.rs")
+        )));
+
+        let stats = handler.tool_stats().await;
+        assert_eq!(stats[0].failures_by_shape["{path: string}"]["execution"], 3);
+    }
+
+    #[test]
+    fn test_latency_percentiles() {
+        let mut pattern = ExecutionPattern::new("test_tool".to_string());
+        pattern.recent_durations_ms = (1..=100).collect();
+
+        assert_eq!(pattern.percentile(50), Some(Duration::from_millis(50)));
+        assert_eq!(pattern.percentile(95), Some(Duration::from_millis(95)));
+        assert_eq!(
+            ExecutionPattern::new("empty".to_string()).percentile(50),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_learned_patterns_persist() {
+        let dir = TempDir::new();
+        let path = dir.join("tool_learning.json");
+        let input = serde_json::json!({ "path": "a.txt" });
+
+        let handler = SmartFeedbackHandler::load(path.clone());
+        handler
+            .handle_call(&failed_read("exec_1"), &input)
+            .await
+            .unwrap();
+
+        let reloaded = SmartFeedbackHandler::load(path.clone());
+        let stats = reloaded.tool_stats().await;
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].execution_count, 1);
+        assert_eq!(stats[0].failures_by_shape["{path: string}"]["execution"], 1);
+
+        // Disabled learning leaves the saved patterns alone
+        let disabled = SmartFeedbackHandler::load(path.clone()).with_learning(false);
+        disabled
+            .handle_call(&failed_read("exec_2"), &input)
+            .await
+            .unwrap();
+        assert_eq!(
+            SmartFeedbackHandler::load(path.clone()).tool_stats().await[0].execution_count,
+            1
+        );
+    }
 }
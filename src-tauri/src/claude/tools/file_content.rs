@@ -0,0 +1,324 @@
+//! Content decoding helpers for the file reading tool: binary detection,
+//! lossy decoding of non-UTF-8 text, hex previews and line-range selection.
+
+use crate::claude::constants::file_limits::{BINARY_PREVIEW_BYTES, BINARY_SNIFF_BYTES};
+use anyhow::Result;
+
+/// Encoding a text file was decoded with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextEncoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+impl TextEncoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TextEncoding::Utf8 => "UTF-8",
+            TextEncoding::Utf16Le => "UTF-16LE",
+            TextEncoding::Utf16Be => "UTF-16BE",
+            TextEncoding::Latin1 => "latin-1",
+        }
+    }
+}
+
+/// Result of inspecting a file's bytes
+#[derive(Debug)]
+pub enum FileContent {
+    Text {
+        content: String,
+        encoding: TextEncoding,
+    },
+    Binary {
+        content_type: &'static str,
+    },
+}
+
+/// Guess a content type from well-known magic numbers
+fn sniff_content_type(bytes: &[u8]) -> &'static str {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF8", "image/gif"),
+        (b"%PDF", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"\x7fELF", "application/x-elf"),
+        (b"\0asm", "application/wasm"),
+    ];
+
+    SIGNATURES
+        .iter()
+        .find(|(magic, _)| bytes.starts_with(magic))
+        .map(|(_, content_type)| *content_type)
+        .unwrap_or("application/octet-stream")
+}
+
+fn decode_utf16(bytes: &[u8], little_endian: bool) -> String {
+    let units = bytes.chunks_exact(2).map(|pair| {
+        if little_endian {
+            u16::from_le_bytes([pair[0], pair[1]])
+        } else {
+            u16::from_be_bytes([pair[0], pair[1]])
+        }
+    });
+    char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+/// Detect BOM-less UTF-16 by the pattern of zero bytes typical of ASCII-range text
+fn guess_utf16_without_bom(sample: &[u8]) -> Option<bool> {
+    if sample.len() < 4 || !sample.len().is_multiple_of(2) {
+        return None;
+    }
+    let pairs = sample.len() / 2;
+    let even_zeros = sample.iter().step_by(2).filter(|b| **b == 0).count();
+    let odd_zeros = sample
+        .iter()
+        .skip(1)
+        .step_by(2)
+        .filter(|b| **b == 0)
+        .count();
+
+    if odd_zeros * 10 >= pairs * 9 && even_zeros == 0 {
+        Some(true)
+    } else if even_zeros * 10 >= pairs * 9 && odd_zeros == 0 {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Whether bytes look like binary data rather than (single-byte) text
+fn looks_binary(sample: &[u8]) -> bool {
+    if sample.contains(&0) {
+        return true;
+    }
+    // Control characters other than common whitespace, form feed and escape
+    let control = sample
+        .iter()
+        .filter(|b| matches!(**b, 0x01..=0x08 | 0x0E..=0x1A | 0x1C..=0x1F))
+        .count();
+    control * 10 > sample.len()
+}
+
+/// Decode file bytes as text where possible. UTF-8 is preferred; UTF-16 is
+/// recognised by BOM or zero-byte pattern, and other non-UTF-8 text falls back
+/// to latin-1 so it can still be read.
+pub fn decode_file_content(bytes: &[u8]) -> FileContent {
+    if let Some(rest) = bytes.strip_prefix(b"\xef\xbb\xbf") {
+        return FileContent::Text {
+            content: String::from_utf8_lossy(rest).into_owned(),
+            encoding: TextEncoding::Utf8,
+        };
+    }
+    if let Some(rest) = bytes.strip_prefix(b"\xff\xfe") {
+        return FileContent::Text {
+            content: decode_utf16(rest, true),
+            encoding: TextEncoding::Utf16Le,
+        };
+    }
+    if let Some(rest) = bytes.strip_prefix(b"\xfe\xff") {
+        return FileContent::Text {
+            content: decode_utf16(rest, false),
+            encoding: TextEncoding::Utf16Be,
+        };
+    }
+
+    let sample = &bytes[..bytes.len().min(BINARY_SNIFF_BYTES) & !1];
+    if let Some(little_endian) = guess_utf16_without_bom(sample) {
+        return FileContent::Text {
+            content: decode_utf16(bytes, little_endian),
+            encoding: if little_endian {
+                TextEncoding::Utf16Le
+            } else {
+                TextEncoding::Utf16Be
+            },
+        };
+    }
+
+    if looks_binary(&bytes[..bytes.len().min(BINARY_SNIFF_BYTES)]) {
+        return FileContent::Binary {
+            content_type: sniff_content_type(bytes),
+        };
+    }
+
+    match std::str::from_utf8(bytes) {
+        Ok(content) => FileContent::Text {
+            content: content.to_string(),
+            encoding: TextEncoding::Utf8,
+        },
+        Err(_) => FileContent::Text {
+            // Every byte maps to the code point of the same value in latin-1
+            content: bytes.iter().map(|b| *b as char).collect(),
+            encoding: TextEncoding::Latin1,
+        },
+    }
+}
+
+/// Hex dump of the first bytes of a file, e.g. `89 50 4e 47 ...`
+pub fn hex_preview(bytes: &[u8]) -> String {
+    let shown = &bytes[..bytes.len().min(BINARY_PREVIEW_BYTES)];
+    let mut preview = shown
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if bytes.len() > shown.len() {
+        preview.push_str(" ...");
+    }
+    preview
+}
+
+/// Select a 1-based line range and prefix each line with its line number.
+/// Returns the numbered text and the total number of lines in `content`.
+pub fn number_lines(
+    content: &str,
+    offset: Option<usize>,
+    limit: Option<usize>,
+) -> Result<(String, usize)> {
+    let total = content.lines().count();
+    let start = offset.unwrap_or(1);
+    if start == 0 {
+        return Err(anyhow::anyhow!(
+            "'offset' is 1-based and must be at least 1"
+        ));
+    }
+    if start > total.max(1) {
+        return Err(anyhow::anyhow!(
+            "'offset' {} is beyond the end of the file ({} lines)",
+            start,
+            total
+        ));
+    }
+
+    let width = total.max(1).to_string().len().max(4);
+    let numbered = content
+        .lines()
+        .enumerate()
+        .skip(start - 1)
+        .take(limit.unwrap_or(usize::MAX))
+        .map(|(index, line)| format!("{:>width$}\t{}", index + 1, line, width = width))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Ok((numbered, total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::claude::test_support::whitelisted_temp_dir;
+    use crate::claude::tools::{AgentTool, ReadFileTool, ToolResultData};
+    use serde_json::json;
+
+    #[test]
+    fn test_decode_utf8_and_latin1() {
+        match decode_file_content("héllo\n".as_bytes()) {
+            FileContent::Text { content, encoding } => {
+                assert_eq!(content, "héllo\n");
+                assert_eq!(encoding, TextEncoding::Utf8);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        match decode_file_content(b"caf\xe9\n") {
+            FileContent::Text { content, encoding } => {
+                assert_eq!(content, "café\n");
+                assert_eq!(encoding, TextEncoding::Latin1);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_decode_utf16() {
+        let mut bytes = vec![0xff, 0xfe];
+        bytes.extend("hi\n".encode_utf16().flat_map(|u| u.to_le_bytes()));
+        match decode_file_content(&bytes) {
+            FileContent::Text { content, encoding } => {
+                assert_eq!(content, "hi\n");
+                assert_eq!(encoding, TextEncoding::Utf16Le);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // Without a BOM the zero-byte pattern identifies the byte order
+        let bytes: Vec<u8> = "hello"
+            .encode_utf16()
+            .flat_map(|u| u.to_be_bytes())
+            .collect();
+        match decode_file_content(&bytes) {
+            FileContent::Text { content, encoding } => {
+                assert_eq!(content, "hello");
+                assert_eq!(encoding, TextEncoding::Utf16Be);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_detect_binary() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\x01\x02";
+        match decode_file_content(png) {
+            FileContent::Binary { content_type } => assert_eq!(content_type, "image/png"),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(hex_preview(png).starts_with("89 50 4e 47"));
+        assert!(hex_preview(&[0u8; 100]).ends_with(" ..."));
+    }
+
+    #[test]
+    fn test_number_lines() {
+        let content = "a\nb\nc\nd\n";
+        let (text, total) = number_lines(content, Some(2), Some(2)).unwrap();
+        assert_eq!(total, 4);
+        assert_eq!(text, "   2\tb\n   3\tc");
+
+        let (text, _) = number_lines(content, None, None).unwrap();
+        assert_eq!(text.lines().count(), 4);
+
+        assert!(number_lines(content, Some(0), None).is_err());
+        assert!(number_lines(content, Some(9), None).is_err());
+        assert!(number_lines("", None, None).unwrap().0.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_read_file_tool_ranges_and_binary() {
+        let (dir, whitelist) = whitelisted_temp_dir();
+        std::fs::write(dir.join("lines.txt"), "one\ntwo\nthree\n").unwrap();
+        std::fs::write(dir.join("image.png"), b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+
+        let mut tool = ReadFileTool::new();
+        tool.set_whitelist(whitelist);
+
+        let text = tool
+            .execute(json!({
+                "path": dir.join("lines.txt").to_string_lossy(),
+                "offset": 2,
+                "limit": 1,
+            }))
+            .await
+            .unwrap();
+        assert_eq!(text, "[Showing lines 2-2 of 3]\n   2\ttwo");
+
+        let data = tool
+            .execute_data(json!({ "path": dir.join("image.png").to_string_lossy() }))
+            .await
+            .unwrap();
+        match data {
+            ToolResultData::Binary {
+                content_type,
+                preview,
+                ..
+            } => {
+                assert_eq!(content_type, "image/png");
+                assert!(preview.unwrap().starts_with("89 50 4e 47"));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,709 @@
+//! File management tools: move, copy, delete (to an app-managed trash),
+//! directory creation and file metadata.
+//!
+//! Every tool resolves its paths through the whitelist with its own
+//! `FileOperation`, so each capability can be permitted or denied independently.
+
+use crate::claude::constants::file_limits::{TRASH_DIR_NAME, TRASH_ORIGIN_FILE};
+use crate::claude::secure_fs::{FileAccess, SecureRoot};
+use crate::claude::tools::{
+    create_tool_directory, input_schema_for, open_tool_file, parse_input, path_inputs,
+    resolve_tool_path, tool_root, with_tool_root, AgentTool,
+};
+use crate::claude::types::ToolInputSchema;
+use crate::claude::whitelist::{FileOperation, WhitelistConfig};
+use crate::config::constants::PROTECTED_FILES;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
+
+/// Reject operations that would move, replace or remove a protected file
+fn ensure_not_protected(path: &Path) -> Result<()> {
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+    if PROTECTED_FILES.contains(&file_name) {
+        return Err(anyhow::anyhow!(
+            "Access denied: Cannot modify protected file '{}'",
+            file_name
+        ));
+    }
+    Ok(())
+}
+
+/// Move a path beneath `source_root` to `destination` beneath
+/// `destination_root`, copying only when the move crosses filesystems (e.g.
+/// the trash lives on a different mount)
+async fn move_path(
+    source_root: SecureRoot,
+    source: &Path,
+    destination_root: SecureRoot,
+    destination: &Path,
+    replace: bool,
+) -> Result<()> {
+    let (source, destination) = (source.to_path_buf(), destination.to_path_buf());
+    tokio::task::spawn_blocking(move || {
+        source_root.move_to(&source, &destination_root, &destination, replace)
+    })
+    .await
+    .map_err(|e| anyhow::anyhow!("Move task failed: {}", e))?
+}
+
+/// Input for `move_file`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct MoveFileInput {
+    /// The path of the file or directory to move
+    source: String,
+    /// The new path for the file or directory
+    destination: String,
+    /// Replace the destination file if it already exists
+    #[serde(default)]
+    overwrite: bool,
+}
+
+#[derive(Debug)]
+pub struct MoveFileTool {
+    whitelist: Option<Arc<RwLock<WhitelistConfig>>>,
+}
+
+impl MoveFileTool {
+    pub fn new() -> Self {
+        Self { whitelist: None }
+    }
+}
+
+#[async_trait]
+impl AgentTool for MoveFileTool {
+    fn set_whitelist(&mut self, whitelist: Arc<RwLock<WhitelistConfig>>) {
+        self.whitelist = Some(whitelist);
+    }
+
+    fn modified_paths(&self, input: &Value) -> Vec<(String, FileOperation)> {
+        path_inputs(input, &["source", "destination"], FileOperation::Move)
+    }
+
+    fn name(&self) -> &str {
+        "move_file"
+    }
+
+    fn description(&self) -> &str {
+        "Move or rename a file or directory"
+    }
+
+    fn input_schema(&self) -> ToolInputSchema {
+        input_schema_for::<MoveFileInput>()
+    }
+
+    async fn execute(&self, input: Value) -> Result<String> {
+        let input: MoveFileInput = parse_input(input)?;
+        let (source_str, destination_str) = (input.source.as_str(), input.destination.as_str());
+        let overwrite = input.overwrite;
+
+        let source =
+            resolve_tool_path(self.whitelist.as_ref(), source_str, FileOperation::Move).await?;
+        let destination = resolve_tool_path(
+            self.whitelist.as_ref(),
+            destination_str,
+            FileOperation::Move,
+        )
+        .await?;
+
+        ensure_not_protected(&source)?;
+        ensure_not_protected(&destination)?;
+
+        // Entries are looked at and moved relative to their whitelisted
+        // directories; without `overwrite` the rename itself refuses to
+        // replace a destination that appears after this check
+        let source_exists = with_tool_root(self.whitelist.as_ref(), &source, |root, path| {
+            Ok(root.symlink_metadata(path).is_ok())
+        })
+        .await?;
+        if !source_exists {
+            return Err(anyhow::anyhow!(
+                "Source '{}' does not exist",
+                source.display()
+            ));
+        }
+
+        let existing = with_tool_root(self.whitelist.as_ref(), &destination, |root, path| {
+            Ok(root.symlink_metadata(path).ok())
+        })
+        .await?;
+        if let Some(existing) = existing {
+            if !overwrite {
+                return Err(anyhow::anyhow!(
+                    "Destination '{}' already exists (set 'overwrite' to replace it)",
+                    destination.display()
+                ));
+            }
+            if existing.is_dir() {
+                return Err(anyhow::anyhow!(
+                    "Destination '{}' is a directory and cannot be overwritten",
+                    destination.display()
+                ));
+            }
+        }
+
+        move_path(
+            tool_root(self.whitelist.as_ref(), &source).await?,
+            &source,
+            tool_root(self.whitelist.as_ref(), &destination).await?,
+            &destination,
+            overwrite,
+        )
+        .await?;
+
+        Ok(format!(
+            "Moved '{}' to '{}'",
+            source.display(),
+            destination.display()
+        ))
+    }
+}
+
+/// Input for `copy_file`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct CopyFileInput {
+    /// The path of the file to copy
+    source: String,
+    /// The path of the new copy
+    destination: String,
+    /// Replace the destination file if it already exists
+    #[serde(default)]
+    overwrite: bool,
+}
+
+#[derive(Debug)]
+pub struct CopyFileTool {
+    whitelist: Option<Arc<RwLock<WhitelistConfig>>>,
+}
+
+impl CopyFileTool {
+    pub fn new() -> Self {
+        Self { whitelist: None }
+    }
+}
+
+#[async_trait]
+impl AgentTool for CopyFileTool {
+    fn set_whitelist(&mut self, whitelist: Arc<RwLock<WhitelistConfig>>) {
+        self.whitelist = Some(whitelist);
+    }
+
+    fn modified_paths(&self, input: &Value) -> Vec<(String, FileOperation)> {
+        path_inputs(input, &["destination"], FileOperation::Copy)
+    }
+
+    fn name(&self) -> &str {
+        "copy_file"
+    }
+
+    fn description(&self) -> &str {
+        "Copy a file to a new location"
+    }
+
+    fn input_schema(&self) -> ToolInputSchema {
+        input_schema_for::<CopyFileInput>()
+    }
+
+    async fn execute(&self, input: Value) -> Result<String> {
+        let input: CopyFileInput = parse_input(input)?;
+        let (source_str, destination_str) = (input.source.as_str(), input.destination.as_str());
+        let overwrite = input.overwrite;
+
+        let source =
+            resolve_tool_path(self.whitelist.as_ref(), source_str, FileOperation::Copy).await?;
+        let destination = resolve_tool_path(
+            self.whitelist.as_ref(),
+            destination_str,
+            FileOperation::Copy,
+        )
+        .await?;
+
+        let destination_exists =
+            with_tool_root(self.whitelist.as_ref(), &destination, |root, path| {
+                Ok(root.symlink_metadata(path).is_ok())
+            })
+            .await?;
+        if destination_exists {
+            if !overwrite {
+                return Err(anyhow::anyhow!(
+                    "Destination '{}' already exists (set 'overwrite' to replace it)",
+                    destination.display()
+                ));
+            }
+            ensure_not_protected(&destination)?;
+        }
+
+        // Both ends are opened beneath their whitelisted directories; without
+        // `overwrite` the destination must still not exist when it is created
+        let access = if overwrite {
+            FileAccess::Write
+        } else {
+            FileAccess::CreateNew
+        };
+        let mut reader = open_tool_file(self.whitelist.as_ref(), &source, FileAccess::Read).await?;
+        if !reader.metadata().await?.is_file() {
+            return Err(anyhow::anyhow!(
+                "Source '{}' is not a file",
+                source.display()
+            ));
+        }
+        let mut writer = open_tool_file(self.whitelist.as_ref(), &destination, access).await?;
+        let copied = async {
+            let bytes = tokio::io::copy(&mut reader, &mut writer).await?;
+            writer.flush().await?;
+            Ok::<_, std::io::Error>(bytes)
+        };
+        match copied.await {
+            Ok(bytes) => Ok(format!(
+                "Copied {} bytes from '{}' to '{}'",
+                bytes,
+                source.display(),
+                destination.display()
+            )),
+            Err(e) => Err(anyhow::anyhow!(
+                "Failed to copy '{}' to '{}': {}",
+                source.display(),
+                destination.display(),
+                e
+            )),
+        }
+    }
+}
+
+/// Input for `delete_file`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct DeleteFileInput {
+    /// The path of the file or directory to delete
+    path: String,
+    /// Required to delete a non-empty directory
+    #[serde(default)]
+    recursive: bool,
+}
+
+#[derive(Debug)]
+pub struct DeleteFileTool {
+    whitelist: Option<Arc<RwLock<WhitelistConfig>>>,
+    trash_dir: PathBuf,
+}
+
+impl DeleteFileTool {
+    pub fn new() -> Self {
+        Self {
+            whitelist: None,
+            trash_dir: std::env::temp_dir()
+                .join("rustic-agent")
+                .join(TRASH_DIR_NAME),
+        }
+    }
+
+    /// Use a specific trash directory (normally `<app data dir>/trash`)
+    pub fn with_trash_dir(mut self, trash_dir: PathBuf) -> Self {
+        self.trash_dir = trash_dir;
+        self
+    }
+
+    /// Move a path beneath `root` into its own entry in the trash, recording its origin
+    async fn move_to_trash(&self, root: SecureRoot, path: &Path) -> Result<PathBuf> {
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| anyhow::anyhow!("Cannot delete '{}'", path.display()))?;
+
+        let entry_dir = self.trash_dir.join(format!(
+            "{}_{}",
+            Utc::now().format("%Y%m%dT%H%M%S"),
+            &uuid::Uuid::new_v4().simple().to_string()[..8]
+        ));
+        tokio::fs::create_dir_all(&entry_dir).await.map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to create trash directory '{}': {}",
+                entry_dir.display(),
+                e
+            )
+        })?;
+
+        // The trash is app-managed, so its entry is opened by name
+        let trash_root = SecureRoot::open(&entry_dir, false)?;
+        let trashed_path = entry_dir.join(file_name);
+        move_path(root, path, trash_root, &trashed_path, false).await?;
+
+        let origin = serde_json::json!({
+            "original_path": path.display().to_string(),
+            "deleted_at": Utc::now().to_rfc3339(),
+        });
+        tokio::fs::write(
+            entry_dir.join(TRASH_ORIGIN_FILE),
+            serde_json::to_string_pretty(&origin)?,
+        )
+        .await?;
+
+        Ok(trashed_path)
+    }
+}
+
+#[async_trait]
+impl AgentTool for DeleteFileTool {
+    fn set_whitelist(&mut self, whitelist: Arc<RwLock<WhitelistConfig>>) {
+        self.whitelist = Some(whitelist);
+    }
+
+    fn modified_paths(&self, input: &Value) -> Vec<(String, FileOperation)> {
+        path_inputs(input, &["path"], FileOperation::Delete)
+    }
+
+    fn name(&self) -> &str {
+        "delete_file"
+    }
+
+    fn description(&self) -> &str {
+        "Delete a file or directory by moving it to the application trash (recoverable)"
+    }
+
+    fn input_schema(&self) -> ToolInputSchema {
+        input_schema_for::<DeleteFileInput>()
+    }
+
+    async fn execute(&self, input: Value) -> Result<String> {
+        let input: DeleteFileInput = parse_input(input)?;
+        let path_str = input.path.as_str();
+        let recursive = input.recursive;
+
+        let safe_path =
+            resolve_tool_path(self.whitelist.as_ref(), path_str, FileOperation::Delete).await?;
+
+        ensure_not_protected(&safe_path)?;
+
+        let non_empty_directory =
+            with_tool_root(self.whitelist.as_ref(), &safe_path, move |root, path| {
+                let metadata = root
+                    .symlink_metadata(path)
+                    .map_err(|e| anyhow::anyhow!("Cannot delete '{}': {}", path.display(), e))?;
+                Ok(metadata.is_dir() && !recursive && !root.read_dir(path)?.is_empty())
+            })
+            .await?;
+
+        if non_empty_directory {
+            return Err(anyhow::anyhow!(
+                "Directory '{}' is not empty (set 'recursive' to delete it)",
+                safe_path.display()
+            ));
+        }
+
+        let root = tool_root(self.whitelist.as_ref(), &safe_path).await?;
+        let trashed_path = self.move_to_trash(root, &safe_path).await?;
+
+        Ok(format!(
+            "Moved '{}' to trash at '{}'",
+            safe_path.display(),
+            trashed_path.display()
+        ))
+    }
+}
+
+/// Input for `create_directory`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct CreateDirectoryInput {
+    /// The path of the directory to create
+    path: String,
+}
+
+#[derive(Debug)]
+pub struct CreateDirectoryTool {
+    whitelist: Option<Arc<RwLock<WhitelistConfig>>>,
+}
+
+impl CreateDirectoryTool {
+    pub fn new() -> Self {
+        Self { whitelist: None }
+    }
+}
+
+#[async_trait]
+impl AgentTool for CreateDirectoryTool {
+    fn set_whitelist(&mut self, whitelist: Arc<RwLock<WhitelistConfig>>) {
+        self.whitelist = Some(whitelist);
+    }
+
+    fn name(&self) -> &str {
+        "create_directory"
+    }
+
+    fn description(&self) -> &str {
+        "Create a directory, including any missing parent directories"
+    }
+
+    fn input_schema(&self) -> ToolInputSchema {
+        input_schema_for::<CreateDirectoryInput>()
+    }
+
+    async fn execute(&self, input: Value) -> Result<String> {
+        let input: CreateDirectoryInput = parse_input(input)?;
+        let path_str = input.path.as_str();
+
+        let safe_path = resolve_tool_path(
+            self.whitelist.as_ref(),
+            path_str,
+            FileOperation::CreateDirectory,
+        )
+        .await?;
+
+        if safe_path.is_dir() {
+            return Ok(format!(
+                "Directory '{}' already exists",
+                safe_path.display()
+            ));
+        }
+
+        match create_tool_directory(self.whitelist.as_ref(), &safe_path).await {
+            Ok(_) => Ok(format!("Created directory '{}'", safe_path.display())),
+            Err(e) => Err(anyhow::anyhow!(
+                "Failed to create directory '{}': {}",
+                safe_path.display(),
+                e
+            )),
+        }
+    }
+}
+
+/// Input for `file_info`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct FileInfoInput {
+    /// The path of the file or directory to inspect
+    path: String,
+}
+
+#[derive(Debug)]
+pub struct FileInfoTool {
+    whitelist: Option<Arc<RwLock<WhitelistConfig>>>,
+}
+
+impl FileInfoTool {
+    pub fn new() -> Self {
+        Self { whitelist: None }
+    }
+}
+
+#[async_trait]
+impl AgentTool for FileInfoTool {
+    fn set_whitelist(&mut self, whitelist: Arc<RwLock<WhitelistConfig>>) {
+        self.whitelist = Some(whitelist);
+    }
+
+    fn name(&self) -> &str {
+        "file_info"
+    }
+
+    fn description(&self) -> &str {
+        "Get metadata for a file or directory (type, size, timestamps, permissions)"
+    }
+
+    fn input_schema(&self) -> ToolInputSchema {
+        input_schema_for::<FileInfoInput>()
+    }
+
+    async fn execute(&self, input: Value) -> Result<String> {
+        let input: FileInfoInput = parse_input(input)?;
+        let path_str = input.path.as_str();
+
+        let safe_path =
+            resolve_tool_path(self.whitelist.as_ref(), path_str, FileOperation::Info).await?;
+
+        let metadata = with_tool_root(self.whitelist.as_ref(), &safe_path, |root, path| {
+            root.symlink_metadata(path)
+        })
+        .await?;
+
+        let file_type = if metadata.is_symlink() {
+            "symlink"
+        } else if metadata.is_dir() {
+            "directory"
+        } else {
+            "file"
+        };
+
+        let to_rfc3339 = |time: std::io::Result<std::time::SystemTime>| -> Option<String> {
+            time.ok().map(|t| DateTime::<Utc>::from(t).to_rfc3339())
+        };
+
+        #[cfg(unix)]
+        let permissions = {
+            use std::os::unix::fs::PermissionsExt;
+            Some(format!("{:o}", metadata.permissions().mode() & 0o777))
+        };
+        #[cfg(not(unix))]
+        let permissions: Option<String> = None;
+
+        let info = serde_json::json!({
+            "path": safe_path.display().to_string(),
+            "name": safe_path.file_name().map(|n| n.to_string_lossy().to_string()),
+            "type": file_type,
+            "size": metadata.len(),
+            "modified": to_rfc3339(metadata.modified()),
+            "created": to_rfc3339(metadata.created()),
+            "accessed": to_rfc3339(metadata.accessed()),
+            "readonly": metadata.permissions().readonly(),
+            "permissions": permissions,
+        });
+
+        Ok(serde_json::to_string_pretty(&info)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::claude::test_support::whitelisted_temp_dir;
+    use serde_json::json;
+
+    fn path_str(path: &Path) -> String {
+        path.to_string_lossy().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_move_file() {
+        let (dir, whitelist) = whitelisted_temp_dir();
+        std::fs::write(dir.join("a.txt"), "hello").unwrap();
+
+        let mut tool = MoveFileTool::new();
+        tool.set_whitelist(whitelist);
+
+        tool.execute(json!({
+            "source": path_str(&dir.join("a.txt")),
+            "destination": path_str(&dir.join("b.txt")),
+        }))
+        .await
+        .unwrap();
+
+        assert!(!dir.join("a.txt").exists());
+        assert_eq!(std::fs::read_to_string(dir.join("b.txt")).unwrap(), "hello");
+    }
+
+    #[tokio::test]
+    async fn test_copy_file_requires_overwrite() {
+        let (dir, whitelist) = whitelisted_temp_dir();
+        std::fs::write(dir.join("a.txt"), "new").unwrap();
+        std::fs::write(dir.join("b.txt"), "old").unwrap();
+
+        let mut tool = CopyFileTool::new();
+        tool.set_whitelist(whitelist);
+
+        let input = json!({
+            "source": path_str(&dir.join("a.txt")),
+            "destination": path_str(&dir.join("b.txt")),
+        });
+        assert!(tool.execute(input).await.is_err());
+        assert_eq!(std::fs::read_to_string(dir.join("b.txt")).unwrap(), "old");
+
+        tool.execute(json!({
+            "source": path_str(&dir.join("a.txt")),
+            "destination": path_str(&dir.join("b.txt")),
+            "overwrite": true,
+        }))
+        .await
+        .unwrap();
+        assert_eq!(std::fs::read_to_string(dir.join("b.txt")).unwrap(), "new");
+    }
+
+    #[tokio::test]
+    async fn test_delete_moves_to_trash() {
+        let (dir, whitelist) = whitelisted_temp_dir();
+        let trash_dir = dir.join("trash-store");
+        std::fs::write(dir.join("stale.txt"), "stale").unwrap();
+
+        let mut tool = DeleteFileTool::new().with_trash_dir(trash_dir.clone());
+        tool.set_whitelist(whitelist);
+
+        tool.execute(json!({"path": path_str(&dir.join("stale.txt"))}))
+            .await
+            .unwrap();
+
+        assert!(!dir.join("stale.txt").exists());
+
+        let entry = std::fs::read_dir(&trash_dir)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        assert_eq!(
+            std::fs::read_to_string(entry.join("stale.txt")).unwrap(),
+            "stale"
+        );
+        assert!(entry.join(TRASH_ORIGIN_FILE).exists());
+    }
+
+    #[tokio::test]
+    async fn test_delete_non_empty_directory_requires_recursive() {
+        let (dir, whitelist) = whitelisted_temp_dir();
+        std::fs::create_dir_all(dir.join("module")).unwrap();
+        std::fs::write(dir.join("module").join("mod.rs"), "").unwrap();
+
+        let mut tool = DeleteFileTool::new().with_trash_dir(dir.join("trash-store"));
+        tool.set_whitelist(whitelist);
+
+        let path = path_str(&dir.join("module"));
+        assert!(tool.execute(json!({"path": path})).await.is_err());
+        assert!(dir.join("module").exists());
+
+        tool.execute(json!({"path": path, "recursive": true}))
+            .await
+            .unwrap();
+        assert!(!dir.join("module").exists());
+    }
+
+    #[tokio::test]
+    async fn test_create_directory_and_file_info() {
+        let (dir, whitelist) = whitelisted_temp_dir();
+        let nested = dir.join("a").join("b");
+
+        let mut create_tool = CreateDirectoryTool::new();
+        create_tool.set_whitelist(whitelist.clone());
+        create_tool
+            .execute(json!({"path": path_str(&nested)}))
+            .await
+            .unwrap();
+        assert!(nested.is_dir());
+
+        let mut info_tool = FileInfoTool::new();
+        info_tool.set_whitelist(whitelist);
+        let info: Value = serde_json::from_str(
+            &info_tool
+                .execute(json!({"path": path_str(&nested)}))
+                .await
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(info["type"], "directory");
+        assert_eq!(info["name"], "b");
+    }
+
+    #[tokio::test]
+    async fn test_denied_operation_is_rejected() {
+        let (dir, whitelist) = whitelisted_temp_dir();
+        std::fs::write(dir.join("keep.txt"), "keep").unwrap();
+        whitelist
+            .write()
+            .await
+            .set_operation_allowed(FileOperation::Delete, false);
+
+        let mut tool = DeleteFileTool::new().with_trash_dir(dir.join("trash-store"));
+        tool.set_whitelist(whitelist);
+
+        let result = tool
+            .execute(json!({"path": path_str(&dir.join("keep.txt"))}))
+            .await;
+        assert!(result.is_err());
+        assert!(dir.join("keep.txt").exists());
+    }
+}
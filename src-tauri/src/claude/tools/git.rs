@@ -0,0 +1,1132 @@
+//! Git integration tools: status, diff, log, show, blame and commit.
+//!
+//! All tools shell out to the local `git` binary against a repository inside
+//! the whitelist and return structured JSON. Only local operations are
+//! exposed: nothing here fetches, pushes or talks to a remote. `git_commit`
+//! is opt-in and always requires user approval before it runs.
+
+use crate::claude::constants::git_limits::{
+    DEFAULT_LOG_COUNT, MAX_LOG_COUNT, MAX_PATCH_BYTES, MAX_REF_LENGTH,
+};
+use crate::claude::tools::{input_schema_for, parse_input, resolve_tool_path, AgentTool};
+use crate::claude::types::ToolInputSchema;
+use crate::claude::whitelist::{FileOperation, WhitelistConfig};
+use anyhow::Result;
+use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::process::Command;
+use tokio::sync::RwLock;
+
+/// Field and record separators used in `--format` strings
+const FIELD_SEP: char = '\x1f';
+const RECORD_SEP: char = '\x1e';
+const COMMIT_FORMAT: &str = "%H%x1f%h%x1f%an%x1f%ae%x1f%aI%x1f%s%x1e";
+
+/// Run git in `repo` and return stdout, failing with git's stderr on a non-zero exit.
+/// Repository config must not be able to run programs: fsmonitor hooks are
+/// disabled and diff output never goes through external diff or textconv drivers.
+async fn run_git(repo: &Path, args: &[&str]) -> Result<String> {
+    let mut command = Command::new("git");
+    command.arg("-C").arg(repo).args([
+        "-c",
+        "core.quotepath=off",
+        "-c",
+        "core.fsmonitor=",
+        "--no-pager",
+    ]);
+    match args.split_first() {
+        Some((subcommand, rest)) if matches!(*subcommand, "diff" | "show" | "log") => {
+            command
+                .arg(subcommand)
+                .args(["--no-ext-diff", "--no-textconv"])
+                .args(rest);
+        }
+        _ => {
+            command.args(args);
+        }
+    }
+    let output = command
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("GIT_OPTIONAL_LOCKS", "0")
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to run git: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow::anyhow!("git {} failed: {}", args[0], stderr.trim()));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Reject revisions that could be parsed as options, refer to remote URLs or
+/// name something other than a commit (`HEAD:path` blobs, `^{tree}` peels)
+fn validate_ref(value: &str) -> Result<&str> {
+    if value.is_empty() || value.len() > MAX_REF_LENGTH {
+        return Err(anyhow::anyhow!(
+            "Invalid revision: must be 1-{} characters",
+            MAX_REF_LENGTH
+        ));
+    }
+    if value.starts_with('-')
+        || value.contains(':')
+        || value.contains("^{")
+        || value.chars().any(|c| c.is_whitespace() || c.is_control())
+    {
+        return Err(anyhow::anyhow!("Invalid revision '{}'", value));
+    }
+    Ok(value)
+}
+
+fn optional_ref(value: Option<&str>) -> Result<Option<&str>> {
+    value.map(validate_ref).transpose()
+}
+
+/// Resolve a revision to the commit it names, so a blob or tree id cannot be
+/// shown in place of a commit
+async fn resolve_commit(repo: &Path, revision: &str) -> Result<String> {
+    let peeled = format!("{}^{{commit}}", revision);
+    run_git(repo, &["rev-parse", "--verify", "--quiet", &peeled])
+        .await
+        .map(|hash| hash.trim().to_string())
+        .map_err(|_| anyhow::anyhow!("Revision '{}' is not a commit", revision))
+}
+
+/// Pathspec for whole-repository output: `scope` minus the changed files the
+/// whitelist would not let the caller read (blocked patterns, list-only
+/// directories, ignored files). `changed` is `--name-only -z` output; the
+/// excluded paths are returned alongside.
+async fn readable_pathspec(
+    whitelist: Option<&Arc<RwLock<WhitelistConfig>>>,
+    repo: &Path,
+    scope: &str,
+    changed: &str,
+) -> (Vec<String>, Vec<String>) {
+    let mut pathspec = vec![scope.to_string()];
+    let mut excluded = Vec::new();
+    for path in changed.split('\0').filter(|path| !path.is_empty()) {
+        let full_path = repo.join(path);
+        if resolve_tool_path(whitelist, &full_path.to_string_lossy(), FileOperation::Read)
+            .await
+            .is_err()
+        {
+            pathspec.push(format!(":(top,exclude,literal){}", path));
+            excluded.push(path.to_string());
+        }
+    }
+    (pathspec, excluded)
+}
+
+/// Resolve the repository root for a tool call. Both the requested directory and
+/// the repository's top level must be readable, so a whitelisted subdirectory
+/// (or a list-only directory) cannot be used to read history.
+async fn resolve_repository(
+    whitelist: Option<&Arc<RwLock<WhitelistConfig>>>,
+    repository: Option<&str>,
+) -> Result<PathBuf> {
+    let repo_str = repository.unwrap_or(".");
+    let dir = resolve_tool_path(whitelist, repo_str, FileOperation::Read).await?;
+    if !dir.is_dir() {
+        return Err(anyhow::anyhow!("'{}' is not a directory", dir.display()));
+    }
+
+    let top_level = run_git(&dir, &["rev-parse", "--show-toplevel"])
+        .await
+        .map_err(|_| anyhow::anyhow!("'{}' is not inside a git repository", dir.display()))?;
+    let top_level = top_level.trim();
+
+    resolve_tool_path(whitelist, top_level, FileOperation::Read).await
+}
+
+/// Resolve a file parameter through the whitelist and make it relative to the repository
+async fn repository_relative_path(
+    whitelist: Option<&Arc<RwLock<WhitelistConfig>>>,
+    repo: &Path,
+    path_str: &str,
+    operation: FileOperation,
+) -> Result<String> {
+    let candidate = if Path::new(path_str).is_absolute() {
+        PathBuf::from(path_str)
+    } else {
+        repo.join(path_str)
+    };
+    let resolved = resolve_tool_path(whitelist, &candidate.to_string_lossy(), operation).await?;
+    let relative = resolved.strip_prefix(repo).map_err(|_| {
+        anyhow::anyhow!(
+            "Path '{}' is outside the repository '{}'",
+            resolved.display(),
+            repo.display()
+        )
+    })?;
+    Ok(relative.to_string_lossy().to_string())
+}
+
+/// Truncate patch text on a line boundary, reporting whether anything was cut
+fn truncate_patch(patch: String) -> (String, bool) {
+    if patch.len() <= MAX_PATCH_BYTES {
+        return (patch, false);
+    }
+    let mut end = MAX_PATCH_BYTES;
+    while !patch.is_char_boundary(end) {
+        end -= 1;
+    }
+    let end = patch[..end].rfind('\n').map(|i| i + 1).unwrap_or(end);
+    (patch[..end].to_string(), true)
+}
+
+/// Parse `git diff --numstat` output into per-file change counts
+fn parse_numstat(output: &str) -> Vec<Value> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, '\t');
+            let additions = parts.next()?;
+            let deletions = parts.next()?;
+            let path = parts.next()?;
+            // Binary files report "-" for both counts
+            Some(json!({
+                "path": path,
+                "additions": additions.parse::<u64>().ok(),
+                "deletions": deletions.parse::<u64>().ok(),
+                "binary": additions == "-",
+            }))
+        })
+        .collect()
+}
+
+/// Parse log output produced with `COMMIT_FORMAT`
+fn parse_commits(output: &str) -> Vec<Value> {
+    output
+        .split(RECORD_SEP)
+        .map(|record| record.trim_start_matches('\n'))
+        .filter(|record| !record.is_empty())
+        .filter_map(|record| {
+            let fields: Vec<&str> = record.split(FIELD_SEP).collect();
+            if fields.len() < 6 {
+                return None;
+            }
+            Some(json!({
+                "hash": fields[0],
+                "short_hash": fields[1],
+                "author": fields[2],
+                "email": fields[3],
+                "date": fields[4],
+                "subject": fields[5].trim_end(),
+            }))
+        })
+        .collect()
+}
+
+/// Parse `git status --porcelain=v1 --branch -z` output
+fn parse_status(output: &str) -> Value {
+    let mut branch = Value::Null;
+    let mut upstream = Value::Null;
+    let mut ahead = 0u64;
+    let mut behind = 0u64;
+    let mut files = Vec::new();
+
+    let mut entries = output.split('\0').filter(|e| !e.is_empty());
+    while let Some(entry) = entries.next() {
+        if let Some(header) = entry.strip_prefix("## ") {
+            let (names, tracking) = match header.split_once(" [") {
+                Some((names, tracking)) => (names, tracking.trim_end_matches(']')),
+                None => (header, ""),
+            };
+            match names.split_once("...") {
+                Some((local, remote)) => {
+                    branch = json!(local);
+                    upstream = json!(remote);
+                }
+                None => branch = json!(names.trim_start_matches("No commits yet on ")),
+            }
+            for part in tracking.split(", ") {
+                if let Some(n) = part.strip_prefix("ahead ") {
+                    ahead = n.parse().unwrap_or(0);
+                } else if let Some(n) = part.strip_prefix("behind ") {
+                    behind = n.parse().unwrap_or(0);
+                }
+            }
+            continue;
+        }
+
+        if entry.len() < 4 {
+            continue;
+        }
+        let index_status = &entry[0..1];
+        let worktree_status = &entry[1..2];
+        let path = &entry[3..];
+        // Renames and copies are followed by the original path
+        let original_path = if matches!(index_status, "R" | "C") {
+            entries.next()
+        } else {
+            None
+        };
+
+        files.push(json!({
+            "path": path,
+            "index_status": index_status,
+            "worktree_status": worktree_status,
+            "original_path": original_path,
+            "staged": !matches!(index_status, " " | "?" | "!"),
+            "untracked": index_status == "?",
+        }));
+    }
+
+    json!({
+        "branch": branch,
+        "upstream": upstream,
+        "ahead": ahead,
+        "behind": behind,
+        "clean": files.is_empty(),
+        "files": files,
+    })
+}
+
+/// Parse `git blame --porcelain` output into one entry per line
+fn parse_blame(output: &str) -> Vec<Value> {
+    let mut commits: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut lines = Vec::new();
+    let mut current: Option<(String, u64)> = None;
+
+    for line in output.lines() {
+        if let Some(content) = line.strip_prefix('\t') {
+            if let Some((hash, line_number)) = current.take() {
+                let info = commits.get(&hash);
+                let field = |key: &str| info.and_then(|i| i.get(key)).cloned();
+                let time = field("author-time")
+                    .and_then(|t| t.parse::<i64>().ok())
+                    .and_then(|t| chrono::DateTime::from_timestamp(t, 0))
+                    .map(|t| t.to_rfc3339());
+                lines.push(json!({
+                    "line": line_number,
+                    "commit": hash,
+                    "author": field("author"),
+                    "date": time,
+                    "summary": field("summary"),
+                    "content": content,
+                }));
+            }
+            continue;
+        }
+
+        let mut parts = line.split(' ');
+        let first = parts.next().unwrap_or("");
+        if first.len() == 40 && first.chars().all(|c| c.is_ascii_hexdigit()) {
+            let final_line = parts.nth(1).and_then(|n| n.parse().ok()).unwrap_or(0);
+            commits.entry(first.to_string()).or_default();
+            current = Some((first.to_string(), final_line));
+        } else if let Some((hash, _)) = &current {
+            if let Some((key, value)) = line.split_once(' ') {
+                if let Some(info) = commits.get_mut(hash) {
+                    info.insert(key.to_string(), value.to_string());
+                }
+            }
+        }
+    }
+
+    lines
+}
+
+fn to_json_string(value: Value) -> Result<String> {
+    serde_json::to_string_pretty(&value)
+        .map_err(|e| anyhow::anyhow!("Failed to serialize git result: {}", e))
+}
+
+/// Input for `git_status`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct GitStatusInput {
+    /// Path to the repository or a directory inside it (default: current directory)
+    repository: Option<String>,
+}
+
+/// Input for `git_diff`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct GitDiffInput {
+    /// Path to the repository or a directory inside it (default: current directory)
+    repository: Option<String>,
+    /// Show staged changes instead of unstaged ones
+    #[serde(default)]
+    staged: bool,
+    /// Base revision to diff from (e.g. 'main' or 'HEAD~3')
+    from: Option<String>,
+    /// Revision to diff to; requires 'from' (default: working tree)
+    to: Option<String>,
+    /// Limit the diff to this file or directory
+    path: Option<String>,
+}
+
+/// Input for `git_log`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct GitLogInput {
+    /// Path to the repository or a directory inside it (default: current directory)
+    repository: Option<String>,
+    /// Maximum number of commits to return
+    #[serde(default = "default_log_count")]
+    #[schemars(range(min = 1, max = "MAX_LOG_COUNT"))]
+    max_count: usize,
+    /// Revision or range to list (default: HEAD)
+    revision: Option<String>,
+    /// Only list commits touching this file or directory
+    path: Option<String>,
+}
+
+fn default_log_count() -> usize {
+    DEFAULT_LOG_COUNT
+}
+
+/// Input for `git_show`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct GitShowInput {
+    /// Path to the repository or a directory inside it (default: current directory)
+    repository: Option<String>,
+    /// Commit to show (default: HEAD)
+    revision: Option<String>,
+}
+
+/// Input for `git_blame`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct GitBlameInput {
+    /// Path to the repository or a directory inside it (default: current directory)
+    repository: Option<String>,
+    /// The file to blame, absolute or relative to the repository
+    path: String,
+    /// First line to blame (1-based, default: 1)
+    #[schemars(range(min = 1))]
+    start_line: Option<u64>,
+    /// Last line to blame (default: end of file)
+    #[schemars(range(min = 1))]
+    end_line: Option<u64>,
+    /// Blame the file as of this revision (default: working tree)
+    revision: Option<String>,
+}
+
+/// Input for `git_commit`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct GitCommitInput {
+    /// Path to the repository or a directory inside it (default: current directory)
+    repository: Option<String>,
+    /// The commit message
+    #[schemars(length(min = 1))]
+    message: String,
+    /// Files to stage before committing
+    #[serde(default)]
+    paths: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct GitStatusTool {
+    whitelist: Option<Arc<RwLock<WhitelistConfig>>>,
+}
+
+impl GitStatusTool {
+    pub fn new() -> Self {
+        Self { whitelist: None }
+    }
+}
+
+#[async_trait]
+impl AgentTool for GitStatusTool {
+    fn set_whitelist(&mut self, whitelist: Arc<RwLock<WhitelistConfig>>) {
+        self.whitelist = Some(whitelist);
+    }
+
+    fn name(&self) -> &str {
+        "git_status"
+    }
+
+    fn description(&self) -> &str {
+        "Show the current branch and the staged, unstaged and untracked files of a git repository"
+    }
+
+    fn input_schema(&self) -> ToolInputSchema {
+        input_schema_for::<GitStatusInput>()
+    }
+
+    async fn execute(&self, input: Value) -> Result<String> {
+        let input: GitStatusInput = parse_input(input)?;
+        let repo = resolve_repository(self.whitelist.as_ref(), input.repository.as_deref()).await?;
+        let output = run_git(
+            &repo,
+            &[
+                "status",
+                "--porcelain=v1",
+                "--branch",
+                "-z",
+                "--untracked-files=all",
+            ],
+        )
+        .await?;
+
+        let mut status = parse_status(&output);
+        status["repository"] = json!(repo.to_string_lossy());
+        to_json_string(status)
+    }
+}
+
+#[derive(Debug)]
+pub struct GitDiffTool {
+    whitelist: Option<Arc<RwLock<WhitelistConfig>>>,
+}
+
+impl GitDiffTool {
+    pub fn new() -> Self {
+        Self { whitelist: None }
+    }
+}
+
+#[async_trait]
+impl AgentTool for GitDiffTool {
+    fn set_whitelist(&mut self, whitelist: Arc<RwLock<WhitelistConfig>>) {
+        self.whitelist = Some(whitelist);
+    }
+
+    fn name(&self) -> &str {
+        "git_diff"
+    }
+
+    fn description(&self) -> &str {
+        "Show changes in a git repository: unstaged changes (default), staged changes, or the difference between two revisions"
+    }
+
+    fn input_schema(&self) -> ToolInputSchema {
+        input_schema_for::<GitDiffInput>()
+    }
+
+    async fn execute(&self, input: Value) -> Result<String> {
+        let input: GitDiffInput = parse_input(input)?;
+        let repo = resolve_repository(self.whitelist.as_ref(), input.repository.as_deref()).await?;
+        let staged = input.staged;
+        let from_ref = optional_ref(input.from.as_deref())?;
+        let to_ref = optional_ref(input.to.as_deref())?;
+        let from = match from_ref {
+            Some(revision) => Some(resolve_commit(&repo, revision).await?),
+            None => None,
+        };
+        let to = match to_ref {
+            Some(revision) => Some(resolve_commit(&repo, revision).await?),
+            None => None,
+        };
+
+        if to.is_some() && from.is_none() {
+            return Err(anyhow::anyhow!("'to' requires 'from' to be set"));
+        }
+        if staged && from.is_some() {
+            return Err(anyhow::anyhow!(
+                "'staged' cannot be combined with revisions"
+            ));
+        }
+
+        let mut args: Vec<&str> = vec!["diff"];
+        if staged {
+            args.push("--cached");
+        }
+        args.extend(from.as_deref());
+        args.extend(to.as_deref());
+
+        let path = match input.path.as_deref() {
+            Some(p) => Some(
+                repository_relative_path(self.whitelist.as_ref(), &repo, p, FileOperation::Read)
+                    .await?,
+            ),
+            None => None,
+        };
+
+        let scope = path.as_deref().unwrap_or(".");
+        let mut list_args = args.clone();
+        list_args.extend(["--name-only", "-z", "--no-renames", "--", scope]);
+        let changed = run_git(&repo, &list_args).await?;
+        let (pathspec, excluded) =
+            readable_pathspec(self.whitelist.as_ref(), &repo, scope, &changed).await;
+
+        let mut numstat_args = args.clone();
+        numstat_args.extend(["--numstat", "--"]);
+        numstat_args.extend(pathspec.iter().map(String::as_str));
+        args.push("--");
+        args.extend(pathspec.iter().map(String::as_str));
+
+        let files = parse_numstat(&run_git(&repo, &numstat_args).await?);
+        let (patch, truncated) = truncate_patch(run_git(&repo, &args).await?);
+
+        to_json_string(json!({
+            "repository": repo.to_string_lossy(),
+            "mode": if staged { "staged" } else if from.is_some() { "revisions" } else { "unstaged" },
+            "from": from_ref,
+            "to": to_ref,
+            "files": files,
+            "excluded": excluded,
+            "patch": patch,
+            "truncated": truncated,
+        }))
+    }
+}
+
+#[derive(Debug)]
+pub struct GitLogTool {
+    whitelist: Option<Arc<RwLock<WhitelistConfig>>>,
+}
+
+impl GitLogTool {
+    pub fn new() -> Self {
+        Self { whitelist: None }
+    }
+}
+
+#[async_trait]
+impl AgentTool for GitLogTool {
+    fn set_whitelist(&mut self, whitelist: Arc<RwLock<WhitelistConfig>>) {
+        self.whitelist = Some(whitelist);
+    }
+
+    fn name(&self) -> &str {
+        "git_log"
+    }
+
+    fn description(&self) -> &str {
+        "List recent commits of a git repository, optionally limited to a revision or path"
+    }
+
+    fn input_schema(&self) -> ToolInputSchema {
+        input_schema_for::<GitLogInput>()
+    }
+
+    async fn execute(&self, input: Value) -> Result<String> {
+        let input: GitLogInput = parse_input(input)?;
+        let repo = resolve_repository(self.whitelist.as_ref(), input.repository.as_deref()).await?;
+        let max_count = input.max_count.clamp(1, MAX_LOG_COUNT);
+        let revision = optional_ref(input.revision.as_deref())?;
+
+        let max_count_arg = format!("--max-count={}", max_count);
+        let format_arg = format!("--format={}", COMMIT_FORMAT);
+        let mut args = vec!["log", max_count_arg.as_str(), format_arg.as_str()];
+        args.extend(revision);
+
+        let path = match input.path.as_deref() {
+            Some(p) => Some(
+                repository_relative_path(self.whitelist.as_ref(), &repo, p, FileOperation::Read)
+                    .await?,
+            ),
+            None => None,
+        };
+        if let Some(p) = &path {
+            args.extend(["--", p.as_str()]);
+        }
+
+        let commits = parse_commits(&run_git(&repo, &args).await?);
+        to_json_string(json!({
+            "repository": repo.to_string_lossy(),
+            "count": commits.len(),
+            "commits": commits,
+        }))
+    }
+}
+
+#[derive(Debug)]
+pub struct GitShowTool {
+    whitelist: Option<Arc<RwLock<WhitelistConfig>>>,
+}
+
+impl GitShowTool {
+    pub fn new() -> Self {
+        Self { whitelist: None }
+    }
+}
+
+#[async_trait]
+impl AgentTool for GitShowTool {
+    fn set_whitelist(&mut self, whitelist: Arc<RwLock<WhitelistConfig>>) {
+        self.whitelist = Some(whitelist);
+    }
+
+    fn name(&self) -> &str {
+        "git_show"
+    }
+
+    fn description(&self) -> &str {
+        "Show a commit's metadata, changed files and patch"
+    }
+
+    fn input_schema(&self) -> ToolInputSchema {
+        input_schema_for::<GitShowInput>()
+    }
+
+    async fn execute(&self, input: Value) -> Result<String> {
+        let input: GitShowInput = parse_input(input)?;
+        let repo = resolve_repository(self.whitelist.as_ref(), input.repository.as_deref()).await?;
+        let revision = optional_ref(input.revision.as_deref())?.unwrap_or("HEAD");
+        let hash = resolve_commit(&repo, revision).await?;
+        let hash = hash.as_str();
+
+        let format_arg = format!("--format={}", COMMIT_FORMAT);
+        let commit =
+            parse_commits(&run_git(&repo, &["show", "--no-patch", &format_arg, hash]).await?)
+                .into_iter()
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("Revision '{}' is not a commit", revision))?;
+        let body = run_git(&repo, &["show", "--no-patch", "--format=%b", hash]).await?;
+
+        let changed = run_git(
+            &repo,
+            &[
+                "show",
+                "--name-only",
+                "-z",
+                "--no-renames",
+                "--format=",
+                hash,
+            ],
+        )
+        .await?;
+        let (pathspec, excluded) =
+            readable_pathspec(self.whitelist.as_ref(), &repo, ".", &changed).await;
+        let mut numstat_args = vec!["show", "--numstat", "--format=", hash, "--"];
+        numstat_args.extend(pathspec.iter().map(String::as_str));
+        let mut patch_args = vec!["show", "--format=", hash, "--"];
+        patch_args.extend(pathspec.iter().map(String::as_str));
+
+        let files = parse_numstat(&run_git(&repo, &numstat_args).await?);
+        let (patch, truncated) = truncate_patch(run_git(&repo, &patch_args).await?);
+
+        to_json_string(json!({
+            "repository": repo.to_string_lossy(),
+            "commit": commit,
+            "body": body.trim(),
+            "files": files,
+            "excluded": excluded,
+            "patch": patch,
+            "truncated": truncated,
+        }))
+    }
+}
+
+#[derive(Debug)]
+pub struct GitBlameTool {
+    whitelist: Option<Arc<RwLock<WhitelistConfig>>>,
+}
+
+impl GitBlameTool {
+    pub fn new() -> Self {
+        Self { whitelist: None }
+    }
+}
+
+#[async_trait]
+impl AgentTool for GitBlameTool {
+    fn set_whitelist(&mut self, whitelist: Arc<RwLock<WhitelistConfig>>) {
+        self.whitelist = Some(whitelist);
+    }
+
+    fn name(&self) -> &str {
+        "git_blame"
+    }
+
+    fn description(&self) -> &str {
+        "Show which commit and author last changed each line of a file"
+    }
+
+    fn input_schema(&self) -> ToolInputSchema {
+        input_schema_for::<GitBlameInput>()
+    }
+
+    async fn execute(&self, input: Value) -> Result<String> {
+        let input: GitBlameInput = parse_input(input)?;
+        let repo = resolve_repository(self.whitelist.as_ref(), input.repository.as_deref()).await?;
+        let path = repository_relative_path(
+            self.whitelist.as_ref(),
+            &repo,
+            &input.path,
+            FileOperation::Read,
+        )
+        .await?;
+        let revision = optional_ref(input.revision.as_deref())?;
+
+        let range = match (input.start_line, input.end_line) {
+            (None, None) => None,
+            (start, end) => {
+                let start = start.unwrap_or(1).max(1);
+                if end.is_some_and(|e| e < start) {
+                    return Err(anyhow::anyhow!(
+                        "'end_line' must not be before 'start_line'"
+                    ));
+                }
+                Some(match end {
+                    Some(end) => format!("-L{},{}", start, end),
+                    None => format!("-L{},", start),
+                })
+            }
+        };
+
+        let mut args = vec!["blame", "--porcelain"];
+        if let Some(range) = &range {
+            args.push(range.as_str());
+        }
+        args.extend(revision);
+        args.extend(["--", path.as_str()]);
+
+        let lines = parse_blame(&run_git(&repo, &args).await?);
+        to_json_string(json!({
+            "repository": repo.to_string_lossy(),
+            "path": path,
+            "lines": lines,
+        }))
+    }
+}
+
+#[derive(Debug)]
+pub struct GitCommitTool {
+    whitelist: Option<Arc<RwLock<WhitelistConfig>>>,
+}
+
+impl GitCommitTool {
+    pub fn new() -> Self {
+        Self { whitelist: None }
+    }
+}
+
+#[async_trait]
+impl AgentTool for GitCommitTool {
+    fn set_whitelist(&mut self, whitelist: Arc<RwLock<WhitelistConfig>>) {
+        self.whitelist = Some(whitelist);
+    }
+
+    fn name(&self) -> &str {
+        "git_commit"
+    }
+
+    fn description(&self) -> &str {
+        "Create a commit from the staged changes, optionally staging the given paths first. Requires user approval."
+    }
+
+    fn input_schema(&self) -> ToolInputSchema {
+        input_schema_for::<GitCommitInput>()
+    }
+
+    fn requires_approval(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, input: Value) -> Result<String> {
+        let input: GitCommitInput = parse_input(input)?;
+        let repo = resolve_repository(self.whitelist.as_ref(), input.repository.as_deref()).await?;
+        let message = input.message.trim();
+        if message.is_empty() {
+            return Err(anyhow::anyhow!("Commit message cannot be empty"));
+        }
+
+        // Committing writes to the repository, so require write access to its root
+        resolve_tool_path(
+            self.whitelist.as_ref(),
+            &repo.to_string_lossy(),
+            FileOperation::Write,
+        )
+        .await?;
+
+        let mut paths = Vec::new();
+        for path_str in &input.paths {
+            paths.push(
+                repository_relative_path(
+                    self.whitelist.as_ref(),
+                    &repo,
+                    path_str,
+                    FileOperation::Write,
+                )
+                .await?,
+            );
+        }
+
+        if !paths.is_empty() {
+            let mut args = vec!["add", "--"];
+            args.extend(paths.iter().map(|p| p.as_str()));
+            run_git(&repo, &args).await?;
+        }
+
+        run_git(&repo, &["commit", "-m", message]).await?;
+
+        let format_arg = format!("--format={}", COMMIT_FORMAT);
+        let commit =
+            parse_commits(&run_git(&repo, &["show", "--no-patch", &format_arg, "HEAD"]).await?)
+                .into_iter()
+                .next();
+        let files =
+            parse_numstat(&run_git(&repo, &["show", "--numstat", "--format=", "HEAD"]).await?);
+
+        to_json_string(json!({
+            "repository": repo.to_string_lossy(),
+            "commit": commit,
+            "files": files,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::claude::test_support::{whitelisted_temp_dir, TempDir};
+    use crate::claude::tools::{ToolExecutionContext, ToolExecutionEngine, ToolRequest};
+
+    /// Create a whitelisted temp directory containing a git repository with one commit
+    async fn setup_test_repo() -> (TempDir, Arc<RwLock<WhitelistConfig>>) {
+        let (dir, whitelist) = whitelisted_temp_dir();
+        run_git(&dir, &["init", "-q", "-b", "main"]).await.unwrap();
+        run_git(&dir, &["config", "user.name", "Test User"])
+            .await
+            .unwrap();
+        run_git(&dir, &["config", "user.email", "test@example.com"])
+            .await
+            .unwrap();
+        std::fs::write(dir.join("a.txt"), "one\ntwo\n").unwrap();
+        run_git(&dir, &["add", "a.txt"]).await.unwrap();
+        run_git(&dir, &["commit", "-q", "-m", "Initial commit"])
+            .await
+            .unwrap();
+        (dir, whitelist)
+    }
+
+    async fn execute_json<T: AgentTool>(
+        mut tool: T,
+        whitelist: &Arc<RwLock<WhitelistConfig>>,
+        input: Value,
+    ) -> Value {
+        tool.set_whitelist(whitelist.clone());
+        serde_json::from_str(&tool.execute(input).await.unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_git_status_and_diff() {
+        let (dir, whitelist) = setup_test_repo().await;
+        let repo = dir.to_string_lossy().to_string();
+        std::fs::write(dir.join("a.txt"), "one\nTWO\n").unwrap();
+        std::fs::write(dir.join("new.txt"), "new").unwrap();
+
+        let status = execute_json(
+            GitStatusTool::new(),
+            &whitelist,
+            json!({ "repository": repo }),
+        )
+        .await;
+        assert_eq!(status["branch"], "main");
+        assert_eq!(status["clean"], false);
+        let files = status["files"].as_array().unwrap();
+        assert!(files
+            .iter()
+            .any(|f| f["path"] == "a.txt" && f["worktree_status"] == "M"));
+        assert!(files
+            .iter()
+            .any(|f| f["path"] == "new.txt" && f["untracked"] == true));
+
+        let diff = execute_json(
+            GitDiffTool::new(),
+            &whitelist,
+            json!({ "repository": repo }),
+        )
+        .await;
+        assert_eq!(diff["mode"], "unstaged");
+        assert_eq!(diff["files"][0]["path"], "a.txt");
+        assert_eq!(diff["files"][0]["additions"], 1);
+        assert!(diff["patch"].as_str().unwrap().contains("+TWO"));
+
+        let staged = execute_json(
+            GitDiffTool::new(),
+            &whitelist,
+            json!({ "repository": repo, "staged": true }),
+        )
+        .await;
+        assert!(staged["files"].as_array().unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_repository_config_cannot_run_programs() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (dir, whitelist) = setup_test_repo().await;
+        let repo = dir.to_string_lossy().to_string();
+        let marker = dir.join(".git").join("ran");
+        let script = dir.join(".git").join("hook.sh");
+        std::fs::write(
+            &script,
+            format!("#!/bin/sh\ntouch '{}'\n", marker.display()),
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let script = script.to_string_lossy().to_string();
+        for key in ["core.fsmonitor", "diff.external"] {
+            run_git(&dir, &["config", key, &script]).await.unwrap();
+        }
+        std::fs::write(dir.join("a.txt"), "one\nTWO\n").unwrap();
+
+        execute_json(
+            GitStatusTool::new(),
+            &whitelist,
+            json!({ "repository": repo }),
+        )
+        .await;
+        let diff = execute_json(
+            GitDiffTool::new(),
+            &whitelist,
+            json!({ "repository": repo }),
+        )
+        .await;
+        execute_json(
+            GitShowTool::new(),
+            &whitelist,
+            json!({ "repository": repo }),
+        )
+        .await;
+
+        assert!(diff["patch"].as_str().unwrap().contains("+TWO"));
+        assert!(!marker.exists());
+    }
+
+    #[tokio::test]
+    async fn test_git_log_show_and_blame() {
+        let (dir, whitelist) = setup_test_repo().await;
+        let repo = dir.to_string_lossy().to_string();
+
+        let log = execute_json(GitLogTool::new(), &whitelist, json!({ "repository": repo })).await;
+        assert_eq!(log["count"], 1);
+        assert_eq!(log["commits"][0]["subject"], "Initial commit");
+        assert_eq!(log["commits"][0]["author"], "Test User");
+
+        let show = execute_json(
+            GitShowTool::new(),
+            &whitelist,
+            json!({ "repository": repo }),
+        )
+        .await;
+        assert_eq!(show["commit"]["subject"], "Initial commit");
+        assert_eq!(show["files"][0]["path"], "a.txt");
+        assert!(show["patch"].as_str().unwrap().contains("+one"));
+
+        let blame = execute_json(
+            GitBlameTool::new(),
+            &whitelist,
+            json!({ "repository": repo, "path": "a.txt", "start_line": 2, "end_line": 2 }),
+        )
+        .await;
+        let lines = blame["lines"].as_array().unwrap();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["line"], 2);
+        assert_eq!(lines[0]["content"], "two");
+        assert_eq!(lines[0]["author"], "Test User");
+        assert_eq!(lines[0]["summary"], "Initial commit");
+    }
+
+    #[tokio::test]
+    async fn test_git_rejects_option_like_revisions_and_outside_repos() {
+        let (dir, whitelist) = setup_test_repo().await;
+        let mut tool = GitLogTool::new();
+        tool.set_whitelist(whitelist);
+
+        let result = tool
+            .execute(json!({
+                "repository": dir.to_string_lossy(),
+                "revision": "--output=/tmp/x",
+            }))
+            .await;
+        assert!(result.is_err());
+
+        let result = tool
+            .execute(json!({ "repository": std::env::temp_dir().to_string_lossy() }))
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_git_show_and_diff_keep_unreadable_files_out() {
+        let (dir, whitelist) = setup_test_repo().await;
+        let repo = dir.to_string_lossy().to_string();
+        std::fs::write(dir.join(".env"), "API_KEY=committed-secret\n").unwrap();
+        std::fs::write(dir.join("a.txt"), "one\ntwo\nthree\n").unwrap();
+        run_git(&dir, &["add", ".env", "a.txt"]).await.unwrap();
+        run_git(&dir, &["commit", "-q", "-m", "Add config"])
+            .await
+            .unwrap();
+
+        // Blob and tree revisions would print file contents without a path check
+        let mut show_tool = GitShowTool::new();
+        show_tool.set_whitelist(whitelist.clone());
+        for revision in ["HEAD:.env", "HEAD^{tree}"] {
+            let result = show_tool
+                .execute(json!({ "repository": repo, "revision": revision }))
+                .await;
+            assert!(result.is_err(), "{} was accepted", revision);
+        }
+        let blob = run_git(&dir, &["rev-parse", "HEAD:.env"]).await.unwrap();
+        let result = show_tool
+            .execute(json!({ "repository": repo, "revision": blob.trim() }))
+            .await;
+        assert!(result.unwrap_err().to_string().contains("not a commit"));
+
+        let show = execute_json(
+            GitShowTool::new(),
+            &whitelist,
+            json!({ "repository": repo }),
+        )
+        .await;
+        assert_eq!(show["excluded"], json!([".env"]));
+        assert_eq!(show["files"].as_array().unwrap().len(), 1);
+        assert!(!show["patch"].as_str().unwrap().contains("committed-secret"));
+
+        std::fs::write(dir.join(".env"), "API_KEY=working-secret\n").unwrap();
+        let diff = execute_json(
+            GitDiffTool::new(),
+            &whitelist,
+            json!({ "repository": repo }),
+        )
+        .await;
+        assert_eq!(diff["excluded"], json!([".env"]));
+        assert!(!diff["patch"].as_str().unwrap().contains("secret"));
+    }
+
+    #[tokio::test]
+    async fn test_git_commit() {
+        let (dir, whitelist) = setup_test_repo().await;
+        std::fs::write(dir.join("b.txt"), "b").unwrap();
+
+        let result = execute_json(
+            GitCommitTool::new(),
+            &whitelist,
+            json!({
+                "repository": dir.to_string_lossy(),
+                "message": "Add b",
+                "paths": ["b.txt"],
+            }),
+        )
+        .await;
+        assert_eq!(result["commit"]["subject"], "Add b");
+        assert_eq!(result["files"][0]["path"], "b.txt");
+    }
+
+    #[tokio::test]
+    async fn test_engine_refuses_unapproved_git_commit() {
+        let (dir, whitelist) = setup_test_repo().await;
+        let mut engine = ToolExecutionEngine::new();
+        engine.register_tool(Arc::new(GitCommitTool::new()));
+
+        let input = json!({ "repository": dir.to_string_lossy(), "message": "Nope" });
+        let result = engine
+            .execute_single_tool(
+                ToolRequest::new("git_commit".to_string(), input.clone()).with_max_retries(0),
+                ToolExecutionContext::new("git_commit".to_string(), input, whitelist),
+            )
+            .await
+            .unwrap();
+
+        assert!(result.is_error());
+        let log = run_git(&dir, &["log", "--format=%s"]).await.unwrap();
+        assert!(!log.contains("Nope"));
+    }
+}
@@ -0,0 +1,507 @@
+//! Persistent history of tool executions.
+//!
+//! Every call the execution engine finishes is appended to a JSON Lines file
+//! in the app data directory, so the history outlives the client that made
+//! the call. Inputs are stored redacted, next to a hash of the full input.
+
+use crate::claude::{
+    constants::execution_history::{
+        MAX_HISTORY_RECORDS, MAX_INPUT_VALUE_CHARS, REDACTED_INPUT_KEYS,
+    },
+    tools::{
+        chain::ExecutionStats,
+        execution::{ToolExecutionContext, ToolExecutionResult, ToolExecutionStatus},
+    },
+};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// How a recorded call ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionOutcome {
+    Success,
+    PartialSuccess,
+    Failed,
+    Timeout,
+    Cancelled,
+    Skipped,
+}
+
+impl ExecutionOutcome {
+    pub fn is_success(self) -> bool {
+        matches!(self, Self::Success | Self::PartialSuccess)
+    }
+}
+
+impl From<&ToolExecutionStatus> for ExecutionOutcome {
+    fn from(status: &ToolExecutionStatus) -> Self {
+        match status {
+            ToolExecutionStatus::Success => Self::Success,
+            ToolExecutionStatus::PartialSuccess { .. } => Self::PartialSuccess,
+            ToolExecutionStatus::Failed { .. } => Self::Failed,
+            ToolExecutionStatus::Timeout => Self::Timeout,
+            ToolExecutionStatus::Cancelled => Self::Cancelled,
+            ToolExecutionStatus::Skipped { .. } => Self::Skipped,
+        }
+    }
+}
+
+/// One finished tool call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionRecord {
+    pub execution_id: String,
+    pub timestamp: DateTime<Utc>,
+    pub tool_name: String,
+    /// Conversation or workflow the call was made for
+    pub session_id: Option<String>,
+    /// Chain the call was a step of, if any
+    pub chain_id: Option<String>,
+    /// SHA-256 of the full input, to spot repeated calls without storing them
+    pub input_hash: String,
+    /// Input with long strings shortened and sensitive fields removed
+    pub input: Value,
+    pub outcome: ExecutionOutcome,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+    /// Recovery actions taken before the call ended
+    pub recoveries: Vec<String>,
+}
+
+impl ExecutionRecord {
+    pub fn new(
+        context: &ToolExecutionContext,
+        result: &ToolExecutionResult,
+        session_id: Option<String>,
+    ) -> Self {
+        let error = match &result.status {
+            ToolExecutionStatus::Failed { error, .. } => Some(error.message.clone()),
+            ToolExecutionStatus::Timeout | ToolExecutionStatus::Cancelled => {
+                result.error_context.clone()
+            }
+            ToolExecutionStatus::Skipped { reason } => Some(reason.clone()),
+            _ => None,
+        };
+
+        Self {
+            execution_id: context.execution_id.clone(),
+            timestamp: Utc::now(),
+            tool_name: context.tool_name.clone(),
+            session_id,
+            chain_id: context.chain_id.clone(),
+            input_hash: format!("{:x}", Sha256::digest(context.input.to_string().as_bytes())),
+            input: redact_input(&context.input),
+            outcome: ExecutionOutcome::from(&result.status),
+            error,
+            duration_ms: result.metadata.execution_time.as_millis() as u64,
+            recoveries: result
+                .metadata
+                .recoveries
+                .iter()
+                .map(|recovery| recovery.description.clone())
+                .collect(),
+        }
+    }
+
+    /// A call the engine refused or could not run (unknown tool, denied
+    /// approval, open breaker), recorded as failed with the engine's error
+    pub fn rejected(
+        context: &ToolExecutionContext,
+        error: String,
+        duration: Duration,
+        session_id: Option<String>,
+    ) -> Self {
+        Self {
+            execution_id: context.execution_id.clone(),
+            timestamp: Utc::now(),
+            tool_name: context.tool_name.clone(),
+            session_id,
+            chain_id: context.chain_id.clone(),
+            input_hash: format!("{:x}", Sha256::digest(context.input.to_string().as_bytes())),
+            input: redact_input(&context.input),
+            outcome: ExecutionOutcome::Failed,
+            error: Some(error),
+            duration_ms: duration.as_millis() as u64,
+            recoveries: Vec::new(),
+        }
+    }
+}
+
+/// Drop the values of sensitive fields and shorten long strings
+fn redact_input(value: &Value) -> Value {
+    match value {
+        Value::String(text) if text.chars().count() > MAX_INPUT_VALUE_CHARS => {
+            let prefix: String = text.chars().take(MAX_INPUT_VALUE_CHARS).collect();
+            Value::String(format!("{}… ({} chars)", prefix, text.chars().count()))
+        }
+        Value::Array(items) => Value::Array(items.iter().map(redact_input).collect()),
+        Value::Object(object) => Value::Object(
+            object
+                .iter()
+                .map(|(key, value)| {
+                    let redacted = match value {
+                        Value::String(text)
+                            if REDACTED_INPUT_KEYS.contains(&key.to_lowercase().as_str()) =>
+                        {
+                            Value::String(format!("[redacted: {} chars]", text.chars().count()))
+                        }
+                        value => redact_input(value),
+                    };
+                    (key.clone(), redacted)
+                })
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Which records a query returns. Every field that is set must match.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryFilter {
+    pub tool_name: Option<String>,
+    pub session_id: Option<String>,
+    pub outcome: Option<ExecutionOutcome>,
+    /// Only records at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only records before this time
+    pub until: Option<DateTime<Utc>>,
+    /// Keep only the most recent matches
+    pub limit: Option<usize>,
+}
+
+impl HistoryFilter {
+    pub fn matches(&self, record: &ExecutionRecord) -> bool {
+        self.tool_name
+            .as_ref()
+            .is_none_or(|tool_name| &record.tool_name == tool_name)
+            && self
+                .session_id
+                .as_ref()
+                .is_none_or(|session_id| record.session_id.as_ref() == Some(session_id))
+            && self.outcome.is_none_or(|outcome| record.outcome == outcome)
+            && self.since.is_none_or(|since| record.timestamp >= since)
+            && self.until.is_none_or(|until| record.timestamp < until)
+    }
+}
+
+/// Append-only store of execution records, compacted to the most recent
+/// `max_records` once it grows past them by a quarter
+#[derive(Debug)]
+pub struct ExecutionHistoryStore {
+    path: PathBuf,
+    max_records: usize,
+    /// Records in the file, counted on first write
+    count: Mutex<Option<usize>>,
+}
+
+impl ExecutionHistoryStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            max_records: MAX_HISTORY_RECORDS,
+            count: Mutex::new(None),
+        }
+    }
+
+    #[allow(dead_code)] // Used by tests to exercise compaction
+    pub fn with_max_records(mut self, max_records: usize) -> Self {
+        self.max_records = max_records.max(1);
+        self
+    }
+
+    /// Append a record to the history file
+    pub async fn record(&self, record: &ExecutionRecord) -> Result<()> {
+        let mut count = self.count.lock().await;
+        let current = match *count {
+            Some(current) => current,
+            None => self.read_all()?.len(),
+        };
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(record)?)?;
+
+        let mut current = current + 1;
+        if current > self.max_records + self.max_records / 4 {
+            current = self.compact()?;
+        }
+        *count = Some(current);
+        Ok(())
+    }
+
+    /// Records matching the filter, oldest first
+    pub async fn query(&self, filter: &HistoryFilter) -> Result<Vec<ExecutionRecord>> {
+        let _count = self.count.lock().await;
+        let mut records: Vec<ExecutionRecord> = self
+            .read_all()?
+            .into_iter()
+            .filter(|record| filter.matches(record))
+            .collect();
+        if let Some(limit) = filter.limit {
+            let excess = records.len().saturating_sub(limit);
+            records.drain(..excess);
+        }
+        Ok(records)
+    }
+
+    /// Statistics over the matching records. Steps of one chain count as a
+    /// single run that succeeded only if every step did.
+    pub async fn stats(&self, filter: &HistoryFilter) -> Result<ExecutionStats> {
+        let records = self.query(filter).await?;
+
+        let mut runs: Vec<(bool, Duration)> = Vec::new();
+        let mut chain_runs: HashMap<&str, usize> = HashMap::new();
+        for record in &records {
+            let duration = Duration::from_millis(record.duration_ms);
+            let success = record.outcome.is_success();
+            let run = match &record.chain_id {
+                Some(chain_id) => *chain_runs.entry(chain_id).or_insert_with(|| {
+                    runs.push((true, Duration::ZERO));
+                    runs.len() - 1
+                }),
+                None => {
+                    runs.push((true, Duration::ZERO));
+                    runs.len() - 1
+                }
+            };
+            runs[run].0 &= success;
+            runs[run].1 += duration;
+        }
+
+        let total_chains = runs.len();
+        let successful_chains = runs.iter().filter(|(success, _)| *success).count();
+        Ok(ExecutionStats {
+            total_chains,
+            successful_chains,
+            failed_chains: total_chains - successful_chains,
+            average_execution_time: if total_chains > 0 {
+                runs.iter().map(|(_, duration)| *duration).sum::<Duration>() / total_chains as u32
+            } else {
+                Duration::from_millis(0)
+            },
+            success_rate: if total_chains > 0 {
+                successful_chains as f32 / total_chains as f32
+            } else {
+                0.0
+            },
+        })
+    }
+
+    /// Matching records as JSON Lines, oldest first
+    pub async fn export(&self, filter: &HistoryFilter) -> Result<String> {
+        let mut lines = String::new();
+        for record in self.query(filter).await? {
+            lines.push_str(&serde_json::to_string(&record)?);
+            lines.push('\n');
+        }
+        Ok(lines)
+    }
+
+    /// Every readable record in the file. Lines that fail to parse (e.g. a
+    /// write cut short by a crash) are skipped.
+    fn read_all(&self) -> Result<Vec<ExecutionRecord>> {
+        let file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut records = Vec::new();
+        for line in std::io::BufReader::new(file).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Ok(record) = serde_json::from_str(&line) {
+                records.push(record);
+            }
+        }
+        Ok(records)
+    }
+
+    /// Rewrite the file with only the most recent records
+    fn compact(&self) -> Result<usize> {
+        let mut records = self.read_all()?;
+        let excess = records.len().saturating_sub(self.max_records);
+        records.drain(..excess);
+
+        let temp_path = self.path.with_extension("jsonl.tmp");
+        let mut contents = String::new();
+        for record in &records {
+            contents.push_str(&serde_json::to_string(record)?);
+            contents.push('\n');
+        }
+        std::fs::write(&temp_path, contents)?;
+        std::fs::rename(&temp_path, &self.path)?;
+        Ok(records.len())
+    }
+
+    #[allow(dead_code)]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::claude::{
+        test_support::TempDir,
+        tools::execution::{ToolError, ToolResultData},
+        whitelist::WhitelistConfig,
+    };
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+
+    fn temp_store() -> (TempDir, ExecutionHistoryStore) {
+        let dir = TempDir::new();
+        let store = ExecutionHistoryStore::new(dir.join("tool_history.jsonl"));
+        (dir, store)
+    }
+
+    fn record(tool_name: &str, input: Value, success: bool) -> ExecutionRecord {
+        let context = ToolExecutionContext::new(
+            tool_name.to_string(),
+            input,
+            Arc::new(RwLock::new(WhitelistConfig::default())),
+        );
+        let result = if success {
+            ToolExecutionResult::success(
+                context.execution_id.clone(),
+                tool_name.to_string(),
+                ToolResultData::text("ok"),
+            )
+        } else {
+            ToolExecutionResult::failure(
+                context.execution_id.clone(),
+                tool_name.to_string(),
+                ToolError::execution_error("disk on fire"),
+                false,
+            )
+        };
+        ExecutionRecord::new(&context, &result, Some("session-1".to_string()))
+    }
+
+    #[test]
+    fn test_records_redact_sensitive_and_long_inputs() {
+        let long_path = "a/".repeat(200);
+        let record = record(
+            "write_file",
+            serde_json::json!({"path": long_path, "content": "secret text"}),
+            true,
+        );
+
+        assert_eq!(record.input["content"], "[redacted: 11 chars]");
+        assert!(record.input["path"]
+            .as_str()
+            .unwrap()
+            .ends_with("(400 chars)"));
+        assert_eq!(record.input_hash.len(), 64);
+        assert_eq!(record.outcome, ExecutionOutcome::Success);
+    }
+
+    #[tokio::test]
+    async fn test_history_survives_reopening_and_filters() {
+        let (_dir, store) = temp_store();
+        store
+            .record(&record("read_file", serde_json::json!({"path": "a"}), true))
+            .await
+            .unwrap();
+        store
+            .record(&record("git_log", serde_json::json!({}), false))
+            .await
+            .unwrap();
+        let mut other_session = record("read_file", serde_json::json!({"path": "b"}), true);
+        other_session.session_id = Some("session-2".to_string());
+        store.record(&other_session).await.unwrap();
+
+        let reopened = ExecutionHistoryStore::new(store.path().to_path_buf());
+        let all = reopened.query(&HistoryFilter::default()).await.unwrap();
+        assert_eq!(all.len(), 3);
+
+        let reads = HistoryFilter {
+            tool_name: Some("read_file".to_string()),
+            session_id: Some("session-1".to_string()),
+            ..HistoryFilter::default()
+        };
+        assert_eq!(reopened.query(&reads).await.unwrap().len(), 1);
+
+        let failures = HistoryFilter {
+            outcome: Some(ExecutionOutcome::Failed),
+            ..HistoryFilter::default()
+        };
+        let failed = reopened.query(&failures).await.unwrap();
+        assert_eq!(failed[0].error.as_deref(), Some("disk on fire"));
+
+        let future = HistoryFilter {
+            since: Some(Utc::now() + chrono::Duration::hours(1)),
+            ..HistoryFilter::default()
+        };
+        assert!(reopened.query(&future).await.unwrap().is_empty());
+
+        let latest = HistoryFilter {
+            limit: Some(1),
+            ..HistoryFilter::default()
+        };
+        let latest = reopened.query(&latest).await.unwrap();
+        assert_eq!(latest[0].session_id.as_deref(), Some("session-2"));
+
+        let exported = reopened.export(&HistoryFilter::default()).await.unwrap();
+        assert_eq!(exported.lines().count(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_stats_count_chain_steps_as_one_run() {
+        let (_dir, store) = temp_store();
+        let mut first = record("read_file", serde_json::json!({}), true);
+        first.chain_id = Some("chain_1".to_string());
+        let mut second = record("write_file", serde_json::json!({}), false);
+        second.chain_id = Some("chain_1".to_string());
+        for record in [
+            first,
+            second,
+            record("git_log", serde_json::json!({}), true),
+        ] {
+            store.record(&record).await.unwrap();
+        }
+
+        let stats = store.stats(&HistoryFilter::default()).await.unwrap();
+        assert_eq!(stats.total_chains, 2);
+        assert_eq!(stats.successful_chains, 1);
+        assert_eq!(stats.failed_chains, 1);
+    }
+
+    #[tokio::test]
+    async fn test_history_is_compacted_to_recent_records() {
+        let (_dir, store) = temp_store();
+        let store = store.with_max_records(4);
+        for index in 0..6 {
+            store
+                .record(&record(
+                    "read_file",
+                    serde_json::json!({"index": index}),
+                    true,
+                ))
+                .await
+                .unwrap();
+        }
+
+        let records = store.query(&HistoryFilter::default()).await.unwrap();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].input["index"], 2);
+    }
+}
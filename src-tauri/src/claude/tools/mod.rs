@@ -5,10 +5,23 @@
 //! simple string-based results to provide structured data, comprehensive error
 //! handling, and intelligent recovery mechanisms.
 
+pub mod approval;
+pub mod breaker;
+pub mod cargo;
 pub mod chain;
+pub mod checkpoint;
 pub mod execution;
 pub mod feedback;
+pub mod file_content;
+pub mod file_management;
+pub mod git;
+pub mod history;
+pub mod outline;
+pub mod plugin;
 pub mod recovery;
+pub mod schema;
+pub mod symbol_index;
+pub mod workflow;
 
 // Re-export main types for convenience
 pub use execution::{FollowUpAction, StatusLevel, ToolExecutionContext};
@@ -16,21 +29,47 @@ pub use execution::{FollowUpAction, StatusLevel, ToolExecutionContext};
 // Reserved for future integration with Claude client
 pub use chain::{ToolExecutionEngine, ToolRequest};
 #[allow(unused_imports)]
-pub use execution::{ToolExecutionResult, ToolResultData, ToolResultMetadata};
+pub use execution::{
+    AppliedRecovery, RecoveryKind, ToolExecutionResult, ToolOutput, ToolResultData,
+    ToolResultMetadata,
+};
 #[allow(unused_imports)]
 pub use feedback::FeedbackManager;
+pub use feedback::{FeedbackDelivery, HintNotifier, LearnedFeedback, SmartFeedbackHandler};
 #[allow(unused_imports)]
 pub use recovery::ToolRecoveryManager;
 
+pub use approval::{ApprovalGate, ApprovalManager, ApprovalNotifier, ApprovalPolicy};
+pub use breaker::ToolBreakers;
+pub use cargo::CargoCheckTool;
+pub use checkpoint::CheckpointStore;
+pub use file_management::{
+    CopyFileTool, CreateDirectoryTool, DeleteFileTool, FileInfoTool, MoveFileTool,
+};
+pub use git::{GitBlameTool, GitCommitTool, GitDiffTool, GitLogTool, GitShowTool, GitStatusTool};
+pub use history::ExecutionHistoryStore;
+pub use outline::CodeOutlineTool;
+pub use plugin::{load_plugins, WasmPluginTool};
+pub use schema::{input_schema_for, parse_input, validate_input};
+pub use symbol_index::{FindSymbolTool, SharedSymbolIndex, SymbolIndex};
+pub use workflow::{load_workflows, WorkflowDefinition};
+
 // Re-export existing tool types for backward compatibility
-use crate::claude::types::{PropertySchema, Tool, ToolInputSchema};
+use crate::claude::constants::file_limits::DEFAULT_WHITELIST_MAX_FILE_SIZE;
+use crate::claude::secure_fs::{FileAccess, SecureRoot};
+use crate::claude::types::{Tool, ToolInputSchema};
 use crate::claude::whitelist::{validate_path, FileOperation, WhitelistConfig};
 use anyhow::Result;
 use async_trait::async_trait;
+use execution::{FileItem, FileItemType};
+use file_content::{decode_file_content, hex_preview, number_lines, FileContent, TextEncoding};
+use schemars::JsonSchema;
+use serde::Deserialize;
 use serde_json::Value;
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::sync::RwLock;
 
 #[async_trait]
@@ -39,7 +78,30 @@ pub trait AgentTool: Send + Sync + std::fmt::Debug {
     fn description(&self) -> &str;
     fn input_schema(&self) -> ToolInputSchema;
     async fn execute(&self, input: Value) -> Result<String>;
+
+    /// Execute and return structured result data. Tools with output richer than
+    /// plain text override this; the default wraps `execute`.
+    async fn execute_data(&self, input: Value) -> Result<ToolResultData> {
+        self.execute(input).await.map(ToolResultData::Text)
+    }
+
+    /// Execute and return result data with warnings and bytes processed for the
+    /// result metadata. The engine calls this; the default wraps `execute_data`.
+    async fn execute_output(&self, input: Value) -> Result<ToolOutput> {
+        self.execute_data(input).await.map(ToolOutput::from)
+    }
     fn set_whitelist(&mut self, whitelist: Arc<RwLock<WhitelistConfig>>);
+
+    /// Whether the user must approve each call before the tool runs
+    fn requires_approval(&self) -> bool {
+        false
+    }
+
+    /// Paths a call may create, change or remove, with the operation used to
+    /// resolve them. The engine checkpoints these before the tool runs.
+    fn modified_paths(&self, _input: &Value) -> Vec<(String, FileOperation)> {
+        Vec::new()
+    }
 }
 
 #[derive(Debug)]
@@ -59,13 +121,22 @@ impl ToolRegistry {
         self.tools.insert(name, Box::new(tool));
     }
 
+    /// Register a tool whose type is only known at runtime, such as one
+    /// imported from an MCP server
+    pub fn register_boxed(&mut self, tool: Box<dyn AgentTool>) {
+        self.tools.insert(tool.name().to_string(), tool);
+    }
+
     pub fn get_tool(&self, name: &str) -> Option<&dyn AgentTool> {
         self.tools.get(name).map(|tool| tool.as_ref())
     }
 
     pub async fn execute_tool(&self, name: &str, input: Value) -> Result<String> {
         match self.get_tool(name) {
-            Some(tool) => tool.execute(input).await,
+            Some(tool) => {
+                validate_input(&tool.input_schema(), &input)?;
+                tool.execute(input).await
+            }
             None => Err(anyhow::anyhow!("Tool '{}' not found", name)),
         }
     }
@@ -95,6 +166,143 @@ impl Default for ToolRegistry {
     }
 }
 
+/// Path parameters present in `input`, paired with the operation they are resolved for
+pub fn path_inputs(
+    input: &Value,
+    keys: &[&str],
+    operation: FileOperation,
+) -> Vec<(String, FileOperation)> {
+    keys.iter()
+        .filter_map(|key| input.get(*key).and_then(|v| v.as_str()))
+        .map(|path| (path.to_string(), operation))
+        .collect()
+}
+
+/// Validate a path parameter and resolve it through the whitelist, falling back
+/// to the current directory when no whitelist has been set on the tool
+pub async fn resolve_tool_path(
+    whitelist: Option<&Arc<RwLock<WhitelistConfig>>>,
+    path_str: &str,
+    operation: FileOperation,
+) -> Result<PathBuf> {
+    if path_str.is_empty() {
+        return Err(anyhow::anyhow!("Path parameter cannot be empty"));
+    }
+
+    if path_str.len() > 4096 {
+        return Err(anyhow::anyhow!(
+            "Path parameter too long (max 4096 characters)"
+        ));
+    }
+
+    // Prevent null bytes in paths (security issue)
+    if path_str.contains('\0') {
+        return Err(anyhow::anyhow!("Invalid path: contains null bytes"));
+    }
+
+    if let Some(whitelist) = whitelist {
+        let whitelist_guard = whitelist.read().await;
+        return validate_path(path_str, &whitelist_guard, operation);
+    }
+
+    // Fallback to basic validation if no whitelist is set
+    let current_dir = std::env::current_dir()
+        .map_err(|e| anyhow::anyhow!("Cannot determine current directory: {}", e))?;
+    let path = Path::new(path_str);
+    let canonical_path = if path.is_absolute() {
+        path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+    } else {
+        current_dir
+            .join(path)
+            .canonicalize()
+            .unwrap_or_else(|_| current_dir.join(path))
+    };
+
+    if !canonical_path.starts_with(&current_dir) {
+        return Err(anyhow::anyhow!(
+            "Access denied: Path '{}' is outside allowed directory",
+            canonical_path.display()
+        ));
+    }
+
+    Ok(canonical_path)
+}
+
+/// Directory that a path returned by `resolve_tool_path` is opened relative to
+async fn tool_root(
+    whitelist: Option<&Arc<RwLock<WhitelistConfig>>>,
+    path: &Path,
+) -> Result<SecureRoot> {
+    match whitelist {
+        Some(whitelist) => whitelist.read().await.secure_root(path),
+        None => {
+            let current_dir = std::env::current_dir()
+                .map_err(|e| anyhow::anyhow!("Cannot determine current directory: {}", e))?;
+            SecureRoot::open(&current_dir, true)
+        }
+    }
+}
+
+/// Open a path returned by `resolve_tool_path` relative to the whitelisted
+/// directory it is in, so a symlink swapped in after the check cannot
+/// redirect the access
+pub async fn open_tool_file(
+    whitelist: Option<&Arc<RwLock<WhitelistConfig>>>,
+    path: &Path,
+    access: FileAccess,
+) -> Result<tokio::fs::File> {
+    let root = tool_root(whitelist, path).await?;
+    let path = path.to_path_buf();
+    let file = tokio::task::spawn_blocking(move || root.open_file(&path, access))
+        .await
+        .map_err(|e| anyhow::anyhow!("Open task failed: {}", e))??;
+    Ok(tokio::fs::File::from_std(file))
+}
+
+/// Run a blocking file system operation on a path returned by
+/// `resolve_tool_path`, relative to the whitelisted directory it is in
+async fn with_tool_root<T, F>(
+    whitelist: Option<&Arc<RwLock<WhitelistConfig>>>,
+    path: &Path,
+    operation: F,
+) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&SecureRoot, &Path) -> Result<T> + Send + 'static,
+{
+    let root = tool_root(whitelist, path).await?;
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || operation(&root, &path))
+        .await
+        .map_err(|e| anyhow::anyhow!("File system task failed: {}", e))?
+}
+
+/// Create a directory returned by `resolve_tool_path`, and its missing
+/// parents, relative to the whitelisted directory it is in
+pub async fn create_tool_directory(
+    whitelist: Option<&Arc<RwLock<WhitelistConfig>>>,
+    path: &Path,
+) -> Result<()> {
+    let root = tool_root(whitelist, path).await?;
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || root.create_dir_all(&path))
+        .await
+        .map_err(|e| anyhow::anyhow!("Create directory task failed: {}", e))?
+}
+
+/// Input for `read_file`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct ReadFileInput {
+    /// The path to the file to read
+    path: String,
+    /// The 1-based line number to start reading from (default: 1)
+    #[schemars(range(min = 1))]
+    offset: Option<usize>,
+    /// The maximum number of lines to read (default: whole file)
+    limit: Option<usize>,
+}
+
 #[derive(Debug)]
 pub struct ReadFileTool {
     whitelist: Option<Arc<RwLock<WhitelistConfig>>>,
@@ -117,106 +325,110 @@ impl AgentTool for ReadFileTool {
     }
 
     fn description(&self) -> &str {
-        "Read the contents of a file from the filesystem"
+        "Read the contents of a file with line numbers, optionally limited to a range of lines"
     }
 
     fn input_schema(&self) -> ToolInputSchema {
-        let mut properties = HashMap::new();
-        properties.insert(
-            "path".to_string(),
-            PropertySchema {
-                r#type: "string".to_string(),
-                description: "The path to the file to read".to_string(),
-                items: None,
-            },
-        );
-
-        ToolInputSchema {
-            r#type: "object".to_string(),
-            properties,
-            required: vec!["path".to_string()],
-            additional_properties: false,
-        }
+        input_schema_for::<ReadFileInput>()
     }
 
     async fn execute(&self, input: Value) -> Result<String> {
-        // Enhanced input validation
-        let path_str = input
-            .get("path")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow::anyhow!("Missing or invalid 'path' parameter"))?;
-
-        // Path validation
-        if path_str.is_empty() {
-            return Err(anyhow::anyhow!("Path parameter cannot be empty"));
-        }
+        Ok(self.execute_data(input).await?.render())
+    }
 
-        if path_str.len() > 4096 {
-            return Err(anyhow::anyhow!(
-                "Path parameter too long (max 4096 characters)"
-            ));
-        }
+    async fn execute_data(&self, input: Value) -> Result<ToolResultData> {
+        Ok(self.execute_output(input).await?.data)
+    }
 
-        // Prevent null bytes in paths (security issue)
-        if path_str.contains('\0') {
-            return Err(anyhow::anyhow!("Invalid path: contains null bytes"));
-        }
+    async fn execute_output(&self, input: Value) -> Result<ToolOutput> {
+        let ReadFileInput {
+            path,
+            offset,
+            limit,
+        } = parse_input(input)?;
 
-        // Validate and sanitize the path using whitelist
-        let safe_path = if let Some(whitelist) = &self.whitelist {
-            let whitelist_guard = whitelist.read().await;
-            validate_path(path_str, &whitelist_guard, FileOperation::Read)?
-        } else {
-            // Fallback to basic validation if no whitelist is set
-            let current_dir = std::env::current_dir()
-                .map_err(|e| anyhow::anyhow!("Cannot determine current directory: {}", e))?;
-            let path = Path::new(path_str);
-            let canonical_path = if path.is_absolute() {
-                path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
-            } else {
-                current_dir
-                    .join(path)
-                    .canonicalize()
-                    .unwrap_or_else(|_| current_dir.join(path))
-            };
+        let safe_path =
+            resolve_tool_path(self.whitelist.as_ref(), &path, FileOperation::Read).await?;
 
-            if !canonical_path.starts_with(&current_dir) {
-                return Err(anyhow::anyhow!(
-                    "Access denied: Path '{}' is outside allowed directory",
-                    canonical_path.display()
-                ));
-            }
-            canonical_path
+        let max_file_size = match &self.whitelist {
+            Some(whitelist) => whitelist.read().await.max_file_size_for(&safe_path),
+            None => DEFAULT_WHITELIST_MAX_FILE_SIZE,
         };
 
-        // Input validation
-        if safe_path.to_string_lossy().len() > 4096 {
-            return Err(anyhow::anyhow!("File path too long (max 4096 characters)"));
+        // Check the size of the opened file before loading anything into memory
+        let mut file =
+            open_tool_file(self.whitelist.as_ref(), &safe_path, FileAccess::Read).await?;
+        let metadata = file
+            .metadata()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to read file '{}': {}", safe_path.display(), e))?;
+        if !metadata.is_file() {
+            return Err(anyhow::anyhow!("'{}' is not a file", safe_path.display()));
+        }
+        if metadata.len() > max_file_size {
+            return Err(anyhow::anyhow!(
+                "File too large: {} bytes (limit: {} bytes)",
+                metadata.len(),
+                max_file_size
+            ));
         }
 
-        // Use async file operations
-        match tokio::fs::read_to_string(&safe_path).await {
-            Ok(content) => {
-                // Validate file size
-                const MAX_FILE_SIZE: usize = 10 * 1024 * 1024; // 10MB limit for reading
-                if content.len() > MAX_FILE_SIZE {
-                    return Err(anyhow::anyhow!(
-                        "File too large: {} bytes (limit: {} bytes)",
-                        content.len(),
-                        MAX_FILE_SIZE
+        let mut bytes = Vec::with_capacity(metadata.len() as usize);
+        file.read_to_end(&mut bytes)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to read file '{}': {}", safe_path.display(), e))?;
+
+        let bytes_read = bytes.len() as u64;
+        let output = match decode_file_content(&bytes) {
+            FileContent::Binary { content_type } => ToolOutput::new(
+                ToolResultData::binary_with_preview(content_type, bytes_read, hex_preview(&bytes)),
+            ),
+            FileContent::Text { content, encoding } => {
+                let (numbered, total_lines) = number_lines(&content, offset, limit)?;
+
+                let mut notes = Vec::new();
+                if encoding != TextEncoding::Utf8 {
+                    notes.push(format!("[Decoded as {}]", encoding.as_str()));
+                }
+                let first = offset.unwrap_or(1);
+                let shown = numbered.lines().count();
+                if shown < total_lines {
+                    notes.push(format!(
+                        "[Showing lines {}-{} of {}]",
+                        first,
+                        first + shown.saturating_sub(1),
+                        total_lines
                     ));
                 }
-                Ok(content)
+
+                let text = if notes.is_empty() {
+                    numbered
+                } else {
+                    format!("{}\n{}", notes.join("\n"), numbered)
+                };
+                let output = ToolOutput::new(ToolResultData::text(text));
+                if encoding == TextEncoding::Latin1 {
+                    // latin-1 is the fallback guess, so some characters may be wrong
+                    output.with_warning("File is not valid UTF-8 or UTF-16; decoded as latin-1")
+                } else {
+                    output
+                }
             }
-            Err(e) => Err(anyhow::anyhow!(
-                "Failed to read file '{}': {}",
-                safe_path.display(),
-                e
-            )),
-        }
+        };
+        Ok(output.with_bytes_processed(bytes_read))
     }
 }
 
+/// Input for `write_file`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct WriteFileInput {
+    /// The path to the file to write
+    path: String,
+    /// The content to write to the file
+    content: String,
+}
+
 #[derive(Debug)]
 pub struct WriteFileTool {
     whitelist: Option<Arc<RwLock<WhitelistConfig>>>,
@@ -234,6 +446,22 @@ impl AgentTool for WriteFileTool {
         self.whitelist = Some(whitelist);
     }
 
+    fn modified_paths(&self, input: &Value) -> Vec<(String, FileOperation)> {
+        path_inputs(input, &["path"], FileOperation::Write)
+    }
+
+    async fn execute_output(&self, input: Value) -> Result<ToolOutput> {
+        let bytes = input
+            .get("content")
+            .and_then(|v| v.as_str())
+            .map(|content| content.len() as u64);
+        let output = ToolOutput::new(ToolResultData::Text(self.execute(input).await?));
+        Ok(match bytes {
+            Some(bytes) => output.with_bytes_processed(bytes),
+            None => output,
+        })
+    }
+
     fn name(&self) -> &str {
         "write_file"
     }
@@ -243,59 +471,11 @@ impl AgentTool for WriteFileTool {
     }
 
     fn input_schema(&self) -> ToolInputSchema {
-        let mut properties = HashMap::new();
-        properties.insert(
-            "path".to_string(),
-            PropertySchema {
-                r#type: "string".to_string(),
-                description: "The path to the file to write".to_string(),
-                items: None,
-            },
-        );
-        properties.insert(
-            "content".to_string(),
-            PropertySchema {
-                r#type: "string".to_string(),
-                description: "The content to write to the file".to_string(),
-                items: None,
-            },
-        );
-
-        ToolInputSchema {
-            r#type: "object".to_string(),
-            properties,
-            required: vec!["path".to_string(), "content".to_string()],
-            additional_properties: false,
-        }
+        input_schema_for::<WriteFileInput>()
     }
 
     async fn execute(&self, input: Value) -> Result<String> {
-        // Enhanced input validation
-        let path_str = input
-            .get("path")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow::anyhow!("Missing or invalid 'path' parameter"))?;
-
-        let content = input
-            .get("content")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow::anyhow!("Missing or invalid 'content' parameter"))?;
-
-        // Path validation
-        if path_str.is_empty() {
-            return Err(anyhow::anyhow!("Path parameter cannot be empty"));
-        }
-
-        if path_str.len() > 4096 {
-            return Err(anyhow::anyhow!(
-                "Path parameter too long (max 4096 characters)"
-            ));
-        }
-
-        // Prevent null bytes in paths (security issue)
-        if path_str.contains('\0') {
-            return Err(anyhow::anyhow!("Invalid path: contains null bytes"));
-        }
+        let WriteFileInput { path, content } = parse_input(input)?;
 
         // Content validation
         if content.len() > 50 * 1024 * 1024 {
@@ -305,32 +485,8 @@ impl AgentTool for WriteFileTool {
             ));
         }
 
-        // Validate and sanitize the path using whitelist
-        let safe_path = if let Some(whitelist) = &self.whitelist {
-            let whitelist_guard = whitelist.read().await;
-            validate_path(path_str, &whitelist_guard, FileOperation::Write)?
-        } else {
-            // Fallback to basic validation if no whitelist is set
-            let current_dir = std::env::current_dir()
-                .map_err(|e| anyhow::anyhow!("Cannot determine current directory: {}", e))?;
-            let path = Path::new(path_str);
-            let canonical_path = if path.is_absolute() {
-                path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
-            } else {
-                current_dir
-                    .join(path)
-                    .canonicalize()
-                    .unwrap_or_else(|_| current_dir.join(path))
-            };
-
-            if !canonical_path.starts_with(&current_dir) {
-                return Err(anyhow::anyhow!(
-                    "Access denied: Path '{}' is outside allowed directory",
-                    canonical_path.display()
-                ));
-            }
-            canonical_path
-        };
+        let safe_path =
+            resolve_tool_path(self.whitelist.as_ref(), &path, FileOperation::Write).await?;
 
         // Check if we're trying to overwrite important files
         let file_name = safe_path.file_name().and_then(|n| n.to_str()).unwrap_or("");
@@ -352,8 +508,14 @@ impl AgentTool for WriteFileTool {
             ));
         }
 
-        // Use async file operations
-        match tokio::fs::write(&safe_path, content).await {
+        // Write through a handle opened beneath the whitelisted directory
+        let mut file =
+            open_tool_file(self.whitelist.as_ref(), &safe_path, FileAccess::Write).await?;
+        let written = async {
+            file.write_all(content.as_bytes()).await?;
+            file.flush().await
+        };
+        match written.await {
             Ok(_) => Ok(format!(
                 "Successfully wrote {} bytes to '{}'",
                 content.len(),
@@ -368,6 +530,14 @@ impl AgentTool for WriteFileTool {
     }
 }
 
+/// Input for `list_directory`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct ListDirectoryInput {
+    /// The path to the directory to list
+    path: String,
+}
+
 #[derive(Debug)]
 pub struct ListDirectoryTool {
     whitelist: Option<Arc<RwLock<WhitelistConfig>>>,
@@ -394,112 +564,92 @@ impl AgentTool for ListDirectoryTool {
     }
 
     fn input_schema(&self) -> ToolInputSchema {
-        let mut properties = HashMap::new();
-        properties.insert(
-            "path".to_string(),
-            PropertySchema {
-                r#type: "string".to_string(),
-                description: "The path to the directory to list".to_string(),
-                items: None,
-            },
-        );
-
-        ToolInputSchema {
-            r#type: "object".to_string(),
-            properties,
-            required: vec!["path".to_string()],
-            additional_properties: false,
-        }
+        input_schema_for::<ListDirectoryInput>()
     }
 
     async fn execute(&self, input: Value) -> Result<String> {
-        // Input validation and sanitization
-        let path_str = input
-            .get("path")
-            .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow::anyhow!("Missing or invalid 'path' parameter"))?;
-
-        // Additional path validation
-        if path_str.is_empty() {
-            return Err(anyhow::anyhow!("Path parameter cannot be empty"));
+        let output = self.execute_output(input).await?;
+        let mut text = output.data.render();
+        for warning in output.warnings {
+            text.push_str(&format!("\n[{}]", warning));
         }
+        Ok(text)
+    }
 
-        if path_str.len() > 4096 {
-            return Err(anyhow::anyhow!(
-                "Path parameter too long (max 4096 characters)"
-            ));
-        }
+    async fn execute_data(&self, input: Value) -> Result<ToolResultData> {
+        Ok(self.execute_output(input).await?.data)
+    }
 
-        // Validate and sanitize the path using whitelist
-        let safe_path = if let Some(whitelist) = &self.whitelist {
-            let whitelist_guard = whitelist.read().await;
-            validate_path(path_str, &whitelist_guard, FileOperation::List)?
-        } else {
-            // Fallback to basic validation if no whitelist is set
-            let current_dir = std::env::current_dir()
-                .map_err(|e| anyhow::anyhow!("Cannot determine current directory: {}", e))?;
-            let path = Path::new(path_str);
-            let canonical_path = if path.is_absolute() {
-                path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
-            } else {
-                current_dir
-                    .join(path)
-                    .canonicalize()
-                    .unwrap_or_else(|_| current_dir.join(path))
-            };
+    async fn execute_output(&self, input: Value) -> Result<ToolOutput> {
+        let ListDirectoryInput { path } = parse_input(input)?;
+        let safe_path =
+            resolve_tool_path(self.whitelist.as_ref(), &path, FileOperation::List).await?;
 
-            if !canonical_path.starts_with(&current_dir) {
-                return Err(anyhow::anyhow!(
-                    "Access denied: Path '{}' is outside allowed directory",
-                    canonical_path.display()
+        // Entries matched by ignore files are left out when the whitelist honors them
+        let ignore_matcher = match &self.whitelist {
+            Some(whitelist) => whitelist.read().await.ignore_matcher(&safe_path),
+            None => None,
+        };
+        let mut ignored_count = 0;
+
+        // Read relative to the whitelisted directory's handle, so a symlink
+        // swapped in after validation cannot redirect the listing
+        let entries = with_tool_root(self.whitelist.as_ref(), &safe_path, |root, path| {
+            root.read_dir(path)
+        })
+        .await?;
+
+        let mut items = Vec::new();
+        let mut warnings = Vec::new();
+        const MAX_ENTRIES: usize = 1000; // Limit directory listing
+
+        for entry in entries {
+            if items.len() >= MAX_ENTRIES {
+                warnings.push(format!(
+                    "Listing truncated, showing first {} entries",
+                    MAX_ENTRIES
                 ));
+                break;
+            }
+
+            let name = entry.name.to_string_lossy().to_string();
+
+            // Skip hidden files and sensitive directories
+            if name.starts_with('.') && !name.eq(".") && !name.eq("..") {
+                continue;
             }
-            canonical_path
-        };
 
-        // Use async file operations
-        match tokio::fs::read_dir(&safe_path).await {
-            Ok(mut entries) => {
-                let mut result = Vec::new();
-                let mut count = 0;
-                const MAX_ENTRIES: usize = 1000; // Limit directory listing
-
-                while let Some(entry) = entries.next_entry().await.transpose() {
-                    if count >= MAX_ENTRIES {
-                        result.push(format!(
-                            "... (truncated, showing first {} entries)",
-                            MAX_ENTRIES
-                        ));
-                        break;
-                    }
-
-                    match entry {
-                        Ok(entry) => {
-                            let name = entry.file_name().to_string_lossy().to_string();
-
-                            // Skip hidden files and sensitive directories
-                            if name.starts_with('.') && !name.eq(".") && !name.eq("..") {
-                                continue;
-                            }
-
-                            let file_type = match entry.file_type().await {
-                                Ok(ft) if ft.is_dir() => "directory",
-                                Ok(_) => "file",
-                                Err(_) => "unknown",
-                            };
-                            result.push(format!("{} ({})", name, file_type));
-                            count += 1;
-                        }
-                        Err(e) => result.push(format!("Error reading entry: {}", e)),
-                    }
+            let item_type = match &entry.metadata {
+                Some(metadata) if metadata.is_dir() => FileItemType::Directory,
+                Some(metadata) if metadata.is_symlink() => FileItemType::Symlink,
+                Some(_) => FileItemType::File,
+                None => FileItemType::Unknown,
+            };
+            if let Some(matcher) = &ignore_matcher {
+                let is_dir = matches!(item_type, FileItemType::Directory);
+                if matcher.is_ignored(&safe_path.join(&entry.name), is_dir) {
+                    ignored_count += 1;
+                    continue;
                 }
-                Ok(result.join("\n"))
             }
-            Err(e) => Err(anyhow::anyhow!(
-                "Failed to read directory '{}': {}",
-                safe_path.display(),
-                e
-            )),
+            let mut item = FileItem::new(name, item_type.clone());
+            if let Some(metadata) = &entry.metadata {
+                if matches!(item_type, FileItemType::File) {
+                    item = item.with_size(metadata.len());
+                }
+                if let Ok(modified) = metadata.modified() {
+                    item = item.with_modified(modified.into());
+                }
+            }
+            items.push(item);
+        }
+        items.sort_by(|a, b| a.name.cmp(&b.name));
+
+        if ignored_count > 0 {
+            warnings.push(format!("{} entries hidden by ignore files", ignored_count));
         }
+        let mut output = ToolOutput::new(ToolResultData::directory_listing(items));
+        output.warnings = warnings;
+        Ok(output)
     }
 }
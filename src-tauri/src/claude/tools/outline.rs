@@ -0,0 +1,390 @@
+//! Source code outlines using tree-sitter.
+//!
+//! Extracts the definitions (functions, types, classes, impls, ...) of Rust,
+//! TypeScript/JavaScript and Python files. The same extraction feeds the
+//! `code_outline` tool and the on-disk symbol index used by `find_symbol`.
+
+use crate::claude::constants::code_index::{MAX_SIGNATURE_CHARS, MAX_SOURCE_FILE_BYTES};
+use crate::claude::tools::{
+    input_schema_for, parse_input, resolve_tool_path, AgentTool, ToolResultData,
+};
+use crate::claude::types::ToolInputSchema;
+use crate::claude::whitelist::{FileOperation, WhitelistConfig};
+use anyhow::Result;
+use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tree_sitter::{Node, Parser};
+
+/// Languages supported by the outline parser
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceLanguage {
+    Rust,
+    TypeScript,
+    Tsx,
+    JavaScript,
+    Python,
+}
+
+impl SourceLanguage {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "rs" => Some(Self::Rust),
+            "ts" | "mts" | "cts" => Some(Self::TypeScript),
+            "tsx" => Some(Self::Tsx),
+            "js" | "jsx" | "mjs" | "cjs" => Some(Self::JavaScript),
+            "py" | "pyi" => Some(Self::Python),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Rust => "rust",
+            Self::TypeScript => "typescript",
+            Self::Tsx => "tsx",
+            Self::JavaScript => "javascript",
+            Self::Python => "python",
+        }
+    }
+
+    fn grammar(&self) -> tree_sitter::Language {
+        match self {
+            Self::Rust => tree_sitter_rust::LANGUAGE.into(),
+            Self::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            Self::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+            Self::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
+            Self::Python => tree_sitter_python::LANGUAGE.into(),
+        }
+    }
+}
+
+/// A definition found in a source file. Lines are 1-based.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: String,
+    pub line_start: usize,
+    pub line_end: usize,
+    pub signature: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+}
+
+/// How a syntax node contributes to the outline
+struct Definition {
+    kind: &'static str,
+    name: String,
+    /// Name that nested definitions report as their parent, if the node is a container
+    container: Option<String>,
+}
+
+fn field_text(node: Node, field: &str, source: &[u8]) -> Option<String> {
+    node.child_by_field_name(field)
+        .and_then(|n| n.utf8_text(source).ok())
+        .map(|s| s.to_string())
+}
+
+fn function_kind(in_type: bool) -> &'static str {
+    if in_type {
+        "method"
+    } else {
+        "function"
+    }
+}
+
+fn classify(
+    language: SourceLanguage,
+    node: Node,
+    source: &[u8],
+    in_type: bool,
+) -> Option<Definition> {
+    let named = |kind: &'static str| {
+        field_text(node, "name", source).map(|name| Definition {
+            kind,
+            name,
+            container: None,
+        })
+    };
+    let container = |kind: &'static str| {
+        field_text(node, "name", source).map(|name| Definition {
+            kind,
+            container: Some(name.clone()),
+            name,
+        })
+    };
+
+    match language {
+        SourceLanguage::Rust => match node.kind() {
+            "function_item" | "function_signature_item" => named(function_kind(in_type)),
+            "struct_item" => named("struct"),
+            "enum_item" => named("enum"),
+            "union_item" => named("union"),
+            "trait_item" => container("trait"),
+            "mod_item" => container("module"),
+            "const_item" => named("const"),
+            "static_item" => named("static"),
+            "type_item" => named("type"),
+            "macro_definition" => named("macro"),
+            "impl_item" => {
+                let type_name = field_text(node, "type", source)?;
+                let name = match field_text(node, "trait", source) {
+                    Some(trait_name) => format!("impl {} for {}", trait_name, type_name),
+                    None => format!("impl {}", type_name),
+                };
+                Some(Definition {
+                    kind: "impl",
+                    name,
+                    container: Some(type_name),
+                })
+            }
+            _ => None,
+        },
+        SourceLanguage::Python => match node.kind() {
+            "function_definition" => named(function_kind(in_type)),
+            "class_definition" => container("class"),
+            _ => None,
+        },
+        SourceLanguage::TypeScript | SourceLanguage::Tsx | SourceLanguage::JavaScript => {
+            match node.kind() {
+                "function_declaration" | "generator_function_declaration" => named("function"),
+                "class_declaration" | "abstract_class_declaration" => container("class"),
+                "method_definition" | "abstract_method_signature" => named("method"),
+                "interface_declaration" => named("interface"),
+                "type_alias_declaration" => named("type"),
+                "enum_declaration" => named("enum"),
+                "internal_module" | "module" => container("namespace"),
+                "variable_declarator" => {
+                    let value = node.child_by_field_name("value")?;
+                    if matches!(
+                        value.kind(),
+                        "arrow_function" | "function_expression" | "function"
+                    ) {
+                        named("function")
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            }
+        }
+    }
+}
+
+/// First line of a definition, used as a compact signature
+fn signature(node: Node, source: &[u8]) -> String {
+    let text = node.utf8_text(source).unwrap_or("");
+    let first_line = text.lines().next().unwrap_or("").trim();
+    let first_line = first_line.trim_end_matches('{').trim_end();
+    match first_line.char_indices().nth(MAX_SIGNATURE_CHARS) {
+        Some((end, _)) => format!("{}…", &first_line[..end]),
+        None => first_line.to_string(),
+    }
+}
+
+/// Parse `source` and return its definitions in source order
+pub fn parse_symbols(language: SourceLanguage, source: &str) -> Result<Vec<Symbol>> {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&language.grammar())
+        .map_err(|e| anyhow::anyhow!("Failed to load {} grammar: {}", language.as_str(), e))?;
+    let tree = parser
+        .parse(source, None)
+        .ok_or_else(|| anyhow::anyhow!("Failed to parse {} source", language.as_str()))?;
+    let bytes = source.as_bytes();
+
+    let mut symbols = Vec::new();
+    // Explicit stack instead of recursion so deeply nested code cannot overflow
+    let mut stack: Vec<(Node, Option<String>, bool)> = vec![(tree.root_node(), None, false)];
+
+    while let Some((node, parent, in_type)) = stack.pop() {
+        let mut child_parent = parent.clone();
+        let mut child_in_type = in_type;
+
+        if let Some(definition) = classify(language, node, bytes, in_type) {
+            symbols.push(Symbol {
+                name: definition.name,
+                kind: definition.kind.to_string(),
+                line_start: node.start_position().row + 1,
+                line_end: node.end_position().row + 1,
+                signature: signature(node, bytes),
+                parent: parent.clone(),
+            });
+
+            match definition.container {
+                Some(name) => {
+                    child_in_type = definition.kind != "module" && definition.kind != "namespace";
+                    child_parent = Some(name);
+                }
+                // Function bodies and plain type definitions are not outlined further
+                None => continue,
+            }
+        }
+
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node.named_children(&mut cursor).collect();
+        for child in children.into_iter().rev() {
+            stack.push((child, child_parent.clone(), child_in_type));
+        }
+    }
+
+    Ok(symbols)
+}
+
+/// Read and outline a file, returning `None` for unsupported languages
+pub fn outline_file(path: &Path) -> Result<Option<(SourceLanguage, Vec<Symbol>)>> {
+    let Some(language) = SourceLanguage::from_path(path) else {
+        return Ok(None);
+    };
+    let metadata = std::fs::metadata(path)?;
+    if metadata.len() > MAX_SOURCE_FILE_BYTES {
+        return Err(anyhow::anyhow!(
+            "File too large to outline: {} bytes (limit: {} bytes)",
+            metadata.len(),
+            MAX_SOURCE_FILE_BYTES
+        ));
+    }
+    let bytes = std::fs::read(path)?;
+    let source = String::from_utf8_lossy(&bytes);
+    Ok(Some((language, parse_symbols(language, &source)?)))
+}
+
+/// Input for `code_outline`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct CodeOutlineInput {
+    /// The source file to outline
+    path: String,
+}
+
+#[derive(Debug)]
+pub struct CodeOutlineTool {
+    whitelist: Option<Arc<RwLock<WhitelistConfig>>>,
+}
+
+impl CodeOutlineTool {
+    pub fn new() -> Self {
+        Self { whitelist: None }
+    }
+}
+
+#[async_trait]
+impl AgentTool for CodeOutlineTool {
+    fn set_whitelist(&mut self, whitelist: Arc<RwLock<WhitelistConfig>>) {
+        self.whitelist = Some(whitelist);
+    }
+
+    fn name(&self) -> &str {
+        "code_outline"
+    }
+
+    fn description(&self) -> &str {
+        "List the functions, types, classes and other definitions in a Rust, TypeScript, JavaScript or Python file with their line ranges"
+    }
+
+    fn input_schema(&self) -> ToolInputSchema {
+        input_schema_for::<CodeOutlineInput>()
+    }
+
+    async fn execute(&self, input: Value) -> Result<String> {
+        match self.execute_data(input).await? {
+            ToolResultData::Json(value) => Ok(serde_json::to_string_pretty(&value)?),
+            _ => Err(anyhow::anyhow!("Unexpected code_outline result")),
+        }
+    }
+
+    async fn execute_data(&self, input: Value) -> Result<ToolResultData> {
+        let input: CodeOutlineInput = parse_input(input)?;
+        let path =
+            resolve_tool_path(self.whitelist.as_ref(), &input.path, FileOperation::Read).await?;
+
+        let outline_path = path.clone();
+        let (language, symbols) = tokio::task::spawn_blocking(move || outline_file(&outline_path))
+            .await
+            .map_err(|e| anyhow::anyhow!("Outline task failed: {}", e))??
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Unsupported file type '{}': expected Rust, TypeScript, JavaScript or Python",
+                    path.display()
+                )
+            })?;
+
+        Ok(ToolResultData::json(json!({
+            "path": path.to_string_lossy(),
+            "language": language.as_str(),
+            "count": symbols.len(),
+            "symbols": symbols,
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(symbols: &[Symbol]) -> Vec<(&str, &str, Option<&str>)> {
+        symbols
+            .iter()
+            .map(|s| (s.kind.as_str(), s.name.as_str(), s.parent.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn test_rust_outline() {
+        let source = "pub struct Point { x: i32 }\n\
+            impl Point {\n    pub fn new() -> Self { Point { x: 0 } }\n}\n\
+            trait Shape { fn area(&self) -> f64; }\n\
+            fn main() {\n    fn nested() {}\n}\n";
+        let symbols = parse_symbols(SourceLanguage::Rust, source).unwrap();
+
+        assert_eq!(
+            names(&symbols),
+            vec![
+                ("struct", "Point", None),
+                ("impl", "impl Point", None),
+                ("method", "new", Some("Point")),
+                ("trait", "Shape", None),
+                ("method", "area", Some("Shape")),
+                ("function", "main", None),
+            ]
+        );
+        assert_eq!(symbols[2].line_start, 3);
+        assert_eq!(
+            symbols[2].signature,
+            "pub fn new() -> Self { Point { x: 0 } }"
+        );
+    }
+
+    #[test]
+    fn test_typescript_and_python_outline() {
+        let ts = "export interface Props { a: string }\n\
+            export class Widget {\n  render() { return 1; }\n}\n\
+            export const helper = () => 2;\n";
+        let symbols = parse_symbols(SourceLanguage::TypeScript, ts).unwrap();
+        assert_eq!(
+            names(&symbols),
+            vec![
+                ("interface", "Props", None),
+                ("class", "Widget", None),
+                ("method", "render", Some("Widget")),
+                ("function", "helper", None),
+            ]
+        );
+
+        let py = "class Greeter:\n    def greet(self):\n        pass\n\ndef main():\n    pass\n";
+        let symbols = parse_symbols(SourceLanguage::Python, py).unwrap();
+        assert_eq!(
+            names(&symbols),
+            vec![
+                ("class", "Greeter", None),
+                ("method", "greet", Some("Greeter")),
+                ("function", "main", None),
+            ]
+        );
+    }
+}
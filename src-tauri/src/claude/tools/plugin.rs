@@ -0,0 +1,754 @@
+//! WebAssembly plugin tools. Modules dropped into the plugins directory are
+//! loaded at startup and run sandboxed: they only see the file system through
+//! host functions that check the capabilities granted in the plugin's manifest,
+//! resolve every path through `validate_path` and open it beneath the
+//! whitelisted directory's [`SecureRoot`], and each call is bounded by fuel and
+//! memory limits. Plugins granted `write` need the user's approval per call.
+//!
+//! Plugin ABI (all messages are UTF-8 JSON in the plugin's memory, passed as a
+//! pointer and length; results are returned packed as `(ptr << 32) | len`):
+//!
+//! - exports `memory`, `alloc(len: i32) -> i32`
+//! - exports `describe() -> i64` returning `{"name", "description", "input_schema"}`
+//! - exports `execute(ptr: i32, len: i32) -> i64`, called with the tool input
+//!   and returning `{"ok": output}` or `{"error": message}`
+//! - may import from `host`: `read_file(path_ptr, path_len) -> i64`,
+//!   `write_file(path_ptr, path_len, data_ptr, data_len) -> i64`,
+//!   `list_directory(path_ptr, path_len) -> i64` and `log(ptr, len)`; the file
+//!   functions answer `{"ok": ...}` or `{"error": message}` the same way
+//!
+//! A `<module>.toml` manifest next to `<module>.wasm` grants capabilities and
+//! sets limits, e.g. `capabilities = ["read", "list"]`, `fuel = 2000000000`,
+//! `max_memory_mb = 128`. Without one a plugin gets no file access.
+
+use super::AgentTool;
+use crate::claude::constants::plugins::{
+    DEFAULT_FUEL, DEFAULT_MAX_MEMORY_MB, DESCRIBE_FUEL, MAX_FUEL, MAX_MEMORY_MB, MAX_MESSAGE_BYTES,
+};
+use crate::claude::secure_fs::{FileAccess, SecureRoot};
+use crate::claude::types::ToolInputSchema;
+use crate::claude::whitelist::{validate_path, FileOperation, WhitelistConfig};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use wasmtime::{
+    Caller, Config, Engine, Extern, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder,
+    Trap,
+};
+
+/// File access a plugin may be granted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginCapability {
+    Read,
+    Write,
+    List,
+}
+
+impl PluginCapability {
+    fn operation(self) -> FileOperation {
+        match self {
+            PluginCapability::Read => FileOperation::Read,
+            PluginCapability::Write => FileOperation::Write,
+            PluginCapability::List => FileOperation::List,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            PluginCapability::Read => "read",
+            PluginCapability::Write => "write",
+            PluginCapability::List => "list",
+        }
+    }
+}
+
+/// Grants and limits for one plugin, read from the `.toml` next to its module
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PluginManifest {
+    pub enabled: bool,
+    pub capabilities: Vec<PluginCapability>,
+    /// Fuel each call may burn, roughly one unit per instruction
+    pub fuel: u64,
+    /// Linear memory the plugin may use, in megabytes
+    pub max_memory_mb: u64,
+}
+
+impl Default for PluginManifest {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            capabilities: Vec::new(),
+            fuel: DEFAULT_FUEL,
+            max_memory_mb: DEFAULT_MAX_MEMORY_MB,
+        }
+    }
+}
+
+impl PluginManifest {
+    /// Load the manifest for a module, defaulting when there is none
+    pub fn load_for(module_path: &Path) -> Result<Self> {
+        let path = module_path.with_extension("toml");
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let manifest: Self = toml::from_str(&std::fs::read_to_string(&path)?)
+            .map_err(|e| anyhow!("Invalid plugin manifest {}: {}", path.display(), e))?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.fuel == 0 || self.fuel > MAX_FUEL {
+            return Err(anyhow!(
+                "Invalid plugin fuel: {} (must be 1-{})",
+                self.fuel,
+                MAX_FUEL
+            ));
+        }
+        if self.max_memory_mb == 0 || self.max_memory_mb > MAX_MEMORY_MB {
+            return Err(anyhow!(
+                "Invalid plugin max_memory_mb: {} (must be 1-{})",
+                self.max_memory_mb,
+                MAX_MEMORY_MB
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// What a plugin declares about itself from `describe`
+#[derive(Debug, Deserialize)]
+struct PluginDescription {
+    name: String,
+    description: String,
+    input_schema: ToolInputSchema,
+}
+
+/// Result of a plugin call or a host function call
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum PluginResponse {
+    Ok(Value),
+    Error(String),
+}
+
+/// State available to host functions during a call
+struct HostState {
+    limits: StoreLimits,
+    capabilities: Vec<PluginCapability>,
+    whitelist: Option<Arc<RwLock<WhitelistConfig>>>,
+}
+
+impl HostState {
+    /// Resolve a path the plugin asked for, returning it with the whitelisted
+    /// directory to access it beneath and the maximum file size the whitelist allows
+    fn resolve(
+        &self,
+        path: &str,
+        capability: PluginCapability,
+    ) -> Result<(PathBuf, SecureRoot, u64)> {
+        if !self.capabilities.contains(&capability) {
+            return Err(anyhow!(
+                "Plugin was not granted the '{}' capability",
+                capability.as_str()
+            ));
+        }
+        let whitelist = self
+            .whitelist
+            .as_ref()
+            .ok_or_else(|| anyhow!("File access is unavailable: no whitelist is configured"))?;
+        // Calls run on a blocking thread, so the lock can be waited on
+        let whitelist = whitelist.blocking_read();
+        let resolved = validate_path(path, &whitelist, capability.operation())?;
+        let root = whitelist.secure_root(&resolved)?;
+        let max_file_size = whitelist.max_file_size_for(&resolved);
+        Ok((resolved, root, max_file_size))
+    }
+}
+
+/// Engine shared by all plugins, with fuel metering enabled
+pub fn plugin_engine() -> Result<Engine> {
+    let mut config = Config::new();
+    config.consume_fuel(true);
+    Engine::new(&config)
+}
+
+/// Load every enabled `.wasm` plugin in `dir`. Plugins that fail to load are
+/// reported and skipped so one broken module does not block the others.
+pub fn load_plugins(
+    dir: &Path,
+    whitelist: Option<Arc<RwLock<WhitelistConfig>>>,
+) -> Vec<WasmPluginTool> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let engine = match plugin_engine() {
+        Ok(engine) => engine,
+        Err(e) => {
+            eprintln!("Failed to start the plugin runtime: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "wasm"))
+        .collect();
+    paths.sort();
+
+    let mut names = HashSet::new();
+    let mut plugins = Vec::new();
+    for path in paths {
+        let plugin = PluginManifest::load_for(&path).and_then(|manifest| {
+            if !manifest.enabled {
+                return Ok(None);
+            }
+            let module = Module::from_file(&engine, &path)?;
+            WasmPluginTool::new(&engine, module, manifest, whitelist.clone()).map(Some)
+        });
+
+        match plugin {
+            Ok(Some(plugin)) if names.insert(plugin.name.clone()) => {
+                eprintln!("🧩 Loaded plugin '{}' from {}", plugin.name, path.display());
+                plugins.push(plugin);
+            }
+            Ok(Some(plugin)) => eprintln!(
+                "Skipping plugin {}: another plugin is already named '{}'",
+                path.display(),
+                plugin.name
+            ),
+            Ok(None) => {}
+            Err(e) => eprintln!("Failed to load plugin {}: {}", path.display(), e),
+        }
+    }
+    plugins
+}
+
+/// A tool implemented by a WebAssembly module. Each call runs in a fresh
+/// instance with its own fuel and memory budget.
+#[derive(Clone)]
+pub struct WasmPluginTool {
+    name: String,
+    description: String,
+    input_schema: ToolInputSchema,
+    engine: Engine,
+    module: Module,
+    manifest: PluginManifest,
+    whitelist: Option<Arc<RwLock<WhitelistConfig>>>,
+}
+
+impl std::fmt::Debug for WasmPluginTool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WasmPluginTool")
+            .field("name", &self.name)
+            .field("manifest", &self.manifest)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WasmPluginTool {
+    /// Instantiate the module once to ask it to describe itself
+    pub fn new(
+        engine: &Engine,
+        module: Module,
+        manifest: PluginManifest,
+        whitelist: Option<Arc<RwLock<WhitelistConfig>>>,
+    ) -> Result<Self> {
+        // Named after the module until it describes itself, for error messages
+        let mut plugin = Self {
+            name: module.name().unwrap_or("plugin").to_string(),
+            description: String::new(),
+            input_schema: ToolInputSchema {
+                r#type: "object".to_string(),
+                properties: Default::default(),
+                required: Vec::new(),
+                additional_properties: false,
+            },
+            engine: engine.clone(),
+            module,
+            manifest,
+            whitelist,
+        };
+
+        // No file access while describing; the plugin is not running a call yet
+        let description = plugin.call("describe", None, DESCRIBE_FUEL, Vec::new())?;
+        let description: PluginDescription = serde_json::from_slice(&description)
+            .map_err(|e| anyhow!("Plugin returned an invalid description: {}", e))?;
+
+        let valid_name = !description.name.is_empty()
+            && description.name.len() <= 64
+            && description
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid_name {
+            return Err(anyhow!(
+                "Invalid plugin tool name '{}' (use 1-64 letters, digits, '_' or '-')",
+                description.name
+            ));
+        }
+
+        plugin.name = description.name;
+        plugin.description = description.description;
+        plugin.input_schema = description.input_schema;
+        Ok(plugin)
+    }
+
+    /// Run an export in a fresh instance and return the message it points to
+    fn call(
+        &self,
+        export: &str,
+        input: Option<&[u8]>,
+        fuel: u64,
+        capabilities: Vec<PluginCapability>,
+    ) -> Result<Vec<u8>> {
+        let max_memory_bytes = (self.manifest.max_memory_mb * 1024 * 1024) as usize;
+        let mut store = Store::new(
+            &self.engine,
+            HostState {
+                limits: StoreLimitsBuilder::new()
+                    .memory_size(max_memory_bytes)
+                    .instances(1)
+                    .trap_on_grow_failure(true)
+                    .build(),
+                capabilities,
+                whitelist: self.whitelist.clone(),
+            },
+        );
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(fuel)?;
+
+        let instance = host_linker(&self.engine)?
+            .instantiate(&mut store, &self.module)
+            .map_err(|e| self.runtime_error(e))?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| anyhow!("Plugin '{}' does not export its memory", self.name))?;
+
+        let packed = match input {
+            Some(input) => {
+                let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+                let ptr = alloc
+                    .call(&mut store, input.len() as i32)
+                    .map_err(|e| self.runtime_error(e))?;
+                memory.write(&mut store, ptr as u32 as usize, input)?;
+                instance
+                    .get_typed_func::<(i32, i32), i64>(&mut store, export)?
+                    .call(&mut store, (ptr, input.len() as i32))
+            }
+            None => instance
+                .get_typed_func::<(), i64>(&mut store, export)?
+                .call(&mut store, ()),
+        }
+        .map_err(|e| self.runtime_error(e))?;
+
+        let (ptr, len) = unpack(packed);
+        read_memory(memory.data(&store), ptr, len)
+    }
+
+    /// Explain traps caused by the sandbox limits in plain terms
+    fn runtime_error(&self, error: anyhow::Error) -> anyhow::Error {
+        if error.downcast_ref::<Trap>() == Some(&Trap::OutOfFuel) {
+            return anyhow!(
+                "Plugin '{}' exceeded its fuel limit of {} and was stopped",
+                self.name,
+                self.manifest.fuel
+            );
+        }
+        let message = format!("{:#}", error);
+        if (message.contains("memory") && message.contains("limit")) || message.contains("growth") {
+            return anyhow!(
+                "Plugin '{}' exceeded its memory limit of {}MB",
+                self.name,
+                self.manifest.max_memory_mb
+            );
+        }
+        anyhow!("Plugin '{}' failed: {}", self.name, message)
+    }
+
+    fn execute_blocking(&self, input: &[u8]) -> Result<String> {
+        let output = self.call(
+            "execute",
+            Some(input),
+            self.manifest.fuel,
+            self.manifest.capabilities.clone(),
+        )?;
+        let response: PluginResponse = serde_json::from_slice(&output)
+            .map_err(|e| anyhow!("Plugin '{}' returned an invalid result: {}", self.name, e))?;
+
+        match response {
+            PluginResponse::Ok(Value::String(text)) => Ok(text),
+            PluginResponse::Ok(value) => Ok(serde_json::to_string_pretty(&value)?),
+            PluginResponse::Error(message) => Err(anyhow!("{}", message)),
+        }
+    }
+}
+
+#[async_trait]
+impl AgentTool for WasmPluginTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn input_schema(&self) -> ToolInputSchema {
+        self.input_schema.clone()
+    }
+
+    async fn execute(&self, input: Value) -> Result<String> {
+        let plugin = self.clone();
+        let input = serde_json::to_vec(&input)?;
+        // Fuel bounds how long the blocking call can run
+        tokio::task::spawn_blocking(move || plugin.execute_blocking(&input))
+            .await
+            .map_err(|e| anyhow!("Plugin '{}' crashed: {}", self.name, e))?
+    }
+
+    fn set_whitelist(&mut self, whitelist: Arc<RwLock<WhitelistConfig>>) {
+        self.whitelist = Some(whitelist);
+    }
+
+    /// The files a plugin writes are not known before it runs, so calls of
+    /// plugins that may write are put in front of the user
+    fn requires_approval(&self) -> bool {
+        self.manifest
+            .capabilities
+            .contains(&PluginCapability::Write)
+    }
+}
+
+fn pack(ptr: i32, len: usize) -> i64 {
+    (((ptr as u32 as u64) << 32) | len as u32 as u64) as i64
+}
+
+fn unpack(packed: i64) -> (u32, u32) {
+    let packed = packed as u64;
+    ((packed >> 32) as u32, packed as u32)
+}
+
+fn read_memory(data: &[u8], ptr: u32, len: u32) -> Result<Vec<u8>> {
+    if len as usize > MAX_MESSAGE_BYTES {
+        return Err(anyhow!(
+            "Plugin message of {} bytes exceeds the {} byte limit",
+            len,
+            MAX_MESSAGE_BYTES
+        ));
+    }
+    let start = ptr as usize;
+    data.get(start..start + len as usize)
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| anyhow!("Plugin message points outside its memory"))
+}
+
+fn guest_memory(caller: &mut Caller<'_, HostState>) -> wasmtime::Result<Memory> {
+    caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| anyhow!("Plugin does not export its memory"))
+}
+
+fn read_guest_string(
+    caller: &mut Caller<'_, HostState>,
+    ptr: i32,
+    len: i32,
+) -> wasmtime::Result<String> {
+    let memory = guest_memory(caller)?;
+    let bytes = read_memory(memory.data(&*caller), ptr as u32, len as u32)?;
+    String::from_utf8(bytes).map_err(|_| anyhow!("Plugin passed a string that is not UTF-8"))
+}
+
+/// Copy a host function's answer into memory allocated by the plugin
+fn respond(caller: &mut Caller<'_, HostState>, response: Result<Value>) -> wasmtime::Result<i64> {
+    let message = match response {
+        Ok(value) => json!({ "ok": value }),
+        Err(e) => json!({ "error": e.to_string() }),
+    };
+    let bytes = serde_json::to_vec(&message)?;
+
+    let alloc = caller
+        .get_export("alloc")
+        .and_then(Extern::into_func)
+        .ok_or_else(|| anyhow!("Plugin does not export alloc"))?
+        .typed::<i32, i32>(&*caller)?;
+    let ptr = alloc.call(&mut *caller, bytes.len() as i32)?;
+    guest_memory(caller)?.write(&mut *caller, ptr as u32 as usize, &bytes)?;
+    Ok(pack(ptr, bytes.len()))
+}
+
+fn host_read_file(state: &HostState, path: &str) -> Result<Value> {
+    let (path, root, max_size) = state.resolve(path, PluginCapability::Read)?;
+    let mut file = root.open_file(&path, FileAccess::Read)?;
+    let size = file.metadata()?.len();
+    if size > max_size || size as usize > MAX_MESSAGE_BYTES {
+        return Err(anyhow!("File too large for a plugin: {} bytes", size));
+    }
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    Ok(Value::String(content))
+}
+
+fn host_write_file(state: &HostState, path: &str, content: &str) -> Result<Value> {
+    let (path, root, max_size) = state.resolve(path, PluginCapability::Write)?;
+    if content.len() as u64 > max_size {
+        return Err(anyhow!(
+            "Content too large: {} bytes (max {})",
+            content.len(),
+            max_size
+        ));
+    }
+    let mut file = root.open_file(&path, FileAccess::Write)?;
+    file.write_all(content.as_bytes())?;
+    Ok(json!(content.len()))
+}
+
+fn host_list_directory(state: &HostState, path: &str) -> Result<Value> {
+    let (path, root, _) = state.resolve(path, PluginCapability::List)?;
+    let mut names: Vec<String> = root
+        .read_dir(&path)?
+        .into_iter()
+        .map(|entry| entry.name.to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    Ok(json!(names))
+}
+
+/// Host functions plugins may import from the `host` module
+fn host_linker(engine: &Engine) -> Result<Linker<HostState>> {
+    let mut linker = Linker::new(engine);
+
+    linker.func_wrap(
+        "host",
+        "read_file",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> wasmtime::Result<i64> {
+            let path = read_guest_string(&mut caller, ptr, len)?;
+            let response = host_read_file(caller.data(), &path);
+            respond(&mut caller, response)
+        },
+    )?;
+
+    linker.func_wrap(
+        "host",
+        "write_file",
+        |mut caller: Caller<'_, HostState>,
+         path_ptr: i32,
+         path_len: i32,
+         data_ptr: i32,
+         data_len: i32|
+         -> wasmtime::Result<i64> {
+            let path = read_guest_string(&mut caller, path_ptr, path_len)?;
+            let content = read_guest_string(&mut caller, data_ptr, data_len)?;
+            let response = host_write_file(caller.data(), &path, &content);
+            respond(&mut caller, response)
+        },
+    )?;
+
+    linker.func_wrap(
+        "host",
+        "list_directory",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> wasmtime::Result<i64> {
+            let path = read_guest_string(&mut caller, ptr, len)?;
+            let response = host_list_directory(caller.data(), &path);
+            respond(&mut caller, response)
+        },
+    )?;
+
+    linker.func_wrap(
+        "host",
+        "log",
+        |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> wasmtime::Result<()> {
+            let message = read_guest_string(&mut caller, ptr, len)?;
+            eprintln!("[plugin] {}", message);
+            Ok(())
+        },
+    )?;
+
+    Ok(linker)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::claude::test_support::whitelisted_temp_dir;
+
+    /// Quote text for a WAT data segment
+    fn wat_string(text: &str) -> String {
+        text.replace('\\', "\\\\").replace('"', "\\\"")
+    }
+
+    /// A plugin whose `execute` returns what `body` leaves on the stack, with
+    /// `data` placed at offset 1024 for the body to use
+    fn plugin_wat(name: &str, body: &str, data: &str, memory_pages: u32) -> String {
+        let description = json!({
+            "name": name,
+            "description": "Test plugin",
+            "input_schema": {
+                "type": "object",
+                "properties": {"path": {"type": "string"}},
+                "required": [],
+            },
+        })
+        .to_string();
+
+        format!(
+            r#"(module
+  (import "host" "read_file" (func $read_file (param i32 i32) (result i64)))
+  (memory (export "memory") {pages})
+  (global $next (mut i32) (i32.const 8192))
+  (data (i32.const 0) "{description}")
+  (data (i32.const 1024) "{data}")
+  (func (export "alloc") (param $len i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $next))
+    (global.set $next (i32.add (global.get $next) (local.get $len)))
+    (local.get $ptr))
+  (func (export "describe") (result i64)
+    (i64.const {description_len}))
+  (func (export "execute") (param i32 i32) (result i64)
+    {body}))"#,
+            pages = memory_pages,
+            description = wat_string(&description),
+            description_len = description.len(),
+            data = wat_string(data),
+            body = body,
+        )
+    }
+
+    fn load(
+        wat: &str,
+        manifest: PluginManifest,
+        whitelist: Option<Arc<RwLock<WhitelistConfig>>>,
+    ) -> Result<WasmPluginTool> {
+        let engine = plugin_engine()?;
+        let module = Module::new(&engine, wat)?;
+        WasmPluginTool::new(&engine, module, manifest, whitelist)
+    }
+
+    #[tokio::test]
+    async fn test_plugin_declares_itself_and_executes() {
+        let output = r#"{"ok":"hello from wasm"}"#;
+        let body = format!(
+            "(i64.or (i64.shl (i64.const 1024) (i64.const 32)) (i64.const {}))",
+            output.len()
+        );
+        let plugin = load(
+            &plugin_wat("hello", &body, output, 1),
+            PluginManifest::default(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(plugin.name(), "hello");
+        assert_eq!(plugin.description(), "Test plugin");
+        assert_eq!(plugin.input_schema().properties["path"].r#type, "string");
+        assert_eq!(plugin.execute(json!({})).await.unwrap(), "hello from wasm");
+    }
+
+    #[tokio::test]
+    async fn test_file_access_needs_capability_and_whitelist() {
+        let (dir, whitelist) = whitelisted_temp_dir();
+        let inside = dir.join("notes.txt");
+        std::fs::write(&inside, "plugin can read this").unwrap();
+        let outside = std::env::temp_dir().join("rustic-agent-plugin-outside.txt");
+
+        let reader = |path: &Path| {
+            let path = path.to_string_lossy().to_string();
+            let body = format!(
+                "(call $read_file (i32.const 1024) (i32.const {}))",
+                path.len()
+            );
+            plugin_wat("reader", &body, &path, 1)
+        };
+        let granted = PluginManifest {
+            capabilities: vec![PluginCapability::Read],
+            ..PluginManifest::default()
+        };
+
+        let plugin = load(&reader(&inside), granted.clone(), Some(whitelist.clone())).unwrap();
+        assert!(!plugin.requires_approval());
+        assert_eq!(
+            plugin.execute(json!({})).await.unwrap(),
+            "plugin can read this"
+        );
+
+        // Plugins that may write need the user's approval for each call
+        let writer = PluginManifest {
+            capabilities: vec![PluginCapability::Read, PluginCapability::Write],
+            ..PluginManifest::default()
+        };
+        let plugin = load(&reader(&inside), writer, Some(whitelist.clone())).unwrap();
+        assert!(plugin.requires_approval());
+
+        // Same module without the read capability
+        let plugin = load(
+            &reader(&inside),
+            PluginManifest::default(),
+            Some(whitelist.clone()),
+        )
+        .unwrap();
+        let error = plugin.execute(json!({})).await.unwrap_err().to_string();
+        assert!(
+            error.contains("not granted the 'read' capability"),
+            "{}",
+            error
+        );
+
+        // Paths outside the whitelist are refused by validate_path
+        let plugin = load(&reader(&outside), granted, Some(whitelist)).unwrap();
+        let error = plugin.execute(json!({})).await.unwrap_err().to_string();
+        assert!(error.contains("Access denied"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_fuel_limit_stops_runaway_plugin() {
+        let body = "(loop $forever (br $forever)) (i64.const 0)";
+        let manifest = PluginManifest {
+            fuel: 100_000,
+            ..PluginManifest::default()
+        };
+        let plugin = load(&plugin_wat("spin", body, "", 1), manifest, None).unwrap();
+
+        let error = plugin.execute(json!({})).await.unwrap_err().to_string();
+        assert!(error.contains("exceeded its fuel limit"), "{}", error);
+    }
+
+    #[test]
+    fn test_memory_limit_is_enforced() {
+        // 32 pages of 64KB is 2MB, over a 1MB limit
+        let manifest = PluginManifest {
+            max_memory_mb: 1,
+            ..PluginManifest::default()
+        };
+        let error = load(&plugin_wat("big", "(i64.const 0)", "", 32), manifest, None).unwrap_err();
+        assert!(error.to_string().contains("memory"), "{}", error);
+    }
+
+    #[test]
+    fn test_manifest_limits_are_validated() {
+        let manifest: PluginManifest =
+            toml::from_str("capabilities = [\"read\", \"list\"]\nmax_memory_mb = 16").unwrap();
+        assert_eq!(
+            manifest.capabilities,
+            vec![PluginCapability::Read, PluginCapability::List]
+        );
+        assert_eq!(manifest.fuel, DEFAULT_FUEL);
+        manifest.validate().unwrap();
+
+        let manifest = PluginManifest {
+            max_memory_mb: MAX_MEMORY_MB + 1,
+            ..PluginManifest::default()
+        };
+        assert!(manifest.validate().is_err());
+        assert!(toml::from_str::<PluginManifest>("network = true").is_err());
+    }
+}
@@ -1,6 +1,8 @@
 use crate::claude::{
-    error::ClaudeResult,
-    tools::execution::{ToolError, ToolErrorType, ToolExecutionContext},
+    error::{ClaudeError, ClaudeResult, ErrorContext},
+    tools::execution::{RecoveryKind, ToolError, ToolErrorType, ToolExecutionContext},
+    tools::{create_tool_directory, resolve_tool_path},
+    whitelist::FileOperation,
 };
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
@@ -76,6 +78,49 @@ impl RecoveryAction {
             RecoveryAction::AbortChain { .. } => Duration::from_millis(0),
         }
     }
+
+    /// Describe what carrying out this action does, one entry per effect
+    pub fn describe(&self) -> Vec<(RecoveryKind, String)> {
+        match self {
+            RecoveryAction::Retry {
+                delay,
+                modified_input: Some(_),
+                ..
+            } => vec![(
+                RecoveryKind::ModifyInput,
+                format!("Retried with modified input after {:?}", delay),
+            )],
+            RecoveryAction::Retry { delay, .. } => {
+                vec![(RecoveryKind::Retry, format!("Retried after {:?}", delay))]
+            }
+            RecoveryAction::FallbackTool {
+                tool_name, reason, ..
+            } => vec![(
+                RecoveryKind::FallbackTool,
+                format!("Switched to fallback tool '{}': {}", tool_name, reason),
+            )],
+            RecoveryAction::UserIntervention { message, .. } => {
+                vec![(RecoveryKind::UserIntervention, message.clone())]
+            }
+            RecoveryAction::SkipAndContinue { reason, .. } => {
+                vec![(RecoveryKind::Skip, format!("Skipped: {}", reason))]
+            }
+            RecoveryAction::AbortChain { reason, .. } => {
+                vec![(RecoveryKind::Abort, format!("Aborted: {}", reason))]
+            }
+            RecoveryAction::FixAndRetry {
+                fix_description,
+                fix_actions,
+                ..
+            } => {
+                let mut steps: Vec<_> = fix_actions.iter().map(FixAction::describe).collect();
+                if steps.is_empty() {
+                    steps.push((RecoveryKind::Retry, fix_description.clone()));
+                }
+                steps
+            }
+        }
+    }
 }
 
 /// Specific fix actions that can be applied automatically
@@ -100,6 +145,35 @@ pub enum FixAction {
     },
 }
 
+impl FixAction {
+    /// Describe the effect of this fix for result metadata
+    pub fn describe(&self) -> (RecoveryKind, String) {
+        match self {
+            FixAction::ModifyInput {
+                parameter,
+                new_value,
+                reason,
+            } => (
+                RecoveryKind::ModifyInput,
+                format!("Set '{}' to {} ({})", parameter, new_value, reason),
+            ),
+            FixAction::IncreaseTimeout { new_timeout } => (
+                RecoveryKind::IncreaseTimeout,
+                format!("Raised timeout to {:?}", new_timeout),
+            ),
+            FixAction::CreateDirectory { path } => (
+                RecoveryKind::CreateDirectory,
+                format!("Created missing directory '{}'", path),
+            ),
+            FixAction::ChangePermissions { path, permissions } => (
+                RecoveryKind::OtherFix,
+                format!("Suggested permissions '{}' for '{}'", permissions, path),
+            ),
+            FixAction::Custom { description, .. } => (RecoveryKind::OtherFix, description.clone()),
+        }
+    }
+}
+
 /// Trait for implementing tool-specific recovery strategies
 #[async_trait]
 pub trait RecoveryStrategy: Send + Sync + std::fmt::Debug {
@@ -245,16 +319,43 @@ impl ToolRecoveryManager {
                 retry_delay,
                 ..
             } => {
-                // Execute fix actions
+                // Fixes to the input and timeout are applied to the context the
+                // engine retries with; the rest act on the environment
+                let mut new_context = context.clone();
                 for fix_action in fix_actions {
-                    if let Err(e) = self.execute_fix_action(fix_action, context).await {
-                        eprintln!("Warning: Fix action failed: {}", e);
+                    match fix_action {
+                        FixAction::ModifyInput {
+                            parameter,
+                            new_value,
+                            ..
+                        } => {
+                            let Some(input) = new_context.input.as_object_mut() else {
+                                return Err(ClaudeError::ToolError {
+                                    tool_name: context.tool_name.clone(),
+                                    message: format!(
+                                        "Cannot set '{}': tool input is not an object",
+                                        parameter
+                                    ),
+                                    context: Some(ErrorContext::new("recovery_modify_input")),
+                                });
+                            };
+                            input.insert(parameter.clone(), new_value.clone());
+                        }
+                        FixAction::IncreaseTimeout { new_timeout } => {
+                            new_context.metadata.timeout =
+                                (*new_timeout).max(new_context.metadata.timeout);
+                        }
+                        _ => {}
                     }
+                    // A fix that could not be applied makes the retry pointless
+                    self.execute_fix_action(fix_action, context).await?;
                 }
 
                 tokio::time::sleep(*retry_delay).await;
+                let changed = new_context.input != context.input
+                    || new_context.metadata.timeout != context.metadata.timeout;
                 Ok(RecoveryResult::Retry {
-                    modified_context: None,
+                    modified_context: changed.then_some(new_context),
                 })
             }
             RecoveryAction::UserIntervention {
@@ -287,7 +388,7 @@ impl ToolRecoveryManager {
     async fn execute_fix_action(
         &self,
         fix_action: &FixAction,
-        _context: &ToolExecutionContext,
+        context: &ToolExecutionContext,
     ) -> ClaudeResult<()> {
         match fix_action {
             FixAction::ModifyInput {
@@ -306,19 +407,39 @@ impl ToolRecoveryManager {
                 Ok(())
             }
             FixAction::CreateDirectory { path } => {
+                if !self.config.enable_automatic_fixes {
+                    return Err(ClaudeError::ToolError {
+                        tool_name: context.tool_name.clone(),
+                        message: format!("Not creating '{}': automatic fixes are disabled", path),
+                        context: Some(ErrorContext::new("recovery_create_directory")),
+                    });
+                }
+
                 eprintln!("Fix: Creating directory '{}'", path);
-                // In a real implementation, this would actually create the directory
-                // with proper permission checks
-                Ok(())
+                // Same whitelist checks as the create_directory tool, and created
+                // beneath the whitelisted root like it
+                let whitelist = Some(&context.whitelist);
+                let created = async {
+                    let safe_path =
+                        resolve_tool_path(whitelist, path, FileOperation::CreateDirectory).await?;
+                    create_tool_directory(whitelist, &safe_path).await
+                };
+                created.await.map_err(|e| ClaudeError::ToolError {
+                    tool_name: context.tool_name.clone(),
+                    message: format!("Cannot create directory '{}': {}", path, e),
+                    context: Some(ErrorContext::new("recovery_create_directory")),
+                })
             }
             FixAction::ChangePermissions { path, permissions } => {
-                eprintln!(
-                    "Fix: Changing permissions of '{}' to '{}'",
-                    path, permissions
-                );
-                // In a real implementation, this would change file permissions
-                // with proper security validation
-                Ok(())
+                // Permission changes are left to the user; retrying unchanged is pointless
+                Err(ClaudeError::ToolError {
+                    tool_name: context.tool_name.clone(),
+                    message: format!(
+                        "Cannot change permissions of '{}' to '{}' automatically",
+                        path, permissions
+                    ),
+                    context: Some(ErrorContext::new("recovery_change_permissions")),
+                })
             }
             FixAction::Custom { description, .. } => {
                 eprintln!("Fix: {}", description);
@@ -600,20 +721,17 @@ impl RecoveryStrategy for FileOperationRecoveryStrategy {
                 }
             }
             ToolErrorType::ValidationError => {
-                // Check if it's a missing directory issue
-                if error.message.contains("No such file or directory")
-                    || error.message.contains("not found")
-                {
-                    if let Some(path) = context.input.get("path").and_then(|p| p.as_str()) {
-                        if let Some(parent) = std::path::Path::new(path).parent() {
-                            actions.push(RecoveryAction::FixAndRetry {
-                                fix_description: "Create missing parent directory".to_string(),
-                                fix_actions: vec![FixAction::CreateDirectory {
-                                    path: parent.to_string_lossy().to_string(),
-                                }],
-                                retry_delay: Duration::from_millis(500),
-                            });
-                        }
+                // Only a missing parent of a file being written is created; a
+                // missing file to read is the model's mistake, not a directory to make
+                if let Some(path) = written_path(context) {
+                    if let Some(parent) = std::path::Path::new(path).parent() {
+                        actions.push(RecoveryAction::FixAndRetry {
+                            fix_description: "Create missing parent directory".to_string(),
+                            fix_actions: vec![FixAction::CreateDirectory {
+                                path: parent.to_string_lossy().to_string(),
+                            }],
+                            retry_delay: Duration::from_millis(500),
+                        });
                     }
                 }
             }
@@ -646,6 +764,16 @@ impl RecoveryStrategy for FileOperationRecoveryStrategy {
     }
 }
 
+/// The file a write-type tool creates, whose parent directory recovery may create
+fn written_path(context: &ToolExecutionContext) -> Option<&str> {
+    let parameter = match context.tool_name.as_str() {
+        "write_file" => "path",
+        "copy_file" | "move_file" => "destination",
+        _ => return None,
+    };
+    context.input.get(parameter).and_then(|p| p.as_str())
+}
+
 /// Specialized recovery strategy for timeout errors
 #[derive(Debug)]
 pub struct TimeoutRecoveryStrategy;
@@ -661,17 +789,18 @@ impl RecoveryStrategy for TimeoutRecoveryStrategy {
     async fn suggest_recovery(
         &self,
         _error: &ToolError,
-        _context: &ToolExecutionContext,
+        context: &ToolExecutionContext,
         attempt_count: u32,
     ) -> ClaudeResult<Vec<RecoveryAction>> {
         let mut actions = Vec::new();
 
         if attempt_count < 2 {
-            // Suggest retry with increased timeout
+            // Suggest retry with increased timeout, at least doubling the current one
             actions.push(RecoveryAction::FixAndRetry {
                 fix_description: "Increase timeout and retry".to_string(),
                 fix_actions: vec![FixAction::IncreaseTimeout {
-                    new_timeout: Duration::from_secs(60 * (attempt_count + 2) as u64),
+                    new_timeout: Duration::from_secs(60 * (attempt_count + 2) as u64)
+                        .max(context.metadata.timeout * 2),
                 }],
                 retry_delay: Duration::from_secs(5),
             });
@@ -708,6 +837,7 @@ impl RecoveryStrategy for TimeoutRecoveryStrategy {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::claude::test_support::whitelisted_temp_dir;
     use crate::claude::whitelist::WhitelistConfig;
 
     fn create_test_context() -> ToolExecutionContext {
@@ -757,6 +887,46 @@ mod tests {
             .any(|action| matches!(action, RecoveryAction::UserIntervention { .. })));
     }
 
+    #[tokio::test]
+    async fn test_missing_directory_is_only_created_for_written_files() {
+        let strategy = FileOperationRecoveryStrategy::new();
+        let not_found = ToolError::validation_error("No such file or directory (os error 2)");
+        let created_directory = |actions: &[RecoveryAction]| {
+            actions.iter().find_map(|action| match action {
+                RecoveryAction::FixAndRetry { fix_actions, .. } => {
+                    fix_actions.iter().find_map(|fix| match fix {
+                        FixAction::CreateDirectory { path } => Some(path.clone()),
+                        _ => None,
+                    })
+                }
+                _ => None,
+            })
+        };
+
+        let whitelist = Arc::new(RwLock::new(WhitelistConfig::default()));
+        let copy = ToolExecutionContext::new(
+            "copy_file".to_string(),
+            serde_json::json!({"source": "/data/a.txt", "destination": "/out/new/b.txt"}),
+            whitelist.clone(),
+        );
+        let actions = strategy
+            .suggest_recovery(&not_found, &copy, 0)
+            .await
+            .unwrap();
+        assert_eq!(created_directory(&actions).as_deref(), Some("/out/new"));
+
+        let read = ToolExecutionContext::new(
+            "read_file".to_string(),
+            serde_json::json!({"path": "/data/missing/a.txt"}),
+            whitelist,
+        );
+        let actions = strategy
+            .suggest_recovery(&not_found, &read, 0)
+            .await
+            .unwrap();
+        assert_eq!(created_directory(&actions), None);
+    }
+
     #[tokio::test]
     async fn test_timeout_recovery_strategy() {
         let strategy = TimeoutRecoveryStrategy::new();
@@ -849,6 +1019,84 @@ mod tests {
         assert!(json.contains("/tmp/test"));
     }
 
+    #[tokio::test]
+    async fn test_modify_input_fix_updates_retry_input() {
+        let manager = ToolRecoveryManager::default();
+        let context = create_test_context();
+        let action = RecoveryAction::FixAndRetry {
+            fix_description: "Use a smaller page".to_string(),
+            fix_actions: vec![FixAction::ModifyInput {
+                parameter: "max_lines".to_string(),
+                new_value: serde_json::json!(100),
+                reason: "Output too large".to_string(),
+            }],
+            retry_delay: Duration::from_millis(0),
+        };
+
+        let result = manager
+            .execute_recovery_action(&action, &context)
+            .await
+            .unwrap();
+        let RecoveryResult::Retry {
+            modified_context: Some(modified),
+        } = result
+        else {
+            panic!("Expected retry with a modified context");
+        };
+        assert_eq!(modified.input["max_lines"], 100);
+        assert_eq!(modified.input["path"], "/test/file.txt");
+        assert_eq!(action.describe()[0].0, RecoveryKind::ModifyInput);
+    }
+
+    #[tokio::test]
+    async fn test_increase_timeout_fix_extends_context() {
+        let manager = ToolRecoveryManager::default();
+        let context = create_test_context().with_timeout(Duration::from_secs(30));
+        let action = RecoveryAction::FixAndRetry {
+            fix_description: "Increase timeout and retry".to_string(),
+            fix_actions: vec![FixAction::IncreaseTimeout {
+                new_timeout: Duration::from_secs(120),
+            }],
+            retry_delay: Duration::from_millis(0),
+        };
+
+        match manager.execute_recovery_action(&action, &context).await {
+            Ok(RecoveryResult::Retry {
+                modified_context: Some(new_context),
+            }) => assert_eq!(new_context.metadata.timeout, Duration::from_secs(120)),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_directory_fix_action() {
+        let (dir, whitelist) = whitelisted_temp_dir();
+        let context =
+            ToolExecutionContext::new("write_file".to_string(), serde_json::json!({}), whitelist);
+        let manager = ToolRecoveryManager::new();
+        let missing = dir.join("missing").join("parent");
+
+        manager
+            .execute_fix_action(
+                &FixAction::CreateDirectory {
+                    path: missing.to_string_lossy().to_string(),
+                },
+                &context,
+            )
+            .await
+            .unwrap();
+        assert!(missing.is_dir());
+
+        // Paths outside the whitelist are refused
+        let outside = FixAction::CreateDirectory {
+            path: "/rustic-agent-outside-whitelist".to_string(),
+        };
+        assert!(manager
+            .execute_fix_action(&outside, &context)
+            .await
+            .is_err());
+    }
+
     #[tokio::test]
     async fn test_recovery_attempt_limiting() {
         let mut manager = ToolRecoveryManager::new();
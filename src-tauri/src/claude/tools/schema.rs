@@ -0,0 +1,329 @@
+//! JSON Schema support for tool inputs.
+//!
+//! Tools declare a typed input struct deriving `Deserialize` and `JsonSchema`.
+//! The schema sent to Claude is derived from that struct, and the engine
+//! validates incoming input against it before the tool runs, so tools only
+//! have to deserialize input that is already known to be well formed.
+
+use crate::claude::types::{PropertySchema, ToolInputSchema};
+use anyhow::Result;
+use schemars::{gen::SchemaSettings, JsonSchema};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// Derive a tool's input schema from its typed input struct
+pub fn input_schema_for<T: JsonSchema>() -> ToolInputSchema {
+    let settings = SchemaSettings::draft07().with(|settings| {
+        // Claude gets one self-contained schema, and optional fields are
+        // expressed by leaving them out of `required`
+        settings.inline_subschemas = true;
+        settings.option_add_null_type = false;
+    });
+    let root = settings.into_generator().into_root_schema_for::<T>();
+
+    serde_json::to_value(root.schema)
+        .and_then(serde_json::from_value)
+        .unwrap_or_else(|e| {
+            panic!(
+                "Input schema for {} is not expressible as a tool schema: {}",
+                std::any::type_name::<T>(),
+                e
+            )
+        })
+}
+
+/// Deserialize validated tool input into the tool's typed input struct
+pub fn parse_input<T: DeserializeOwned>(input: Value) -> Result<T> {
+    serde_json::from_value(input).map_err(|e| anyhow::anyhow!("Invalid input: {}", e))
+}
+
+/// Check tool input against the tool's schema, reporting every violation
+pub fn validate_input(schema: &ToolInputSchema, input: &Value) -> Result<()> {
+    let mut errors = Vec::new();
+    match input.as_object() {
+        Some(object) => validate_object(
+            &schema.properties,
+            &schema.required,
+            Some(schema.additional_properties),
+            object,
+            "input",
+            &mut errors,
+        ),
+        None => errors.push("input: expected an object".to_string()),
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("Invalid input: {}", errors.join("; ")))
+    }
+}
+
+fn validate_object(
+    properties: &HashMap<String, PropertySchema>,
+    required: &[String],
+    additional_properties: Option<bool>,
+    object: &Map<String, Value>,
+    path: &str,
+    errors: &mut Vec<String>,
+) {
+    for name in required {
+        if object.get(name).is_none_or(Value::is_null) {
+            errors.push(format!("{}: missing required property '{}'", path, name));
+        }
+    }
+
+    for (name, value) in object {
+        match properties.get(name) {
+            // Optional properties may be given as null, which means "not set"
+            Some(_) if value.is_null() && !required.contains(name) => {}
+            Some(schema) => validate_value(schema, value, &format!("{}.{}", path, name), errors),
+            None if additional_properties == Some(false) => {
+                errors.push(format!("{}: unknown property '{}'", path, name));
+            }
+            None => {}
+        }
+    }
+}
+
+fn validate_value(schema: &PropertySchema, value: &Value, path: &str, errors: &mut Vec<String>) {
+    if !schema.one_of.is_empty() {
+        let matching = schema
+            .one_of
+            .iter()
+            .filter(|variant| matches_schema(variant, value))
+            .count();
+        if matching != 1 {
+            errors.push(format!(
+                "{}: must match exactly one allowed form (matched {})",
+                path, matching
+            ));
+            return;
+        }
+    }
+    if !schema.any_of.is_empty()
+        && !schema
+            .any_of
+            .iter()
+            .any(|variant| matches_schema(variant, value))
+    {
+        errors.push(format!("{}: does not match any allowed form", path));
+        return;
+    }
+
+    if !schema.r#type.is_empty() && !type_matches(&schema.r#type, value) {
+        errors.push(format!(
+            "{}: expected {}, got {}",
+            path,
+            schema.r#type,
+            type_name(value)
+        ));
+        return;
+    }
+
+    if let Some(allowed) = &schema.enum_values {
+        if !allowed.contains(value) {
+            let allowed: Vec<String> = allowed.iter().map(Value::to_string).collect();
+            errors.push(format!(
+                "{}: must be one of {}, got {}",
+                path,
+                allowed.join(", "),
+                value
+            ));
+        }
+    }
+
+    match value {
+        Value::Number(number) => {
+            let number = number.as_f64().unwrap_or_default();
+            if let Some(minimum) = schema.minimum.filter(|minimum| number < *minimum) {
+                errors.push(format!("{}: must be at least {}", path, minimum));
+            }
+            if let Some(maximum) = schema.maximum.filter(|maximum| number > *maximum) {
+                errors.push(format!("{}: must be at most {}", path, maximum));
+            }
+        }
+        Value::String(text) => {
+            let length = text.chars().count() as u64;
+            if let Some(min_length) = schema.min_length.filter(|min| length < *min) {
+                errors.push(format!(
+                    "{}: must be at least {} characters",
+                    path, min_length
+                ));
+            }
+            if let Some(max_length) = schema.max_length.filter(|max| length > *max) {
+                errors.push(format!(
+                    "{}: must be at most {} characters",
+                    path, max_length
+                ));
+            }
+        }
+        Value::Array(items) => {
+            let count = items.len() as u64;
+            if let Some(min_items) = schema.min_items.filter(|min| count < *min) {
+                errors.push(format!("{}: must have at least {} items", path, min_items));
+            }
+            if let Some(max_items) = schema.max_items.filter(|max| count > *max) {
+                errors.push(format!("{}: must have at most {} items", path, max_items));
+            }
+            if let Some(item_schema) = &schema.items {
+                for (index, item) in items.iter().enumerate() {
+                    validate_value(item_schema, item, &format!("{}[{}]", path, index), errors);
+                }
+            }
+        }
+        Value::Object(object) => validate_object(
+            &schema.properties,
+            &schema.required,
+            schema.additional_properties,
+            object,
+            path,
+            errors,
+        ),
+        Value::Bool(_) | Value::Null => {}
+    }
+}
+
+fn matches_schema(schema: &PropertySchema, value: &Value) -> bool {
+    let mut errors = Vec::new();
+    validate_value(schema, value, "", &mut errors);
+    errors.is_empty()
+}
+
+fn type_matches(expected: &str, value: &Value) -> bool {
+    match expected {
+        "string" => value.is_string(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "boolean" => value.is_boolean(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(number) if number.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    #[derive(Debug, Deserialize, Serialize, JsonSchema, PartialEq)]
+    #[serde(rename_all = "snake_case")]
+    enum Mode {
+        Fast,
+        Thorough,
+    }
+
+    #[derive(Debug, Deserialize, JsonSchema)]
+    #[serde(deny_unknown_fields)]
+    struct Range {
+        start: u32,
+        end: u32,
+    }
+
+    /// Sample tool input
+    #[derive(Debug, Deserialize, JsonSchema)]
+    #[serde(deny_unknown_fields)]
+    struct SampleInput {
+        /// File to inspect
+        path: String,
+        /// How hard to look
+        #[serde(default = "default_mode")]
+        mode: Mode,
+        /// Number of results
+        #[schemars(range(min = 1, max = 50))]
+        limit: Option<u32>,
+        /// Lines to inspect
+        range: Option<Range>,
+    }
+
+    fn default_mode() -> Mode {
+        Mode::Fast
+    }
+
+    #[test]
+    fn test_schema_derived_from_input_struct() {
+        let schema = input_schema_for::<SampleInput>();
+        assert_eq!(schema.r#type, "object");
+        assert_eq!(schema.required, vec!["path".to_string()]);
+        assert!(!schema.additional_properties);
+
+        let path = &schema.properties["path"];
+        assert_eq!(path.r#type, "string");
+        assert_eq!(path.description, "File to inspect");
+
+        let mode = &schema.properties["mode"];
+        assert_eq!(
+            mode.enum_values,
+            Some(vec![json!("fast"), json!("thorough")])
+        );
+        assert_eq!(mode.default, Some(json!("fast")));
+
+        let limit = &schema.properties["limit"];
+        assert_eq!(limit.r#type, "integer");
+        assert_eq!(limit.minimum, Some(1.0));
+        assert_eq!(limit.maximum, Some(50.0));
+
+        let range = &schema.properties["range"];
+        assert_eq!(range.properties["start"].r#type, "integer");
+        assert_eq!(range.required.len(), 2);
+
+        // Serializes as plain JSON Schema for the API
+        let value = serde_json::to_value(&schema).unwrap();
+        assert_eq!(
+            value["properties"]["mode"]["enum"],
+            json!(["fast", "thorough"])
+        );
+        assert_eq!(value["additionalProperties"], json!(false));
+    }
+
+    #[test]
+    fn test_validate_input_reports_violations() {
+        let schema = input_schema_for::<SampleInput>();
+
+        validate_input(&schema, &json!({"path": "src/main.rs"})).unwrap();
+        validate_input(
+            &schema,
+            &json!({"path": "a", "mode": "thorough", "limit": 5, "range": {"start": 1, "end": 2}}),
+        )
+        .unwrap();
+        validate_input(&schema, &json!({"path": "a", "limit": null})).unwrap();
+
+        let error = validate_input(
+            &schema,
+            &json!({"mode": "slow", "limit": 100, "range": {"start": "1", "end": 2}, "extra": 1}),
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("missing required property 'path'"));
+        assert!(error.contains("input.mode: must be one of \"fast\", \"thorough\""));
+        assert!(error.contains("input.limit: must be at most 50"));
+        assert!(error.contains("input.range.start: expected integer, got string"));
+        assert!(error.contains("unknown property 'extra'"));
+
+        assert!(validate_input(&schema, &json!("src/main.rs")).is_err());
+    }
+
+    #[test]
+    fn test_parse_input_applies_defaults() {
+        let input: SampleInput = parse_input(json!({"path": "a"})).unwrap();
+        assert_eq!(input.path, "a");
+        assert_eq!(input.mode, Mode::Fast);
+        assert!(input.limit.is_none());
+    }
+}
@@ -0,0 +1,500 @@
+//! On-disk symbol index backing the `find_symbol` tool.
+//!
+//! Directories are scanned on first use and the resulting definitions are
+//! persisted as JSON in the app data dir. `FileWatcherService` keeps the index
+//! fresh by re-parsing files as they are created, modified or removed.
+
+use crate::claude::constants::code_index::{
+    DEFAULT_SYMBOL_RESULTS, MAX_INDEXED_FILES, MAX_SOURCE_FILE_BYTES, MAX_SYMBOL_RESULTS,
+    SKIPPED_DIRECTORIES,
+};
+use crate::claude::tools::outline::{outline_file, SourceLanguage, Symbol};
+use crate::claude::tools::{
+    input_schema_for, parse_input, resolve_tool_path, AgentTool, ToolResultData,
+};
+use crate::claude::types::ToolInputSchema;
+use crate::claude::whitelist::{validate_path, FileOperation, WhitelistConfig};
+use anyhow::Result;
+use async_trait::async_trait;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+use tokio::sync::RwLock;
+
+pub type SharedSymbolIndex = Arc<RwLock<SymbolIndex>>;
+
+/// Symbols extracted from one file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedFile {
+    pub language: SourceLanguage,
+    /// Modification time in seconds since the Unix epoch when the file was parsed
+    pub modified: Option<u64>,
+    pub symbols: Vec<Symbol>,
+}
+
+/// A symbol search result
+#[derive(Debug, Clone, Serialize)]
+pub struct SymbolMatch {
+    pub file: PathBuf,
+    #[serde(flatten)]
+    pub symbol: Symbol,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct SymbolIndex {
+    #[serde(skip)]
+    storage_path: Option<PathBuf>,
+    roots: HashSet<PathBuf>,
+    files: HashMap<PathBuf, IndexedFile>,
+}
+
+// Manual impl: the index can hold many thousands of symbols
+impl std::fmt::Debug for SymbolIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SymbolIndex")
+            .field("storage_path", &self.storage_path)
+            .field("roots", &self.roots)
+            .field("files", &self.files.len())
+            .finish()
+    }
+}
+
+fn modified_secs(path: &Path) -> Option<u64> {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+}
+
+fn index_file(path: &Path) -> Option<IndexedFile> {
+    match outline_file(path) {
+        Ok(Some((language, symbols))) => Some(IndexedFile {
+            language,
+            modified: modified_secs(path),
+            symbols,
+        }),
+        _ => None,
+    }
+}
+
+/// Walk `root` and parse every supported source file. Symlinks, hidden
+/// directories and build/dependency directories are skipped.
+pub fn scan_directory(root: &Path) -> HashMap<PathBuf, IndexedFile> {
+    let mut files = HashMap::new();
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let path = entry.path();
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+
+            if file_type.is_dir() {
+                if !name.starts_with('.') && !SKIPPED_DIRECTORIES.contains(&name.as_ref()) {
+                    pending.push(path);
+                }
+            } else if file_type.is_file() && SourceLanguage::from_path(&path).is_some() {
+                if files.len() >= MAX_INDEXED_FILES {
+                    return files;
+                }
+                let small_enough = entry
+                    .metadata()
+                    .map(|m| m.len() <= MAX_SOURCE_FILE_BYTES)
+                    .unwrap_or(false);
+                if small_enough {
+                    if let Some(indexed) = index_file(&path) {
+                        files.insert(path, indexed);
+                    }
+                }
+            }
+        }
+    }
+
+    files
+}
+
+/// Relevance of a symbol name for a query: exact, prefix, then substring matches
+fn match_rank(name: &str, query: &str) -> Option<u8> {
+    let name = name.to_lowercase();
+    if name == query {
+        Some(0)
+    } else if name.starts_with(query) {
+        Some(1)
+    } else if name.contains(query) {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+impl SymbolIndex {
+    pub fn new(storage_path: Option<PathBuf>) -> Self {
+        Self {
+            storage_path,
+            ..Default::default()
+        }
+    }
+
+    /// Load a persisted index, starting empty if it is missing or unreadable
+    pub fn load(storage_path: PathBuf) -> Self {
+        let mut index = std::fs::read_to_string(&storage_path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<SymbolIndex>(&content).ok())
+            .unwrap_or_default();
+        index.storage_path = Some(storage_path);
+        index
+    }
+
+    /// Persist the index; a no-op for in-memory indexes
+    pub async fn save(&self) -> Result<()> {
+        let Some(path) = &self.storage_path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let content = serde_json::to_string(self)?;
+        tokio::fs::write(path, content).await?;
+        Ok(())
+    }
+
+    /// Whether `dir` is covered by a directory that has already been scanned
+    pub fn is_indexed(&self, dir: &Path) -> bool {
+        self.roots.iter().any(|root| dir.starts_with(root))
+    }
+
+    /// Replace everything under `root` with a fresh scan result
+    pub fn insert_scan(&mut self, root: PathBuf, files: HashMap<PathBuf, IndexedFile>) {
+        self.files.retain(|path, _| !path.starts_with(&root));
+        self.files.extend(files);
+        // A new root subsumes any previously scanned subdirectories
+        self.roots.retain(|existing| !existing.starts_with(&root));
+        self.roots.insert(root);
+    }
+
+    /// Re-parse a changed file, or drop it if it no longer exists. Returns
+    /// whether the index changed.
+    pub fn update_file(&mut self, path: &Path) -> bool {
+        if !self.is_indexed(path) {
+            return false;
+        }
+        if path.is_dir() {
+            return false;
+        }
+        if !path.exists() {
+            // Removing a directory removes everything below it
+            let before = self.files.len();
+            self.files.retain(|file, _| !file.starts_with(path));
+            return self.files.len() != before;
+        }
+        if SourceLanguage::from_path(path).is_none() {
+            return false;
+        }
+
+        let modified = modified_secs(path);
+        if let Some(existing) = self.files.get(path) {
+            if existing.modified.is_some() && existing.modified == modified {
+                return false;
+            }
+        }
+
+        match index_file(path) {
+            Some(indexed) => {
+                self.files.insert(path.to_path_buf(), indexed);
+            }
+            None => {
+                self.files.remove(path);
+            }
+        }
+        true
+    }
+
+    /// Find symbols under `root` whose name contains `query` (case-insensitive),
+    /// best matches first
+    pub fn search(
+        &self,
+        query: &str,
+        kind: Option<&str>,
+        root: &Path,
+        limit: usize,
+        mut allowed: impl FnMut(&Path) -> bool,
+    ) -> Vec<SymbolMatch> {
+        let query = query.to_lowercase();
+        let mut matches: Vec<(u8, SymbolMatch)> = self
+            .files
+            .iter()
+            .filter(|(path, _)| path.starts_with(root))
+            .flat_map(|(path, file)| {
+                file.symbols.iter().filter_map(|symbol| {
+                    if kind.is_some_and(|k| !symbol.kind.eq_ignore_ascii_case(k)) {
+                        return None;
+                    }
+                    match_rank(&symbol.name, &query).map(|rank| {
+                        (
+                            rank,
+                            SymbolMatch {
+                                file: path.clone(),
+                                symbol: symbol.clone(),
+                            },
+                        )
+                    })
+                })
+            })
+            .collect();
+
+        matches.sort_by(|(rank_a, a), (rank_b, b)| {
+            rank_a
+                .cmp(rank_b)
+                .then_with(|| a.symbol.name.len().cmp(&b.symbol.name.len()))
+                .then_with(|| a.file.cmp(&b.file))
+                .then_with(|| a.symbol.line_start.cmp(&b.symbol.line_start))
+        });
+        // Files are checked in rank order, so hidden top matches make room for
+        // allowed ones further down instead of shortening the results
+        matches
+            .into_iter()
+            .map(|(_, m)| m)
+            .filter(|m| allowed(&m.file))
+            .take(limit)
+            .collect()
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+}
+
+/// Input for `find_symbol`
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct FindSymbolInput {
+    /// Symbol name or part of it (case-insensitive)
+    #[schemars(length(min = 1))]
+    query: String,
+    /// Project directory to search (default: current directory)
+    path: Option<String>,
+    /// Only return this kind of symbol, e.g. 'function', 'method', 'struct', 'class'
+    kind: Option<String>,
+    /// Maximum number of results
+    #[serde(default = "default_symbol_results")]
+    #[schemars(range(min = 1, max = "MAX_SYMBOL_RESULTS"))]
+    limit: usize,
+}
+
+fn default_symbol_results() -> usize {
+    DEFAULT_SYMBOL_RESULTS
+}
+
+#[derive(Debug)]
+pub struct FindSymbolTool {
+    whitelist: Option<Arc<RwLock<WhitelistConfig>>>,
+    index: SharedSymbolIndex,
+}
+
+impl FindSymbolTool {
+    pub fn new(index: SharedSymbolIndex) -> Self {
+        Self {
+            whitelist: None,
+            index,
+        }
+    }
+}
+
+#[async_trait]
+impl AgentTool for FindSymbolTool {
+    fn set_whitelist(&mut self, whitelist: Arc<RwLock<WhitelistConfig>>) {
+        self.whitelist = Some(whitelist);
+    }
+
+    fn name(&self) -> &str {
+        "find_symbol"
+    }
+
+    fn description(&self) -> &str {
+        "Find where functions, types, classes and other definitions are declared across a project by name"
+    }
+
+    fn input_schema(&self) -> ToolInputSchema {
+        input_schema_for::<FindSymbolInput>()
+    }
+
+    async fn execute(&self, input: Value) -> Result<String> {
+        match self.execute_data(input).await? {
+            ToolResultData::Json(value) => Ok(serde_json::to_string_pretty(&value)?),
+            _ => Err(anyhow::anyhow!("Unexpected find_symbol result")),
+        }
+    }
+
+    async fn execute_data(&self, input: Value) -> Result<ToolResultData> {
+        let input: FindSymbolInput = parse_input(input)?;
+        let query = input.query.trim();
+        if query.is_empty() {
+            return Err(anyhow::anyhow!("Query cannot be empty"));
+        }
+        let path_str = input.path.as_deref().unwrap_or(".");
+        let kind = input.kind.as_deref();
+        let limit = input.limit.clamp(1, MAX_SYMBOL_RESULTS);
+
+        let root =
+            resolve_tool_path(self.whitelist.as_ref(), path_str, FileOperation::List).await?;
+
+        // Scan on first use; afterwards the file watcher keeps the index fresh
+        let needs_scan = !self.index.read().await.is_indexed(&root);
+        if needs_scan {
+            let scan_root = root.clone();
+            let files = tokio::task::spawn_blocking(move || scan_directory(&scan_root))
+                .await
+                .map_err(|e| anyhow::anyhow!("Indexing task failed: {}", e))?;
+            let mut index = self.index.write().await;
+            index.insert_scan(root.clone(), files);
+            if let Err(e) = index.save().await {
+                eprintln!("Failed to save symbol index: {}", e);
+            }
+        }
+
+        // Leave out results the whitelist would not let Claude read, checking
+        // each file once
+        let whitelist = match &self.whitelist {
+            Some(whitelist) => Some(whitelist.read().await),
+            None => None,
+        };
+        let mut checked: HashMap<PathBuf, bool> = HashMap::new();
+        let matches = self
+            .index
+            .read()
+            .await
+            .search(query, kind, &root, limit, |file| {
+                let Some(whitelist) = &whitelist else {
+                    return true;
+                };
+                *checked.entry(file.to_path_buf()).or_insert_with(|| {
+                    validate_path(&file.to_string_lossy(), whitelist, FileOperation::Read).is_ok()
+                })
+            });
+
+        Ok(ToolResultData::json(json!({
+            "query": query,
+            "root": root.to_string_lossy(),
+            "count": matches.len(),
+            "matches": matches,
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::claude::test_support::{whitelisted_temp_dir, TempDir};
+
+    fn setup_project() -> (TempDir, Arc<RwLock<WhitelistConfig>>) {
+        let (dir, whitelist) = whitelisted_temp_dir();
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::create_dir_all(dir.join("target")).unwrap();
+        std::fs::write(
+            dir.join("src/lib.rs"),
+            "pub struct Parser;\nimpl Parser {\n    pub fn parse_all(&self) {}\n}\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("target/gen.rs"), "pub fn parse_generated() {}\n").unwrap();
+        std::fs::write(dir.join("app.py"), "def parse_args():\n    pass\n").unwrap();
+        (dir, whitelist)
+    }
+
+    #[tokio::test]
+    async fn test_find_symbol_scans_and_ranks() {
+        let (dir, whitelist) = setup_project();
+        let index = Arc::new(RwLock::new(SymbolIndex::new(Some(
+            dir.join(".index/symbols.json"),
+        ))));
+        let mut tool = FindSymbolTool::new(index.clone());
+        tool.set_whitelist(whitelist);
+
+        let result = tool
+            .execute_data(json!({ "query": "parse_", "path": dir.to_string_lossy() }))
+            .await
+            .unwrap();
+        let ToolResultData::Json(value) = result else {
+            panic!("expected JSON result");
+        };
+        let names: Vec<&str> = value["matches"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["name"].as_str().unwrap())
+            .collect();
+        // Prefix matches rank before substring matches; target/ is never indexed
+        assert_eq!(names, vec!["parse_all", "parse_args"]);
+        assert_eq!(value["matches"][0]["parent"], "Parser");
+        assert!(dir.join(".index/symbols.json").exists());
+
+        let loaded = SymbolIndex::load(dir.join(".index/symbols.json"));
+        assert!(loaded.is_indexed(&dir.join("src")));
+        assert_eq!(loaded.file_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_find_symbol_fills_the_limit_past_blocked_files() {
+        let (dir, whitelist) = setup_project();
+        // The best match sits in a file the whitelist blocks
+        std::fs::write(dir.join("src/hidden.rs"), "pub fn parse() {}\n").unwrap();
+        let rules = crate::claude::whitelist::DirectoryRules {
+            blocked_patterns: vec!["hidden.rs".to_string()],
+            ..Default::default()
+        };
+        whitelist
+            .write()
+            .await
+            .set_directory_rules(&dir, rules)
+            .unwrap();
+        let mut tool = FindSymbolTool::new(Arc::new(RwLock::new(SymbolIndex::new(None))));
+        tool.set_whitelist(whitelist);
+
+        let result = tool
+            .execute_data(json!({ "query": "parse", "path": dir.to_string_lossy(), "limit": 1 }))
+            .await
+            .unwrap();
+        let ToolResultData::Json(value) = result else {
+            panic!("expected JSON result");
+        };
+        assert_eq!(value["count"], 1);
+        assert_eq!(value["matches"][0]["name"], "Parser");
+    }
+
+    #[tokio::test]
+    async fn test_update_file_tracks_changes() {
+        let (dir, _whitelist) = setup_project();
+        let mut index = SymbolIndex::new(None);
+        index.insert_scan(dir.to_path_buf(), scan_directory(&dir));
+
+        let lib = dir.join("src/lib.rs");
+        std::fs::write(&lib, "pub enum Token {}\n").unwrap();
+        // Force a different mtime so the change is not mistaken for a no-op
+        std::fs::File::options()
+            .write(true)
+            .open(&lib)
+            .unwrap()
+            .set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(5))
+            .unwrap();
+        assert!(index.update_file(&lib));
+        assert_eq!(index.search("token", None, &dir, 10, |_| true).len(), 1);
+        assert!(index.search("parser", None, &dir, 10, |_| true).is_empty());
+
+        std::fs::remove_file(&lib).unwrap();
+        assert!(index.update_file(&lib));
+        assert!(index.search("token", None, &dir, 10, |_| true).is_empty());
+
+        // Files outside any scanned root are ignored
+        assert!(!index.update_file(&std::env::temp_dir().join("elsewhere.rs")));
+    }
+}
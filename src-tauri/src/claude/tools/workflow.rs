@@ -0,0 +1,576 @@
+//! Reusable tool workflows defined in TOML files. A workflow is a list of
+//! steps, each calling one tool; steps run in dependency order through
+//! [`ToolExecutionEngine::execute_tool_chain_with_progress`] and can pass
+//! results along with `{{steps.<id>.output}}` templates, or select part of a
+//! JSON output with a path like `{{steps.list.output.files[0]}}`:
+//!
+//! ```toml
+//! name = "summarize-readme"
+//! description = "Copy the README's first section into NOTES.md"
+//!
+//! [[steps]]
+//! id = "read"
+//! tool = "read_file"
+//! input = { path = "README.md" }
+//!
+//! [[steps]]
+//! id = "write"
+//! tool = "write_file"
+//! timeout_secs = 30
+//! max_retries = 1
+//! input = { path = "NOTES.md", content = "{{steps.read.output}}" }
+//! ```
+//!
+//! A step that references another step's output depends on it implicitly;
+//! `depends_on` adds ordering without passing data.
+//!
+//! `on_failure` (`"fail_fast"`, `"continue"` or `"skip_dependents"`) sets what
+//! happens when a step fails, for the whole workflow or per step, and a step's
+//! `compensate = { tool = "...", input = { ... } }` runs when that step fails.
+
+use super::chain::{
+    step_references, ChainExecutionResult, ChainExecutionStatus, FailurePolicy, StepOutcome,
+    ToolRequest,
+};
+use crate::claude::constants::{tool_timeouts::MAX_TOOL_TIMEOUT_SECS, workflows::MAX_STEP_RETRIES};
+use crate::claude::error::{ClaudeError, ClaudeResult, ErrorContext};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::time::Duration;
+
+/// A workflow as written in its TOML file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkflowDefinition {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Failure policy for steps that do not set their own
+    #[serde(default)]
+    pub on_failure: FailurePolicy,
+    pub steps: Vec<WorkflowStep>,
+}
+
+/// One tool call of a workflow
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkflowStep {
+    pub id: String,
+    pub tool: String,
+    #[serde(default = "empty_input")]
+    pub input: serde_json::Value,
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// Attempt timeout overriding the tool's default
+    pub timeout_secs: Option<u64>,
+    pub max_retries: Option<u32>,
+    pub on_failure: Option<FailurePolicy>,
+    /// Tool call that undoes the step's effects if it fails
+    pub compensate: Option<WorkflowCompensation>,
+}
+
+/// Tool call run when a workflow step fails
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkflowCompensation {
+    pub tool: String,
+    #[serde(default = "empty_input")]
+    pub input: serde_json::Value,
+}
+
+fn empty_input() -> serde_json::Value {
+    serde_json::Value::Object(Default::default())
+}
+
+fn invalid(workflow: &str, message: String) -> ClaudeError {
+    ClaudeError::ValidationError {
+        field: "workflow".to_string(),
+        message,
+        context: Some(ErrorContext::new("workflow_validation").add_metadata("workflow", workflow)),
+    }
+}
+
+impl WorkflowDefinition {
+    pub fn from_toml(content: &str) -> ClaudeResult<Self> {
+        toml::from_str(content).map_err(|e| ClaudeError::ConfigError {
+            message: format!("Invalid workflow file: {}", e),
+            context: Some(ErrorContext::new("workflow_parse")),
+        })
+    }
+
+    /// Steps each step waits for: its `depends_on` plus the steps whose
+    /// output it uses
+    fn dependencies(step: &WorkflowStep) -> Vec<String> {
+        let mut dependencies = step.depends_on.clone();
+        for reference in step_references(&step.input) {
+            if !dependencies.contains(&reference) {
+                dependencies.push(reference);
+            }
+        }
+        dependencies
+    }
+
+    /// Check the workflow can run: unique step ids, known tools and steps,
+    /// sane limits and no dependency cycles
+    pub fn validate(&self, is_known_tool: impl Fn(&str) -> bool) -> ClaudeResult<()> {
+        if self.name.trim().is_empty() {
+            return Err(invalid(
+                &self.name,
+                "Workflow name cannot be empty".to_string(),
+            ));
+        }
+        if self.steps.is_empty() {
+            return Err(invalid(&self.name, "Workflow has no steps".to_string()));
+        }
+
+        let mut ids = HashSet::new();
+        for step in &self.steps {
+            if step.id.trim().is_empty() {
+                return Err(invalid(&self.name, "Step id cannot be empty".to_string()));
+            }
+            if !ids.insert(step.id.as_str()) {
+                return Err(invalid(
+                    &self.name,
+                    format!("Duplicate step id '{}'", step.id),
+                ));
+            }
+        }
+
+        for step in &self.steps {
+            if !is_known_tool(&step.tool) {
+                return Err(invalid(
+                    &self.name,
+                    format!("Step '{}' uses unknown tool '{}'", step.id, step.tool),
+                ));
+            }
+            if !step.input.is_object() {
+                return Err(invalid(
+                    &self.name,
+                    format!("Step '{}' input must be a table", step.id),
+                ));
+            }
+            if let Some(timeout) = step.timeout_secs {
+                if timeout == 0 || timeout > MAX_TOOL_TIMEOUT_SECS {
+                    return Err(invalid(
+                        &self.name,
+                        format!(
+                            "Step '{}' timeout_secs must be 1-{}",
+                            step.id, MAX_TOOL_TIMEOUT_SECS
+                        ),
+                    ));
+                }
+            }
+            if step
+                .max_retries
+                .is_some_and(|retries| retries > MAX_STEP_RETRIES)
+            {
+                return Err(invalid(
+                    &self.name,
+                    format!(
+                        "Step '{}' max_retries cannot exceed {}",
+                        step.id, MAX_STEP_RETRIES
+                    ),
+                ));
+            }
+            if let Some(compensation) = &step.compensate {
+                if !is_known_tool(&compensation.tool) {
+                    return Err(invalid(
+                        &self.name,
+                        format!(
+                            "Compensation of step '{}' uses unknown tool '{}'",
+                            step.id, compensation.tool
+                        ),
+                    ));
+                }
+                if let Some(reference) = step_references(&compensation.input)
+                    .into_iter()
+                    .find(|reference| !ids.contains(reference.as_str()))
+                {
+                    return Err(invalid(
+                        &self.name,
+                        format!(
+                            "Compensation of step '{}' references unknown step '{}'",
+                            step.id, reference
+                        ),
+                    ));
+                }
+            }
+            for dependency in Self::dependencies(step) {
+                if dependency == step.id {
+                    return Err(invalid(
+                        &self.name,
+                        format!("Step '{}' depends on itself", step.id),
+                    ));
+                }
+                if !ids.contains(dependency.as_str()) {
+                    return Err(invalid(
+                        &self.name,
+                        format!(
+                            "Step '{}' depends on unknown step '{}'",
+                            step.id, dependency
+                        ),
+                    ));
+                }
+            }
+        }
+
+        if let Some(cycle) = self.find_cycle() {
+            return Err(invalid(
+                &self.name,
+                format!("Dependency cycle: {}", cycle.join(" -> ")),
+            ));
+        }
+        Ok(())
+    }
+
+    /// A dependency cycle as the step ids along it, if there is one
+    fn find_cycle(&self) -> Option<Vec<String>> {
+        let dependencies: HashMap<&str, Vec<String>> = self
+            .steps
+            .iter()
+            .map(|step| (step.id.as_str(), Self::dependencies(step)))
+            .collect();
+
+        fn visit<'a>(
+            id: &'a str,
+            dependencies: &'a HashMap<&str, Vec<String>>,
+            path: &mut Vec<&'a str>,
+            done: &mut HashSet<&'a str>,
+        ) -> Option<Vec<String>> {
+            if let Some(start) = path.iter().position(|step| *step == id) {
+                let mut cycle: Vec<String> = path[start..].iter().map(|s| s.to_string()).collect();
+                cycle.push(id.to_string());
+                return Some(cycle);
+            }
+            if done.contains(id) {
+                return None;
+            }
+
+            path.push(id);
+            for dependency in dependencies.get(id).into_iter().flatten() {
+                if let Some(cycle) = visit(dependency, dependencies, path, done) {
+                    return Some(cycle);
+                }
+            }
+            path.pop();
+            done.insert(id);
+            None
+        }
+
+        let mut done = HashSet::new();
+        self.steps
+            .iter()
+            .find_map(|step| visit(&step.id, &dependencies, &mut Vec::new(), &mut done))
+    }
+
+    /// Chain requests for the engine, identified by step id
+    pub fn to_requests(&self) -> Vec<ToolRequest> {
+        self.steps
+            .iter()
+            .map(|step| {
+                let mut request = ToolRequest::new(step.tool.clone(), step.input.clone())
+                    .with_metadata("workflow".to_string(), self.name.clone());
+                request.id = step.id.clone();
+                request.depends_on = Self::dependencies(step);
+                if let Some(timeout) = step.timeout_secs {
+                    request = request.with_timeout(Duration::from_secs(timeout));
+                }
+                if let Some(max_retries) = step.max_retries {
+                    request = request.with_max_retries(max_retries);
+                }
+                if let Some(policy) = step.on_failure {
+                    request = request.with_failure_policy(policy);
+                }
+                if let Some(compensation) = &step.compensate {
+                    let mut compensation =
+                        ToolRequest::new(compensation.tool.clone(), compensation.input.clone());
+                    compensation.id = format!("{}.compensate", step.id);
+                    request = request.with_compensation(compensation);
+                }
+                request
+            })
+            .collect()
+    }
+}
+
+/// Load every `.toml` workflow in `dir`. Files that fail to parse are
+/// reported and skipped; later files cannot replace an earlier name.
+pub fn load_workflows(dir: &Path) -> Vec<WorkflowDefinition> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    paths.sort();
+
+    let mut workflows: Vec<WorkflowDefinition> = Vec::new();
+    for path in paths {
+        let workflow = std::fs::read_to_string(&path)
+            .map_err(|e| ClaudeError::ConfigError {
+                message: e.to_string(),
+                context: None,
+            })
+            .and_then(|content| WorkflowDefinition::from_toml(&content));
+        match workflow {
+            Ok(workflow) if workflows.iter().any(|w| w.name == workflow.name) => eprintln!(
+                "Skipping workflow {}: another workflow is already named '{}'",
+                path.display(),
+                workflow.name
+            ),
+            Ok(workflow) => workflows.push(workflow),
+            Err(e) => eprintln!("Failed to load workflow {}: {}", path.display(), e),
+        }
+    }
+    workflows
+}
+
+/// Workflow as listed in the UI
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkflowSummary {
+    pub name: String,
+    pub description: String,
+    pub steps: Vec<String>,
+}
+
+impl From<&WorkflowDefinition> for WorkflowSummary {
+    fn from(workflow: &WorkflowDefinition) -> Self {
+        Self {
+            name: workflow.name.clone(),
+            description: workflow.description.clone(),
+            steps: workflow.steps.iter().map(|step| step.id.clone()).collect(),
+        }
+    }
+}
+
+/// Progress of a running workflow, emitted as the chain status changes
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkflowProgress {
+    pub workflow: String,
+    pub status: ChainExecutionStatus,
+}
+
+/// Outcome of one step of a finished workflow run
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkflowStepReport {
+    pub id: String,
+    pub tool: String,
+    /// Input the step ran with, after templates were resolved; null for
+    /// steps that did not run
+    pub input: serde_json::Value,
+    pub outcome: StepOutcome,
+    /// Output of the step, empty if it did not run
+    pub output: String,
+}
+
+/// Outcome of a workflow run
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkflowRunReport {
+    pub workflow: String,
+    pub chain_id: String,
+    pub status: ChainExecutionStatus,
+    pub steps: Vec<WorkflowStepReport>,
+    pub total_time_ms: u64,
+}
+
+impl WorkflowRunReport {
+    pub fn new(workflow: &WorkflowDefinition, result: &ChainExecutionResult) -> Self {
+        let steps = result
+            .step_outcomes
+            .iter()
+            .map(|(id, outcome)| WorkflowStepReport {
+                id: id.clone(),
+                tool: workflow
+                    .steps
+                    .iter()
+                    .find(|step| step.id == *id)
+                    .map(|step| step.tool.clone())
+                    .unwrap_or_default(),
+                input: result.resolved_inputs.get(id).cloned().unwrap_or_default(),
+                outcome: outcome.clone(),
+                output: result
+                    .results
+                    .get(id)
+                    .map(|step| step.into_content_block())
+                    .unwrap_or_default(),
+            })
+            .collect();
+
+        Self {
+            workflow: workflow.name.clone(),
+            chain_id: result.chain_id.clone(),
+            status: result.status.clone(),
+            steps,
+            total_time_ms: result.total_time.as_millis() as u64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::claude::tools::{AgentTool, ToolExecutionEngine};
+    use crate::claude::whitelist::WhitelistConfig;
+    use std::sync::{Arc, Mutex};
+    use tokio::sync::RwLock;
+
+    /// Returns its `text` input
+    #[derive(Debug)]
+    struct EchoTool;
+
+    #[derive(serde::Deserialize, schemars::JsonSchema)]
+    struct EchoInput {
+        text: String,
+    }
+
+    #[async_trait::async_trait]
+    impl AgentTool for EchoTool {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn description(&self) -> &str {
+            "Returns its input text"
+        }
+
+        fn input_schema(&self) -> crate::claude::types::ToolInputSchema {
+            crate::claude::tools::input_schema_for::<EchoInput>()
+        }
+
+        async fn execute(&self, input: serde_json::Value) -> anyhow::Result<String> {
+            let input: EchoInput = crate::claude::tools::parse_input(input)?;
+            Ok(input.text)
+        }
+
+        fn set_whitelist(&mut self, _whitelist: Arc<RwLock<WhitelistConfig>>) {}
+    }
+
+    const GREETING: &str = r#"
+name = "greeting"
+description = "Pass a greeting along"
+
+[[steps]]
+id = "hello"
+tool = "echo"
+input = { text = "hello" }
+
+[[steps]]
+id = "shout"
+tool = "echo"
+max_retries = 1
+timeout_secs = 5
+input = { text = "{{ steps.hello.output }}, world" }
+"#;
+
+    #[test]
+    fn test_templates_become_dependencies() {
+        let workflow = WorkflowDefinition::from_toml(GREETING).unwrap();
+        workflow.validate(|tool| tool == "echo").unwrap();
+
+        let requests = workflow.to_requests();
+        assert_eq!(requests[0].id, "hello");
+        assert!(requests[0].depends_on.is_empty());
+        assert_eq!(requests[1].depends_on, vec!["hello".to_string()]);
+        assert_eq!(requests[1].timeout, Some(Duration::from_secs(5)));
+        assert_eq!(requests[1].max_retries, Some(1));
+    }
+
+    #[test]
+    fn test_validation_rejects_unknown_tools_and_cycles() {
+        let workflow = WorkflowDefinition::from_toml(GREETING).unwrap();
+        let error = workflow.validate(|_| false).unwrap_err().to_string();
+        assert!(error.contains("unknown tool 'echo'"), "{}", error);
+
+        let mut cyclic = workflow.clone();
+        cyclic.steps[0].depends_on = vec!["shout".to_string()];
+        let error = cyclic.validate(|_| true).unwrap_err().to_string();
+        assert!(error.contains("hello -> shout -> hello"), "{}", error);
+
+        let mut dangling = workflow.clone();
+        dangling.steps[1].input = serde_json::json!({"text": "{{steps.missing.output}}"});
+        let error = dangling.validate(|_| true).unwrap_err().to_string();
+        assert!(error.contains("unknown step 'missing'"), "{}", error);
+
+        assert!(WorkflowDefinition::from_toml("name = \"x\"\nsteps = []\nextra = 1").is_err());
+    }
+
+    #[test]
+    fn test_failure_policies_and_compensation_become_request_settings() {
+        let workflow = WorkflowDefinition::from_toml(
+            r#"
+name = "careful"
+on_failure = "skip_dependents"
+
+[[steps]]
+id = "backup"
+tool = "echo"
+input = { text = "saved" }
+
+[[steps]]
+id = "write"
+tool = "echo"
+on_failure = "fail_fast"
+input = { text = "{{steps.backup.output}}" }
+compensate = { tool = "echo", input = { text = "restore {{steps.backup.output}}" } }
+"#,
+        )
+        .unwrap();
+        workflow.validate(|tool| tool == "echo").unwrap();
+        assert_eq!(workflow.on_failure, FailurePolicy::SkipDependents);
+
+        let requests = workflow.to_requests();
+        assert_eq!(requests[0].on_failure, None);
+        assert_eq!(requests[1].on_failure, Some(FailurePolicy::FailFast));
+        let compensation = requests[1].compensation.as_ref().unwrap();
+        assert_eq!(compensation.id, "write.compensate");
+
+        let mut unknown = workflow.clone();
+        unknown.steps[1].compensate.as_mut().unwrap().tool = "restore".to_string();
+        let error = unknown
+            .validate(|tool| tool == "echo")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("Compensation of step 'write' uses unknown tool 'restore'"),
+            "{}",
+            error
+        );
+    }
+
+    #[tokio::test]
+    async fn test_workflow_runs_with_step_outputs_and_progress() {
+        let mut engine = ToolExecutionEngine::new();
+        engine.register_tool(Arc::new(EchoTool));
+        let workflow = WorkflowDefinition::from_toml(GREETING).unwrap();
+        workflow.validate(|tool| engine.has_tool(tool)).unwrap();
+
+        let statuses = Mutex::new(Vec::new());
+        let result = engine
+            .execute_tool_chain_with_progress(
+                workflow.to_requests(),
+                Arc::new(RwLock::new(WhitelistConfig::default())),
+                workflow.on_failure,
+                |status| statuses.lock().unwrap().push(status.clone()),
+            )
+            .await
+            .unwrap();
+
+        assert!(result.is_success());
+        let report = WorkflowRunReport::new(&workflow, &result);
+        assert_eq!(report.steps[1].output, "hello, world");
+        assert_eq!(report.steps[1].input["text"], "hello, world");
+
+        let statuses = statuses.into_inner().unwrap();
+        assert!(matches!(
+            &statuses[0],
+            ChainExecutionStatus::Running { current_step, .. } if current_step == "hello"
+        ));
+        assert!(matches!(
+            statuses.last(),
+            Some(ChainExecutionStatus::Completed { .. })
+        ));
+    }
+}
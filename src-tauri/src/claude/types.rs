@@ -32,7 +32,8 @@ pub enum ContentBlock {
         tool_use_id: String,
         content: String,
         is_error: Option<bool>,
-        #[serde(skip_serializing_if = "Option::is_none")]
+        /// Kept for the UI only; the API rejects unknown fields
+        #[serde(skip_serializing, default)]
         metadata: Option<crate::claude::tools::ToolResultMetadata>,
     },
     // Future-ready for streaming
@@ -158,18 +159,54 @@ pub struct Tool {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolInputSchema {
     pub r#type: String,
+    #[serde(default)]
     pub properties: HashMap<String, PropertySchema>,
+    #[serde(default)]
     pub required: Vec<String>,
-    #[serde(rename = "additionalProperties")]
+    #[serde(rename = "additionalProperties", default)]
     pub additional_properties: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// JSON Schema for a single tool input value
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PropertySchema {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub r#type: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub description: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub items: Option<Box<PropertySchema>>,
+    #[serde(rename = "enum", default, skip_serializing_if = "Option::is_none")]
+    pub enum_values: Option<Vec<serde_json::Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minimum: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maximum: Option<f64>,
+    #[serde(rename = "minLength", default, skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<u64>,
+    #[serde(rename = "maxLength", default, skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<u64>,
+    #[serde(rename = "minItems", default, skip_serializing_if = "Option::is_none")]
+    pub min_items: Option<u64>,
+    #[serde(rename = "maxItems", default, skip_serializing_if = "Option::is_none")]
+    pub max_items: Option<u64>,
+    /// Properties of a nested object
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub properties: HashMap<String, PropertySchema>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required: Vec<String>,
+    #[serde(
+        rename = "additionalProperties",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub additional_properties: Option<bool>,
+    #[serde(rename = "oneOf", default, skip_serializing_if = "Vec::is_empty")]
+    pub one_of: Vec<PropertySchema>,
+    #[serde(rename = "anyOf", default, skip_serializing_if = "Vec::is_empty")]
+    pub any_of: Vec<PropertySchema>,
 }
 
 impl ClaudeMessage {
@@ -315,6 +352,19 @@ mod tests {
         assert_eq!(tool_block, deserialized);
     }
 
+    #[test]
+    fn test_tool_result_metadata_not_sent_to_api() {
+        let block = ContentBlock::tool_result_with_metadata(
+            "test_123",
+            "done",
+            Some(false),
+            crate::claude::tools::ToolResultMetadata::default(),
+        );
+        let serialized = serde_json::to_value(&block).unwrap();
+        assert!(serialized.get("metadata").is_none());
+        assert_eq!(serialized["content"], "done");
+    }
+
     #[test]
     fn test_claude_message_creation() {
         let user_msg = ClaudeMessage::user_text("Hello Claude 4!");
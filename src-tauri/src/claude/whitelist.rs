@@ -1,13 +1,20 @@
+use crate::claude::audit::{AuditEvent, AuditLog};
+use crate::claude::ignore_files::{self, IgnoreMatcher};
+use crate::claude::secure_fs::SecureRoot;
 use anyhow::{anyhow, Result};
 use glob::Pattern;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WhitelistConfig {
-    /// Set of allowed directories (canonical paths)
-    allowed_directories: HashSet<PathBuf>,
+    /// Allowed directories (canonical paths) with their own rules, sorted by path.
+    /// Configs saved before per-directory rules held plain paths; those load
+    /// as read-write entries that use the global limits.
+    #[serde(deserialize_with = "deserialize_directories")]
+    allowed_directories: Vec<DirectoryEntry>,
 
     /// Whether to allow access to subdirectories of whitelisted paths
     allow_subdirectories: bool,
@@ -26,12 +33,161 @@ pub struct WhitelistConfig {
 
     /// Whether the whitelist is enabled (false = fallback to current directory only)
     enabled: bool,
+
+    /// File operations that are denied regardless of directory (e.g. "delete")
+    #[serde(default)]
+    denied_operations: HashSet<FileOperation>,
+
+    /// Whether `.gitignore`, `.ignore` and `.agentignore` files inside
+    /// whitelisted directories hide the paths they match
+    #[serde(default)]
+    respect_ignore_files: bool,
+
+    /// Where every path decision is recorded, if anywhere
+    #[serde(skip)]
+    audit_log: Option<Arc<AuditLog>>,
+}
+
+/// What a whitelisted directory may be used for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DirectoryPermission {
+    /// Reading, listing and inspecting files
+    ReadOnly,
+    /// Every operation that is not denied globally
+    #[default]
+    ReadWrite,
+    /// Listing directories and inspecting files, without reading their contents
+    ListOnly,
+}
+
+impl DirectoryPermission {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DirectoryPermission::ReadOnly => "read-only",
+            DirectoryPermission::ReadWrite => "read-write",
+            DirectoryPermission::ListOnly => "list-only",
+        }
+    }
+
+    /// Check if an operation is permitted in a directory with this permission.
+    /// Copies create a file, so they count as writes.
+    pub fn allows(&self, operation: FileOperation) -> bool {
+        match self {
+            DirectoryPermission::ReadWrite => true,
+            DirectoryPermission::ReadOnly => matches!(
+                operation,
+                FileOperation::Read | FileOperation::List | FileOperation::Info
+            ),
+            DirectoryPermission::ListOnly => {
+                matches!(operation, FileOperation::List | FileOperation::Info)
+            }
+        }
+    }
+}
+
+/// Rules for one whitelisted directory; unset limits fall back to the global ones
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DirectoryRules {
+    #[serde(default)]
+    pub permission: DirectoryPermission,
+
+    /// Maximum subdirectory depth below this directory (0 = unlimited)
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+
+    /// File patterns blocked in this directory in addition to the global ones.
+    /// Patterns are matched against the path relative to the directory and
+    /// against each of its components.
+    #[serde(default)]
+    pub blocked_patterns: Vec<String>,
+
+    /// Maximum file size for reads and copies in this directory (in bytes)
+    #[serde(default)]
+    pub max_file_size: Option<u64>,
+}
+
+impl DirectoryRules {
+    /// Check that every blocked pattern is a valid glob
+    pub fn validate(&self) -> Result<()> {
+        for pattern in &self.blocked_patterns {
+            Pattern::new(pattern)
+                .map_err(|e| anyhow!("Invalid blocked pattern '{}': {}", pattern, e))?;
+        }
+        Ok(())
+    }
+}
+
+/// A whitelisted directory and its rules
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DirectoryEntry {
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub rules: DirectoryRules,
+}
+
+impl DirectoryEntry {
+    /// Check if a path inside this directory matches one of its blocked patterns
+    fn is_blocked(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.path) else {
+            return false;
+        };
+        matches_any(&self.rules.blocked_patterns, relative)
+    }
+}
+
+/// Accept both the current entry list and the plain path list of older configs
+fn deserialize_directories<'de, D>(deserializer: D) -> Result<Vec<DirectoryEntry>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StoredDirectory {
+        Path(PathBuf),
+        Entry(DirectoryEntry),
+    }
+
+    let stored = Vec::<StoredDirectory>::deserialize(deserializer)?;
+    let mut entries: Vec<DirectoryEntry> = stored
+        .into_iter()
+        .map(|directory| match directory {
+            StoredDirectory::Path(path) => DirectoryEntry {
+                path,
+                rules: DirectoryRules::default(),
+            },
+            StoredDirectory::Entry(entry) => entry,
+        })
+        .collect();
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    entries.dedup_by(|a, b| a.path == b.path);
+    Ok(entries)
+}
+
+/// Check a path, and each of its components, against glob patterns
+fn matches_any(patterns: &[String], path: &Path) -> bool {
+    let globs: Vec<Pattern> = patterns
+        .iter()
+        .filter_map(|pattern| Pattern::new(pattern).ok())
+        .collect();
+
+    let path_str = path.to_string_lossy();
+    if globs.iter().any(|glob| glob.matches(&path_str)) {
+        return true;
+    }
+
+    path.components().any(|component| {
+        component
+            .as_os_str()
+            .to_str()
+            .is_some_and(|name| globs.iter().any(|glob| glob.matches(name)))
+    })
 }
 
 impl Default for WhitelistConfig {
     fn default() -> Self {
         Self {
-            allowed_directories: HashSet::new(),
+            allowed_directories: Vec::new(),
             allow_subdirectories: true,
             max_depth: 0, // Unlimited by default
             follow_symlinks: false,
@@ -52,14 +208,28 @@ impl Default for WhitelistConfig {
             ],
             max_file_size: 10 * 1024 * 1024, // 10MB
             enabled: false,
+            denied_operations: HashSet::new(),
+            respect_ignore_files: false,
+            audit_log: None,
         }
     }
 }
 
 impl WhitelistConfig {
-    /// Add a directory to the whitelist
+    /// Add a directory to the whitelist with full access
     pub fn add_directory(&mut self, path: impl AsRef<Path>) -> Result<PathBuf> {
+        self.add_directory_with_rules(path, DirectoryRules::default())
+    }
+
+    /// Add a directory to the whitelist with its own rules, replacing the
+    /// rules if it is already whitelisted
+    pub fn add_directory_with_rules(
+        &mut self,
+        path: impl AsRef<Path>,
+        rules: DirectoryRules,
+    ) -> Result<PathBuf> {
         let path = path.as_ref();
+        rules.validate()?;
 
         // Canonicalize the path to prevent path traversal attacks
         let canonical_path = path
@@ -74,7 +244,45 @@ impl WhitelistConfig {
             ));
         }
 
-        self.allowed_directories.insert(canonical_path.clone());
+        match self
+            .allowed_directories
+            .binary_search_by(|entry| entry.path.cmp(&canonical_path))
+        {
+            Ok(index) => self.allowed_directories[index].rules = rules,
+            Err(index) => self.allowed_directories.insert(
+                index,
+                DirectoryEntry {
+                    path: canonical_path.clone(),
+                    rules,
+                },
+            ),
+        }
+        Ok(canonical_path)
+    }
+
+    /// Change the rules of a directory that is already whitelisted
+    pub fn set_directory_rules(
+        &mut self,
+        path: impl AsRef<Path>,
+        rules: DirectoryRules,
+    ) -> Result<PathBuf> {
+        let path = path.as_ref();
+        rules.validate()?;
+        let canonical_path = path
+            .canonicalize()
+            .map_err(|e| anyhow!("Failed to canonicalize path '{}': {}", path.display(), e))?;
+
+        let entry = self
+            .allowed_directories
+            .iter_mut()
+            .find(|entry| entry.path == canonical_path)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Directory '{}' is not in the whitelist",
+                    canonical_path.display()
+                )
+            })?;
+        entry.rules = rules;
         Ok(canonical_path)
     }
 
@@ -85,14 +293,24 @@ impl WhitelistConfig {
             .canonicalize()
             .map_err(|e| anyhow!("Failed to canonicalize path '{}': {}", path.display(), e))?;
 
-        Ok(self.allowed_directories.remove(&canonical_path))
+        let before = self.allowed_directories.len();
+        self.allowed_directories
+            .retain(|entry| entry.path != canonical_path);
+        Ok(self.allowed_directories.len() < before)
     }
 
     /// List all whitelisted directories
     pub fn list_directories(&self) -> Vec<PathBuf> {
-        let mut dirs: Vec<_> = self.allowed_directories.iter().cloned().collect();
-        dirs.sort();
-        dirs
+        self.allowed_directories
+            .iter()
+            .map(|entry| entry.path.clone())
+            .collect()
+    }
+
+    /// All whitelisted directories with their rules, sorted by path
+    #[allow(dead_code)]
+    pub fn entries(&self) -> &[DirectoryEntry] {
+        &self.allowed_directories
     }
 
     /// Clear all whitelisted directories
@@ -103,30 +321,7 @@ impl WhitelistConfig {
 
     /// Check if a path matches any blocked pattern
     pub fn is_blocked_by_pattern(&self, path: &Path) -> bool {
-        let path_str = path.to_string_lossy();
-
-        for pattern in &self.blocked_patterns {
-            if let Ok(glob_pattern) = Pattern::new(pattern) {
-                if glob_pattern.matches(&path_str) {
-                    return true;
-                }
-            }
-        }
-
-        // Also check individual path components
-        for component in path.components() {
-            if let Some(name) = component.as_os_str().to_str() {
-                for pattern in &self.blocked_patterns {
-                    if let Ok(glob_pattern) = Pattern::new(pattern) {
-                        if glob_pattern.matches(name) {
-                            return true;
-                        }
-                    }
-                }
-            }
-        }
-
-        false
+        matches_any(&self.blocked_patterns, path)
     }
 
     /// Calculate the depth of a path relative to a base path
@@ -136,8 +331,11 @@ impl WhitelistConfig {
             .map(|relative| relative.components().count())
     }
 
-    /// Check if a path is allowed according to whitelist rules
-    pub fn is_path_allowed(&self, path: &Path) -> Result<()> {
+    /// Check if a path is allowed according to whitelist rules, returning the
+    /// whitelisted directory whose rules govern it. When several directories
+    /// contain the path the deepest one wins; `None` means the whitelist is
+    /// disabled and only the global rules apply.
+    pub fn is_path_allowed(&self, path: &Path) -> Result<Option<&DirectoryEntry>> {
         // If whitelist is disabled, only allow current directory and subdirectories
         if !self.enabled {
             let current_dir = std::env::current_dir()
@@ -150,41 +348,34 @@ impl WhitelistConfig {
                 ));
             }
 
-            return Ok(());
+            return Ok(None);
         }
 
-        // Check if path is within any whitelisted directory
-        let mut is_allowed = false;
-
-        for allowed_dir in &self.allowed_directories {
-            if path == allowed_dir {
-                is_allowed = true;
-                break;
+        // Sorted by path, so a parent always comes before the directories inside it
+        for entry in self.allowed_directories.iter().rev() {
+            if path == entry.path {
+                return Ok(Some(entry));
             }
 
-            if self.allow_subdirectories && path.starts_with(allowed_dir) {
+            if self.allow_subdirectories && path.starts_with(&entry.path) {
                 // Check depth restriction
-                if self.max_depth > 0 {
-                    if let Some(depth) = Self::calculate_depth(allowed_dir, path) {
-                        if depth > self.max_depth {
+                let max_depth = entry.rules.max_depth.unwrap_or(self.max_depth);
+                if max_depth > 0 {
+                    if let Some(depth) = Self::calculate_depth(&entry.path, path) {
+                        if depth > max_depth {
                             continue; // Try next allowed directory
                         }
                     }
                 }
 
-                is_allowed = true;
-                break;
+                return Ok(Some(entry));
             }
         }
 
-        if !is_allowed {
-            return Err(anyhow!(
-                "Access denied: Path '{}' is not in the whitelist",
-                path.display()
-            ));
-        }
-
-        Ok(())
+        Err(anyhow!(
+            "Access denied: Path '{}' is not in the whitelist",
+            path.display()
+        ))
     }
 
     /// Enable or disable the whitelist
@@ -202,6 +393,76 @@ impl WhitelistConfig {
     pub fn max_file_size(&self) -> u64 {
         self.max_file_size
     }
+
+    /// Maximum file size allowed for a path, taking the rules of the
+    /// whitelisted directory it is in into account
+    pub fn max_file_size_for(&self, path: &Path) -> u64 {
+        match self.is_path_allowed(path) {
+            Ok(Some(entry)) => entry.rules.max_file_size.unwrap_or(self.max_file_size),
+            _ => self.max_file_size,
+        }
+    }
+
+    /// Permit or deny a file operation for all whitelisted directories
+    pub fn set_operation_allowed(&mut self, operation: FileOperation, allowed: bool) {
+        if allowed {
+            self.denied_operations.remove(&operation);
+        } else {
+            self.denied_operations.insert(operation);
+        }
+    }
+
+    /// Check if a file operation is permitted
+    pub fn is_operation_allowed(&self, operation: FileOperation) -> bool {
+        !self.denied_operations.contains(&operation)
+    }
+
+    /// Open the directory governing a path checked by `validate_path`, so the
+    /// path can be opened relative to it instead of by name
+    pub fn secure_root(&self, path: &Path) -> Result<SecureRoot> {
+        let root = match self.is_path_allowed(path)? {
+            Some(entry) => entry.path.clone(),
+            None => std::env::current_dir()
+                .map_err(|e| anyhow!("Cannot determine current directory: {}", e))?,
+        };
+        SecureRoot::open(&root, self.follow_symlinks)
+    }
+
+    /// Record every decision made by `validate_path` in an audit log
+    pub fn set_audit_log(&mut self, audit_log: Arc<AuditLog>) {
+        self.audit_log = Some(audit_log);
+    }
+
+    /// Honor or disregard ignore files inside whitelisted directories
+    pub fn set_respect_ignore_files(&mut self, respect: bool) {
+        self.respect_ignore_files = respect;
+    }
+
+    /// Directory whose ignore files apply to `path`: the whitelisted directory
+    /// governing it, or the current directory when the whitelist is disabled
+    fn ignore_root(&self, path: &Path) -> Option<PathBuf> {
+        if !self.respect_ignore_files {
+            return None;
+        }
+        match self.is_path_allowed(path) {
+            Ok(Some(entry)) => Some(entry.path.clone()),
+            Ok(None) => std::env::current_dir().ok(),
+            Err(_) => None,
+        }
+    }
+
+    /// Check if an ignore file excludes a canonical path
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        self.ignore_root(path)
+            .is_some_and(|root| ignore_files::is_ignored(&root, path, path.is_dir()))
+    }
+
+    /// Ignore rules for the entries of a canonical directory, or `None` when
+    /// ignore files are not honored
+    pub fn ignore_matcher(&self, directory: &Path) -> Option<IgnoreMatcher> {
+        self.ignore_root(directory)
+            .map(|root| IgnoreMatcher::for_directory(&root, directory))
+    }
 }
 
 /// Persistence module for saving/loading whitelist configuration
@@ -209,6 +470,9 @@ pub mod persistence {
     use super::*;
     use tauri::{AppHandle, Manager};
 
+    /// File the whitelist is persisted to in the app config directory
+    pub const CONFIG_FILE_NAME: &str = "whitelist.json";
+
     /// Get the path to the whitelist configuration file
     pub fn get_config_path(app: &AppHandle) -> Result<PathBuf> {
         let config_dir = app
@@ -219,7 +483,7 @@ pub mod persistence {
         // Ensure the directory exists
         std::fs::create_dir_all(&config_dir)?;
 
-        Ok(config_dir.join("whitelist.json"))
+        Ok(config_dir.join(CONFIG_FILE_NAME))
     }
 
     /// Save whitelist configuration to disk
@@ -237,8 +501,12 @@ pub mod persistence {
 
     /// Load whitelist configuration from disk
     pub async fn load(app: &AppHandle) -> Result<WhitelistConfig> {
-        let path = get_config_path(app)?;
+        load_from(&get_config_path(app)?)
+    }
 
+    /// Load whitelist configuration from a file, for modes that run without
+    /// an app handle
+    pub fn load_from(path: &Path) -> Result<WhitelistConfig> {
         if !path.exists() {
             // Return default config if file doesn't exist
             return Ok(WhitelistConfig::default());
@@ -248,31 +516,88 @@ pub mod persistence {
         let mut config: WhitelistConfig = serde_json::from_str(&json)?;
 
         // Validate loaded paths still exist
-        config.allowed_directories.retain(|path| path.exists());
+        config
+            .allowed_directories
+            .retain(|entry| entry.path.exists());
 
         Ok(config)
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum FileOperation {
     Read,
     Write,
     List,
+    Move,
+    Copy,
+    Delete,
+    CreateDirectory,
+    Info,
+}
+
+impl FileOperation {
+    /// Human readable name used in error messages
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileOperation::Read => "read",
+            FileOperation::Write => "write",
+            FileOperation::List => "list",
+            FileOperation::Move => "move",
+            FileOperation::Copy => "copy",
+            FileOperation::Delete => "delete",
+            FileOperation::CreateDirectory => "create_directory",
+            FileOperation::Info => "info",
+        }
+    }
 }
 
-/// Validate and sanitize a path according to whitelist rules
+/// Validate and sanitize a path according to whitelist rules, recording the
+/// decision in the audit log when one is set
 pub fn validate_path(
     path: &str,
     whitelist: &WhitelistConfig,
     operation: FileOperation,
 ) -> Result<PathBuf> {
-    let path = Path::new(path);
+    // Reject operations that have been disabled in the whitelist
+    let resolved = if whitelist.is_operation_allowed(operation) {
+        resolve_path(Path::new(path), whitelist)
+    } else {
+        Err(anyhow!(
+            "Access denied: '{}' operations are disabled in the whitelist",
+            operation.as_str()
+        ))
+    };
+
+    let (canonical_path, result) = match resolved {
+        Ok(canonical) => (
+            Some(canonical.display().to_string()),
+            check_access(&canonical, whitelist, operation).map(|()| canonical),
+        ),
+        Err(e) => (None, Err(e)),
+    };
+
+    if let Some(audit_log) = &whitelist.audit_log {
+        audit_log.record(AuditEvent::PathAccess {
+            operation,
+            path: path.to_string(),
+            canonical_path,
+            allowed: result.is_ok(),
+            reason: result.as_ref().err().map(|e| e.to_string()),
+        });
+    }
 
+    result
+}
+
+/// Resolve a requested path to the canonical path the rules are checked
+/// against, without checking the rules or recording an audit entry
+pub fn resolve_path(path: &Path, whitelist: &WhitelistConfig) -> Result<PathBuf> {
     // First, canonicalize the path to resolve any .. or . components
     let canonical_path = if path.is_absolute() {
         if whitelist.follow_symlinks {
-            path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+            canonicalize_missing(path)?
         } else {
             // Custom canonicalization that doesn't follow symlinks
             canonicalize_no_follow(path)?
@@ -284,33 +609,71 @@ pub fn validate_path(
 
         let full_path = current_dir.join(path);
         if whitelist.follow_symlinks {
-            full_path.canonicalize().unwrap_or(full_path)
+            canonicalize_missing(&full_path)?
         } else {
             canonicalize_no_follow(&full_path)?
         }
     };
 
+    Ok(canonical_path)
+}
+
+/// Check a canonical path against the whitelist's rules for an operation
+fn check_access(
+    canonical_path: &Path,
+    whitelist: &WhitelistConfig,
+    operation: FileOperation,
+) -> Result<()> {
     // Check if path is blocked by pattern
-    if whitelist.is_blocked_by_pattern(&canonical_path) {
+    if whitelist.is_blocked_by_pattern(canonical_path) {
         return Err(anyhow!(
             "Access denied: Path '{}' matches a blocked pattern",
             canonical_path.display()
         ));
     }
 
-    // Check if path is allowed by whitelist
-    whitelist.is_path_allowed(&canonical_path)?;
+    // Check if path is allowed by whitelist, then against its directory's rules
+    let entry = whitelist.is_path_allowed(canonical_path)?;
+    if let Some(entry) = entry {
+        if !entry.rules.permission.allows(operation) {
+            return Err(anyhow!(
+                "Access denied: '{}' is {} and does not allow '{}' operations",
+                entry.path.display(),
+                entry.rules.permission.as_str(),
+                operation.as_str()
+            ));
+        }
+
+        if entry.is_blocked(canonical_path) {
+            return Err(anyhow!(
+                "Access denied: Path '{}' matches a blocked pattern of '{}'",
+                canonical_path.display(),
+                entry.path.display()
+            ));
+        }
+    }
+    let max_file_size = entry
+        .and_then(|entry| entry.rules.max_file_size)
+        .unwrap_or(whitelist.max_file_size());
+
+    // Keep build output, dependencies and generated files out of reach when asked to
+    if whitelist.is_ignored(canonical_path) {
+        return Err(anyhow!(
+            "Access denied: Path '{}' is excluded by an ignore file",
+            canonical_path.display()
+        ));
+    }
 
     // Additional checks based on operation type
     match operation {
         FileOperation::Read => {
             // Check file size for read operations
-            if let Ok(metadata) = std::fs::metadata(&canonical_path) {
-                if metadata.len() > whitelist.max_file_size() {
+            if let Ok(metadata) = std::fs::metadata(canonical_path) {
+                if metadata.len() > max_file_size {
                     return Err(anyhow!(
                         "File too large: {} bytes (limit: {} bytes)",
                         metadata.len(),
-                        whitelist.max_file_size()
+                        max_file_size
                     ));
                 }
             }
@@ -318,6 +681,32 @@ pub fn validate_path(
         FileOperation::Write => {
             // Additional write-specific checks could go here
         }
+        FileOperation::Copy => {
+            // The copied data ends up in a new file, so apply the read size limit
+            if let Ok(metadata) = std::fs::metadata(canonical_path) {
+                if metadata.is_file() && metadata.len() > max_file_size {
+                    return Err(anyhow!(
+                        "File too large: {} bytes (limit: {} bytes)",
+                        metadata.len(),
+                        max_file_size
+                    ));
+                }
+            }
+        }
+        FileOperation::Delete => {
+            // Never allow removing a whitelisted root itself
+            if whitelist
+                .allowed_directories
+                .iter()
+                .any(|entry| entry.path == canonical_path)
+            {
+                return Err(anyhow!(
+                    "Access denied: Cannot delete whitelisted directory '{}'",
+                    canonical_path.display()
+                ));
+            }
+        }
+        FileOperation::Move | FileOperation::CreateDirectory | FileOperation::Info => {}
         FileOperation::List => {
             // Ensure it's a directory for list operations
             if !canonical_path.is_dir() {
@@ -329,7 +718,36 @@ pub fn validate_path(
         }
     }
 
-    Ok(canonical_path)
+    Ok(())
+}
+
+/// Canonicalize a path that may not exist yet: the longest existing prefix is
+/// canonicalized and the missing names are appended, so `..` or a symlink
+/// cannot hide behind a missing directory
+fn canonicalize_missing(path: &Path) -> Result<PathBuf> {
+    let mut existing = path;
+    let mut missing = Vec::new();
+    loop {
+        if let Ok(canonical) = existing.canonicalize() {
+            return Ok(missing
+                .iter()
+                .rev()
+                .fold(canonical, |path, name| path.join(name)));
+        }
+        // A trailing `..` cannot be resolved without its parent existing
+        match (existing.file_name(), existing.parent()) {
+            (Some(name), Some(parent)) => {
+                missing.push(name);
+                existing = parent;
+            }
+            _ => {
+                return Err(anyhow!(
+                    "Access denied: Cannot resolve '{}'",
+                    path.display()
+                ))
+            }
+        }
+    }
 }
 
 /// Canonicalize a path without following symlinks
@@ -380,3 +798,236 @@ fn canonicalize_no_follow(path: &Path) -> Result<PathBuf> {
 
     Ok(result)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::claude::test_support::TempDir;
+
+    #[test]
+    fn test_plain_directory_lists_migrate_to_read_write_entries() {
+        let json = serde_json::json!({
+            "allowed_directories": ["/b", "/a", "/b"],
+            "allow_subdirectories": true,
+            "max_depth": 0,
+            "follow_symlinks": false,
+            "blocked_patterns": ["*.env"],
+            "max_file_size": 1024,
+            "enabled": true
+        });
+
+        let config: WhitelistConfig = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            config.list_directories(),
+            vec![PathBuf::from("/a"), PathBuf::from("/b")]
+        );
+        assert!(config
+            .entries()
+            .iter()
+            .all(|entry| entry.rules == DirectoryRules::default()));
+
+        // Saved again, the entries keep their rules
+        let saved = serde_json::to_value(&config).unwrap();
+        assert_eq!(saved["allowed_directories"][0]["permission"], "read_write");
+        let reloaded: WhitelistConfig = serde_json::from_value(saved).unwrap();
+        assert_eq!(reloaded.entries(), config.entries());
+    }
+
+    #[test]
+    fn test_permissions_are_checked_against_the_deepest_entry() {
+        let root = TempDir::new();
+        let docs = root.join("docs");
+        std::fs::create_dir_all(&docs).unwrap();
+        std::fs::write(docs.join("guide.md"), "guide").unwrap();
+
+        let mut whitelist = WhitelistConfig::default();
+        whitelist.set_enabled(true);
+        whitelist.add_directory(&root).unwrap();
+        whitelist
+            .add_directory_with_rules(
+                &docs,
+                DirectoryRules {
+                    permission: DirectoryPermission::ReadOnly,
+                    ..DirectoryRules::default()
+                },
+            )
+            .unwrap();
+
+        let guide = docs.join("guide.md");
+        let guide = guide.to_string_lossy();
+        assert!(validate_path(&guide, &whitelist, FileOperation::Read).is_ok());
+        let denied = validate_path(&guide, &whitelist, FileOperation::Write).unwrap_err();
+        assert!(denied.to_string().contains("read-only"));
+
+        let notes = root.join("notes.md");
+        assert!(validate_path(&notes.to_string_lossy(), &whitelist, FileOperation::Write).is_ok());
+
+        whitelist
+            .set_directory_rules(
+                &docs,
+                DirectoryRules {
+                    permission: DirectoryPermission::ListOnly,
+                    ..DirectoryRules::default()
+                },
+            )
+            .unwrap();
+        assert!(validate_path(&guide, &whitelist, FileOperation::Read).is_err());
+        assert!(validate_path(&docs.to_string_lossy(), &whitelist, FileOperation::List).is_ok());
+
+        // Dropping the docs entry leaves it under the root's rules
+        whitelist.remove_directory(&docs).unwrap();
+        assert!(validate_path(&guide, &whitelist, FileOperation::Write).is_ok());
+    }
+
+    #[test]
+    fn test_entry_limits_apply_inside_their_directory() {
+        let root = TempDir::new();
+        std::fs::create_dir_all(root.join("target/debug")).unwrap();
+        std::fs::write(root.join("big.txt"), "x".repeat(100)).unwrap();
+
+        let mut whitelist = WhitelistConfig::default();
+        whitelist.set_enabled(true);
+        whitelist
+            .add_directory_with_rules(
+                &root,
+                DirectoryRules {
+                    max_depth: Some(1),
+                    blocked_patterns: vec!["target".to_string()],
+                    max_file_size: Some(10),
+                    ..DirectoryRules::default()
+                },
+            )
+            .unwrap();
+
+        let big = root.join("big.txt");
+        let too_large =
+            validate_path(&big.to_string_lossy(), &whitelist, FileOperation::Read).unwrap_err();
+        assert!(too_large.to_string().contains("limit: 10 bytes"));
+        assert_eq!(whitelist.max_file_size_for(&big), 10);
+
+        let blocked = root.join("target");
+        assert!(
+            validate_path(&blocked.to_string_lossy(), &whitelist, FileOperation::List)
+                .unwrap_err()
+                .to_string()
+                .contains("blocked pattern")
+        );
+
+        let deep = root.join("src/claude/mod.rs");
+        assert!(validate_path(&deep.to_string_lossy(), &whitelist, FileOperation::Write).is_err());
+
+        assert!(whitelist
+            .set_directory_rules(
+                &root,
+                DirectoryRules {
+                    blocked_patterns: vec!["[".to_string()],
+                    ..DirectoryRules::default()
+                },
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_missing_paths_cannot_climb_out_when_following_symlinks() {
+        let root = TempDir::new();
+        let allowed = root.join("allowed");
+        std::fs::create_dir_all(&allowed).unwrap();
+
+        let mut whitelist = WhitelistConfig::default();
+        whitelist.set_enabled(true);
+        whitelist.add_directory(&allowed).unwrap();
+        whitelist.follow_symlinks = true;
+
+        // Neither `missing` nor `new.txt` exist; the parts that do are resolved
+        let escape = allowed.join("missing/../../outside.txt");
+        assert!(
+            validate_path(&escape.to_string_lossy(), &whitelist, FileOperation::Write).is_err()
+        );
+        let resolved = validate_path(
+            &allowed.join("sub/../new.txt").to_string_lossy(),
+            &whitelist,
+            FileOperation::Write,
+        );
+        assert!(resolved.is_err());
+
+        std::fs::create_dir_all(allowed.join("sub")).unwrap();
+        let resolved = validate_path(
+            &allowed.join("sub/../new.txt").to_string_lossy(),
+            &whitelist,
+            FileOperation::Write,
+        )
+        .unwrap();
+        assert_eq!(resolved, allowed.join("new.txt"));
+    }
+
+    #[test]
+    fn test_path_decisions_are_audited() {
+        let root = TempDir::new();
+        std::fs::write(root.join("main.rs"), "fn main() {}").unwrap();
+        let audit_log = Arc::new(AuditLog::open(root.join("audit/audit_log.jsonl")));
+
+        let mut whitelist = WhitelistConfig::default();
+        whitelist.set_enabled(true);
+        whitelist.add_directory(&root).unwrap();
+        whitelist.set_audit_log(audit_log.clone());
+
+        let main_rs = root.join("main.rs");
+        assert!(validate_path(&main_rs.to_string_lossy(), &whitelist, FileOperation::Read).is_ok());
+        assert!(validate_path("/etc/hosts", &whitelist, FileOperation::Read).is_err());
+
+        let entries = audit_log
+            .query(&crate::claude::audit::AuditFilter::default())
+            .unwrap();
+        assert_eq!(entries.len(), 2);
+        match &entries[0].event {
+            AuditEvent::PathAccess {
+                operation,
+                canonical_path,
+                allowed,
+                ..
+            } => {
+                assert_eq!(*operation, FileOperation::Read);
+                assert_eq!(canonical_path.as_deref(), Some(&*main_rs.to_string_lossy()));
+                assert!(allowed);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        match &entries[1].event {
+            AuditEvent::PathAccess {
+                allowed, reason, ..
+            } => {
+                assert!(!allowed);
+                assert!(reason.as_deref().unwrap().contains("Access denied"));
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        assert!(audit_log.verify().unwrap().valid);
+    }
+
+    #[test]
+    fn test_ignore_files_hide_paths_when_honored() {
+        let root = TempDir::new();
+        std::fs::create_dir_all(root.join("node_modules/left-pad")).unwrap();
+        std::fs::write(root.join(".gitignore"), "node_modules/\n").unwrap();
+        std::fs::write(root.join("main.js"), "").unwrap();
+
+        let mut whitelist = WhitelistConfig::default();
+        whitelist.set_enabled(true);
+        whitelist.add_directory(&root).unwrap();
+
+        let dependency = root.join("node_modules/left-pad");
+        let dependency = dependency.to_string_lossy();
+        assert!(validate_path(&dependency, &whitelist, FileOperation::List).is_ok());
+        assert!(whitelist.ignore_matcher(&root).is_none());
+
+        whitelist.set_respect_ignore_files(true);
+        let denied = validate_path(&dependency, &whitelist, FileOperation::List).unwrap_err();
+        assert!(denied.to_string().contains("ignore file"));
+        let main = root.join("main.js");
+        assert!(validate_path(&main.to_string_lossy(), &whitelist, FileOperation::Read).is_ok());
+
+        let matcher = whitelist.ignore_matcher(&root).unwrap();
+        assert!(matcher.is_ignored(&root.join("node_modules"), true));
+        assert!(!matcher.is_ignored(&main, false));
+    }
+}
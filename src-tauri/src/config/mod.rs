@@ -13,7 +13,7 @@ pub mod validation;
 
 #[allow(unused_imports)]
 pub use constants::*;
-pub use runtime::RuntimeConfig;
+pub use runtime::{McpServerConfig, RuntimeConfig, SecretRuleConfig};
 pub use validation::ValidationLimits;
 
 use anyhow::Result;
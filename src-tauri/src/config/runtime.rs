@@ -10,6 +10,7 @@
 use super::constants::{defaults, ENV_CLAUDE_API_KEY, ENV_LOG_LEVEL};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 /// Runtime configuration that can be modified during execution
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,6 +27,26 @@ pub struct RuntimeConfig {
     pub file_watcher_poll_ms: u64,
     pub file_watcher_debounce_ms: u64,
     pub heartbeat_interval_secs: u64,
+    /// Per-tool execution timeouts in seconds, overriding the built-in defaults
+    #[serde(default)]
+    pub tool_timeouts_secs: HashMap<String, u64>,
+    /// Per-tool limits on calls running at the same time, overriding the built-in defaults
+    #[serde(default)]
+    pub tool_concurrency_limits: HashMap<String, usize>,
+
+    // External Tool Servers
+    /// MCP servers whose tools are offered to Claude alongside the built-in ones
+    #[serde(default)]
+    pub mcp_servers: Vec<McpServerConfig>,
+
+    // Secret Scanning
+    /// What happens to secrets found in tool results and outgoing messages:
+    /// "block", "redact", "warn" or "off"
+    #[serde(default = "default_secret_scan_mode")]
+    pub secret_scan_mode: String,
+    /// Detection rules checked in addition to the built-in ones
+    #[serde(default)]
+    pub secret_rules: Vec<SecretRuleConfig>,
 
     // Retry Configuration
     pub max_retry_attempts: u32,
@@ -41,11 +62,154 @@ pub struct RuntimeConfig {
     pub enable_file_watching: bool,
     pub enable_debug_logging: bool,
     pub enable_thinking_mode: bool,
+    #[serde(default)]
+    pub enable_git_commit: bool,
+    /// Learn latency and failure patterns from tool calls and remember them across runs
+    #[serde(default = "default_true")]
+    pub enable_tool_learning: bool,
+    /// Where learned tool feedback goes: "notes" for Claude, "hints" for the user, or "off"
+    #[serde(default = "default_tool_feedback_delivery")]
+    pub tool_feedback_delivery: String,
 
     // Log Level
     pub log_level: String,
 }
 
+/// An external Model Context Protocol server, either launched as a child
+/// process speaking over stdio or reached over HTTP on the local machine
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpServerConfig {
+    /// Short name used to namespace the server's tools (letters, digits, '-')
+    pub name: String,
+    /// Executable to launch for a stdio server
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Extra environment variables for the launched process
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Endpoint of an HTTP server; only loopback addresses are accepted
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Ask the user before each call to one of this server's tools
+    #[serde(default = "default_true")]
+    pub require_approval: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// A secret detection rule: a regex, an entropy threshold for long tokens, or both
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecretRuleConfig {
+    /// Name shown in reports and redaction placeholders
+    pub name: String,
+    /// Regex matching the secret; with a capture group, only the group is treated as secret
+    #[serde(default)]
+    pub pattern: Option<String>,
+    /// Minimum Shannon entropy in bits per character; without a pattern, every
+    /// token of at least `min_length` characters is checked against it
+    #[serde(default)]
+    pub min_entropy: Option<f64>,
+    #[serde(default = "default_secret_min_length")]
+    pub min_length: usize,
+}
+
+fn default_secret_min_length() -> usize {
+    20
+}
+
+impl SecretRuleConfig {
+    /// Check the name, that the rule detects something, and that the regex compiles
+    pub fn validate(&self) -> Result<()> {
+        if self.name.is_empty()
+            || !self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(anyhow::anyhow!(
+                "Invalid secret rule name '{}': use letters, digits, '_' and '-'",
+                self.name
+            ));
+        }
+        if self.pattern.is_none() && self.min_entropy.is_none() {
+            return Err(anyhow::anyhow!(
+                "Secret rule '{}' needs a pattern, a min_entropy, or both",
+                self.name
+            ));
+        }
+        if let Some(pattern) = &self.pattern {
+            regex::Regex::new(pattern).map_err(|e| {
+                anyhow::anyhow!("Invalid pattern for secret rule '{}': {}", self.name, e)
+            })?;
+        }
+        if let Some(entropy) = self.min_entropy {
+            if !(0.0..=8.0).contains(&entropy) {
+                return Err(anyhow::anyhow!(
+                    "Invalid min_entropy for secret rule '{}': {} (must be 0.0-8.0)",
+                    self.name,
+                    entropy
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn default_secret_scan_mode() -> String {
+    "redact".to_string()
+}
+
+fn default_tool_feedback_delivery() -> String {
+    "notes".to_string()
+}
+
+impl McpServerConfig {
+    /// Check the name and that exactly one local transport is configured
+    pub fn validate(&self) -> Result<()> {
+        if self.name.is_empty()
+            || !self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-')
+        {
+            return Err(anyhow::anyhow!(
+                "Invalid MCP server name: '{}' (use letters, digits and '-')",
+                self.name
+            ));
+        }
+
+        match (&self.command, &self.url) {
+            (Some(command), None) if !command.trim().is_empty() => Ok(()),
+            (None, Some(url)) => {
+                let parsed = reqwest::Url::parse(url).map_err(|e| {
+                    anyhow::anyhow!("Invalid URL for MCP server '{}': {}", self.name, e)
+                })?;
+                let local = matches!(parsed.scheme(), "http" | "https")
+                    && matches!(parsed.host_str(), Some("localhost" | "127.0.0.1" | "[::1]"));
+                if local {
+                    Ok(())
+                } else {
+                    Err(anyhow::anyhow!(
+                        "MCP server '{}' must use a local http(s) URL, got {}",
+                        self.name,
+                        url
+                    ))
+                }
+            }
+            _ => Err(anyhow::anyhow!(
+                "MCP server '{}' needs either a command or a url",
+                self.name
+            )),
+        }
+    }
+}
+
 impl Default for RuntimeConfig {
     fn default() -> Self {
         Self {
@@ -61,6 +225,15 @@ impl Default for RuntimeConfig {
             file_watcher_poll_ms: super::constants::FILE_WATCHER_POLL_MS,
             file_watcher_debounce_ms: super::constants::FILE_WATCHER_DEBOUNCE_MS,
             heartbeat_interval_secs: super::constants::HEARTBEAT_INTERVAL_SECS,
+            tool_timeouts_secs: HashMap::new(),
+            tool_concurrency_limits: HashMap::new(),
+
+            // External Tool Servers
+            mcp_servers: Vec::new(),
+
+            // Secret Scanning
+            secret_scan_mode: default_secret_scan_mode(),
+            secret_rules: Vec::new(),
 
             // Retry Configuration
             max_retry_attempts: super::constants::MAX_RETRY_ATTEMPTS,
@@ -76,6 +249,9 @@ impl Default for RuntimeConfig {
             enable_file_watching: true,
             enable_debug_logging: false,
             enable_thinking_mode: true,
+            enable_git_commit: false,
+            enable_tool_learning: true,
+            tool_feedback_delivery: default_tool_feedback_delivery(),
 
             // Logging
             log_level: "info".to_string(),
@@ -133,6 +309,15 @@ impl RuntimeConfig {
                 file_watching.eq_ignore_ascii_case("true") || file_watching == "1";
         }
 
+        if let Ok(git_commit) = std::env::var("ENABLE_GIT_COMMIT") {
+            self.enable_git_commit = git_commit.eq_ignore_ascii_case("true") || git_commit == "1";
+        }
+
+        if let Ok(tool_learning) = std::env::var("ENABLE_TOOL_LEARNING") {
+            self.enable_tool_learning =
+                tool_learning.eq_ignore_ascii_case("true") || tool_learning == "1";
+        }
+
         Ok(())
     }
 
@@ -160,6 +345,24 @@ impl RuntimeConfig {
         self.file_watcher_poll_ms = other.file_watcher_poll_ms;
         self.file_watcher_debounce_ms = other.file_watcher_debounce_ms;
         self.heartbeat_interval_secs = other.heartbeat_interval_secs;
+        self.tool_timeouts_secs.extend(other.tool_timeouts_secs);
+        self.tool_concurrency_limits
+            .extend(other.tool_concurrency_limits);
+
+        // Merge MCP servers; a server defined again by name replaces the earlier entry
+        for server in other.mcp_servers {
+            self.mcp_servers
+                .retain(|existing| existing.name != server.name);
+            self.mcp_servers.push(server);
+        }
+
+        // Merge secret scanning; a rule defined again by name replaces the earlier one
+        self.secret_scan_mode = other.secret_scan_mode;
+        for rule in other.secret_rules {
+            self.secret_rules
+                .retain(|existing| existing.name != rule.name);
+            self.secret_rules.push(rule);
+        }
 
         // Merge retry config
         self.max_retry_attempts = other.max_retry_attempts;
@@ -175,6 +378,9 @@ impl RuntimeConfig {
         self.enable_file_watching = other.enable_file_watching;
         self.enable_debug_logging = other.enable_debug_logging;
         self.enable_thinking_mode = other.enable_thinking_mode;
+        self.enable_git_commit = other.enable_git_commit;
+        self.enable_tool_learning = other.enable_tool_learning;
+        self.tool_feedback_delivery = other.tool_feedback_delivery;
 
         self.log_level = other.log_level;
     }
@@ -210,6 +416,59 @@ impl RuntimeConfig {
             ));
         }
 
+        // Validate tool timeouts
+        for (tool, secs) in &self.tool_timeouts_secs {
+            if *secs == 0 || *secs > 3600 {
+                return Err(anyhow::anyhow!(
+                    "Invalid timeout for tool '{}': {}s (must be 1-3600)",
+                    tool,
+                    secs
+                ));
+            }
+        }
+
+        // Validate tool concurrency limits
+        for (tool, limit) in &self.tool_concurrency_limits {
+            if *limit == 0 || *limit > 64 {
+                return Err(anyhow::anyhow!(
+                    "Invalid concurrency limit for tool '{}': {} (must be 1-64)",
+                    tool,
+                    limit
+                ));
+            }
+        }
+
+        // Validate tool feedback delivery
+        if !["notes", "hints", "off"].contains(&self.tool_feedback_delivery.as_str()) {
+            return Err(anyhow::anyhow!(
+                "Invalid tool_feedback_delivery: {} (must be notes, hints or off)",
+                self.tool_feedback_delivery
+            ));
+        }
+
+        // Validate secret scanning
+        if !["block", "redact", "warn", "off"].contains(&self.secret_scan_mode.as_str()) {
+            return Err(anyhow::anyhow!(
+                "Invalid secret_scan_mode: {} (must be block, redact, warn or off)",
+                self.secret_scan_mode
+            ));
+        }
+        for rule in &self.secret_rules {
+            rule.validate()?;
+        }
+
+        // Validate MCP servers
+        let mut server_names = std::collections::HashSet::new();
+        for server in &self.mcp_servers {
+            server.validate()?;
+            if !server_names.insert(server.name.as_str()) {
+                return Err(anyhow::anyhow!(
+                    "Duplicate MCP server name: {}",
+                    server.name
+                ));
+            }
+        }
+
         Ok(())
     }
 
@@ -249,6 +508,14 @@ impl RuntimeConfig {
         std::time::Duration::from_millis(self.file_watcher_debounce_ms)
     }
 
+    /// Configured per-tool timeouts as Durations
+    pub fn tool_timeouts(&self) -> HashMap<String, std::time::Duration> {
+        self.tool_timeouts_secs
+            .iter()
+            .map(|(tool, secs)| (tool.clone(), std::time::Duration::from_secs(*secs)))
+            .collect()
+    }
+
     /// Get heartbeat interval as Duration
     #[allow(dead_code)]
     pub fn heartbeat_interval(&self) -> std::time::Duration {
@@ -1,3 +1,6 @@
+use crate::claude::constants::code_index::SKIPPED_DIRECTORIES;
+use crate::claude::tools::SharedSymbolIndex;
+use crate::claude::whitelist::WhitelistConfig;
 use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
@@ -18,6 +21,8 @@ pub struct FileWatcherService {
     watchers: Arc<RwLock<HashMap<PathBuf, RecommendedWatcher>>>,
     app_handle: AppHandle,
     debounce_map: Arc<RwLock<HashMap<PathBuf, Instant>>>,
+    symbol_index: Option<SharedSymbolIndex>,
+    whitelist: Option<Arc<RwLock<WhitelistConfig>>>,
 }
 
 impl FileWatcherService {
@@ -26,9 +31,23 @@ impl FileWatcherService {
             watchers: Arc::new(RwLock::new(HashMap::new())),
             app_handle,
             debounce_map: Arc::new(RwLock::new(HashMap::new())),
+            symbol_index: None,
+            whitelist: None,
         }
     }
 
+    /// Re-index changed source files so find_symbol results stay current
+    pub fn with_symbol_index(mut self, symbol_index: SharedSymbolIndex) -> Self {
+        self.symbol_index = Some(symbol_index);
+        self
+    }
+
+    /// Drop events for paths the whitelist's ignore files exclude
+    pub fn with_whitelist(mut self, whitelist: Arc<RwLock<WhitelistConfig>>) -> Self {
+        self.whitelist = Some(whitelist);
+        self
+    }
+
     pub async fn start_watching(
         &self,
         path: PathBuf,
@@ -48,6 +67,8 @@ impl FileWatcherService {
         let app_handle = self.app_handle.clone();
         let debounce_map = self.debounce_map.clone();
         let watch_path = path.clone();
+        let symbol_index = self.symbol_index.clone();
+        let whitelist = self.whitelist.clone();
 
         // Create file watcher
         let mut watcher = RecommendedWatcher::new(
@@ -61,8 +82,8 @@ impl FileWatcherService {
             Config::default().with_poll_interval(Duration::from_millis(500)),
         )?;
 
-        // Start watching the directory
-        watcher.watch(&path, RecursiveMode::NonRecursive)?;
+        // Watch recursively so nested source changes reach the symbol index
+        watcher.watch(&path, RecursiveMode::Recursive)?;
 
         // Store the watcher
         {
@@ -74,14 +95,28 @@ impl FileWatcherService {
         tokio::spawn(async move {
             let debounce_duration = Duration::from_millis(300);
 
-            while let Some(event) = rx.recv().await {
+            while let Some(mut event) = rx.recv().await {
                 println!("📁 File event received: {:?}", event);
 
                 // Filter out irrelevant events
-                if !Self::is_relevant_event(&event) {
+                if !Self::is_relevant_event(&event, &watch_path) {
                     continue;
                 }
 
+                // Paths excluded by ignore files are neither indexed nor reported
+                if let Some(whitelist) = &whitelist {
+                    let whitelist = whitelist.read().await;
+                    event.paths.retain(|path| !whitelist.is_ignored(path));
+                    if event.paths.is_empty() {
+                        continue;
+                    }
+                }
+
+                // Update the symbol index for every event; only UI events are debounced
+                if let Some(index) = &symbol_index {
+                    Self::update_symbol_index(index, &event.paths).await;
+                }
+
                 // Debounce events to avoid spam
                 let now = Instant::now();
                 {
@@ -133,11 +168,31 @@ impl FileWatcherService {
         println!("🛑 Stopped watching {} directories", count);
     }
 
-    fn is_relevant_event(event: &Event) -> bool {
+    async fn update_symbol_index(index: &SharedSymbolIndex, paths: &[PathBuf]) {
+        let mut index = index.write().await;
+        let mut changed = false;
+        for path in paths {
+            changed |= index.update_file(path);
+        }
+        if changed {
+            if let Err(e) = index.save().await {
+                eprintln!("Failed to save symbol index: {}", e);
+            }
+        }
+    }
+
+    fn is_relevant_event(event: &Event, watch_path: &Path) -> bool {
         match &event.kind {
             EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(_) => {
-                // Filter out temporary files and hidden files
+                // Filter out temporary files, hidden files and build/dependency output
                 !event.paths.iter().any(|path| {
+                    let relative = path.strip_prefix(watch_path).unwrap_or(path);
+                    let in_skipped_dir = relative.components().any(|c| {
+                        SKIPPED_DIRECTORIES.contains(&c.as_os_str().to_string_lossy().as_ref())
+                    });
+                    if in_skipped_dir {
+                        return true;
+                    }
                     if let Some(name) = path.file_name() {
                         let name_str = name.to_string_lossy();
                         name_str.starts_with('.')
@@ -3,13 +3,33 @@
 #![allow(clippy::result_large_err)]
 
 use std::sync::Arc;
-use tauri::{async_runtime::Mutex, Manager};
+use tauri::{async_runtime::Mutex, Emitter, Manager};
 
 mod claude;
 mod config;
 mod file_watcher;
 mod security;
-use claude::whitelist::{persistence, WhitelistConfig};
+use claude::audit::{AuditEntry, AuditFilter, AuditLog, AuditVerification};
+use claude::constants::{
+    approval::POLICY_FILE_NAME, audit::AUDIT_LOG_FILE_NAME, checkpoints::CHECKPOINT_DIR_NAME,
+    code_index::INDEX_FILE_NAME, execution_history::HISTORY_FILE_NAME, feedback_learning, mcp,
+    plugins::PLUGIN_DIR_NAME, secret_scanning::SECRET_REPORT_EVENT, workflows,
+};
+use claude::mcp::{McpAuditLog, McpManager, McpServer, McpServerResource, McpServerStatus};
+use claude::secrets::{SecretReport, SecretScanMode, SecretScanner};
+use claude::tools::approval::{ApprovalDecision, ApprovalRequest};
+use claude::tools::breaker::ToolBreakerStatus;
+use claude::tools::chain::ExecutionStats;
+use claude::tools::checkpoint::{CheckpointSummary, FileDiff, RestoreReport};
+use claude::tools::feedback::LearnedToolStats;
+use claude::tools::history::{ExecutionRecord, HistoryFilter};
+use claude::tools::workflow::{WorkflowProgress, WorkflowRunReport, WorkflowSummary};
+use claude::tools::{
+    load_plugins, load_workflows, ApprovalGate, ApprovalManager, ApprovalNotifier, ApprovalPolicy,
+    CheckpointStore, ExecutionHistoryStore, FeedbackDelivery, HintNotifier, LearnedFeedback,
+    SmartFeedbackHandler, SymbolIndex, ToolBreakers, WorkflowDefinition,
+};
+use claude::whitelist::{persistence, DirectoryRules, FileOperation, WhitelistConfig};
 use claude::{ClaudeClient, ClaudeConfig, Conversation, ConversationMessage};
 use config::{
     constants::{self, error_templates, get_file_icon, DIRECTORY_ICON, SAFETY_BUFFER_RATIO},
@@ -26,6 +46,44 @@ struct AppState {
     app_config: Arc<AppConfig>,
     whitelist: Arc<RwLock<WhitelistConfig>>,
     file_watcher: Arc<FileWatcherService>,
+    approval: ApprovalGate,
+    checkpoints: Option<Arc<CheckpointStore>>,
+    mcp: Arc<McpManager>,
+    workflows: Arc<Vec<WorkflowDefinition>>,
+    breakers: Arc<ToolBreakers>,
+    history: Option<Arc<ExecutionHistoryStore>>,
+    feedback: Option<LearnedFeedback>,
+    /// Secrets found while handling the most recent message
+    secret_report: Arc<Mutex<SecretReport>>,
+    audit: Option<Arc<AuditLog>>,
+}
+
+/// Forwards tool approval requests to the frontend as events
+struct TauriApprovalNotifier {
+    app: tauri::AppHandle,
+}
+
+impl ApprovalNotifier for TauriApprovalNotifier {
+    fn notify(&self, request: &ApprovalRequest) {
+        if let Err(e) = self.app.emit("tool_approval_requested", request) {
+            eprintln!("Failed to emit tool approval request: {}", e);
+        }
+    }
+}
+
+/// Forwards learned tool feedback to the frontend as events
+#[derive(Debug)]
+struct TauriHintNotifier {
+    app: tauri::AppHandle,
+}
+
+impl HintNotifier for TauriHintNotifier {
+    fn notify(&self, tool_name: &str, hint: &str) {
+        let payload = serde_json::json!({ "tool_name": tool_name, "hint": hint });
+        if let Err(e) = self.app.emit(feedback_learning::HINT_EVENT, payload) {
+            eprintln!("Failed to emit tool feedback hint: {}", e);
+        }
+    }
 }
 
 impl AppState {
@@ -125,6 +183,7 @@ async fn set_claude_api_key(
 #[tauri::command]
 async fn send_message_to_claude(
     message: String,
+    app: tauri::AppHandle,
     state: tauri::State<'_, AppState>,
 ) -> Result<String, String> {
     // Input validation
@@ -148,26 +207,48 @@ async fn send_message_to_claude(
     }
 
     // Check if we have a valid configuration
-    let config = state.get_claude_config().await;
+    let mut config = state.get_claude_config().await;
 
     if config.api_key.is_empty() {
         return Err(error_templates::API_KEY_NOT_SET.to_string());
     }
 
+    // Each user message starts a new checkpoint for the files it leads to edit
+    if let Some(checkpoints) = &state.checkpoints {
+        checkpoints.begin_turn(&message).await;
+    }
+
+    // Tool calls are recorded in the history under the conversation they belong to
+    config.session_id = Some(state.conversation.lock().await.id.clone());
+
     // Create Claude client
-    let client = ClaudeClient::new(config).map_err(|e| {
+    let mut client = ClaudeClient::new(config).map_err(|e| {
         error_templates::with_context(error_templates::CLIENT_CREATION_FAILED, &e.to_string())
     })?;
+    client.set_whitelist(state.whitelist.clone());
 
     // Send message to Claude
     let response = {
         let mut conversation = state.conversation.lock().await;
-        client.chat(&mut conversation, message).await.map_err(|e| {
-            error_templates::with_context(error_templates::API_ERROR, &e.to_string())
-        })?
+        client.chat(&mut conversation, message).await
     };
 
-    Ok(response)
+    // Report secrets kept out of (or flagged in) this request, even if it was blocked
+    let secret_report = client.take_secret_report();
+    if !secret_report.is_empty() {
+        if let Err(e) = app.emit(SECRET_REPORT_EVENT, &secret_report) {
+            eprintln!("Failed to emit secret report: {}", e);
+        }
+    }
+    *state.secret_report.lock().await = secret_report;
+
+    response.map_err(|e| error_templates::with_context(error_templates::API_ERROR, &e.to_string()))
+}
+
+/// Secrets found while handling the most recent message
+#[tauri::command]
+async fn get_secret_report(state: tauri::State<'_, AppState>) -> Result<SecretReport, String> {
+    Ok(state.secret_report.lock().await.clone())
 }
 
 #[tauri::command]
@@ -189,8 +270,8 @@ async fn clear_conversation(state: tauri::State<'_, AppState>) -> Result<String,
 async fn execute_list_directory_tool(
     path: String,
     whitelist: Arc<RwLock<WhitelistConfig>>,
-) -> Result<String, String> {
-    use claude::tools::{AgentTool, ListDirectoryTool};
+) -> Result<Vec<claude::tools::execution::FileItem>, String> {
+    use claude::tools::{AgentTool, ListDirectoryTool, ToolResultData};
 
     let mut tool = ListDirectoryTool::new();
     tool.set_whitelist(whitelist);
@@ -198,10 +279,17 @@ async fn execute_list_directory_tool(
     let mut input = serde_json::Map::new();
     input.insert("path".to_string(), Value::String(path));
 
-    tool.execute(Value::Object(input)).await.map_err(|e| {
+    let data = tool.execute_data(Value::Object(input)).await.map_err(|e| {
         println!("❌ Tool execution failed: {}", e);
         error_templates::operation_failed("list directory", &e.to_string())
-    })
+    })?;
+    match data {
+        ToolResultData::DirectoryListing(items) => Ok(items),
+        _ => Err(error_templates::operation_failed(
+            "list directory",
+            "unexpected tool result",
+        )),
+    }
 }
 
 /// Determine the appropriate icon for a file based on its type and extension
@@ -215,30 +303,27 @@ fn determine_file_icon(file_type: &str, file_name: &str) -> String {
     }
 }
 
-/// Parse directory listing results into structured FileItem objects
-fn parse_directory_results(result: &str) -> Result<Vec<FileItem>, String> {
-    let mut items = Vec::new();
-    let current_dir = std::env::current_dir().unwrap_or_default();
-
-    for line in result.lines() {
-        if line.is_empty() || line.starts_with("...") {
-            continue;
-        }
-
-        if let Some((name, type_info)) = line.rsplit_once(" (") {
-            let file_type = type_info.trim_end_matches(')');
-            let icon = determine_file_icon(file_type, name);
+/// Convert a directory listing into the items shown in the file explorer
+fn to_file_items(listing: Vec<claude::tools::execution::FileItem>) -> Vec<FileItem> {
+    use claude::tools::execution::FileItemType;
 
-            items.push(FileItem {
-                name: name.to_string(),
+    let current_dir = std::env::current_dir().unwrap_or_default();
+    listing
+        .into_iter()
+        .map(|item| {
+            let file_type = match item.item_type {
+                FileItemType::Directory => "directory",
+                FileItemType::Unknown => "unknown",
+                FileItemType::File | FileItemType::Symlink => "file",
+            };
+            FileItem {
+                icon: determine_file_icon(file_type, &item.name),
+                path: format!("{}/{}", current_dir.display(), item.name),
+                name: item.name,
                 file_type: file_type.to_string(),
-                icon,
-                path: format!("{}/{}", current_dir.display(), name),
-            });
-        }
-    }
-
-    Ok(items)
+            }
+        })
+        .collect()
 }
 
 #[tauri::command]
@@ -246,8 +331,8 @@ async fn list_directory(
     path: String,
     state: tauri::State<'_, AppState>,
 ) -> Result<Vec<FileItem>, String> {
-    let result = execute_list_directory_tool(path, state.whitelist.clone()).await?;
-    parse_directory_results(&result)
+    let listing = execute_list_directory_tool(path, state.whitelist.clone()).await?;
+    Ok(to_file_items(listing))
 }
 
 #[derive(serde::Serialize)]
@@ -262,12 +347,13 @@ struct FileItem {
 #[tauri::command]
 async fn whitelist_add_directory(
     path: String,
+    rules: Option<DirectoryRules>,
     app: tauri::AppHandle,
     state: tauri::State<'_, AppState>,
 ) -> Result<String, String> {
     let mut whitelist = state.whitelist.write().await;
 
-    match whitelist.add_directory(&path) {
+    match whitelist.add_directory_with_rules(&path, rules.unwrap_or_default()) {
         Ok(canonical_path) => {
             // Save to disk
             if let Err(e) = persistence::save(&app, &whitelist).await {
@@ -285,6 +371,33 @@ async fn whitelist_add_directory(
     }
 }
 
+#[tauri::command]
+async fn whitelist_set_directory_rules(
+    path: String,
+    rules: DirectoryRules,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
+    let mut whitelist = state.whitelist.write().await;
+
+    match whitelist.set_directory_rules(&path, rules) {
+        Ok(canonical_path) => {
+            // Save to disk
+            if let Err(e) = persistence::save(&app, &whitelist).await {
+                return Err(error_templates::with_context(
+                    error_templates::WHITELIST_SAVE_FAILED,
+                    &e.to_string(),
+                ));
+            }
+            Ok(format!("Updated rules for: {}", canonical_path.display()))
+        }
+        Err(e) => Err(error_templates::operation_failed(
+            "update directory rules",
+            &e.to_string(),
+        )),
+    }
+}
+
 #[tauri::command]
 async fn whitelist_remove_directory(
     path: String,
@@ -351,6 +464,54 @@ async fn whitelist_set_enabled(
     ))
 }
 
+#[tauri::command]
+async fn whitelist_set_operation_allowed(
+    operation: FileOperation,
+    allowed: bool,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
+    let mut whitelist = state.whitelist.write().await;
+    whitelist.set_operation_allowed(operation, allowed);
+
+    // Save to disk
+    if let Err(e) = persistence::save(&app, &whitelist).await {
+        return Err(error_templates::with_context(
+            error_templates::WHITELIST_SAVE_FAILED,
+            &e.to_string(),
+        ));
+    }
+
+    Ok(format!(
+        "Operation '{}' {}",
+        operation.as_str(),
+        if allowed { "allowed" } else { "denied" }
+    ))
+}
+
+#[tauri::command]
+async fn whitelist_set_respect_ignore_files(
+    enabled: bool,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
+    let mut whitelist = state.whitelist.write().await;
+    whitelist.set_respect_ignore_files(enabled);
+
+    // Save to disk
+    if let Err(e) = persistence::save(&app, &whitelist).await {
+        return Err(error_templates::with_context(
+            error_templates::WHITELIST_SAVE_FAILED,
+            &e.to_string(),
+        ));
+    }
+
+    Ok(format!(
+        "Ignore files {}",
+        if enabled { "honored" } else { "disregarded" }
+    ))
+}
+
 #[tauri::command]
 async fn whitelist_get_config(
     state: tauri::State<'_, AppState>,
@@ -359,6 +520,255 @@ async fn whitelist_get_config(
     Ok(whitelist.clone())
 }
 
+// Tool approval commands
+#[tauri::command]
+async fn respond_to_tool_approval(
+    request_id: String,
+    approved: bool,
+    message: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
+    state
+        .approval
+        .manager
+        .respond(&request_id, ApprovalDecision { approved, message })
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(format!(
+        "Request {} {}",
+        request_id,
+        if approved { "approved" } else { "denied" }
+    ))
+}
+
+#[tauri::command]
+async fn get_pending_tool_approvals(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<ApprovalRequest>, String> {
+    Ok(state.approval.manager.pending_requests().await)
+}
+
+#[tauri::command]
+async fn get_approval_policy(state: tauri::State<'_, AppState>) -> Result<ApprovalPolicy, String> {
+    Ok(state.approval.policy.read().await.clone())
+}
+
+#[tauri::command]
+async fn set_approval_policy(
+    policy: ApprovalPolicy,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
+    let config_dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+    policy
+        .save(&config_dir.join(POLICY_FILE_NAME))
+        .map_err(|e| format!("Failed to save approval policy: {}", e))?;
+    *state.approval.policy.write().await = policy;
+
+    Ok("Approval policy updated".to_string())
+}
+
+// Checkpoint commands
+fn checkpoint_store(state: &AppState) -> Result<&Arc<CheckpointStore>, String> {
+    state
+        .checkpoints
+        .as_ref()
+        .ok_or_else(|| "Checkpoints are unavailable: no app data directory".to_string())
+}
+
+#[tauri::command]
+async fn list_checkpoints(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<CheckpointSummary>, String> {
+    checkpoint_store(&state)?
+        .list()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_checkpoint_diff(
+    checkpoint_id: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<FileDiff>, String> {
+    checkpoint_store(&state)?
+        .diff(&checkpoint_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn restore_checkpoint(
+    checkpoint_id: String,
+    force: Option<bool>,
+    state: tauri::State<'_, AppState>,
+) -> Result<RestoreReport, String> {
+    let whitelist = state.whitelist.read().await;
+    checkpoint_store(&state)?
+        .restore(&checkpoint_id, force.unwrap_or(false), &whitelist)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// MCP server commands
+#[tauri::command]
+async fn list_mcp_servers(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<McpServerStatus>, String> {
+    Ok(state.mcp.statuses())
+}
+
+#[tauri::command]
+async fn list_mcp_resources(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<McpServerResource>, String> {
+    Ok(state.mcp.list_resources().await)
+}
+
+// Workflow commands
+#[tauri::command]
+async fn list_workflows(state: tauri::State<'_, AppState>) -> Result<Vec<WorkflowSummary>, String> {
+    Ok(state.workflows.iter().map(WorkflowSummary::from).collect())
+}
+
+#[tauri::command]
+async fn run_workflow(
+    name: String,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<WorkflowRunReport, String> {
+    let workflow = state
+        .workflows
+        .iter()
+        .find(|workflow| workflow.name == name)
+        .ok_or_else(|| format!("No workflow named '{}'", name))?;
+
+    let mut config = state.get_claude_config().await;
+    config.session_id = Some(format!("workflow:{}", workflow.name));
+    let engine = ClaudeClient::build_tool_engine(&config, Some(&state.whitelist));
+    workflow
+        .validate(|tool| engine.has_tool(tool))
+        .map_err(|e| e.to_string())?;
+
+    let result = engine
+        .execute_tool_chain_with_progress(
+            workflow.to_requests(),
+            state.whitelist.clone(),
+            workflow.on_failure,
+            |status| {
+                let progress = WorkflowProgress {
+                    workflow: workflow.name.clone(),
+                    status: status.clone(),
+                };
+                if let Err(e) = app.emit(workflows::PROGRESS_EVENT, progress) {
+                    eprintln!("Failed to emit workflow progress: {}", e);
+                }
+            },
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(WorkflowRunReport::new(workflow, &result))
+}
+
+// Tool circuit breaker commands
+#[tauri::command]
+async fn list_tool_breakers(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<ToolBreakerStatus>, String> {
+    Ok(state.breakers.statuses())
+}
+
+#[tauri::command]
+async fn reset_tool_breaker(
+    tool_name: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
+    if state.breakers.reset(&tool_name) {
+        Ok(format!("Circuit breaker for '{}' reset", tool_name))
+    } else {
+        Ok(format!("'{}' has no recorded failures", tool_name))
+    }
+}
+
+// Tool execution history commands
+fn history_store(state: &AppState) -> Result<&Arc<ExecutionHistoryStore>, String> {
+    state
+        .history
+        .as_ref()
+        .ok_or_else(|| "Tool history is unavailable: no app data directory".to_string())
+}
+
+#[tauri::command]
+async fn query_tool_history(
+    filter: Option<HistoryFilter>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<ExecutionRecord>, String> {
+    history_store(&state)?
+        .query(&filter.unwrap_or_default())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_tool_history_stats(
+    filter: Option<HistoryFilter>,
+    state: tauri::State<'_, AppState>,
+) -> Result<ExecutionStats, String> {
+    history_store(&state)?
+        .stats(&filter.unwrap_or_default())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// The matching history as JSON Lines, for the frontend to save
+#[tauri::command]
+async fn export_tool_history(
+    filter: Option<HistoryFilter>,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
+    history_store(&state)?
+        .export(&filter.unwrap_or_default())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Audit log commands
+fn audit_log(state: &AppState) -> Result<&Arc<AuditLog>, String> {
+    state
+        .audit
+        .as_ref()
+        .ok_or_else(|| "Audit log is unavailable: no app data directory".to_string())
+}
+
+#[tauri::command]
+async fn query_audit_log(
+    filter: Option<AuditFilter>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<AuditEntry>, String> {
+    audit_log(&state)?
+        .query(&filter.unwrap_or_default())
+        .map_err(|e| e.to_string())
+}
+
+/// Check that no audit entry was modified, removed or reordered
+#[tauri::command]
+async fn verify_audit_log(state: tauri::State<'_, AppState>) -> Result<AuditVerification, String> {
+    audit_log(&state)?.verify().map_err(|e| e.to_string())
+}
+
+/// What has been learned about each tool's latency and failures
+#[tauri::command]
+async fn get_tool_learning(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<LearnedToolStats>, String> {
+    match &state.feedback {
+        Some(feedback) => Ok(feedback.handler.tool_stats().await),
+        None => Err("Tool learning is disabled".to_string()),
+    }
+}
+
 // File watching commands
 #[tauri::command]
 async fn start_file_watching(state: tauri::State<'_, AppState>) -> Result<String, String> {
@@ -384,7 +794,59 @@ async fn stop_file_watching(state: tauri::State<'_, AppState>) -> Result<String,
     Ok("File watching stopped".to_string())
 }
 
+/// Serve the whitelisted file tools to another MCP client over stdio instead
+/// of opening the app. Uses the whitelist saved by the app, and refuses to
+/// start when it is disabled or lists no directories.
+async fn run_mcp_server(identifier: &str) -> anyhow::Result<()> {
+    let whitelist_path =
+        dirs::config_dir().map(|dir| dir.join(identifier).join(persistence::CONFIG_FILE_NAME));
+    let mut whitelist_config = match &whitelist_path {
+        Some(path) => persistence::load_from(path)?,
+        None => WhitelistConfig::default(),
+    };
+    // A disabled or empty whitelist falls back to the working directory, which
+    // an MCP client chooses; only serve directories the user whitelisted
+    if !whitelist_config.is_enabled() || whitelist_config.list_directories().is_empty() {
+        return Err(anyhow::anyhow!(
+            "no whitelisted directories are configured; add one in the app before starting the MCP server"
+        ));
+    }
+    let audit = dirs::data_dir().map(|data_dir| {
+        Arc::new(AuditLog::open(
+            data_dir.join(identifier).join(AUDIT_LOG_FILE_NAME),
+        ))
+    });
+    if let Some(audit) = &audit {
+        whitelist_config.set_audit_log(audit.clone());
+    }
+
+    let mut server = McpServer::for_file_tools(Arc::new(RwLock::new(whitelist_config)));
+    if let Some(audit) = audit {
+        server = server.with_tool_audit_log(audit);
+    }
+    if let Some(data_dir) = dirs::data_dir() {
+        server = server.with_audit_log(McpAuditLog::new(
+            data_dir.join(identifier).join(mcp::AUDIT_LOG_FILE_NAME),
+        ));
+    }
+
+    eprintln!("Serving file tools over MCP on stdio");
+    server.serve(tokio::io::stdin(), tokio::io::stdout()).await
+}
+
 fn main() {
+    let context = tauri::generate_context!();
+
+    // MCP server mode runs headless; stdout carries the protocol
+    if std::env::args().any(|arg| arg == mcp::SERVER_FLAG) {
+        let identifier = context.config().identifier.clone();
+        if let Err(e) = tauri::async_runtime::block_on(run_mcp_server(&identifier)) {
+            eprintln!("MCP server failed: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     // Load application configuration
     let app_config = AppConfig::load().unwrap_or_else(|e| {
         eprintln!("Failed to load app config: {}, using defaults", e);
@@ -407,6 +869,20 @@ fn main() {
         max_tokens: app_config.runtime.max_tokens,
         temperature: app_config.runtime.temperature,
         model_registry: crate::claude::ModelRegistry::new(),
+        data_dir: None,
+        enable_git_commit: app_config.runtime.enable_git_commit,
+        symbol_index: None,
+        approval: None,
+        checkpoints: None,
+        tool_timeouts: app_config.runtime.tool_timeouts(),
+        mcp: None,
+        plugins: Vec::new(),
+        breakers: None,
+        history: None,
+        session_id: None,
+        feedback: None,
+        secrets: None,
+        audit: None,
     };
 
     tauri::Builder::default()
@@ -432,15 +908,160 @@ fn main() {
                 }
             }
 
-            // Create file watcher service
-            let file_watcher = Arc::new(FileWatcherService::new(app.handle().clone()));
+            // Tools keep app-managed state (e.g. the trash) in the app data dir
+            let mut claude_config = initial_config;
+            claude_config.data_dir = app.path().app_data_dir().ok();
+
+            // File access, tool calls and API requests are appended to a hash-chained log
+            let audit = claude_config
+                .data_dir
+                .as_ref()
+                .map(|data_dir| Arc::new(AuditLog::open(data_dir.join(AUDIT_LOG_FILE_NAME))));
+            if let Some(audit) = &audit {
+                whitelist_config.set_audit_log(audit.clone());
+            }
+            claude_config.audit = audit.clone();
+
+            // Symbol index for find_symbol, persisted in the app data dir
+            let symbol_index = Arc::new(RwLock::new(match &claude_config.data_dir {
+                Some(data_dir) => SymbolIndex::load(data_dir.join(INDEX_FILE_NAME)),
+                None => SymbolIndex::new(None),
+            }));
+            claude_config.symbol_index = Some(symbol_index.clone());
+
+            // Approval policy for tool calls, persisted in the app config dir
+            let policy = match app.path().app_config_dir() {
+                Ok(config_dir) => ApprovalPolicy::load(&config_dir.join(POLICY_FILE_NAME))
+                    .unwrap_or_else(|e| {
+                        eprintln!("Failed to load approval policy: {}", e);
+                        ApprovalPolicy::default()
+                    }),
+                Err(_) => ApprovalPolicy::default(),
+            };
+            let approval = ApprovalGate::new(
+                Arc::new(RwLock::new(policy)),
+                Arc::new(ApprovalManager::new(Arc::new(TauriApprovalNotifier {
+                    app: app.handle().clone(),
+                }))),
+            );
+            claude_config.approval = Some(approval.clone());
+
+            // Files are snapshotted per user turn before tools modify them
+            let checkpoints = claude_config
+                .data_dir
+                .as_ref()
+                .map(|data_dir| Arc::new(CheckpointStore::new(data_dir.join(CHECKPOINT_DIR_NAME))));
+            claude_config.checkpoints = checkpoints.clone();
+
+            // Finished tool calls are kept on disk for later inspection
+            let history = claude_config.data_dir.as_ref().map(|data_dir| {
+                Arc::new(ExecutionHistoryStore::new(data_dir.join(HISTORY_FILE_NAME)))
+            });
+            claude_config.history = history.clone();
+
+            // Learned tool patterns survive restarts when there is a data dir
+            let feedback = app_config.runtime.enable_tool_learning.then(|| {
+                let handler = match &claude_config.data_dir {
+                    Some(data_dir) => SmartFeedbackHandler::load(
+                        data_dir.join(feedback_learning::LEARNING_FILE_NAME),
+                    ),
+                    None => SmartFeedbackHandler::new(),
+                };
+                let delivery = app_config
+                    .runtime
+                    .tool_feedback_delivery
+                    .parse()
+                    .unwrap_or_else(|e| {
+                        eprintln!("{}", e);
+                        FeedbackDelivery::default()
+                    });
+                LearnedFeedback::new(Arc::new(handler), delivery).with_notifier(Arc::new(
+                    TauriHintNotifier {
+                        app: app.handle().clone(),
+                    },
+                ))
+            });
+            claude_config.feedback = feedback.clone();
+
+            // Secrets are screened out of tool results and messages before they are sent
+            claude_config.secrets = match app_config.runtime.secret_scan_mode.as_str() {
+                "off" => None,
+                mode => {
+                    let mode = mode.parse().unwrap_or_else(|e| {
+                        eprintln!("{}", e);
+                        SecretScanMode::default()
+                    });
+                    match SecretScanner::new(mode).with_rules(&app_config.runtime.secret_rules) {
+                        Ok(scanner) => Some(Arc::new(scanner)),
+                        Err(e) => {
+                            eprintln!("Ignoring configured secret rules: {}", e);
+                            Some(Arc::new(SecretScanner::new(mode)))
+                        }
+                    }
+                }
+            };
+
+            // MCP servers connect in the background; their tools are picked
+            // up by clients created after the connection completes
+            let mcp = Arc::new(McpManager::new());
+            claude_config.mcp = Some(mcp.clone());
+            let mcp_servers = app_config.runtime.mcp_servers.clone();
+            let mcp_connector = mcp.clone();
+            tauri::async_runtime::spawn(async move {
+                mcp_connector.connect_all(&mcp_servers).await;
+            });
+
+            // Tool failures and concurrency are tracked across all clients
+            let breakers = Arc::new(
+                app_config
+                    .runtime
+                    .tool_concurrency_limits
+                    .iter()
+                    .fold(ToolBreakers::default(), |breakers, (tool, limit)| {
+                        breakers.with_concurrency_limit(tool.clone(), *limit)
+                    }),
+            );
+            claude_config.breakers = Some(breakers.clone());
+
+            // Plugins reach files only through the shared whitelist
+            let whitelist = Arc::new(RwLock::new(whitelist_config));
+            if let Some(data_dir) = &claude_config.data_dir {
+                claude_config.plugins =
+                    load_plugins(&data_dir.join(PLUGIN_DIR_NAME), Some(whitelist.clone()));
+            }
+
+            // Workflows live next to the app config file, in config/workflows
+            let workflows = match std::env::current_dir() {
+                Ok(current_dir) => load_workflows(
+                    &current_dir
+                        .join("config")
+                        .join(workflows::WORKFLOW_DIR_NAME),
+                ),
+                Err(_) => Vec::new(),
+            };
+
+            // Create file watcher service; it keeps the symbol index up to date
+            let file_watcher = Arc::new(
+                FileWatcherService::new(app.handle().clone())
+                    .with_symbol_index(symbol_index)
+                    .with_whitelist(whitelist.clone()),
+            );
 
             let app_state = AppState {
                 conversation: Arc::new(Mutex::new(Conversation::default())),
-                config: Arc::new(Mutex::new(initial_config)),
+                config: Arc::new(Mutex::new(claude_config)),
                 app_config: Arc::new(app_config),
-                whitelist: Arc::new(RwLock::new(whitelist_config)),
+                whitelist,
                 file_watcher,
+                approval,
+                checkpoints,
+                mcp,
+                workflows: Arc::new(workflows),
+                breakers,
+                history,
+                feedback,
+                secret_report: Arc::new(Mutex::new(SecretReport::default())),
+                audit,
             };
 
             app.manage(app_state);
@@ -452,17 +1073,40 @@ fn main() {
             initialize_with_env_key,
             set_claude_api_key,
             send_message_to_claude,
+            get_secret_report,
             get_conversation_history,
             clear_conversation,
             list_directory,
             whitelist_add_directory,
+            whitelist_set_directory_rules,
             whitelist_remove_directory,
             whitelist_list_directories,
             whitelist_set_enabled,
+            whitelist_set_operation_allowed,
+            whitelist_set_respect_ignore_files,
             whitelist_get_config,
+            respond_to_tool_approval,
+            get_pending_tool_approvals,
+            get_approval_policy,
+            set_approval_policy,
+            list_checkpoints,
+            get_checkpoint_diff,
+            restore_checkpoint,
+            list_mcp_servers,
+            list_mcp_resources,
+            list_workflows,
+            run_workflow,
+            list_tool_breakers,
+            reset_tool_breaker,
+            query_tool_history,
+            get_tool_history_stats,
+            export_tool_history,
+            get_tool_learning,
+            query_audit_log,
+            verify_audit_log,
             start_file_watching,
             stop_file_watching,
         ])
-        .run(tauri::generate_context!())
+        .run(context)
         .expect("error while running tauri application");
 }